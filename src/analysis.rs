@@ -1,8 +1,10 @@
 use crate::cir::{Command, Execute, FuncCall, Function, FunctionId, ScoreHolder};
+use crate::compile_ir::{compile_terminator, load_params, GlobalVarList};
+use either::Either;
 use std::collections::{HashSet, HashMap};
 use llvm_ir::Terminator;
-use llvm_ir::terminator::{Br, CondBr};
-use petgraph::prelude::{DiGraph, NodeIndex};
+use llvm_ir::types::Types;
+use llvm_ir::terminator::Br;
 
 #[derive(Debug, PartialEq, Clone)]
 #[allow(clippy::large_enum_variant)]
@@ -35,38 +37,6 @@ impl AbstractBlock {
         }
     }
 
-    pub fn all_dests(&self, func_starts: &HashMap<String, FunctionId>) -> Option<Vec<FunctionId>> {
-        if let Some(d) = self.get_dest(func_starts) {
-            Some(vec![d])
-        } else if let Some((_, d1, d2)) = self.get_cond_dest() {
-            Some(vec![d1, d2])
-        } else {
-            None
-        }
-    }
-
-    /// If this block ends in a CondBr, returns the condition, the true branch,
-    /// and then the false branch
-    pub fn get_cond_dest(&self) -> Option<(ScoreHolder, FunctionId, FunctionId)> {
-        match self.term.as_ref()? {
-            BlockEnd::Normal(Terminator::CondBr(CondBr { condition, true_dest, false_dest, debugloc: _ })) => {
-                if let llvm_ir::Operand::LocalOperand { name, ty } = condition {
-                    if matches!(&**ty, llvm_ir::Type::IntegerType { bits: 1 }) {
-                        let cond = ScoreHolder::from_local_name(name.clone(), 1).into_iter().next().unwrap();
-                        let true_dest = FunctionId::new_block(&self.parent.name, true_dest.clone());
-                        let false_dest = FunctionId::new_block(&self.parent.name, false_dest.clone());
-                        Some((cond, true_dest, false_dest))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        }
-    }
-
     /*pub fn last(&self) -> &AbstractBlock {
         if let BlockEnd::Inlined(ab) = &self.term {
             ab.last()
@@ -119,10 +89,10 @@ fn estimate_count(
             if id.name.starts_with("intrinsic:") {
                 *crate::intrinsics::INTRINSIC_COUNTS.get(id).unwrap()
             } else if id.name == "stdout:putc" {
-                // FIXME:
-                None
+                Some(1 + text_count(crate::PUTC_STR) + text_count(crate::FLUSH_STR))
             } else {
-                let func = *list.get(id).unwrap();
+                // Nothing is known about a function that isn't in the list
+                let func = *list.get(id)?;
                 Some(1 + estimate_total_count(list, func_starts, func)?)
             }
         }
@@ -130,6 +100,17 @@ fn estimate_count(
     }
 }
 
+/// An upper bound on the commands run by the source of a function that isn't parsed,
+/// assuming every `run` is taken
+fn text_count(source: &str) -> usize {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| 1 + l.matches(" run ").count())
+        .sum()
+}
+
 pub(crate) fn estimate_total_count(
     list: &HashMap<FunctionId, &Function>,
     func_starts: &HashMap<String, FunctionId>,
@@ -145,6 +126,15 @@ fn estimate_total_count_inner(
     func_starts: &HashMap<String, FunctionId>,
     block: &Function,
 ) -> Option<usize> {
+    if crate::compile_ir::is_dispatch(&block.id) {
+        // Only one branch of these gets taken
+        let mut longest = 0;
+        for cmd in block.cmds.iter() {
+            longest = longest.max(estimate_count(visited, list, func_starts, cmd)?);
+        }
+        return Some(block.cmds.len() - 1 + longest);
+    }
+
     let mut total = 0;
     for cmd in block.cmds.iter() {
         total += estimate_count(visited, list, func_starts, cmd)?;
@@ -152,100 +142,6 @@ fn estimate_total_count_inner(
     Some(total)
 }
 
-pub(crate) fn has_call_cycle(funcs: &[&Function], index: usize) -> bool {
-    let mut visited = HashSet::new();
-    has_call_cycle_inner(funcs, index, &mut visited)
-}
-
-fn has_call_cycle_inner(funcs: &[&Function], index: usize, visited: &mut HashSet<usize>) -> bool {
-    for cmd in funcs[index].cmds.iter() {
-        if let Command::FuncCall(FuncCall { id }) = cmd {
-            let callee_idx = funcs.iter().enumerate().find(|f| &f.1.id == id).unwrap().0;
-            if visited.contains(&callee_idx) {
-                return true;
-            } else {
-                visited.insert(callee_idx);
-                if has_call_cycle_inner(funcs, callee_idx, visited) {
-                    return true;
-                }
-            }
-        }
-    }
-
-    false
-}
-
-/*#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn no_cycle() {
-        let funcs = vec![
-            Function {
-                id: FunctionId::new("foo"),
-                cmds: vec![Command::FuncCall(FuncCall { id: FunctionId::new("bar") })]
-            },
-            Function {
-                id: FunctionId::new("bar"),
-                cmds: vec![]
-            },
-        ];
-
-        assert_eq!(has_call_cycle(&funcs, 0), false);
-        assert_eq!(has_call_cycle(&funcs, 1), false);
-    }
-
-    #[test]
-    fn has_cycle() {
-        let funcs = vec![
-            Function {
-                id: FunctionId::new("foo"),
-                cmds: vec![Command::FuncCall(FuncCall { id: FunctionId::new("bar") })]
-            },
-            Function {
-                id: FunctionId::new("bar"),
-                cmds: vec![Command::FuncCall(FuncCall { id: FunctionId::new("foo") })]
-            },
-        ];
-
-        assert_eq!(has_call_cycle(&funcs, 0), true);
-        assert_eq!(has_call_cycle(&funcs, 1), true);
-    }
-
-    #[test]
-    fn has_loop() {
-        let funcs = vec![
-            Function {
-                id: FunctionId::new("foo"),
-                cmds: vec![Command::FuncCall(FuncCall { id: FunctionId::new("foo") })]
-            },
-        ];
-
-        assert_eq!(has_call_cycle(&funcs, 0), true);
-    }
-}*/
-
-/*struct CountList(HashMap<FunctionId, Option<usize>>);
-
-impl CountList {
-    fn get(&self, id: &FunctionId) -> Option<usize> {
-        *self.0.get(id).unwrap()
-    }
-
-    fn new(funcs: &[AbstractBlock], func_starts: &HashMap<String, FunctionId>, list: &HashMap<FunctionId, AbstractBlock>) -> Self {
-        let mut s = CountList(HashMap::new());
-
-        for block in funcs {
-            s.0.insert(block.body.id.clone(), estimate_total_count(&list, func_starts, &block));
-        }
-
-        s
-    }
-}*/
-
-pub(crate) type BlockTree<'a> = (DiGraph<ChainNode<'a>, BlockEdge>, NodeIndex<u32>);
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum BlockEdge {
     None,
@@ -304,141 +200,108 @@ impl BlockEdge {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) struct ChainNode<'a> {
-    pub block: &'a AbstractBlock,
-    pub cmd_count: Option<usize>,
-    pub depth: usize,
-}
-
-/*
-// this returns a tree, but petgraph is really nice
-fn build_call_chain<'a>(funcs: &HashMap<&FunctionId, &'a AbstractBlock>, block: &'a AbstractBlock, func_starts: &HashMap<String, FunctionId>, counts: &CountList) -> BlockTree<'a> {
-    let mut result = Graph::new();
-
-    let root_cmds = counts.get(&block.body.id);
-    let head = result.add_node(ChainNode {
-        block,
-        cmd_count: root_cmds,
-        depth: 0,
-    });
-    let mut externals = HashSet::new();
-    if root_cmds.is_some() {
-        externals.insert(head);
+/// A block only gets fused with the blocks after it if all of them together take at most this many commands,
+/// which are run before anything checks `%%cmdcount` against `%%CMD_LIMIT` again
+const MAX_FUSED_COMMANDS: usize = 1_000;
+
+/// Picks the blocks that are branched to with a direct `function` call, running them right away
+/// instead of through the next command block. Returns the blocks each block branches to that way.
+///
+/// Only edges to a later LLVM basic block of the same function are fused, so that following
+/// fused edges always ends. Both sides of a conditional branch (and any of the cases of a switch)
+/// can be fused, which is how a whole acyclic region runs in one go, and since only one of them
+/// is taken, the longest one is what has to fit. The default case of a switch is never fused.
+pub(crate) fn fuse_blocks(blocks: &[AbstractBlock], globals: &GlobalVarList, tys: &Types) -> HashMap<FunctionId, Vec<FunctionId>> {
+    let index = blocks.iter().map(|b| (b.body.id.clone(), b)).collect::<HashMap<_, _>>();
+
+    let mut fused = HashMap::new();
+    let mut counts = HashMap::new();
+    for block in blocks.iter() {
+        fused_count(block, &index, globals, tys, &mut fused, &mut counts);
     }
 
+    fused
+}
 
-    let mut changed = true;
-    while changed {
-        changed = false;
+/// The commands run by `block` and the blocks it's fused with, deciding which those are if needed
+fn fused_count(
+    block: &AbstractBlock,
+    index: &HashMap<FunctionId, &AbstractBlock>,
+    globals: &GlobalVarList,
+    tys: &Types,
+    fused: &mut HashMap<FunctionId, Vec<FunctionId>>,
+    counts: &mut HashMap<FunctionId, Option<usize>>,
+) -> Option<usize> {
+    if let Some(count) = counts.get(&block.body.id) {
+        return *count;
+    }
 
-        println!("External count: {}", externals.len());
-        let ext = externals.iter().copied().collect::<Vec<_>>();
-        for leaf in ext {
-            let parent_cmds = result[leaf].cmd_count.unwrap();
+    let count = fused_count_inner(block, index, globals, tys, fused, counts);
+    counts.insert(block.body.id.clone(), count);
+    count
+}
 
-            let new_depth = result[leaf].depth + 1;
+fn fused_count_inner(
+    block: &AbstractBlock,
+    index: &HashMap<FunctionId, &AbstractBlock>,
+    globals: &GlobalVarList,
+    tys: &Types,
+    fused: &mut HashMap<FunctionId, Vec<FunctionId>>,
+    counts: &mut HashMap<FunctionId, Option<usize>>,
+) -> Option<usize> {
+    let mut cmds = block.body.cmds.clone();
+    if block.needs_prolog {
+        cmds.extend(load_params(&block.parent, tys).ok()?);
+    }
 
-            if new_depth > MAX_TREE_DEPTH {
-                externals.remove(&leaf);
-                continue;
+    let dests = match &block.term {
+        Some(BlockEnd::Normal(term)) => match compile_terminator(&block.parent, term, globals, tys).ok()? {
+            (tail, Either::Left(dests)) => {
+                cmds.extend(tail);
+                dests
             }
-
-            if let Some(dest) = result[leaf].block.get_dest(func_starts) {
-                if dest.name.contains("intrinsic") {
-                    // TODO:
-                } else {
-                    let dest = funcs.get(&dest).unwrap_or_else(|| panic!("{}", dest));
-
-                    if let Some(new_cmds) = counts.get(&dest.body.id) {
-                        let inline_ok =
-                            parent_cmds + new_cmds < MAX_INLINE_COMMANDS &&
-                            dest.body.id != result[leaf].block.body.id;
-                            // && !would_cause_duplicate(&result, head, dest, func_starts);
-
-                        if inline_ok {
-                            assert!(externals.remove(&leaf));
-
-                            //println!("{} -> {}", result[leaf].block.body.id, dest.body.id);
-                            //println!("count is now {}", parent_cmds + new_cmds);
-
-                            //result[leaf].block.term = None;
-                            let dest_node_idx = result.add_node(ChainNode { block: dest, cmd_count: Some(parent_cmds + new_cmds), depth: new_depth });
-                            result.add_edge(leaf, dest_node_idx, BlockEdge::None);
-                            assert!(externals.insert(dest_node_idx));
-
-
-                            changed = true;
-                        }
-                    }
-                }
+            (tail, Either::Right(_)) => {
+                cmds.extend(tail);
+                Vec::new()
             }
+        },
+        _ => Vec::new(),
+    };
+
+    // Checking the command count and picking the next command block for each edge
+    let own = estimate_total_count(&HashMap::new(), &HashMap::new(), &Function::new(block.body.id.clone(), cmds))? + 6 + 4 * dests.len();
+
+    let position = |id: &FunctionId| block.parent.basic_blocks.iter().position(|b| b.name == id.block);
+    let start = match position(&block.body.id) {
+        Some(start) => start,
+        None => return Some(own),
+    };
+
+    let mut longest = 0;
+    let mut fused_dests = Vec::new();
+    for (edge, dest) in dests.iter() {
+        let later = position(dest).is_some_and(|p| p > start);
+        let single_cond = !matches!(edge, BlockEdge::SwitchDefault { .. });
+        if !later || !single_cond {
+            continue;
+        }
 
-            if !changed {
-                if let Some((c, true_dest, false_dest)) = result[leaf].block.get_cond_dest() {
-                    let true_dest = funcs.get(&true_dest).unwrap_or_else(|| panic!("{}",  true_dest));
-                    let false_dest = funcs.get(&false_dest).unwrap_or_else(|| panic!("{}", false_dest));
-
-                    if let Some(true_cmds) = counts.get(&true_dest.body.id) {
-                        if let Some(false_cmds) = counts.get(&false_dest.body.id) {
-                            let new_cmds = true_cmds.max(false_cmds);
-                            if parent_cmds + new_cmds < MAX_INLINE_COMMANDS {
-                                assert!(externals.remove(&leaf));
-
-                                //println!("{} -> {}\n\t -> {}", result[leaf].block.body.id, true_dest.body.id, false_dest.body.id);
-                                //println!("count is now {}", parent_cmds + new_cmds);
-
-                                //result[leaf].block.term = None;
-
-                                let true_idx = result.add_node(ChainNode { block: true_dest, cmd_count: Some(parent_cmds + new_cmds), depth: new_depth });
-                                result.add_edge(leaf, true_idx, BlockEdge::Cond { value: c.clone(), inverted: false });
-                                assert!(externals.insert(true_idx));
-
-                                let false_idx = result.add_node(ChainNode { block: false_dest, cmd_count: Some(parent_cmds + new_cmds), depth: new_depth });
-                                result.add_edge(leaf, false_idx, BlockEdge::Cond { value: c.clone(), inverted: true });
-                                assert!(externals.insert(false_idx));
+        let dest_block = match index.get(dest) {
+            Some(b) => *b,
+            None => continue,
+        };
 
-                                changed = true;
-                            }
-                        }
-                    }
-                }
+        if let Some(count) = fused_count(dest_block, index, globals, tys, fused, counts) {
+            if own + longest.max(count) <= MAX_FUSED_COMMANDS {
+                longest = longest.max(count);
+                fused_dests.push(dest.clone());
             }
         }
     }
 
-    println!("Finished one");
+    if !fused_dests.is_empty() {
+        fused.insert(block.body.id.clone(), fused_dests);
+    }
 
-    (result, head)
+    Some(own + longest)
 }
-*/
-
-/*pub(crate) fn build_call_chains<'a>(funcs: &'a [AbstractBlock], func_starts: &HashMap<String, FunctionId>) -> Vec<BlockTree<'a>> {
-    /*
-        If leaf ends in a direct branch to tail:
-            if total cmds okay and leaf != tail:
-                append tail to leaf
-        
-        If leaf ends in a cond branch to tail1 or tail2:
-            if total cmds okay and the path from root to leaf contains neither `tail1` nor `tail2`:
-                append tail1 and tail2 to leaf
-    */
-
-    let list = funcs.iter().map(|f| (f.body.id.clone(), f.clone())).collect();
-    let counts = CountList::new(funcs, func_starts, &list);
-
-    println!("Building chains out of {} MC basic blocks", funcs.len());
-
-    let funcs = funcs.iter().map(|f| (&f.body.id, f)).collect::<HashMap<&FunctionId, &AbstractBlock>>();
-
-    let graphs = funcs.iter().map(|(_, f)| build_call_chain(&funcs, f, func_starts, &counts)).collect::<Vec<_>>();
-
-    println!("Max graph nodes: {}", graphs.iter().map(|g| g.0.node_count()).max().unwrap());
-
-    /*for g in &graphs {
-        println!("{:?}", g);
-    }*/
-
-    graphs
-}*/
-
@@ -1,5 +1,5 @@
 use langcraft::interpreter::InterpError;
-use langcraft::{Datapack, Interpreter, BuildOptions};
+use langcraft::{Datapack, Interpreter, BuildOptions, MemoryBackend, MemoryLayout};
 use std::path::PathBuf;
 
 // TODO: Allow specifying breakpoints somehow
@@ -21,8 +21,8 @@ fn run_interpreter(interp: &mut Interpreter) -> Result<(), Box<dyn std::error::E
                 eprintln!("Continuing");
                 hit_breakpoint = false;
             } else if input == "BTRACE" {
-                for (f, c) in interp.call_stack() {
-                    eprintln!("{} line {}", f.id, f.get_line(c));
+                for frame in interp.backtrace() {
+                    eprintln!("{}", frame);
                 }
                 eprintln!();
             } else if input.starts_with("REG") {
@@ -156,8 +156,8 @@ pub struct Options {
     pub interpret: bool,
     /// Compare output
     pub compare: bool,
-    /// The path to the bitcode file to compile
-    pub bc_path: PathBuf,
+    /// The paths to the bitcode or textual IR files to link together and compile
+    pub bc_paths: Vec<PathBuf>,
     pub output_folder: PathBuf,
     pub build_opts: BuildOptions,
 }
@@ -167,8 +167,16 @@ fn parse_arguments() -> Result<Options, String> {
     let mut compare = false;
     let mut trace_bbs = false;
     let mut force_input = false;
-    let mut bc_path = None;
+    let mut bc_paths = Vec::new();
     let mut output_folder = None;
+    let mut library = None;
+    let mut memory = MemoryLayout::default();
+    let mut memory_backend = MemoryBackend::default();
+    let mut heap = None;
+    let mut stack_check = false;
+    let mut check_pointers = false;
+    let mut mmio = None;
+    let mut opt_level = 0;
 
     let args = std::env::args().skip(1);
 
@@ -178,19 +186,46 @@ fn parse_arguments() -> Result<Options, String> {
                 interpret = true
             } else if arg == "--trace-bbs" {
                 trace_bbs = true;
+            } else if arg == "--stack-check" {
+                stack_check = true;
+            } else if arg == "--check-pointers" {
+                check_pointers = true;
+            } else if arg == "--mmio" {
+                mmio = Some(langcraft::mmio::DEFAULT_BASE);
+            } else if let Some(addr) = arg.strip_prefix("--mmio=") {
+                let parsed = if let Some(hex) = addr.strip_prefix("0x") {
+                    u32::from_str_radix(&hex.replace('_', ""), 16)
+                } else {
+                    addr.parse()
+                };
+                mmio = Some(parsed.map_err(|_| format!("invalid MMIO address `{}`", addr))?);
+            } else if arg == "-O" {
+                opt_level = 2;
+            } else if let Some(level) = arg.strip_prefix("-O") {
+                opt_level = match level.parse() {
+                    Ok(level) if level <= 2 => level,
+                    _ => return Err(format!("invalid optimization level `{}`", level)),
+                };
             } else if arg == "--compare" {
                 compare = true;
-            } else if arg.starts_with("--out=") {
+            } else if let Some(tail) = arg.strip_prefix("--out=") {
                 if output_folder.is_none() {
-                    let tail = &arg["--out=".len()..];
                     output_folder = Some(PathBuf::from(tail));
                 } else {
                     return Err(String::from("at most one `--out` argument may be specified"));
                 }
+            } else if let Some(name) = arg.strip_prefix("--lib=") {
+                library = Some(name.to_string());
+            } else if let Some(size) = arg.strip_prefix("--memory=") {
+                memory = size.parse()?;
+            } else if let Some(size) = arg.strip_prefix("--heap=") {
+                heap = Some(size.parse().map_err(|_| format!("invalid heap size `{}`", size))?);
+            } else if let Some(backend) = arg.strip_prefix("--memory-backend=") {
+                memory_backend = backend.parse()?;
             } else if arg == "--help" {
                 // give help text then exit
                 println!("Usage: langcraft [OPTION]... [FILE]...");
-                println!("Convert an LLVM bitcode file to a Minecraft datapack");
+                println!("Link LLVM bitcode or textual IR files into a Minecraft datapack");
                 println!();
                 println!("Options:");
                 println!("\t--help          display this help message");
@@ -198,6 +233,15 @@ fn parse_arguments() -> Result<Options, String> {
                 println!("\t--run           run the command interpreter on the generated code");
                 println!("\t--compare       compare the interpreter output to latest.log");
                 println!("\t--trace-bbs     insert a print command at the beginning of each LLVM basic block");
+                println!("\t--lib=NAMESPACE build a library exporting each external function as `NAMESPACE:api/<name>` instead of running `main`");
+                println!("\t--memory=XxYxZ  set the size in blocks of the memory area, one word per block (default is `128x16x16`)");
+                println!("\t--memory-backend=jukebox|storage");
+                println!("\t                keep memory in jukeboxes or in `data storage` (default is `jukebox`)");
+                println!("\t--heap=BYTES    reserve BYTES at the top of memory for `malloc` (default is a quarter of memory)");
+                println!("\t--stack-check   report a stack overflow when the stack grows into the heap");
+                println!("\t--check-pointers report null, out of bounds, or misaligned pointers when they're used");
+                println!("\t--mmio[=ADDR]   map I/O ports at ADDR (default is `0xFFFF0000`)");
+                println!("\t-O0, -O1, -O2   optimize the generated commands, where `-O` is `-O2` (default is `-O0`)");
                 std::process::exit(0);
             } else if arg == "--" {
                 // force potential options to be arguments
@@ -206,30 +250,39 @@ fn parse_arguments() -> Result<Options, String> {
                 return Err(format!("invalid option `{}`",arg));
             }
         } else {
-            // The non-option argument is a path
-            if bc_path.is_none() {
-                bc_path = Some(PathBuf::from(arg));
-            } else {
-                return Err("only one input file may be specified".into());
-            }
+            // Every non-option argument is an input path
+            bc_paths.push(PathBuf::from(arg));
         }
     }
 
     let output_folder = output_folder.unwrap_or_else(|| PathBuf::from("out/"));
 
-    let bc_path = bc_path.ok_or("no input file was specified")?;
+    if bc_paths.is_empty() {
+        return Err("no input file was specified".into());
+    }
 
     if compare && !interpret {
         return Err(String::from("the `--compare` option requires `--run`"));
     }
 
+    if library.is_some() && interpret {
+        return Err(String::from("the `--run` option cannot be used with `--lib`"));
+    }
+
     Ok(Options {
         interpret,
         compare,
-        bc_path,
+        bc_paths,
         output_folder,
         build_opts: BuildOptions {
             trace_bbs,
+            library,
+            memory: memory.with_backend(memory_backend),
+            heap,
+            stack_check,
+            check_pointers,
+            mmio,
+            opt_level,
         }
     })
 }
@@ -265,7 +318,7 @@ fn main() {
         std::process::exit(1);
     }
 
-    let datapack = Datapack::from_bc(&options.bc_path, &options.build_opts).unwrap_or_else(|err| {
+    let datapack = Datapack::from_files(&options.bc_paths, &options.build_opts).unwrap_or_else(|err| {
         eprintln!("error when compiling: {}", err);
         std::process::exit(1);
     });
@@ -279,6 +332,10 @@ fn main() {
             .sum::<usize>()
     );
 
+    if let Some(report) = &datapack.opt_report {
+        println!("{}", report);
+    }
+
     datapack.save(&options.output_folder).unwrap_or_else(|err| {
         eprintln!("error when saving datapack: {}", err);
         std::process::exit(1);
@@ -298,6 +355,15 @@ fn main() {
                 eprintln!("==== End output ====");
                 eprintln!("Program finished normally in {} ticks", interp.tick);
 
+                match interp.heap_usage() {
+                    Ok(Some(usage)) if usage.used_blocks > 0 => {
+                        eprintln!("Leaked {} bytes in {} heap allocations", usage.used_bytes, usage.used_blocks);
+                    }
+                    Ok(Some(usage)) => eprintln!("All heap allocations were freed ({} bytes free)", usage.free_bytes),
+                    Ok(None) => {}
+                    Err(err) => eprintln!("{}", err),
+                }
+
                 if options.compare {
                     compare_output(&interp);
                 }
@@ -310,10 +376,17 @@ fn main() {
                 }
                 eprintln!("=== End output ===");
                 eprintln!("=== Call stack ===");
-                for (f, c) in interp.call_stack() {
-                    eprintln!("{} line {}", f.id, f.get_line(c));
+                for frame in interp.backtrace() {
+                    eprintln!("{}", frame);
                 }
                 eprintln!("=== End call stack ===");
+                if !interp.unwind_trace.is_empty() {
+                    eprintln!("=== Unwinding ===");
+                    for step in interp.unwind_trace.iter() {
+                        eprintln!("{}", step);
+                    }
+                    eprintln!("=== End unwinding ===");
+                }
                 eprintln!("Encountered interpreter error: {}", err);
             }
         }
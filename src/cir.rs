@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 use llvm_ir::Name;
 pub use raw_text::*;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::{RangeFrom, RangeInclusive, RangeToInclusive};
@@ -527,21 +527,49 @@ impl fmt::Display for FunctionId {
     }
 }
 
+/// A location in the original source code, taken from LLVM debug info
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SourceLoc {
+    pub file: String,
+    pub line: u32,
+    pub col: Option<u32>,
+}
+
+impl fmt::Display for SourceLoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(col) = self.col {
+            write!(f, ":{}", col)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Function {
     pub id: FunctionId,
     pub cmds: Vec<Command>,
+    /// Maps the index of a command to the source location it was generated from
+    pub locs: BTreeMap<usize, SourceLoc>,
 }
 
 impl Function {
-    pub fn from_str(id: FunctionId, cmds: &str) -> Result<Self, ()> {
+    pub fn new(id: FunctionId, cmds: Vec<Command>) -> Self {
+        Function { id, cmds, locs: BTreeMap::new() }
+    }
+
+    pub fn from_str(id: FunctionId, cmds: &str) -> Result<Self, String> {
         let cmds = cmds
             .lines()
             .filter(|l| !l.is_empty())
             .map(|l| l.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(Function { id, cmds })
+        Ok(Function::new(id, cmds))
+    }
+
+    pub fn source_loc(&self, idx: usize) -> Option<&SourceLoc> {
+        self.locs.get(&idx)
     }
 
     pub fn get_line(&self, idx: usize) -> usize {
@@ -686,6 +714,20 @@ impl Execute {
 
         result
     }
+
+    pub fn holders_mut(&mut self) -> Vec<&mut ScoreHolder> {
+        let mut result = Vec::new();
+
+        for subcmd in self.subcommands.iter_mut() {
+            result.extend(subcmd.holders_mut());
+        }
+
+        if let Some(run) = &mut self.run {
+            result.extend(run.holders_mut());
+        }
+
+        result
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -753,6 +795,17 @@ impl ExecuteSubCmd {
             Self::At { .. } | Self::As { .. } => HashMap::new(),
         }
     }
+
+    pub fn holders_mut(&mut self) -> Vec<&mut ScoreHolder> {
+        match self {
+            Self::Condition { cond, .. } => cond.holders_mut(),
+            Self::Store {
+                kind: ExecuteStoreKind::Score { target: Target::Uuid(target), .. },
+                ..
+            } => vec![target],
+            Self::Store { .. } | Self::Positioned { .. } | Self::At { .. } | Self::As { .. } => Vec::new(),
+        }
+    }
 }
 
 impl fmt::Display for ExecuteSubCmd {
@@ -826,11 +879,29 @@ pub enum ExecuteCondition {
     },
 }
 
+impl ExecuteCondition {
+    pub fn holders_mut(&mut self) -> Vec<&mut ScoreHolder> {
+        let mut result = Vec::new();
+
+        if let ExecuteCondition::Score { target, kind, .. } = self {
+            if let Target::Uuid(target) = target {
+                result.push(target);
+            }
+
+            if let ExecuteCondKind::Relation { source: Target::Uuid(source), .. } = kind {
+                result.push(source);
+            }
+        }
+
+        result
+    }
+}
+
 impl FromStr for ExecuteCondition {
-    type Err = ();
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(CommandParser { tail: s }.parse_execute_cond())
+        CommandParser { tail: s }.parse_execute_cond()
     }
 }
 
@@ -923,6 +994,7 @@ pub enum Command {
     Data(Data),
     Tellraw(Box<Tellraw>),
     Teleport(Teleport),
+    TimeQuery(TimeQuery),
     Comment(String),
 }
 
@@ -940,6 +1012,7 @@ impl Command {
             | Self::SetBlock(_)
             | Self::FuncCall(_)
             | Self::Teleport(_)
+            | Self::TimeQuery(_)
             | Self::Fill(_)
             | Self::CloneCmd(_)
             | Self::ObjRemove(_)
@@ -947,6 +1020,28 @@ impl Command {
             | Self::Comment(_) => HashMap::new(),
         }
     }
+
+    /// Every score holder that `holder_uses` reports, so they can be renamed
+    pub fn holders_mut(&mut self) -> Vec<&mut ScoreHolder> {
+        match self {
+            Self::ScoreOp(ScoreOp { target, source, .. }) => {
+                let mut result = Vec::new();
+                if let Target::Uuid(target) = target {
+                    result.push(target);
+                }
+                if let Target::Uuid(source) = source {
+                    result.push(source);
+                }
+                result
+            }
+            Self::ScoreSet(ScoreSet { target: Target::Uuid(target), .. })
+            | Self::ScoreGet(ScoreGet { target: Target::Uuid(target), .. })
+            | Self::ScoreAdd(ScoreAdd { target: Target::Uuid(target), .. }) => vec![target],
+            Self::Execute(c) => c.holders_mut(),
+            Self::Tellraw(c) => c.message.iter_mut().flat_map(|m| m.holders_mut()).collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -960,8 +1055,16 @@ struct CommandParser<'a> {
     tail: &'a str,
 }
 
-impl CommandParser<'_> {
-    pub fn next_word(&mut self) -> Option<&str> {
+/// The message for a word that `CommandParser` didn't expect, or for running out of words
+fn unexpected(word: Option<&str>) -> String {
+    match word {
+        Some(word) => format!("unexpected `{}`", word),
+        None => "unexpected end of command".to_string(),
+    }
+}
+
+impl<'a> CommandParser<'a> {
+    pub fn next_word(&mut self) -> Option<&'a str> {
         if self.tail.is_empty() {
             return None;
         }
@@ -975,7 +1078,7 @@ impl CommandParser<'_> {
         }
     }
 
-    pub fn peek_word(&mut self) -> Option<&str> {
+    pub fn peek_word(&mut self) -> Option<&'a str> {
         if self.tail.is_empty() {
             None
         } else if let Some(idx) = self.tail.find(char::is_whitespace) {
@@ -985,80 +1088,91 @@ impl CommandParser<'_> {
         }
     }
 
-    pub fn parse(&mut self) -> Command {
+    /// Like `next_word`, but running out of words is an error
+    pub fn expect_word(&mut self) -> Result<&'a str, String> {
+        self.next_word().ok_or_else(|| unexpected(None))
+    }
+
+    /// Parses the next word as a `T`
+    pub fn parse_word<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.expect_word()?;
+        word.parse().map_err(|_| unexpected(Some(word)))
+    }
+
+    pub fn parse(&mut self) -> Result<Command, String> {
         match self.next_word() {
-            Some("#") => Command::Comment(self.tail.into()),
+            Some("#") => Ok(Command::Comment(std::mem::take(&mut self.tail).into())),
             Some("scoreboard") => self.parse_scoreboard(),
             Some("execute") => self.parse_execute(),
-            Some("function") => FuncCall {
-                id: self.tail.parse().unwrap(),
+            Some("function") => Ok(FuncCall {
+                id: std::mem::take(&mut self.tail).parse()?,
             }
-            .into(),
+            .into()),
             Some("tellraw") => self.parse_tellraw(),
             Some("data") => self.parse_data(),
             Some("tp") => self.parse_teleport(),
             Some("fill") => self.parse_fill(),
             Some("clone") => self.parse_clone(),
             Some("setblock") => self.parse_setblock(),
-            Some("kill") => Kill(self.next_word().unwrap().parse().unwrap()).into(),
-            nw => todo!("{:?}", nw),
+            Some("kill") => Ok(Kill(self.parse_word()?).into()),
+            Some("time") => match self.next_word() {
+                Some("query") => Ok(self.parse_word::<TimeQuery>()?.into()),
+                nw => Err(unexpected(nw)),
+            },
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_clone(&mut self) -> Command {
-        let start = self.parse_pos();
-        let end = self.parse_pos();
-        let dest = self.parse_pos();
-        CloneCmd { start, end, dest }.into()
+    pub fn parse_clone(&mut self) -> Result<Command, String> {
+        let start = self.parse_pos()?;
+        let end = self.parse_pos()?;
+        let dest = self.parse_pos()?;
+        Ok(CloneCmd { start, end, dest }.into())
     }
 
-    pub fn parse_fill(&mut self) -> Command {
-        let start = self.parse_pos();
-        let end = self.parse_pos();
-        let block = self.next_word().unwrap().to_owned();
-        Fill { start, end, block }.into()
+    pub fn parse_fill(&mut self) -> Result<Command, String> {
+        let start = self.parse_pos()?;
+        let end = self.parse_pos()?;
+        let block = self.expect_word()?.to_owned();
+        Ok(Fill { start, end, block }.into())
     }
 
-    pub fn parse_setblock(&mut self) -> Command {
-        let pos = self.parse_pos();
-        let block = self.next_word().unwrap().to_owned();
-        let kind = self
-            .next_word()
-            .map(|w| w.parse().unwrap())
-            .unwrap_or(SetBlockKind::Replace);
-        SetBlock { pos, block, kind }.into()
+    pub fn parse_setblock(&mut self) -> Result<Command, String> {
+        let pos = self.parse_pos()?;
+        let block = self.expect_word()?.to_owned();
+        let kind = match self.peek_word() {
+            Some(_) => self.parse_word()?,
+            None => SetBlockKind::Replace,
+        };
+        Ok(SetBlock { pos, block, kind }.into())
     }
 
-    pub fn parse_teleport(&mut self) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let pos = self.parse_pos();
-        Teleport { target, pos }.into()
+    pub fn parse_teleport(&mut self) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let pos = self.parse_pos()?;
+        Ok(Teleport { target, pos }.into())
     }
 
-    pub fn parse_data(&mut self) -> Command {
-        let is_get = match self.next_word() {
-            Some("get") => true,
-            nw => todo!("{:?}", nw),
-        };
-        let target = self.parse_data_target();
-        let kind = if is_get {
-            let path = self.next_word().unwrap().to_owned();
-            let scale = self.next_word().unwrap().parse::<f32>().unwrap();
-            DataKind::Get { path, scale }
-        } else {
-            todo!()
-        };
+    pub fn parse_data(&mut self) -> Result<Command, String> {
+        match self.next_word() {
+            Some("get") => {}
+            nw => return Err(unexpected(nw)),
+        }
+        let target = self.parse_data_target()?;
+        let path = self.expect_word()?.to_owned();
+        let scale = self.parse_word::<f32>()?;
+        let kind = DataKind::Get { path, scale };
 
-        Data { target, kind }.into()
+        Ok(Data { target, kind }.into())
     }
 
-    pub fn parse_tellraw(&mut self) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let message = serde_json::from_str(self.tail).unwrap();
-        Tellraw { target, message }.into()
+    pub fn parse_tellraw(&mut self) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let message = serde_json::from_str(std::mem::take(&mut self.tail)).map_err(|e| e.to_string())?;
+        Ok(Tellraw { target, message }.into())
     }
 
-    pub fn parse_execute(&mut self) -> Command {
+    pub fn parse_execute(&mut self) -> Result<Command, String> {
         let mut cmd = Execute::new();
 
         loop {
@@ -1066,57 +1180,57 @@ impl CommandParser<'_> {
                 break;
             }
 
-            cmd.with_subcmd(self.parse_execute_subcmd());
+            cmd.with_subcmd(self.parse_execute_subcmd()?);
         }
 
         if self.next_word() == Some("run") {
-            cmd.with_run(self.parse());
+            cmd.with_run(self.parse()?);
         }
 
-        cmd.into()
+        Ok(cmd.into())
     }
 
-    pub fn parse_execute_subcmd(&mut self) -> ExecuteSubCmd {
+    pub fn parse_execute_subcmd(&mut self) -> Result<ExecuteSubCmd, String> {
         match self.next_word() {
-            Some("if") => ExecuteSubCmd::Condition {
+            Some("if") => Ok(ExecuteSubCmd::Condition {
                 is_unless: false,
-                cond: self.parse_execute_cond(),
-            },
-            Some("unless") => ExecuteSubCmd::Condition {
+                cond: self.parse_execute_cond()?,
+            }),
+            Some("unless") => Ok(ExecuteSubCmd::Condition {
                 is_unless: true,
-                cond: self.parse_execute_cond(),
-            },
-            Some("at") => ExecuteSubCmd::At {
-                target: self.next_word().unwrap().parse().unwrap(),
-            },
-            Some("as") => ExecuteSubCmd::As {
-                target: self.next_word().unwrap().parse().unwrap(),
-            },
+                cond: self.parse_execute_cond()?,
+            }),
+            Some("at") => Ok(ExecuteSubCmd::At {
+                target: self.parse_word()?,
+            }),
+            Some("as") => Ok(ExecuteSubCmd::As {
+                target: self.parse_word()?,
+            }),
             Some("store") => self.parse_execute_store(),
-            nw => todo!("{:?}", nw),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_execute_store(&mut self) -> ExecuteSubCmd {
+    pub fn parse_execute_store(&mut self) -> Result<ExecuteSubCmd, String> {
         let is_success = match self.next_word() {
             Some("result") => false,
             Some("success") => true,
-            nw => panic!("{:?}", nw),
+            nw => return Err(unexpected(nw)),
         };
 
         let kind = match self.peek_word() {
             Some("score") => {
                 self.next_word();
-                let target = self.next_word().unwrap().parse().unwrap();
-                let objective = self.next_word().unwrap().to_owned();
+                let target = self.parse_word()?;
+                let objective = self.expect_word()?.to_owned();
 
                 ExecuteStoreKind::Score { target, objective }
             }
             _ => {
-                let target = self.parse_data_target();
-                let path = self.next_word().unwrap().to_owned();
-                let ty = self.next_word().unwrap().to_owned();
-                let scale = self.next_word().unwrap().parse().unwrap();
+                let target = self.parse_data_target()?;
+                let path = self.expect_word()?.to_owned();
+                let ty = self.expect_word()?.to_owned();
+                let scale = self.parse_word()?;
 
                 ExecuteStoreKind::Data {
                     target,
@@ -1127,145 +1241,155 @@ impl CommandParser<'_> {
             }
         };
 
-        ExecuteSubCmd::Store { is_success, kind }
+        Ok(ExecuteSubCmd::Store { is_success, kind })
     }
 
-    pub fn parse_pos(&mut self) -> BlockPos {
+    pub fn parse_pos(&mut self) -> Result<BlockPos, String> {
         let mut coords = Vec::new();
-        coords.push(self.next_word().unwrap().to_string());
-        coords.push(self.next_word().unwrap().to_string());
-        coords.push(self.next_word().unwrap().to_string());
-        coords.join(" ")
+        coords.push(self.expect_word()?.to_string());
+        coords.push(self.expect_word()?.to_string());
+        coords.push(self.expect_word()?.to_string());
+        Ok(coords.join(" "))
     }
 
-    pub fn parse_data_target(&mut self) -> DataTarget {
+    pub fn parse_data_target(&mut self) -> Result<DataTarget, String> {
         match self.next_word() {
-            Some("block") => DataTarget::Block(self.parse_pos()),
+            Some("block") => Ok(DataTarget::Block(self.parse_pos()?)),
             Some("entity") => {
-                let target = self.next_word().unwrap().parse().unwrap();
+                let target = self.parse_word()?;
 
-                DataTarget::Entity(target)
+                Ok(DataTarget::Entity(target))
             }
-            nw => todo!("{:?}", nw),
+            Some("storage") => Ok(DataTarget::Storage(self.expect_word()?.to_owned())),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_execute_cond(&mut self) -> ExecuteCondition {
+    pub fn parse_execute_cond(&mut self) -> Result<ExecuteCondition, String> {
         match self.next_word() {
             Some("score") => {
-                let target = self.next_word().unwrap().parse().unwrap();
-                let target_obj = self.next_word().unwrap().to_owned();
+                let target = self.parse_word()?;
+                let target_obj = self.expect_word()?.to_owned();
                 let kind = match self.next_word() {
-                    Some("matches") => {
-                        ExecuteCondKind::Matches(self.next_word().unwrap().parse().unwrap())
-                    }
+                    Some("matches") => ExecuteCondKind::Matches(self.parse_word()?),
                     Some(s) => {
-                        let relation = s.parse().unwrap();
-                        let source = self.next_word().unwrap().parse().unwrap();
-                        let source_obj = self.next_word().unwrap().to_owned();
+                        let relation = s.parse()?;
+                        let source = self.parse_word()?;
+                        let source_obj = self.expect_word()?.to_owned();
                         ExecuteCondKind::Relation {
                             relation,
                             source,
                             source_obj,
                         }
                     }
-                    nw => todo!("{:?}", nw),
+                    nw => return Err(unexpected(nw)),
                 };
 
-                ExecuteCondition::Score {
+                Ok(ExecuteCondition::Score {
                     target,
                     target_obj,
                     kind,
-                }
+                })
             }
-            nw => todo!("{:?}", nw),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_scoreboard(&mut self) -> Command {
+    pub fn parse_scoreboard(&mut self) -> Result<Command, String> {
         match self.next_word() {
             Some("players") => self.parse_players(),
             Some("objectives") => self.parse_objectives(),
-            nw => todo!("{:?}", nw),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_objectives(&mut self) -> Command {
+    pub fn parse_objectives(&mut self) -> Result<Command, String> {
         match self.next_word() {
             Some("add") => {
-                let obj = self.next_word().unwrap().to_owned();
-                let criteria = self.next_word().unwrap().to_owned();
-                ObjAdd { obj, criteria }.into()
+                let obj = self.expect_word()?.to_owned();
+                let criteria = self.expect_word()?.to_owned();
+                Ok(ObjAdd { obj, criteria }.into())
             }
-            Some("remove") => ObjRemove(self.next_word().unwrap().to_owned()).into(),
-            nw => todo!("{:?}", nw),
+            Some("remove") => Ok(ObjRemove(self.expect_word()?.to_owned()).into()),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_players(&mut self) -> Command {
+    pub fn parse_players(&mut self) -> Result<Command, String> {
         match self.next_word() {
             Some("operation") => self.parse_operation(),
             Some("add") => self.parse_scoreboard_add(false),
             Some("remove") => self.parse_scoreboard_add(true),
             Some("set") => self.parse_scoreboard_set(),
             Some("get") => self.parse_scoreboard_get(),
-            nw => todo!("{:?}", nw),
+            nw => Err(unexpected(nw)),
         }
     }
 
-    pub fn parse_scoreboard_get(&mut self) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let target_obj = self.next_word().unwrap().to_owned();
-        ScoreGet { target, target_obj }.into()
+    pub fn parse_scoreboard_get(&mut self) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let target_obj = self.expect_word()?.to_owned();
+        Ok(ScoreGet { target, target_obj }.into())
     }
 
-    pub fn parse_scoreboard_set(&mut self) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let target_obj = self.next_word().unwrap().to_owned();
-        let score = self.next_word().unwrap().parse::<i32>().unwrap();
-        ScoreSet {
+    pub fn parse_scoreboard_set(&mut self) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let target_obj = self.expect_word()?.to_owned();
+        let score = self.parse_word::<i32>()?;
+        Ok(ScoreSet {
             target,
             target_obj,
             score,
         }
-        .into()
+        .into())
     }
 
-    pub fn parse_scoreboard_add(&mut self, is_remove: bool) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let target_obj = self.next_word().unwrap().to_owned();
-        let score = self.next_word().unwrap().parse::<i32>().unwrap();
+    pub fn parse_scoreboard_add(&mut self, is_remove: bool) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let target_obj = self.expect_word()?.to_owned();
+        let score = self.parse_word::<i32>()?;
         let score = if is_remove { -score } else { score };
-        ScoreAdd {
+        Ok(ScoreAdd {
             target,
             target_obj,
             score,
         }
-        .into()
+        .into())
     }
 
-    pub fn parse_operation(&mut self) -> Command {
-        let target = self.next_word().unwrap().parse().unwrap();
-        let target_obj = self.next_word().unwrap().to_owned();
-        let kind = self.next_word().unwrap().parse().unwrap();
-        let source = self.next_word().unwrap().parse().unwrap();
-        let source_obj = self.next_word().unwrap().to_owned();
-        ScoreOp {
+    pub fn parse_operation(&mut self) -> Result<Command, String> {
+        let target = self.parse_word()?;
+        let target_obj = self.expect_word()?.to_owned();
+        let kind = self.parse_word()?;
+        let source = self.parse_word()?;
+        let source_obj = self.expect_word()?.to_owned();
+        Ok(ScoreOp {
             target,
             target_obj,
             kind,
             source,
             source_obj,
         }
-        .into()
+        .into())
+    }
+
+    /// Fails if anything is left after the command
+    pub fn finish(&mut self) -> Result<(), String> {
+        match self.next_word() {
+            None => Ok(()),
+            nw => Err(unexpected(nw)),
+        }
     }
 }
 
 impl FromStr for Command {
-    type Err = ();
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(CommandParser { tail: s }.parse())
+        let mut parser = CommandParser { tail: s };
+        let cmd = parser.parse()?;
+        parser.finish()?;
+        Ok(cmd)
     }
 }
 
@@ -1287,6 +1411,7 @@ impl fmt::Display for Command {
             Command::Data(s) => s.fmt(f),
             Command::Tellraw(s) => s.fmt(f),
             Command::Teleport(s) => s.fmt(f),
+            Command::TimeQuery(s) => s.fmt(f),
             Command::Comment(s) => {
                 let mut commented = s.replace('\n', "\n# ");
                 commented.insert_str(0, "# ");
@@ -1459,6 +1584,47 @@ impl fmt::Display for Teleport {
     }
 }
 
+/// `time query`, which returns the requested time as its result
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
+pub enum TimeQuery {
+    /// The number of ticks the world has existed for
+    GameTime,
+    /// The time of day in ticks
+    DayTime,
+    Day,
+}
+
+impl fmt::Display for TimeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            TimeQuery::GameTime => "gametime",
+            TimeQuery::DayTime => "daytime",
+            TimeQuery::Day => "day",
+        };
+
+        write!(f, "time query {}", kind)
+    }
+}
+
+impl FromStr for TimeQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gametime" => Ok(TimeQuery::GameTime),
+            "daytime" => Ok(TimeQuery::DayTime),
+            "day" => Ok(TimeQuery::Day),
+            _ => Err(format!("invalid time query `{}`", s)),
+        }
+    }
+}
+
+impl From<TimeQuery> for Command {
+    fn from(t: TimeQuery) -> Self {
+        Command::TimeQuery(t)
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct Tellraw {
     pub target: Target,
@@ -1683,7 +1849,7 @@ impl ScoreOp {
             let target_use = if self.kind == ScoreOpKind::Assign {
                 HolderUse::WriteOnly
             } else {
-                HolderUse::ReadOnly
+                HolderUse::ReadWrite
             };
 
             merge_use(&mut result, target, target_use);
@@ -1814,22 +1980,26 @@ pub enum DataKind {
 pub enum DataModifyKind {
     // TODO: There's others
     Set,
+    Append,
 }
 
 impl fmt::Display for DataModifyKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Set => write!(f, "set"),
+            Self::Append => write!(f, "append"),
         }
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum DataModifySource {
-    // TODO: There's another
     // TODO: This can technically be other datatypes too, I think
     Value(i32),
     ValueString(String),
+    /// A value written out directly as SNBT, like `[I;0,0]`
+    ValueNbt(StringNbt),
+    From(DataTarget, NbtPath),
 }
 
 impl fmt::Display for DataModifySource {
@@ -1842,6 +2012,8 @@ impl fmt::Display for DataModifySource {
                 }
                 write!(f, "value {:?}", v)
             }
+            DataModifySource::ValueNbt(v) => write!(f, "value {}", v),
+            DataModifySource::From(target, path) => write!(f, "from {} {}", target, path),
         }
     }
 }
@@ -1851,6 +2023,7 @@ pub enum DataTarget {
     // TODO: More
     Block(BlockPos),
     Entity(Target),
+    Storage(StorageId),
 }
 
 impl fmt::Display for DataTarget {
@@ -1858,6 +2031,7 @@ impl fmt::Display for DataTarget {
         match self {
             DataTarget::Block(b) => write!(f, "block {}", b),
             DataTarget::Entity(e) => write!(f, "entity {}", e),
+            DataTarget::Storage(s) => write!(f, "storage {}", s),
         }
     }
 }
@@ -190,6 +190,25 @@ impl TextComponent {
 
         result
     }
+
+    pub fn holders_mut(&mut self) -> Vec<&mut ScoreHolder> {
+        let mut result = Vec::new();
+
+        if let Some(ScoreComponent {
+            name, value: None, ..
+        }) = &mut self.score
+        {
+            result.push(name);
+        }
+
+        if let Some(extra) = &mut self.extra {
+            for ex in extra {
+                result.extend(ex.holders_mut());
+            }
+        }
+
+        result
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
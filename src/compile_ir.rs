@@ -5,9 +5,13 @@ use crate::cir::FunctionId as McFuncId;
 use crate::cir::{
     self, Command, Data, DataKind, DataTarget, Execute, ExecuteCondKind, ExecuteCondition,
     ExecuteStoreKind, ExecuteSubCmd, ScoreAdd, ScoreGet, ScoreHolder, ScoreOp, ScoreOpKind,
-    ScoreSet, SetBlock, SetBlockKind, Target, Tellraw,
+    ScoreSet, SetBlock, SetBlockKind, SourceLoc, Target, Tellraw,
 };
+use crate::inline_asm;
+use crate::inliner::{self, Inline};
+use crate::regalloc::{self, Registers};
 use crate::interpreter::InterpError;
+use crate::optimize::OptReport;
 use either::Either;
 use lazy_static::lazy_static;
 use llvm_ir::constant::BitCast as BitCastConst;
@@ -15,21 +19,26 @@ use llvm_ir::constant::GetElementPtr as GetElementPtrConst;
 use llvm_ir::constant::ICmp as ICmpConst;
 use llvm_ir::constant::Select as SelectConst;
 use llvm_ir::constant::ConstantRef;
+use llvm_ir::constant::Float;
 use llvm_ir::instruction::{
-    Add, Alloca, And, AShr, BitCast, Call, ExtractElement, ExtractValue, GetElementPtr, ICmp,
-    InsertElement, InsertValue, IntToPtr, LShr, Load, Mul, Or, Phi, PtrToInt, SDiv, SExt, SRem,
-    Select, Shl, ShuffleVector, Store, Sub, Trunc, UDiv, URem, Xor, ZExt,
+    Add, Alloca, And, AShr, AtomicRMW, BitCast, Call, CmpXchg, ExtractElement, ExtractValue, FAdd,
+    FCmp, FDiv, FMul, FNeg, FPExt, FPToSI, FPToUI, FPTrunc, FSub, GetElementPtr, ICmp,
+    InsertElement, InsertValue, IntToPtr, LandingPad, LShr, Load, Mul, Or, Phi, PtrToInt, RMWBinOp,
+    SDiv, SExt, SIToFP, SRem, Select, Shl, ShuffleVector, Store, Sub, Trunc, UDiv, UIToFP, URem,
+    VAArg, Xor, ZExt,
 };
 use llvm_ir::function::ParameterAttribute;
-use llvm_ir::module::GlobalVariable;
-use llvm_ir::terminator::{Br, CondBr, Ret, Switch, Unreachable};
-use llvm_ir::types::{Typed, TypeRef, Types, NamedStructDef};
+use llvm_ir::module::{GlobalVariable, Linkage};
+use llvm_ir::terminator::{Br, CondBr, Invoke, Resume, Ret, Switch, Unreachable};
+use llvm_ir::types::{FPType, Typed, TypeRef, Types, NamedStructDef};
 use llvm_ir::{
-    Constant, Function, Instruction, IntPredicate, Module, Name, Operand, Terminator, Type,
+    Constant, DebugLoc, FPPredicate, Function, HasDebugLoc, Instruction, IntPredicate, Module, Name,
+    Operand, Terminator, Type,
 };
 use std::alloc::Layout;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::{TryFrom, TryInto};
+use std::ops::Range;
 use std::sync::Mutex;
 
 // FIXME: Alignment for Alloca, functions, and global variables
@@ -184,6 +193,21 @@ pub fn return_holder(word_index: usize) -> ScoreHolder {
     ScoreHolder::new(format!("%return%{}", word_index)).unwrap()
 }
 
+/// Holds the address of the first variadic argument in a variadic function
+pub fn varargs() -> ScoreHolder {
+    ScoreHolder::new("%varargs".to_string()).unwrap()
+}
+
+/// Set to 1 while an exception is unwinding the stack
+pub fn unwinding() -> ScoreHolder {
+    ScoreHolder::new("%%unwinding".to_string()).unwrap()
+}
+
+/// The value given to the last `resume`, which the next landing pad receives
+pub fn exception(word_index: usize) -> ScoreHolder {
+    ScoreHolder::new(format!("%%exception%{}", word_index)).unwrap()
+}
+
 pub fn print_entry(location: &McFuncId) -> Command {
     Tellraw {
         target: cir::Selector {
@@ -206,6 +230,31 @@ pub fn mark_unreachable() -> Command {
     Command::Comment("!INTERPRETER: UNREACHABLE".into())
 }
 
+/// Reports a stack overflow in `function` if `%stackptr` is greater than `max`
+pub fn check_stack(function: &str, max: i32) -> Vec<Command> {
+    let mut report = Execute::new();
+    report.with_if(ExecuteCondition::Score {
+        target: stackptr().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((max + 1..).into()),
+    });
+    report.with_run(Tellraw {
+        target: cir::Selector {
+            var: cir::SelectorVariable::AllPlayers,
+            args: Vec::new(),
+        }
+        .into(),
+        message: cir::TextBuilder::new()
+            .append_text(format!("stack overflow in {}", function))
+            .build(),
+    });
+
+    vec![
+        Command::Comment(format!("!INTERPRETER: STACK CHECK {} {}", max, function)),
+        report.into(),
+    ]
+}
+
 pub fn mark_assertion(is_unless: bool, cond: &ExecuteCondition) -> Command {
     let mut text = "!INTERPRETER: ASSERT ".to_string();
     if is_unless {
@@ -225,8 +274,8 @@ pub fn mark_assertion_matches<R: Into<cir::McRange>>(inverted: bool, score_holde
     })
 }
 
-// %ptr, %x, %y, %z, %param<X> are caller-saved registers
-// all other registers are callee-saved
+// all registers are caller-saved (see `crate::regalloc`),
+// except %stackbaseptr, which each function saves itself
 // %stackptr is... weird
 // %temp<X> are... weird
 
@@ -361,35 +410,351 @@ pub fn invert(target: ScoreHolder) -> Vec<Command> {
     ]
 }
 
-pub fn get_index(x: i32, y: i32, z: i32) -> Result<i32, InterpError> {
-    if 0 <= x && x < 128 && 0 <= y && y < 16 && 0 <= z && z < 16 {
-        Ok((x * 16 * 16 + y * 16 + z) * 4)
-    } else {
-        Err(InterpError::OutOfBoundsAccess(x, y, z))
+/// The most blocks a single `fill` or `clone` command may affect
+const MAX_REGION_BLOCKS: i32 = 32768;
+
+/// Where the words of memory are kept in the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryBackend {
+    /// A cuboid of jukeboxes, which must stay loaded
+    #[default]
+    Jukebox,
+    /// Int arrays in `data storage`, one per slice along X (see `crate::storage`)
+    Storage,
+}
+
+impl std::str::FromStr for MemoryBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jukebox" => Ok(MemoryBackend::Jukebox),
+            "storage" => Ok(MemoryBackend::Storage),
+            _ => Err(format!("unknown memory backend `{}` (expected `jukebox` or `storage`)", s)),
+        }
+    }
+}
+
+/// The size of the cuboid of jukeboxes (starting at 0 0 0) used as memory.
+/// Each jukebox holds one word, and consecutive words go along Z, then Y, then X.
+///
+/// With the storage backend, each slice along X becomes one int array instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub backend: MemoryBackend,
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        MemoryLayout { x: 128, y: 16, z: 16, backend: MemoryBackend::default() }
+    }
+}
+
+impl MemoryLayout {
+    pub fn new(x: i32, y: i32, z: i32) -> Result<Self, String> {
+        if x <= 0 || y <= 0 || z <= 0 {
+            return Err(format!("memory dimensions must be positive, got {}x{}x{}", x, y, z));
+        }
+
+        // Every slice along X gets copied with a single `clone`
+        if y > 256 || y * z > MAX_REGION_BLOCKS {
+            return Err(format!("memory slices of {}x{} are too large (at most 256 high and {} blocks)", y, z, MAX_REGION_BLOCKS));
+        }
+
+        if (x as i64) * (y as i64) * (z as i64) * 4 > i32::MAX as i64 {
+            return Err(format!("{}x{}x{} words do not fit in a 32-bit address space", x, y, z));
+        }
+
+        Ok(MemoryLayout { x, y, z, backend: MemoryBackend::default() })
+    }
+
+    pub fn with_backend(self, backend: MemoryBackend) -> Self {
+        MemoryLayout { backend, ..self }
+    }
+
+    pub fn words(&self) -> usize {
+        (self.x * self.y * self.z) as usize
+    }
+
+    pub fn bytes(&self) -> u32 {
+        self.words() as u32 * 4
+    }
+
+    pub fn get_index(&self, x: i32, y: i32, z: i32) -> Result<i32, InterpError> {
+        if 0 <= x && x < self.x && 0 <= y && y < self.y && 0 <= z && z < self.z {
+            Ok((x * self.y * self.z + y * self.z + z) * 4)
+        } else {
+            Err(InterpError::OutOfBoundsAccess(x, y, z))
+        }
+    }
+
+    /// Returns xyz of the word at `address`, which must be word-aligned
+    /// (unaligned constant accesses are split up by `word_parts` first)
+    pub fn get_address(&self, mut address: i32) -> (i32, i32, i32) {
+        assert_eq!(address % 4, 0, "unaligned address {:#X}", address);
+        address /= 4;
+
+        assert!(0 < address);
+        assert!((address as usize) < self.words());
+        let z = address % self.z;
+        address /= self.z;
+        let y = address % self.y;
+        address /= self.y;
+        let x = address % self.x;
+        (x, y, z)
+    }
+
+    /// The number of words in each slice along X
+    pub fn page_words(&self) -> i32 {
+        self.y * self.z
+    }
+
+    /// Commands for the setup function that clear the memory area and fill it with jukeboxes
+    pub fn build_cmds(&self) -> Vec<Command> {
+        if self.backend == MemoryBackend::Storage {
+            return crate::storage::build_cmds(self);
+        }
+
+        let mut cmds = Vec::new();
+
+        let slice_blocks = self.y * self.z;
+        let max_slices = MAX_REGION_BLOCKS / slice_blocks;
+
+        let mut x = 0;
+        while x < self.x {
+            let width = max_slices.min(self.x - x);
+            cmds.push(cir::Fill {
+                start: format!("{} 0 0", x),
+                end: format!("{} {} {}", x + width - 1, self.y - 1, self.z - 1),
+                block: "minecraft:air".to_string(),
+            }.into());
+            x += width;
+        }
+
+        // Build row
+        for z in 0..self.z {
+            cmds.push(SetBlock {
+                pos: format!("0 0 {}", z),
+                block: "minecraft:jukebox{RecordItem:{id:\"minecraft:stone\",Count:1b,tag:{Memory:1}}}".to_string(),
+                kind: SetBlockKind::Replace,
+            }.into());
+        }
+
+        // Build plane
+        let mut y = 1;
+        while y < self.y {
+            let height = y.min(self.y - y);
+            cmds.push(cir::CloneCmd {
+                start: "0 0 0".to_string(),
+                end: format!("0 {} {}", height - 1, self.z - 1),
+                dest: format!("0 {} 0", y),
+            }.into());
+            y += height;
+        }
+
+        // Build cubes
+        let mut x = 1;
+        while x < self.x {
+            let width = x.min(self.x - x).min(max_slices);
+            cmds.push(cir::CloneCmd {
+                start: "0 0 0".to_string(),
+                end: format!("{} {} {}", width - 1, self.y - 1, self.z - 1),
+                dest: format!("{} 0 0", x),
+            }.into());
+            x += width;
+        }
+
+        cmds
+    }
+}
+
+impl std::str::FromStr for MemoryLayout {
+    type Err = String;
+
+    /// Parses dimensions like `256x16x16`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dims = s
+            .split('x')
+            .map(|d| d.parse::<i32>().map_err(|_| format!("invalid memory dimensions `{}`", s)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let [x, y, z] = dims[..] {
+            MemoryLayout::new(x, y, z)
+        } else {
+            Err(format!("expected memory dimensions like `128x16x16`, got `{}`", s))
+        }
+    }
+}
+
+/// Reads the word at a word-aligned `address` known at compile time into `target`
+pub fn read_memory(target: ScoreHolder, address: i32, layout: &MemoryLayout) -> Command {
+    let (x, y, z) = layout.get_address(address);
+
+    if layout.backend == MemoryBackend::Storage {
+        return crate::storage::load_word(target, x, y * layout.z + z);
+    }
+
+    let mut cmd = Execute::new();
+    cmd.with_subcmd(ExecuteSubCmd::Store {
+        is_success: false,
+        kind: ExecuteStoreKind::Score {
+            target: target.into(),
+            objective: OBJECTIVE.to_string(),
+        },
+    });
+    cmd.with_run(Data {
+        target: DataTarget::Block(format!("{} {} {}", x, y, z)),
+        kind: DataKind::Get {
+            path: "RecordItem.tag.Memory".to_string(),
+            scale: 1.0,
+        },
+    });
+    cmd.into()
+}
+
+/// Writes `source` to the word at a word-aligned `address` known at compile time
+pub fn write_memory(source: ScoreHolder, address: i32, layout: &MemoryLayout) -> Command {
+    let (x, y, z) = layout.get_address(address);
+
+    if layout.backend == MemoryBackend::Storage {
+        return crate::storage::store_word(source, x, y * layout.z + z);
+    }
+
+    let mut cmd = Execute::new();
+    cmd.with_subcmd(ExecuteSubCmd::Store {
+        is_success: false,
+        kind: ExecuteStoreKind::Data {
+            target: DataTarget::Block(format!("{} {} {}", x, y, z)),
+            path: "RecordItem.tag.Memory".to_string(),
+            ty: "int".to_string(),
+            scale: 1.0,
+        },
+    });
+    cmd.with_run(ScoreGet {
+        target: source.into(),
+        target_obj: OBJECTIVE.to_string(),
+    });
+    cmd.into()
+}
+
+/// Scratch holders for accesses at constant addresses, which never live across a call
+fn const_mem_temp(name: &str) -> ScoreHolder {
+    ScoreHolder::new(format!("%%temp_const_mem_{}", name)).unwrap()
+}
+
+/// Splits an access of `size` bytes at `address` into the part of each word it touches,
+/// as (word address, first byte within the word, length, first byte within the value)
+fn word_parts(address: i32, size: u32) -> Vec<(i32, u32, u32, u32)> {
+    let mut parts = Vec::new();
+
+    let mut done = 0;
+    while done < size {
+        let addr = address + done as i32;
+        let start = addr.rem_euclid(4) as u32;
+        let len = (4 - start).min(size - done);
+        parts.push((addr - start as i32, start, len, done));
+        done += len;
+    }
+
+    parts
+}
+
+/// Sets `dest` to the `len` bytes of `word` starting at byte `start`, zero-extended.
+/// Every division here is exact, so it doesn't matter how negative numbers round.
+fn extract_bytes(dest: ScoreHolder, word: ScoreHolder, start: u32, len: u32) -> Vec<Command> {
+    let mut cmds = vec![assign(dest.clone(), word.clone())];
+
+    if len == 4 {
+        return cmds;
+    }
+
+    if start > 0 {
+        let low = const_mem_temp("low");
+        cmds.push(assign(low.clone(), word));
+        cmds.push(make_op_lit(low.clone(), "%=", 1 << (8 * start)));
+        cmds.push(make_op(dest.clone(), "-=", low));
+        cmds.push(make_op_lit(dest.clone(), "/=", 1 << (8 * start)));
+    }
+
+    cmds.push(make_op_lit(dest, "%=", 1 << (8 * len)));
+
+    cmds
+}
+
+/// Returns true if every word touched by an access of `size` bytes at `address`
+/// is in memory (and not the null word), so it can be accessed directly
+fn is_direct_access(address: i32, size: u32, layout: &MemoryLayout) -> bool {
+    4 <= address && (address as i64 + size as i64) <= layout.bytes() as i64
+}
+
+/// Loads `size` bytes (at most a word) from an `address` known at compile time
+/// into `dest`, without going through `%ptr` or any intrinsics
+pub fn load_const_addr(dest: ScoreHolder, address: i32, size: u32, layout: &MemoryLayout) -> Vec<Command> {
+    if (address as u32).is_multiple_of(4) && size == 4 {
+        return vec![read_memory(dest, address, layout)];
+    }
+
+    let word = const_mem_temp("word");
+    let part = const_mem_temp("part");
+
+    let mut cmds = vec![assign_lit(dest.clone(), 0)];
+    for (word_addr, start, len, shift) in word_parts(address, size) {
+        cmds.push(read_memory(word.clone(), word_addr, layout));
+        cmds.extend(extract_bytes(part.clone(), word.clone(), start, len));
+        if shift > 0 {
+            cmds.push(make_op_lit(part.clone(), "*=", 1 << (8 * shift)));
+        }
+        cmds.push(make_op(dest.clone(), "+=", part.clone()));
     }
+
+    cmds
 }
 
-/// Returns xyz
-pub fn get_address(mut address: i32) -> (i32, i32, i32) {
-    if address % 4 != 0 {
-        todo!("{:?}", address);
+/// Stores the low `size` bytes (at most a word) of `value` to an `address` known
+/// at compile time, leaving the rest of each word it touches alone
+pub fn store_const_addr(value: ScoreHolder, address: i32, size: u32, layout: &MemoryLayout) -> Vec<Command> {
+    if (address as u32).is_multiple_of(4) && size == 4 {
+        return vec![write_memory(value, address, layout)];
+    }
+
+    let word = const_mem_temp("word");
+    let part = const_mem_temp("part");
+
+    let mut cmds = Vec::new();
+    for (word_addr, start, len, shift) in word_parts(address, size) {
+        cmds.push(read_memory(word.clone(), word_addr, layout));
+
+        // Clear the old bytes...
+        cmds.extend(extract_bytes(part.clone(), word.clone(), start, len));
+        if start > 0 {
+            cmds.push(make_op_lit(part.clone(), "*=", 1 << (8 * start)));
+        }
+        cmds.push(make_op(word.clone(), "-=", part.clone()));
+
+        // ...and add in the new ones
+        cmds.extend(extract_bytes(part.clone(), value.clone(), shift, len));
+        if start > 0 {
+            cmds.push(make_op_lit(part.clone(), "*=", 1 << (8 * start)));
+        }
+        cmds.push(make_op(word.clone(), "+=", part.clone()));
+
+        cmds.push(write_memory(word.clone(), word_addr, layout));
     }
-    address /= 4;
 
-    assert!(0 < address);
-    assert!(address < 128 * 16 * 16);
-    let z = address % 16;
-    address /= 16;
-    let y = address % 16;
-    address /= 16;
-    let x = address % 128;
-    (x, y, z)
+    cmds
 }
 
 /// Optimized form of setting and then writing to the pointer
 /// when the address and value are known at compile time
-pub fn set_memory(value: i32, address: i32) -> Command {
-    let (x, y, z) = get_address(address);
+pub fn set_memory(value: i32, address: i32, layout: &MemoryLayout) -> Command {
+    let (x, y, z) = layout.get_address(address);
+
+    if layout.backend == MemoryBackend::Storage {
+        return crate::storage::set_word(value, x, y * layout.z + z);
+    }
 
     Data {
         target: DataTarget::Block(format!("{} {} {}", x, y, z)),
@@ -455,65 +820,238 @@ pub fn write_ptr(target: ScoreHolder) -> Command {
 pub struct BuildOptions {
     /// Insert a print command at the beginning of each LLVM basic block 
     pub trace_bbs: bool,
+    /// Build a library with the given namespace instead of a program starting at `main`,
+    /// exporting each externally visible function as `<namespace>:api/<name>`
+    pub library: Option<String>,
+    /// The size of the memory area
+    pub memory: MemoryLayout,
+    /// The number of bytes at the top of memory reserved for the heap
+    /// (a quarter of memory if unset)
+    pub heap: Option<u32>,
+    /// Check that the stack doesn't grow into the heap on function entry and in `alloca`
+    pub stack_check: bool,
+    /// Check that every pointer used to access memory is non-null, in bounds, and aligned
+    pub check_pointers: bool,
+    /// The start of the memory-mapped I/O region, if there is one (see `crate::mmio`)
+    pub mmio: Option<u32>,
+    /// How hard to optimize the generated commands, from 0 (not at all) to 2 (see `crate::optimize`).
+    /// Anything above 0 also inlines small functions that are a single basic block (see `crate::inliner`).
+    pub opt_level: u8,
 }
 
+impl BuildOptions {
+    /// The addresses used by the heap, which are 8-byte aligned
+    pub fn heap_region(&self) -> std::ops::Range<u32> {
+        let end = self.memory.bytes() & !7;
+        let size = self.heap.unwrap_or(end / 4);
+        (end.saturating_sub(size) & !7)..end
+    }
 
-pub fn save_regs<T>(regs: T) -> Vec<Command>
-where
-    T: IntoIterator<Item = ScoreHolder>
-{
-    let base_set = assign(stackbaseptr(), stackptr());
+    /// The highest address the stack pointer may reach, if the stack is checked.
+    /// The stack grows upwards from the static data, so it ends where the heap begins.
+    pub fn stack_limit(&self) -> Option<u32> {
+        if self.stack_check {
+            Some(self.heap_region().start)
+        } else {
+            None
+        }
+    }
+}
 
-    regs
-        .into_iter()
-        .chain(std::iter::once(stackbaseptr()))
-        .filter(|reg| {
-            reg != &stackptr() &&
-            reg != &ptr() &&
-            reg != &condstackptr() &&
-            reg != &condtempholder() &&
-            //reg != &stackbaseptr() &&
-            reg != &ScoreHolder::new("%phi".into()).unwrap() &&
-            !reg.as_ref().contains("%%fixup") &&
-            !reg.as_ref().starts_with("%return%")
-        })
-        .map(push)
-        .flatten()
-        .chain(std::iter::once(base_set))
-        .collect()
+/// Where in the LLVM module a `CompileError` occurred
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ErrorContext {
+    pub function: Option<String>,
+    pub block: Option<Name>,
+    pub debugloc: Option<DebugLoc>,
 }
 
-pub fn load_regs<T, U>(regs: T) -> Vec<Command>
-where
-    T: IntoIterator<Item=ScoreHolder, IntoIter=U>,
-    U: DoubleEndedIterator + Iterator<Item=ScoreHolder>,
-{
-    let base_read = assign(stackptr(), stackbaseptr());
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(function) = &self.function {
+            write!(f, " in function `{}`", function)?;
+        }
 
-    std::iter::once(base_read).chain(
-        regs
-            .into_iter()
-            .filter(|reg| {
-                reg != &stackptr() &&
-                reg != &ptr() &&
-                reg != &condstackptr() &&
-                reg != &condtempholder() &&
-                //reg != &stackbaseptr() &&
-                reg != &ScoreHolder::new("%phi".into()).unwrap() &&
-                !reg.as_ref().contains("%%fixup") &&
-                !reg.as_ref().starts_with("%return%")
-            })
-            .chain(std::iter::once(stackbaseptr()))
-            .rev()
-            .map(pop)
-            .flatten()
-    ).collect()
+        match &self.block {
+            Some(Name::Name(name)) => write!(f, ", block %{}", name)?,
+            Some(Name::Number(num)) => write!(f, ", block %{}", num)?,
+            None => {}
+        }
+
+        if let Some(DebugLoc { line, col, filename, directory }) = &self.debugloc {
+            match directory {
+                Some(dir) => write!(f, " at {}/{}:{}", dir, filename, line)?,
+                None => write!(f, " at {}:{}", filename, line)?,
+            }
+
+            if let Some(col) = col {
+                write!(f, ":{}", col)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An error caused by LLVM IR that the compiler cannot handle
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    UnsupportedInstruction(String, Box<ErrorContext>),
+    UnsupportedType(String, Box<ErrorContext>),
+    UnsupportedIntrinsic(String, Box<ErrorContext>),
+    LayoutError(String, Box<ErrorContext>),
+    UndefinedSymbol(String, Box<ErrorContext>),
+    OutOfMemory(String, Box<ErrorContext>),
+    InvalidAsm(String, Box<ErrorContext>),
+    NameCollision(String, Box<ErrorContext>),
+}
+
+impl CompileError {
+    pub fn unsupported_instr<T: std::fmt::Debug + ?Sized>(instr: &T) -> Self {
+        CompileError::UnsupportedInstruction(format!("{:?}", instr), Box::default())
+    }
+
+    pub fn unsupported_type<T: std::fmt::Debug + ?Sized>(ty: &T) -> Self {
+        CompileError::UnsupportedType(format!("{:?}", ty), Box::default())
+    }
+
+    pub fn unsupported_intrinsic(name: &str) -> Self {
+        CompileError::UnsupportedIntrinsic(name.to_string(), Box::default())
+    }
+
+    pub fn layout<T: std::fmt::Debug + ?Sized>(value: &T) -> Self {
+        CompileError::LayoutError(format!("{:?}", value), Box::default())
+    }
+
+    pub fn undefined_symbol(name: &str) -> Self {
+        CompileError::UndefinedSymbol(name.to_string(), Box::default())
+    }
+
+    pub fn out_of_memory(needed: u64, available: u32) -> Self {
+        CompileError::OutOfMemory(format!("static data needs {} bytes, but memory is only {} bytes", needed, available), Box::default())
+    }
+
+    pub fn invalid_asm(msg: &str) -> Self {
+        CompileError::InvalidAsm(msg.to_string(), Box::default())
+    }
+
+    pub fn name_collision(name: &str, first: &str, second: &str) -> Self {
+        CompileError::NameCollision(format!("@{} and @{} would both be {}", first, second, name), Box::default())
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        match self {
+            CompileError::UnsupportedInstruction(_, ctx)
+            | CompileError::UnsupportedType(_, ctx)
+            | CompileError::UnsupportedIntrinsic(_, ctx)
+            | CompileError::LayoutError(_, ctx)
+            | CompileError::UndefinedSymbol(_, ctx)
+            | CompileError::OutOfMemory(_, ctx)
+            | CompileError::InvalidAsm(_, ctx)
+            | CompileError::NameCollision(_, ctx) => ctx,
+        }
+    }
+
+    fn context_mut(&mut self) -> &mut ErrorContext {
+        match self {
+            CompileError::UnsupportedInstruction(_, ctx)
+            | CompileError::UnsupportedType(_, ctx)
+            | CompileError::UnsupportedIntrinsic(_, ctx)
+            | CompileError::LayoutError(_, ctx)
+            | CompileError::UndefinedSymbol(_, ctx)
+            | CompileError::OutOfMemory(_, ctx)
+            | CompileError::InvalidAsm(_, ctx)
+            | CompileError::NameCollision(_, ctx) => ctx,
+        }
+    }
+
+    /// Fills in any parts of the context that are not already known
+    pub fn with_context(mut self, function: &str, block: Option<&Name>, debugloc: Option<&DebugLoc>) -> Self {
+        let ctx = self.context_mut();
+
+        if ctx.function.is_none() {
+            ctx.function = Some(function.to_string());
+        }
+
+        if ctx.block.is_none() {
+            ctx.block = block.cloned();
+        }
+
+        if ctx.debugloc.is_none() {
+            ctx.debugloc = debugloc.cloned();
+        }
+
+        self
+    }
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CompileError::UnsupportedInstruction(i, ctx) => write!(f, "unsupported instruction {}{}", i, ctx),
+            CompileError::UnsupportedType(t, ctx) => write!(f, "unsupported type {}{}", t, ctx),
+            CompileError::UnsupportedIntrinsic(n, ctx) => write!(f, "unsupported intrinsic {}{}", n, ctx),
+            CompileError::LayoutError(v, ctx) => write!(f, "could not lay out {}{}", v, ctx),
+            CompileError::UndefinedSymbol(n, ctx) => write!(f, "undefined symbol @{}{}", n, ctx),
+            CompileError::OutOfMemory(m, ctx) => write!(f, "out of memory: {}{}", m, ctx),
+            CompileError::InvalidAsm(m, ctx) => write!(f, "invalid inline assembly: {}{}", m, ctx),
+            CompileError::NameCollision(m, ctx) => write!(f, "name collision: {}{}", m, ctx),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+pub type CompileResult<T> = Result<T, CompileError>;
+
+
+/// Returns true if a caller has to save `reg` around a call when it still needs it afterwards.
+/// The others belong to the calling convention, are only ever used within one block,
+/// or (for `%stackbaseptr`) are saved by the callee.
+pub(crate) fn is_saved(reg: &ScoreHolder) -> bool {
+    reg != &stackptr() &&
+    reg != &ptr() &&
+    reg != &condstackptr() &&
+    reg != &condtempholder() &&
+    reg != &stackbaseptr() &&
+    reg != &ScoreHolder::new("%phi".into()).unwrap() &&
+    !reg.as_ref().contains("%%fixup") &&
+    !reg.as_ref().starts_with("%return%") &&
+    // These have to survive the epilogue of every frame that gets unwound
+    reg != &unwinding() &&
+    !reg.as_ref().starts_with("%%exception%")
+}
+
+/// Starts the stack frame of a function. Only the caller's frame pointer gets saved here,
+/// since everything else is saved by the caller (see `crate::regalloc`).
+pub fn save_frame() -> Vec<Command> {
+    let mut cmds = push(stackbaseptr());
+    cmds.push(assign(stackbaseptr(), stackptr()));
+    cmds
+}
+
+/// Undoes `save_frame`, popping everything the function left on the stack
+pub fn load_frame() -> Vec<Command> {
+    let mut cmds = vec![assign(stackptr(), stackbaseptr())];
+    cmds.extend(pop(stackbaseptr()));
+    cmds
+}
+
+type AbstractCompileOutput = (Vec<AbstractBlock>, HashMap<String, Registers>, HashMap<String, McFuncId>);
+
+/// Returns true if anything in `module` can unwind, in which case every call needs somewhere to unwind to
+fn uses_unwinding(module: &Module) -> bool {
+    module
+        .functions
+        .iter()
+        .flat_map(|f| f.basic_blocks.iter())
+        .any(|b| matches!(b.term, Terminator::Resume(_) | Terminator::Invoke(_)))
 }
 
-type AbstractCompileOutput = (Vec<AbstractBlock>, HashMap<String, BTreeSet<ScoreHolder>>, HashMap<String, McFuncId>);
+fn compile_module_abstract(module: &Module, options: &BuildOptions, globals: &GlobalVarList) -> Result<AbstractCompileOutput, Vec<CompileError>> {
+    let can_unwind = uses_unwinding(module);
 
-fn compile_module_abstract(module: &Module, options: &BuildOptions, globals: &GlobalVarList) -> AbstractCompileOutput {
-    let mut clobber_list = HashMap::<String, BTreeSet<ScoreHolder>>::new();
+    let mut registers = HashMap::<String, Registers>::new();
 
     let mut funcs = Vec::new();
 
@@ -521,17 +1059,32 @@ fn compile_module_abstract(module: &Module, options: &BuildOptions, globals: &Gl
 
     let mut func_starts = HashMap::<String, McFuncId>::new();
 
-    for (parent, (mc_funcs, clobbers)) in module
-        .functions
-        .iter()
-        .map(|f| (f, compile_function(f, &globals, &module.types, options)))
-    {
-        for AbstractBlock { body: McFunction { id, .. }, .. } in mc_funcs.iter() {
-            clobber_list.insert(
-                id.name.clone(),
-                clobbers.clone().into_iter().map(|c| c.0).collect(),
-            );
-        }
+    let mut errors = Vec::new();
+
+    let inlines = if options.opt_level > 0 {
+        inliner::find_inlines(module, globals, options)
+    } else {
+        HashMap::new()
+    };
+
+    for parent in module.functions.iter() {
+        // Keep going after a failure so that every unsupported function gets reported
+        let mut mc_funcs = match compile_function(parent, globals, &module.types, options, &inlines, can_unwind) {
+            Ok(f) => f,
+            Err(errs) => {
+                errors.extend(errs);
+                continue;
+            }
+        };
+
+        let func_registers = match regalloc::allocate(&mut mc_funcs, globals, &module.types, options.stack_limit()) {
+            Ok(r) => r,
+            Err(err) => {
+                errors.push(err.with_context(&parent.name, None, parent.debugloc.as_ref()));
+                continue;
+            }
+        };
+        registers.insert(mc_funcs[0].body.id.name.clone(), func_registers);
 
         func_starts.insert(parent.name.clone(), mc_funcs[0].body.id.clone());
 
@@ -561,30 +1114,119 @@ fn compile_module_abstract(module: &Module, options: &BuildOptions, globals: &Gl
         }
     }*/
 
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
     for intr in crate::intrinsics::INTRINSICS.iter() {
         assert_eq!(func_starts.insert(intr.id.to_string(), intr.id.clone()), None);
     }
 
-    (funcs, clobber_list, func_starts)
+    Ok((funcs, registers, func_starts))
 }
 
-pub fn create_return_func() -> McFunction {
-    let mut cmds = Vec::new();
-
-    cmds.extend(pop(temp_fn_ptr()));
-
-    cmds.push(McFuncCall { id: McFuncId::new("rust:__langcraft_call") }.into());
+/// Branches to the function whose index is in `%%tempfuncptr`, or ends the program if it's -1
+static CALL_FUNC: &str = "rust:__langcraft_call";
 
-    McFunction {
-        id: McFuncId::new("rust:__langcraft_return"),
-        cmds,
-    }
-}
+/// Replaces the return address in `%%tempfuncptr` with where that call site unwinds to
+static UNWIND_FUNC: &str = "rust:__langcraft_unwind";
 
-pub fn create_call_func(others: &[McFunction]) -> McFunction {
+pub fn create_return_func(unwinds: bool) -> McFunction {
     let mut cmds = Vec::new();
 
-    // TODO: Make this a build option
+    cmds.extend(pop(temp_fn_ptr()));
+
+    if unwinds {
+        let mut cmd = Execute::new();
+        cmd.with_if(ExecuteCondition::Score {
+            target: unwinding().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((1..=1).into()),
+        });
+        cmd.with_run(McFuncCall { id: McFuncId::new(UNWIND_FUNC) });
+        cmds.push(cmd.into());
+    }
+
+    cmds.push(McFuncCall { id: McFuncId::new(CALL_FUNC) }.into());
+
+    McFunction::new(McFuncId::new("rust:__langcraft_return"), cmds)
+}
+
+/// Creates the function that `rust:__langcraft_return` runs while unwinding, which replaces
+/// the return address with wherever that call site unwinds to, taken from the `!UNWIND` markers
+/// at the start of each call's continuation, along with the tree of functions it uses to find
+/// the call site (see `dispatch`). Returns nothing if nothing can unwind.
+fn create_unwind_funcs(funcs: &mut [McFunction]) -> Vec<McFunction> {
+    let mut sites = Vec::new();
+    for (idx, func) in funcs.iter_mut().enumerate() {
+        func.cmds.retain(|cmd| match cmd {
+            Command::Comment(c) if c.starts_with("!UNWIND ") => {
+                sites.push((idx, c["!UNWIND ".len()..].parse::<McFuncId>().unwrap()));
+                false
+            }
+            _ => true,
+        });
+    }
+
+    if sites.is_empty() {
+        return Vec::new();
+    }
+
+    let targets = sites
+        .into_iter()
+        .map(|(return_idx, target)| {
+            let target_idx = funcs
+                .iter()
+                .position(|f| f.id == target)
+                .unwrap_or_else(|| panic!("could not find {:?}", target));
+
+            (return_idx as i32, target_idx as i32)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let unwind_ptr = ScoreHolder::new("%%unwindptr".to_string()).unwrap();
+
+    let mut tree = Vec::new();
+    let mut cmds = vec![assign(unwind_ptr.clone(), temp_fn_ptr())];
+
+    let return_idxs = targets.keys().copied().collect::<Vec<_>>();
+    cmds.extend(dispatch(UNWIND_FUNC, &temp_fn_ptr(), &return_idxs, &|idx| {
+        assign_lit(unwind_ptr.clone(), targets[&idx])
+    }, &mut tree));
+
+    // It made it all the way out of `main`
+    let mut uncaught = Execute::new();
+    uncaught.with_if(ExecuteCondition::Score {
+        target: temp_fn_ptr().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((-1..=-1).into()),
+    });
+    uncaught.with_run(Tellraw {
+        target: cir::Selector {
+            var: cir::SelectorVariable::AllPlayers,
+            args: Vec::new(),
+        }
+        .into(),
+        message: cir::TextBuilder::new()
+            .append_text("uncaught exception".into())
+            .build(),
+    });
+    cmds.push(uncaught.into());
+    cmds.push(Command::Comment("!INTERPRETER: UNCAUGHT EXCEPTION".to_string()));
+
+    cmds.push(assign(temp_fn_ptr(), unwind_ptr));
+
+    tree.insert(0, McFunction::new(McFuncId::new(UNWIND_FUNC), cmds));
+    tree
+}
+
+/// Creates `rust:__langcraft_call`, which branches to the function at index `%%tempfuncptr` in `others`,
+/// along with the tree of functions it uses to find it (see `dispatch`)
+pub fn create_call_funcs(others: &[McFunction]) -> Vec<McFunction> {
+    let mut funcs = Vec::new();
+    let mut cmds = Vec::new();
+
+    // TODO: Make this a build option
     /*let mut t = cir::TextBuilder::new();
     t.append_text("func ptr: ".to_string());
     t.append_score(temp_fn_ptr(), OBJECTIVE.into(), None);
@@ -614,24 +1256,18 @@ pub fn create_call_func(others: &[McFunction]) -> McFunction {
     });
     cmds.push(cmd.into());
 
-    // This could be improved with a binary search, but eh, whatever
-    for (idx, dest) in others.iter().enumerate() {
-        let mut cmd = Execute::new();       
-        cmd.with_if(ExecuteCondition::Score {
-            target: temp_fn_ptr().into(),
-            target_obj: OBJECTIVE.into(),
-            kind: ExecuteCondKind::Matches((idx as i32..=idx as i32).into()),
-        });
-        cmd.with_run(Data {
+    let idxs = (0..others.len() as i32).collect::<Vec<_>>();
+    cmds.extend(dispatch(CALL_FUNC, &temp_fn_ptr(), &idxs, &|idx| {
+        Data {
             target: DataTarget::Block("~ ~ ~".to_string()),
             kind: cir::DataKind::Modify {
                 path: "Command".to_string(),
                 kind: cir::DataModifyKind::Set,
-                source: cir::DataModifySource::ValueString(McFuncCall { id: dest.id.clone() }.to_string()),
+                source: cir::DataModifySource::ValueString(McFuncCall { id: others[idx as usize].id.clone() }.to_string()),
             },
-        });
-        cmds.push(cmd.into());
-    }
+        }
+        .into()
+    }, &mut funcs));
 
     let mut on_invalid_1 = Execute::new();
     on_invalid_1.with_unless(ExecuteCondition::Score {
@@ -659,9 +1295,50 @@ pub fn create_call_func(others: &[McFunction]) -> McFunction {
     });
     cmds.push(on_invalid_2.into());
 
-    McFunction {
-        id: McFuncId::new("rust:__langcraft_call"),
-        cmds,
+    funcs.insert(0, McFunction::new(McFuncId::new(CALL_FUNC), cmds));
+    funcs
+}
+
+/// Lists of at most this many values are checked one by one instead of being split again
+const DISPATCH_LEAF_SIZE: usize = 8;
+
+/// Returns true if `id` is one of the trees that run exactly one of their branches
+pub(crate) fn is_dispatch(id: &McFuncId) -> bool {
+    id.name.starts_with(CALL_FUNC)
+        || id.name.strip_prefix(UNWIND_FUNC).is_some_and(|rest| rest.starts_with('/'))
+        || crate::storage::is_dispatch(id)
+}
+
+fn when_matches(holder: &ScoreHolder, range: Range<i32>, run: Command) -> Command {
+    let mut cmd = Execute::new();
+    cmd.with_if(ExecuteCondition::Score {
+        target: holder.clone().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((range.start..=range.end - 1).into()),
+    });
+    cmd.with_run(run);
+    cmd.into()
+}
+
+/// Returns the commands of a function that runs `leaf(i)` for the value `i` in `holder`, if it's one of `values`,
+/// which must be sorted. Long lists are split in half with a function for each, named `<root>/<first>_<last>`
+/// and added to `funcs`, so only a logarithmic number of commands run.
+pub(crate) fn dispatch(root: &str, holder: &ScoreHolder, values: &[i32], leaf: &dyn Fn(i32) -> Command, funcs: &mut Vec<McFunction>) -> Vec<Command> {
+    if values.len() <= DISPATCH_LEAF_SIZE {
+        values.iter().map(|&i| when_matches(holder, i..i + 1, leaf(i))).collect()
+    } else {
+        let (low, high) = values.split_at(values.len() / 2);
+
+        let mut cmds = Vec::new();
+        for half in [low, high].iter() {
+            let (first, last) = (half[0], half[half.len() - 1]);
+            let child = McFuncId::new(format!("{}/{}_{}", root, first, last));
+            cmds.push(when_matches(holder, first..last + 1, McFuncCall { id: child.clone() }.into()));
+
+            let child_cmds = dispatch(root, holder, half, leaf, funcs);
+            funcs.push(McFunction::new(child, child_cmds));
+        }
+        cmds
     }
 }
 
@@ -672,7 +1349,8 @@ lazy_static! {
     ).unwrap();
 }
 
-pub fn compile_module(module: &Module, options: &BuildOptions) -> Vec<McFunction> {
+/// Compiles `module`, along with what the optimizer did to it if `options.opt_level` is above 0
+pub fn compile_module(module: &Module, options: &BuildOptions) -> Result<(Vec<McFunction>, Option<OptReport>), Vec<CompileError>> {
     // Steps in compiling a module:
     // 1. Lay out global variables
     // 2. Convert LLVM functions to abstract blocks
@@ -682,11 +1360,31 @@ pub fn compile_module(module: &Module, options: &BuildOptions) -> Vec<McFunction
     // 6. Add global variable init commands
     
     // Step 1: Lay out global variables
-    let mut alloc = StaticAllocator(4);
-    let mut globals = global_var_layout(&module.global_vars, &module.functions, &mut alloc, &module.types);
+    let heap = options.heap_region();
+    if let Some(base) = options.mmio {
+        crate::mmio::check_region(base, &options.memory).map_err(|e| vec![e])?;
+    }
+    let mut alloc = StaticAllocator::new(&options.memory);
+    alloc.limit_to(heap.start);
+    let mut globals = global_var_layout(&module.global_vars, &module.functions, &mut alloc, &heap, &module.types)
+        .map_err(|e| vec![e])?;
 
     // Step 2: Convert LLVM functions to abstract blocks
-    let (funcs, clobber_list, func_starts) = compile_module_abstract(module, options, &globals);
+    let (funcs, registers, func_starts) = compile_module_abstract(module, options, &globals)?;
+
+    // Every holder a function uses starts out with a score, so saving one before it's set still works
+    let mut all_clobbers = BTreeSet::new();
+    for block in funcs.iter() {
+        let mut cmds = block.body.cmds.clone();
+        if block.needs_prolog {
+            cmds.extend(load_params(&block.parent, &module.types).map_err(|e| vec![e])?);
+        }
+        regalloc::rename(&mut cmds, &registers[&block.body.id.name]);
+
+        for cmd in cmds.iter() {
+            all_clobbers.extend(cmd.holder_uses().into_keys().cloned());
+        }
+    }
 
     for func in funcs.iter() {
         if let Some(dest) = func.get_dest(&func_starts) {
@@ -694,18 +1392,44 @@ pub fn compile_module(module: &Module, options: &BuildOptions) -> Vec<McFunction
         }
     }
 
+    // Step 3: Extend abstract blocks with "chains"
+    let fused = crate::analysis::fuse_blocks(&funcs, &globals, &module.types);
+
     // Step 4: Reify call graph to MC functions
+    let mut errors = Vec::new();
     let mut funcs = funcs
         .into_iter()
-        .map(|block| {
-            reify_block(block, &clobber_list, &func_starts, &globals, &module.types)
+        .filter_map(|block| {
+            reify_block(block, &registers, &fused, &func_starts, &globals, &module.types, options.stack_limit())
+                .map_err(|err| errors.push(err))
+                .ok()
         })
         .collect::<Vec<_>>();
 
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
     funcs.extend(crate::intrinsics::INTRINSICS.clone());
 
+    // A library has no single entry point, so every external function gets exported instead
+    // (except variadic ones, since the api only passes arguments in scores)
+    let exports = if options.library.is_some() {
+        let exports = module.functions.iter().filter(|f| f.linkage == Linkage::External && !f.is_var_arg).collect::<Vec<_>>();
+
+        let mut return_words = 0;
+        for func in exports.iter() {
+            return_words = return_words.max(size_in_words(&func.return_type, &module.types).map_err(|e| vec![e])?);
+        }
+        funcs.push(create_api_return_func(return_words));
+
+        exports
+    } else {
+        Vec::new()
+    };
+
     // Step 5: Do relocations
-    let mut funcs = do_relocation(funcs, &func_starts, &mut globals);
+    let (mut funcs, opt_report) = do_relocation(funcs, &func_starts, &mut globals, options)?;
 
     println!("\nIndices:");
     for (idx, f) in funcs.iter().enumerate() {
@@ -713,38 +1437,64 @@ pub fn compile_module(module: &Module, options: &BuildOptions) -> Vec<McFunction
     }
 
     // Step 6: Add global variable init commands
-    let mut init_cmds = compile_global_var_init(&module.global_vars, &mut globals, &module.types);
-    init_cmds.push(assign_lit(condstackptr(), alloc.reserve(COND_STACK_BYTES as u32) as i32));
-    let main_return = alloc.reserve(4);
-    init_cmds.push(set_memory(-1, main_return as i32));
-    init_cmds.push(assign_lit(stackptr(), alloc.reserve(4) as i32));
-    init_cmds.push(assign_lit(stackbaseptr(), 0));
-    init_cmds.extend(make_build_cmds(func_starts.get("main").unwrap()));
-
-    let mut all_clobbers = BTreeSet::new();
-    for c in clobber_list.values() {
-        all_clobbers.extend(c);
+    let mut init_cmds = compile_global_var_init(&module.global_vars, &mut globals, &module.types, &options.memory)
+        .map_err(|e| vec![e])?;
+    let cond_stack_start = alloc.reserve(COND_STACK_BYTES as u32).map_err(|e| vec![e])?;
+    init_cmds.push(assign_lit(condstackptr(), cond_stack_start as i32));
+    let main_return = alloc.reserve(4).map_err(|e| vec![e])?;
+    let stack_start = alloc.reserve(4).map_err(|e| vec![e])?;
+    if options.library.is_some() {
+        init_cmds.push(assign_lit(initialized(), 1));
+    } else {
+        let main_id = func_starts.get("main").ok_or_else(|| vec![CompileError::undefined_symbol("main")])?;
+        init_cmds.push(set_memory(-1, main_return as i32, &options.memory));
+        init_cmds.push(assign_lit(stackptr(), stack_start as i32));
+        init_cmds.push(assign_lit(stackbaseptr(), 0));
+        init_cmds.extend(make_build_cmds(main_id));
     }
 
     #[allow(clippy::reversed_empty_ranges)]
     init_cmds.splice(
         0..0,
-        all_clobbers.iter().map(|c| assign_lit((*c).clone(), 1)),
+        all_clobbers.iter().map(|c| assign_lit(c.clone(), 1)),
     );
 
-    funcs.push(McFunction {
-        id: McFuncId::new("init"),
-        cmds: init_cmds,
-    });
+    if uses_unwinding(module) {
+        init_cmds.push(assign_lit(unwinding(), 0));
+    }
+
+    funcs.push(McFunction::new(McFuncId::new("init"), init_cmds));
 
     funcs.push(ON_TICK.clone());
 
+    if let Some(namespace) = &options.library {
+        let return_idx = funcs.iter().position(|f| f.id == McFuncId::new(API_RETURN_FUNC)).unwrap();
+
+        // Function names can't have every character a symbol can, so different symbols might end up with the same one
+        let mut names = HashMap::<McFuncId, &str>::new();
+        let mut errors = Vec::new();
+        for func in exports {
+            let api_func = create_api_func(namespace, func, &func_starts[&func.name], main_return, stack_start, return_idx, options, &module.types)
+                .map_err(|e| vec![e])?;
+
+            if let Some(other) = names.insert(api_func.id.clone(), &func.name) {
+                errors.push(CompileError::name_collision(&api_func.id.to_string(), other, &func.name).with_context(&func.name, None, None));
+                continue;
+            }
+
+            funcs.push(api_func);
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+    } else {
     //if let Some(main_id) = func_starts.get("main") {
         //let main_idx = funcs.iter().position(|f| &f.id == main_id).unwrap();
         //let (main_x, main_z) = func_idx_to_pos(main_idx);
-    funcs.push(McFunction {
-        id: McFuncId::new("run"),
-        cmds: vec![
+    funcs.push(McFunction::new(
+        McFuncId::new("run"),
+        vec![
             McFuncCall {
                 id: McFuncId::new("init"),
             }
@@ -756,18 +1506,20 @@ pub fn compile_module(module: &Module, options: &BuildOptions) -> Vec<McFunction
             }
             .into(),
         ],
-    });
+    ));
+    }
 
-    //} else {
-    //    todo!("support programs without an entry point")
-    //}
+    // Step 7: Build source maps from the location markers
+    for func in funcs.iter_mut() {
+        extract_source_locs(func);
+    }
 
-    funcs
+    Ok((funcs, opt_report))
 }
 
 /// Finalizes the locations of the generated functions
 /// and applies any necessary fixups
-fn do_relocation<T>(funcs: T, func_starts: &HashMap<String, McFuncId>, globals: &mut GlobalVarList) -> Vec<McFunction>
+fn do_relocation<T>(funcs: T, func_starts: &HashMap<String, McFuncId>, globals: &mut GlobalVarList, options: &BuildOptions) -> Result<(Vec<McFunction>, Option<OptReport>), Vec<CompileError>>
     where T: IntoIterator<Item=McFunction>
 {
     let mut funcs = funcs.into_iter().collect::<Vec<_>>();
@@ -787,12 +1539,20 @@ fn do_relocation<T>(funcs: T, func_starts: &HashMap<String, McFuncId>, globals:
         }
     }
 
-    funcs.push(create_call_func(&funcs));
-    funcs.push(create_return_func());
+    let unwind_funcs = create_unwind_funcs(&mut funcs);
 
-    apply_fixups(&mut funcs, &func_starts);
+    let call_funcs = create_call_funcs(&funcs);
+    funcs.extend(call_funcs);
+    funcs.push(create_return_func(!unwind_funcs.is_empty()));
+    funcs.extend(unwind_funcs);
 
-    funcs
+    if options.memory.backend == MemoryBackend::Storage {
+        funcs.extend(crate::storage::access_funcs(&options.memory));
+    }
+
+    let opt_report = apply_fixups(&mut funcs, func_starts, options)?;
+
+    Ok((funcs, opt_report))
 }
 
 fn apply_branch_fixups(funcs: &mut [McFunction]) {
@@ -1023,11 +1783,25 @@ fn apply_call_fixups(funcs: &mut [McFunction], func_starts: &HashMap<String, McF
 }
 
 // This doesn't change what the function clobbers
-fn apply_fixups(funcs: &mut [McFunction], func_starts: &HashMap<String, McFuncId>) {
+fn apply_fixups(funcs: &mut [McFunction], func_starts: &HashMap<String, McFuncId>, options: &BuildOptions) -> Result<Option<OptReport>, Vec<CompileError>> {
     apply_branch_fixups(funcs);
     apply_return_fixups(funcs);
     apply_func_ref_fixups(funcs, func_starts);
     apply_call_fixups(funcs, func_starts);
+    // These have to happen before counting commands, since they make each access longer,
+    // and the checks need to find the accesses before they're lowered
+    if options.check_pointers {
+        crate::sanitizer::check_memory_accesses(funcs, &options.memory);
+    }
+    if options.memory.backend == MemoryBackend::Storage {
+        crate::storage::lower_memory_accesses(funcs)?;
+    }
+    // Also before counting commands, so the counts only include what's left
+    let opt_report = if options.opt_level > 0 {
+        Some(crate::optimize::optimize(funcs, options.opt_level))
+    } else {
+        None
+    };
     apply_cmd_count_fixups(funcs, func_starts);
 
     // Make sure we didn't miss anything
@@ -1039,6 +1813,8 @@ fn apply_fixups(funcs: &mut [McFunction], func_starts: &HashMap<String, McFuncId
             }
         }
     }
+
+    Ok(opt_report)
 }
 
 fn apply_cmd_count_fixups(funcs: &mut [McFunction], func_starts: &HashMap<String, McFuncId>) {
@@ -1085,6 +1861,100 @@ fn make_build_cmds(main_id: &McFuncId) -> Vec<Command> {
     ]
 }
 
+static API_RETURN_FUNC: &str = "rust:__langcraft_api_return";
+
+/// Set once the global variables of a library have been initialized
+fn initialized() -> ScoreHolder {
+    ScoreHolder::new("%%initialized".to_string()).unwrap()
+}
+
+/// The holders other datapacks use to pass arguments to an exported function
+pub fn api_arg(index: usize, word_index: usize) -> ScoreHolder {
+    ScoreHolder::new(format!("$arg{}%{}", index, word_index)).unwrap()
+}
+
+/// The holders an exported function's return value is written to
+pub fn api_return(word_index: usize) -> ScoreHolder {
+    ScoreHolder::new(format!("$ret%{}", word_index)).unwrap()
+}
+
+/// Set to 1 once an exported function has returned
+pub fn api_done() -> ScoreHolder {
+    ScoreHolder::new("$done".to_string()).unwrap()
+}
+
+fn size_in_words(ty: &Type, tys: &Types) -> CompileResult<usize> {
+    if let Type::VoidType = ty {
+        Ok(0)
+    } else {
+        Ok(type_layout(ty, tys)?.size().div_ceil(4))
+    }
+}
+
+/// Creates the function exported functions return to,
+/// which copies out the return value and then stops execution
+fn create_api_return_func(return_words: usize) -> McFunction {
+    let mut cmds = (0..return_words)
+        .map(|idx| assign(api_return(idx), return_holder(idx)))
+        .collect::<Vec<_>>();
+
+    cmds.push(assign_lit(api_done(), 1));
+
+    // This runs in one of the two chain blocks, so clear both of them to stop the chain
+    for pos in ["-2 0 1", "-2 0 2"].iter() {
+        cmds.push(SetBlock {
+            pos: pos.to_string(),
+            block: "minecraft:air".into(),
+            kind: cir::SetBlockKind::Replace,
+        }.into());
+    }
+
+    McFunction::new(McFuncId::new(API_RETURN_FUNC), cmds)
+}
+
+/// Creates the entry point other datapacks use to call `func`,
+/// which starts it with a fresh stack that returns to the function at `return_idx`
+#[allow(clippy::too_many_arguments)]
+fn create_api_func(namespace: &str, func: &Function, func_id: &McFuncId, return_addr: u32, stack_start: u32, return_idx: usize, options: &BuildOptions, tys: &Types) -> CompileResult<McFunction> {
+    let name = func.name.replace(['$', '.', '-'], "_").to_ascii_lowercase();
+    let id = McFuncId::new(format!("{}:api/{}", namespace, name));
+
+    let mut init_once = Execute::new();
+    init_once.with_unless(ExecuteCondition::Score {
+        target: initialized().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((1..=1).into()),
+    });
+    init_once.with_run(McFuncCall { id: McFuncId::new("init") });
+
+    // Adding 0 makes sure the flag is set, since conditions on unset scores always fail
+    let mark_known = ScoreAdd {
+        target: initialized().into(),
+        target_obj: OBJECTIVE.into(),
+        score: 0,
+    };
+
+    let mut cmds = vec![mark_known.into(), init_once.into(), assign_lit(api_done(), 0)];
+
+    for (idx, arg) in func.parameters.iter().enumerate() {
+        for word in 0..size_in_words(&arg.ty, tys)? {
+            cmds.push(assign(param(idx, word), api_arg(idx, word)));
+        }
+    }
+
+    cmds.push(set_memory(return_idx as i32, return_addr as i32, &options.memory));
+    cmds.push(assign_lit(stackptr(), stack_start as i32));
+    cmds.push(assign_lit(stackbaseptr(), 0));
+    cmds.extend(make_build_cmds(func_id));
+    cmds.push(SetBlock {
+        pos: "-2 1 0".to_string(),
+        block: "minecraft:redstone_block".to_string(),
+        kind: SetBlockKind::Replace,
+    }.into());
+
+    Ok(McFunction::new(id, cmds))
+}
+
 /*fn make_build_cmds(funcs: &[McFunction]) -> Vec<Command> {
     let mut build_cmds = vec![
         cir::Fill {
@@ -1129,9 +1999,9 @@ fn getelementptr_const(
     }: &GetElementPtrConst,
     globals: &GlobalVarList,
     tys: &Types,
-) -> u32 {
+) -> CompileResult<u32> {
     if !in_bounds {
-        todo!("not inbounds constant getelementptr")
+        return Err(CompileError::unsupported_instr("not inbounds constant getelementptr"));
     }
 
     println!("Address: {:?}", address);
@@ -1140,7 +2010,7 @@ fn getelementptr_const(
     let result = if let Constant::GlobalReference { name, ty } = &**address {
         let mut offset = globals
             .get(&name)
-            .unwrap_or_else(|| panic!("couldn't find global {:?}", name))
+            .ok_or_else(|| CompileError::layout(name))?
             .0;
         let mut ty = ty.clone();
 
@@ -1155,27 +2025,30 @@ fn getelementptr_const(
                 Type::NamedStructType {
                     name,
                 } => {
-                    let (element_types, is_packed) = as_struct_ty(tys.named_struct_def(&name).unwrap()).unwrap();
+                    let (element_types, is_packed) = tys
+                        .named_struct_def(&name)
+                        .and_then(as_struct_ty)
+                        .ok_or_else(|| CompileError::layout(&ty))?;
                     ty = element_types[index as usize].clone();
-                    offset += offset_of(element_types, is_packed, index as u32, tys) as u32;
+                    offset += offset_of(element_types, is_packed, index as u32, tys)? as u32;
                 }
                 Type::StructType {
                     element_types,
                     is_packed,
                 } => {
                     ty = element_types[index as usize].clone();
-                    offset += offset_of(&element_types, is_packed, index as u32, tys) as u32;
+                    offset += offset_of(&element_types, is_packed, index as u32, tys)? as u32;
                 }
                 Type::ArrayType {
                     element_type,
                     num_elements: _,
                 } => {
-                    let elem_size = type_layout(&element_type, tys).pad_to_align().size();
+                    let elem_size = type_layout(&element_type, tys)?.pad_to_align().size();
 
                     ty = element_type;
                     offset += elem_size as u32 * index as u32;
                 }
-                _ => todo!("{:?}", ty),
+                _ => return Err(CompileError::unsupported_type(&ty)),
             }
         }
 
@@ -1183,32 +2056,33 @@ fn getelementptr_const(
 
         offset
     } else {
-        todo!("{:?}", address)
+        return Err(CompileError::unsupported_instr(address));
     };
 
     println!("Result: {:?}", result);
 
-    result
+    Ok(result)
 }
 
-type GlobalVarList<'a> = HashMap<&'a Name, (u32, Option<Constant>)>;
+pub(crate) type GlobalVarList<'a> = HashMap<&'a Name, (u32, Option<Constant>)>;
 
 fn compile_global_var_init<'a>(
     vars: &'a [GlobalVariable],
     globals: &mut GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
+    layout: &MemoryLayout,
+) -> CompileResult<Vec<Command>> {
     let mut cmds = Vec::new();
 
-    for var in vars {
-        cmds.extend(one_global_var_init(var, &globals, tys));
+    // Declarations like the heap symbols have an address but nothing to initialize
+    for var in vars.iter().filter(|v| v.initializer.is_some()) {
+        cmds.extend(one_global_var_init(var, globals, tys, layout)?);
     }
 
     // TODO: This needs a better system
     static CONSTANTS: &[(&str, i32)] = &[
         ("%%31BITSHIFT", 1 << 31),
         ("%%ROW_SIZE", ROW_SIZE as i32),
-        ("%%SIXTEEN", 16),
         ("%%-1", -1),
     ];
 
@@ -1219,7 +2093,13 @@ fn compile_global_var_init<'a>(
         ));
     }
 
-    for value in 0..31 {
+    // Used by `intrinsic:setptr` to turn an address into a position (or a page and offset)
+    cmds.push(assign_lit(ScoreHolder::new("%%MEM_Y".to_string()).unwrap(), layout.y));
+    cmds.push(assign_lit(ScoreHolder::new("%%MEM_Z".to_string()).unwrap(), layout.z));
+    cmds.push(assign_lit(ScoreHolder::new("%%MEM_PAGE".to_string()).unwrap(), layout.page_words()));
+
+    // This includes `1 << 31`, so shifting by 31 can multiply by `%%-2147483648`
+    for value in 0..32 {
         cmds.push(assign_lit(
             ScoreHolder::new(format!("%%{}", 1 << value)).unwrap(),
             1 << value,
@@ -1228,43 +2108,63 @@ fn compile_global_var_init<'a>(
 
     cmds.push(assign_lit(cmd_count(), 0));
 
-    cmds
+    Ok(cmds)
 }
 
-fn global_var_layout<'a>(v: &'a [GlobalVariable], funcs: &[Function], alloc: &mut StaticAllocator, tys: &Types) -> GlobalVarList<'a> {
+/// The allocator in the runtime finds the heap through these, like symbols defined by a linker script
+pub const HEAP_START_SYMBOL: &str = "__langcraft_heap_start";
+pub const HEAP_END_SYMBOL: &str = "__langcraft_heap_end";
+
+fn global_var_layout<'a>(v: &'a [GlobalVariable], funcs: &[Function], alloc: &mut StaticAllocator, heap: &std::ops::Range<u32>, tys: &Types) -> CompileResult<GlobalVarList<'a>> {
     let mut result = HashMap::new();
-    for v in v.iter() {
+    // Declarations without an initializer are left out,
+    // so any reference to a global that was never defined is caught later
+    for v in v.iter().filter(|v| v.initializer.is_some()) {
         let pointee_type = if let Type::PointerType { pointee_type, .. } = &v.ty.as_ref() {
             pointee_type
         } else {
             unreachable!()
         };
 
-        let start = alloc.reserve(type_layout(pointee_type, tys).size() as u32);
+        let start = alloc.reserve(type_layout(pointee_type, tys)?.size() as u32)?;
         result.insert(&v.name, (start, Some((**v.initializer.as_ref().unwrap()).clone())));
     }
 
+    for v in v.iter().filter(|v| v.initializer.is_none()) {
+        match &v.name {
+            Name::Name(n) if **n == HEAP_START_SYMBOL => { result.insert(&v.name, (heap.start, None)); }
+            Name::Name(n) if **n == HEAP_END_SYMBOL => { result.insert(&v.name, (heap.end, None)); }
+            _ => {}
+        }
+    }
+
     for func in funcs.iter() {
         let name = Box::leak(Box::new(Name::Name(Box::new(func.name.clone()))));
         result.insert(name, (u32::MAX, None));
     }
 
-    result
+    Ok(result)
 }
 
-pub fn make_zeroed(ty: &Type, tys: &Types) -> Constant {
-    match ty {
+pub fn make_zeroed(ty: &Type, tys: &Types) -> CompileResult<Constant> {
+    let zeroed = match ty {
         Type::NamedStructType {
             name,
         } => {
-            let struct_ty = tys.named_struct_def(name).unwrap();
-            make_zeroed(named_as_type(struct_ty).unwrap(), tys)
+            let struct_ty = tys
+                .named_struct_def(name)
+                .and_then(named_as_type)
+                .ok_or_else(|| CompileError::layout(ty))?;
+            make_zeroed(struct_ty, tys)?
         }
         Type::StructType {
             element_types,
             is_packed,
         } => {
-            let values = element_types.iter().map(|et| ConstantRef::new(make_zeroed(et, tys))).collect();
+            let values = element_types
+                .iter()
+                .map(|et| make_zeroed(et, tys).map(ConstantRef::new))
+                .collect::<CompileResult<_>>()?;
             Constant::Struct {
                 name: None,
                 values,
@@ -1275,8 +2175,7 @@ pub fn make_zeroed(ty: &Type, tys: &Types) -> Constant {
             element_type,
             num_elements,
         } => {
-            let elements = std::iter::repeat(ConstantRef::new(make_zeroed(element_type, tys)))
-                .take(*num_elements)
+            let elements = std::iter::repeat_n(ConstantRef::new(make_zeroed(element_type, tys)?), *num_elements)
                 .collect();
             Constant::Array {
                 element_type: (*element_type).clone(),
@@ -1287,8 +2186,12 @@ pub fn make_zeroed(ty: &Type, tys: &Types) -> Constant {
             bits: *bits,
             value: 0,
         },
-        _ => todo!("{:?}", ty),
-    }
+        Type::FPType(FPType::Single) => Constant::Float(Float::Single(0.0)),
+        Type::FPType(FPType::Double) => Constant::Float(Float::Double(0.0)),
+        _ => return Err(CompileError::unsupported_type(ty)),
+    };
+
+    Ok(zeroed)
 }
 
 fn init_data(
@@ -1297,27 +2200,29 @@ fn init_data(
     mut value: Constant,
     globals: &GlobalVarList,
     tys: &Types,
-) -> BTreeMap<i32, u8> {
+) -> CompileResult<BTreeMap<i32, u8>> {
     if let Constant::AggregateZero(t) = value {
-        value = make_zeroed(&t, tys);
+        value = make_zeroed(&t, tys)?;
     }
     let value = value;
 
-    match ty {
+    let unsupported = |value: &Constant| Err(CompileError::unsupported_instr(value));
+
+    let data = match ty {
         Type::IntegerType { bits: 8 } => {
-            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys) {
+            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys)? {
                 i8::try_from(c).unwrap() as u8
             } else {
-                todo!("{:?}", value)
+                return unsupported(&value);
             };
 
             std::iter::once((start_addr, val)).collect()
         }
         Type::IntegerType { bits: 16 } => {
-            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys) {
+            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys)? {
                 c
             } else {
-                todo!("{:?}", value)
+                return unsupported(&value);
             };
 
             (val as u16)
@@ -1328,10 +2233,10 @@ fn init_data(
                 .collect()
         }
         Type::IntegerType { bits: 32 } => {
-            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys) {
+            let val = if let MaybeConst::Const(c) = eval_constant(&value, globals, tys)? {
                 c
             } else {
-                todo!("{:?}", value)
+                return unsupported(&value);
             };
 
             val.to_le_bytes()
@@ -1344,7 +2249,47 @@ fn init_data(
             let val: u64 = if let Constant::Int { bits: 64, value } = value {
                 value
             } else {
-                todo!("{:?}", value)
+                return unsupported(&value);
+            };
+
+            val.to_le_bytes()
+                .iter()
+                .enumerate()
+                .map(|(idx, byte)| (start_addr + idx as i32, *byte))
+                .collect()
+        }
+        Type::IntegerType { bits } if *bits > 64 => {
+            let words = if let Some(words) = crate::wide_int::words(&value) {
+                words
+            } else {
+                return unsupported(&value);
+            };
+
+            words
+                .iter()
+                .flat_map(|word| word.to_le_bytes().to_vec())
+                .enumerate()
+                .map(|(idx, byte)| (start_addr + idx as i32, byte))
+                .collect()
+        }
+        Type::FPType(FPType::Single) => {
+            let val = if let Constant::Float(Float::Single(value)) = value {
+                value
+            } else {
+                return unsupported(&value);
+            };
+
+            val.to_le_bytes()
+                .iter()
+                .enumerate()
+                .map(|(idx, byte)| (start_addr + idx as i32, *byte))
+                .collect()
+        }
+        Type::FPType(FPType::Double) => {
+            let val = if let Constant::Float(Float::Double(value)) = value {
+                value
+            } else {
+                return unsupported(&value);
             };
 
             val.to_le_bytes()
@@ -1357,9 +2302,9 @@ fn init_data(
             pointee_type: _,
             addr_space: _,
         } => {
-            let val = match eval_constant(&value, globals, tys) {
+            let val = match eval_constant(&value, globals, tys)? {
                 MaybeConst::Const(c) => c,
-                _ => todo!("{:?}", value),
+                _ => return unsupported(&value),
             };
 
             val.to_le_bytes()
@@ -1380,26 +2325,28 @@ fn init_data(
                     assert_eq!(&**element_type, &**et);
                     elements
                 }
-                _ => todo!("{:?}", value),
+                _ => return unsupported(&value),
             };
 
             assert_eq!(*num_elements, vals.len());
 
-            vals.iter()
-                .enumerate()
-                .flat_map(|(idx, val)| {
-                    let offset = offset_of_array(element_type, idx as u32, tys);
-                    let field_addr = start_addr + offset as i32;
-                    init_data(field_addr, element_type, (**val).clone(), globals, tys)
-                })
-                .collect()
+            let mut data = BTreeMap::new();
+            for (idx, val) in vals.iter().enumerate() {
+                let offset = offset_of_array(element_type, idx as u32, tys)?;
+                let field_addr = start_addr + offset as i32;
+                data.extend(init_data(field_addr, element_type, (**val).clone(), globals, tys)?);
+            }
+            data
         }
         Type::NamedStructType {
             name,
         } => {
-            let struct_ty = tys.named_struct_def(name).unwrap();
+            let struct_ty = tys
+                .named_struct_def(name)
+                .and_then(named_as_type)
+                .ok_or_else(|| CompileError::layout(ty))?;
 
-            init_data(start_addr, named_as_type(struct_ty).unwrap(), value, globals, tys)
+            init_data(start_addr, struct_ty, value, globals, tys)?
         }
         Type::StructType {
             element_types,
@@ -1414,29 +2361,28 @@ fn init_data(
                     assert_eq!(*is_packed, ip);
                     values
                 }
-                _ => todo!("data value {:?}", value),
+                _ => return unsupported(&value),
             };
 
             assert_eq!(element_types.len(), vals.len());
 
-            element_types
-                .iter()
-                .zip(vals.iter())
-                .enumerate()
-                .flat_map(|(idx, (field_ty, value))| {
-                    let offset = offset_of(element_types, *is_packed, idx as u32, tys);
-                    let field_addr = start_addr + offset as i32;
-                    init_data(field_addr, field_ty, (**value).clone(), globals, tys)
-                })
-                .collect()
+            let mut data = BTreeMap::new();
+            for (idx, (field_ty, value)) in element_types.iter().zip(vals.iter()).enumerate() {
+                let offset = offset_of(element_types, *is_packed, idx as u32, tys)?;
+                let field_addr = start_addr + offset as i32;
+                data.extend(init_data(field_addr, field_ty, (**value).clone(), globals, tys)?);
+            }
+            data
         }
-        _ => todo!("data type {:?}", ty),
-    }
+        _ => return Err(CompileError::unsupported_type(ty)),
+    };
+
+    Ok(data)
 }
 
-fn one_global_var_init(v: &GlobalVariable, globals: &GlobalVarList, tys: &Types) -> Vec<Command> {
+fn one_global_var_init(v: &GlobalVariable, globals: &GlobalVarList, tys: &Types, layout: &MemoryLayout) -> CompileResult<Vec<Command>> {
     if matches!(v.name, Name::Number(_)) {
-        todo!()
+        return Err(CompileError::layout(&v.name));
     }
 
     let start = globals.get(&v.name).unwrap().0;
@@ -1455,7 +2401,7 @@ fn one_global_var_init(v: &GlobalVariable, globals: &GlobalVarList, tys: &Types)
                 (*v.initializer.clone().unwrap()).clone(),
                 globals,
                 tys,
-            );
+            )?;
 
             let mut cmds = Vec::new();
             cmds.push(assign_lit(target, start as i32));
@@ -1482,13 +2428,13 @@ fn one_global_var_init(v: &GlobalVariable, globals: &GlobalVarList, tys: &Types)
                     .map(|word| i32::from_le_bytes(word.try_into().unwrap()));
 
                 for (word_idx, word) in all_words.enumerate() {
-                    cmds.push(set_memory(word, start as i32 + word_idx as i32 * 4));
+                    cmds.push(set_memory(word, start as i32 + word_idx as i32 * 4, layout));
                 }
             }
 
-            cmds
+            Ok(cmds)
         }
-        _ => todo!("{:?}", v.ty),
+        _ => Err(CompileError::unsupported_type(&v.ty)),
     }
 }
 
@@ -1563,16 +2509,16 @@ fn compile_memset(
     arguments: &[(Operand, Vec<ParameterAttribute>)],
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
+) -> CompileResult<Vec<Command>> {
     if let [(dest, _), (value, _), (len, _), (volatile, _)] = &arguments[..] {
         let volatile = as_const_int(1, volatile).unwrap() != 0;
 
         if volatile {
-            todo!()
+            return Err(CompileError::unsupported_intrinsic("volatile memset"));
         }
 
-        let (mut cmds, dest1) = eval_operand(dest, globals, tys);
-        let (tmp, value1) = eval_operand(value, globals, tys);
+        let (mut cmds, dest1) = eval_operand(dest, globals, tys)?;
+        let (tmp, value1) = eval_operand(value, globals, tys)?;
         cmds.extend(tmp);
 
         let len1 = if let Some(value) = as_const_64(len) {
@@ -1580,7 +2526,7 @@ fn compile_memset(
             cmds.push(assign_lit(len1.clone(), value as i32));
             vec![len1]
         } else {
-            let (tmp, len1) = eval_operand(len, globals, tys);
+            let (tmp, len1) = eval_operand(len, globals, tys)?;
             cmds.extend(tmp);
             len1
         };
@@ -1600,9 +2546,9 @@ fn compile_memset(
             .into(),
         );
 
-        cmds
+        Ok(cmds)
     } else {
-        panic!("{:?}", arguments)
+        Err(CompileError::unsupported_instr(arguments))
     }
 }
 
@@ -1610,7 +2556,7 @@ fn compile_memcpy(
     arguments: &[(Operand, Vec<ParameterAttribute>)],
     globals: &GlobalVarList,
     tys: &Types,
-) -> (Vec<Command>, Option<Vec<Command>>) {
+) -> CompileResult<(Vec<Command>, Option<Vec<Command>>)> {
     let get_align = |attrs: &[ParameterAttribute]| -> Option<u64> {
         attrs
             .iter()
@@ -1628,11 +2574,11 @@ fn compile_memcpy(
         let volatile = as_const_int(1, volatile).unwrap() != 0;
 
         if volatile {
-            todo!()
+            return Err(CompileError::unsupported_intrinsic("volatile memcpy"));
         }
 
-        let (mut cmds, src1) = eval_operand(src, globals, tys);
-        let (tmp, dest1) = eval_operand(dest, globals, tys);
+        let (mut cmds, src1) = eval_operand(src, globals, tys)?;
+        let (tmp, dest1) = eval_operand(dest, globals, tys)?;
         cmds.extend(tmp);
 
         assert_eq!(src1.len(), 1, "multiword pointer {:?}", src);
@@ -1652,7 +2598,7 @@ fn compile_memcpy(
             cmds.push(assign_lit(param(4, 0), 1));
             cmds.push(Command::Comment("!FIXUPCALL intrinsic:memcpy".into()));
 
-            return (cmds, Some(Vec::new()));
+            return Ok((cmds, Some(Vec::new())));
         } else if let Some(((d, s), len)) = dest_align.zip(src_align).zip(as_const_32(len)).filter(|((d, s), _)| d % 4 == 0 && s % 4 == 0) {
             let word_count = len / 4;
             let byte_count = len % 4;
@@ -1716,7 +2662,7 @@ fn compile_memcpy(
 
             cmds.push(Command::Comment("End memcpy".into()));
         } else {
-            let (tmp, len1) = eval_operand(len, globals, tys);
+            let (tmp, len1) = eval_operand(len, globals, tys)?;
             cmds.extend(tmp);
 
             assert_eq!(len1.len(), 1, "multiword length {:?}", len);
@@ -1735,9 +2681,9 @@ fn compile_memcpy(
             );
         }
 
-        (cmds, None)
+        Ok((cmds, None))
     } else {
-        panic!("{:?}", arguments);
+        Err(CompileError::unsupported_instr(arguments))
     }
 }
 
@@ -1745,12 +2691,12 @@ fn setup_arguments(
     arguments: &[(Operand, Vec<ParameterAttribute>)],
     globals: &HashMap<&Name, (u32, Option<Constant>)>,
     tys: &Types,
-) -> Vec<Command> {
+) -> CompileResult<Vec<Command>> {
     let mut before_cmds = Vec::new();
 
     // Set arguments
     for (idx, (arg, _attrs)) in arguments.iter().enumerate() {
-        match eval_maybe_const(arg, globals, tys) {
+        match eval_maybe_const(arg, globals, tys)? {
             MaybeConst::Const(score) => {
                 before_cmds.push(assign_lit(param(idx, 0), score));
             }
@@ -1764,49 +2710,164 @@ fn setup_arguments(
         }
     }
 
-    before_cmds
+    Ok(before_cmds)
 }
 
-fn compile_xor(
-    Xor {
-        operand0,
-        operand1,
-        dest,
-        debugloc: _,
-    }: &Xor,
+/// Pushes the return address and sets up the arguments of a call.
+///
+/// When calling a variadic function, `fixed_args` is the number of named parameters.
+/// The remaining arguments are pushed onto the stack (below the return address)
+/// and their address is passed as one extra parameter, so the caller has to run
+/// the returned cleanup commands once the call returns.
+fn setup_call(
+    arguments: &[(Operand, Vec<ParameterAttribute>)],
+    fixed_args: Option<usize>,
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
-    assert_eq!(operand0.get_type(tys), operand1.get_type(tys));
+) -> CompileResult<(Vec<Command>, Vec<Command>)> {
+    let mut before_cmds = Vec::new();
+    let mut cleanup_cmds = Vec::new();
 
-    let layout = type_layout(&operand0.get_type(tys), tys);
+    let is_variadic = fixed_args.is_some();
+    let fixed_args = fixed_args.unwrap_or(arguments.len());
+    if fixed_args > arguments.len() {
+        return Err(CompileError::unsupported_instr(arguments));
+    }
 
-    if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 1 }) {
-        let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+    let (arguments, var_args) = arguments.split_at(fixed_args);
 
-        let (tmp, op1) = eval_operand(operand1, globals, tys);
+    let vararg_start = get_unique_holder();
+    if is_variadic {
+        before_cmds.push(assign(vararg_start.clone(), stackptr()));
+    }
 
-        cmds.extend(tmp);
+    if !var_args.is_empty() {
+        let mut vararg_words = 0;
 
-        let dest = ScoreHolder::from_local_name(dest.clone(), layout.size());
-        let dest = dest.into_iter().next().unwrap();
+        for (arg, _attrs) in var_args.iter() {
+            let (cmds, source) = match eval_maybe_const(arg, globals, tys)? {
+                MaybeConst::Const(score) => {
+                    let tmp = get_unique_holder();
+                    (vec![assign_lit(tmp.clone(), score)], vec![tmp])
+                }
+                MaybeConst::NonConst(cmds, source) => (cmds, source),
+            };
 
-        let op0 = op0.into_iter().next().unwrap();
-        let op1 = op1.into_iter().next().unwrap();
+            before_cmds.extend(cmds);
+            vararg_words += source.len();
+            for word in source {
+                before_cmds.extend(push(word));
+            }
+        }
 
-        cmds.push(assign_lit(dest.clone(), 0));
+        cleanup_cmds.push(make_op_lit(stackptr(), "-=", 4 * vararg_words as i32));
+    }
 
-        let mut lhs_1 = Execute::new();
-        lhs_1.with_if(ExecuteCondition::Score {
-            target: op0.clone().into(),
-            target_obj: OBJECTIVE.into(),
-            kind: ExecuteCondKind::Matches((1..=1).into()),
-        });
-        lhs_1.with_if(ExecuteCondition::Score {
-            target: op1.clone().into(),
-            target_obj: OBJECTIVE.into(),
-            kind: ExecuteCondKind::Matches((0..=0).into()),
-        });
+    // Push return address
+    before_cmds.extend(push(ScoreHolder::new("%%fixup_return_addr".to_string()).unwrap()));
+
+    before_cmds.extend(setup_arguments(arguments, globals, tys)?);
+
+    if is_variadic {
+        before_cmds.push(assign(param(fixed_args, 0), vararg_start));
+    }
+
+    Ok((before_cmds, cleanup_cmds))
+}
+
+fn compile_va_arg(
+    VAArg {
+        arg_list,
+        cur_type,
+        dest,
+        debugloc: _,
+    }: &VAArg,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let (mut cmds, list) = eval_operand(arg_list, globals, tys)?;
+    let list = list[0].clone();
+
+    // Every variadic argument takes up a whole number of words on the stack
+    let size = type_layout(cur_type, tys)?.size();
+    let dest = ScoreHolder::from_local_name(dest.clone(), size);
+
+    let next_arg = get_unique_holder();
+
+    cmds.push(assign(ptr(), list.clone()));
+    cmds.push(
+        McFuncCall {
+            id: McFuncId::new("intrinsic:setptr"),
+        }
+        .into(),
+    );
+    cmds.push(read_ptr(next_arg.clone()));
+
+    for (word_idx, dest_word) in dest.iter().enumerate() {
+        cmds.push(assign(ptr(), next_arg.clone()));
+        cmds.push(make_op_lit(ptr(), "+=", 4 * word_idx as i32));
+        cmds.push(
+            McFuncCall {
+                id: McFuncId::new("intrinsic:setptr"),
+            }
+            .into(),
+        );
+        cmds.push(read_ptr(dest_word.clone()));
+    }
+
+    cmds.push(make_op_lit(next_arg.clone(), "+=", 4 * dest.len() as i32));
+    cmds.push(assign(ptr(), list));
+    cmds.push(
+        McFuncCall {
+            id: McFuncId::new("intrinsic:setptr"),
+        }
+        .into(),
+    );
+    cmds.push(write_ptr(next_arg));
+
+    Ok(cmds)
+}
+
+fn compile_xor(
+    Xor {
+        operand0,
+        operand1,
+        dest,
+        debugloc: _,
+    }: &Xor,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    assert_eq!(operand0.get_type(tys), operand1.get_type(tys));
+
+    let layout = type_layout(&operand0.get_type(tys), tys)?;
+
+    if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 1 }) {
+        let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+
+        let (tmp, op1) = eval_operand(operand1, globals, tys)?;
+
+        cmds.extend(tmp);
+
+        let dest = ScoreHolder::from_local_name(dest.clone(), layout.size());
+        let dest = dest.into_iter().next().unwrap();
+
+        let op0 = op0.into_iter().next().unwrap();
+        let op1 = op1.into_iter().next().unwrap();
+
+        cmds.push(assign_lit(dest.clone(), 0));
+
+        let mut lhs_1 = Execute::new();
+        lhs_1.with_if(ExecuteCondition::Score {
+            target: op0.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((1..=1).into()),
+        });
+        lhs_1.with_if(ExecuteCondition::Score {
+            target: op1.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((0..=0).into()),
+        });
         lhs_1.with_run(assign_lit(dest.clone(), 1));
 
         let mut rhs_1 = Execute::new();
@@ -1825,7 +2886,7 @@ fn compile_xor(
         cmds.push(lhs_1.into());
         cmds.push(rhs_1.into());
 
-        cmds
+        Ok(cmds)
     } else {
         compile_bitwise_word(operand0, operand1, dest.clone(), BitOp::Xor, globals, tys)
     }
@@ -1840,17 +2901,21 @@ fn compile_shl(
     }: &Shl,
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
+) -> CompileResult<Vec<Command>> {
+    if matches!(&*operand0.get_type(tys), Type::IntegerType { bits } if *bits > 64) {
+        return compile_shift_multiword(operand0, operand1, dest, ShiftKind::Shl, globals, tys);
+    }
+
     let operand1_is_32 = as_const_64(operand1) == Some(32);
 
     if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 64 }) && operand1_is_32 {
-        let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+        let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
         let dest = ScoreHolder::from_local_name(dest.clone(), 8);
 
         cmds.push(assign_lit(dest[0].clone(), 0));
         cmds.push(assign(dest[1].clone(), op0[0].clone()));
 
-        cmds
+        Ok(cmds)
     } else if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 64 }) {
         let (dest_lo, dest_hi) =
             if let [dest_lo, dest_hi] = &ScoreHolder::from_local_name(dest.clone(), 6)[..] {
@@ -1859,30 +2924,29 @@ fn compile_shl(
                 unreachable!()
             };
 
-        let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+        let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
 
         if let Some(shift) = as_const_64(operand1) {
-            let mut intra_byte = |max_bound: i32, dest_lo: ScoreHolder, dest_hi: ScoreHolder| {
-                let mul = 1 << shift;
-                cmds.push(mark_assertion_matches(false, op0[1].clone(), 0..=max_bound));
+            if shift == 0 {
+                cmds.push(assign(dest_lo, op0[0].clone()));
+                cmds.push(assign(dest_hi, op0[1].clone()));
+            } else if shift < 32 {
+                // hi' = (hi << shift) + (lo lshr (32 - shift))
+                cmds.push(assign(param(0, 0), op0[0].clone()));
+                cmds.push(assign_lit(param(1, 0), 32 - shift as i32));
+                cmds.push(
+                    McFuncCall {
+                        id: McFuncId::new("intrinsic:lshr"),
+                    }
+                    .into(),
+                );
+                cmds.push(assign(dest_hi.clone(), op0[1].clone()));
+                cmds.push(make_op_lit(dest_hi.clone(), "*=", 1 << shift));
+                cmds.push(make_op(dest_hi, "+=", param(0, 0)));
 
+                // lo' = lo << shift
                 cmds.push(assign(dest_lo.clone(), op0[0].clone()));
-                cmds.push(assign(dest_hi.clone(), op0[1].clone()));
-                let tmp = get_unique_holder();
-                cmds.push(assign_lit(tmp.clone(), mul));
-                cmds.push(make_op(dest_lo, "*=", tmp.clone()));
-                cmds.push(make_op(dest_hi.clone(), "*=", tmp.clone()));
-                
-                let tmp2 = get_unique_holder();
-                
-                cmds.push(assign(tmp2.clone(),op0[0].clone()));
-                cmds.push(assign_lit(tmp.clone(),1 << (32 - shift)));
-                cmds.push(make_op(tmp2.clone(), "/=", tmp));
-                cmds.push(make_op(dest_hi, "+=", tmp2));
-            };
-            
-            if shift < 32 {
-                intra_byte(((4294967295 as i64) >> shift) as i32, dest_lo, dest_hi);
+                cmds.push(make_op_lit(dest_lo, "*=", 1 << shift));
             } else {
                 let tmp = get_unique_holder();
 
@@ -1892,7 +2956,7 @@ fn compile_shl(
                 cmds.push(make_op(dest_hi, "*=", tmp));
             }
         } else {
-            match eval_maybe_const(operand1,globals,tys) {
+            match eval_maybe_const(operand1,globals,tys)? {
                 MaybeConst::NonConst(tmp, op1) => {
                     let mut op0i = op0.into_iter();
                     let mut op1i = op1.into_iter();
@@ -1917,11 +2981,11 @@ fn compile_shl(
                     cmds.push(assign(dest_lo, param(0, 0)));
                     cmds.push(assign(dest_hi, param(0, 1)));
                 }
-                _ => todo!("Add handling for {:?}", operand1)
+                MaybeConst::Const(_) => unreachable!(),
             }
         }
 
-        cmds
+        Ok(cmds)
     } else if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 48 }) {
         let (dest_lo, dest_hi) =
             if let [dest_lo, dest_hi] = &ScoreHolder::from_local_name(dest.clone(), 6)[..] {
@@ -1932,7 +2996,7 @@ fn compile_shl(
 
         let shift = as_const_int(48, operand1).unwrap();
 
-        let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+        let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
 
         cmds.push(mark_assertion_matches(false, op0[1].clone(), 0..=0));
 
@@ -1973,10 +3037,10 @@ fn compile_shl(
                 cmds.push(assign_lit(dest_lo, 0));
                 cmds.push(assign(dest_hi, op0[0].clone()));
             }
-            _ => todo!("{:?}", operand1),
+            _ => return Err(CompileError::unsupported_instr(operand1)),
         };
 
-        cmds
+        Ok(cmds)
     } else {
         let op0_type = operand0.get_type(tys);
 
@@ -1985,10 +3049,10 @@ fn compile_shl(
             Type::IntegerType { bits: 24 } => 24,
             Type::IntegerType { bits: 16 } => 16,
             Type::IntegerType { bits: 8 } => 8,
-            _ => todo!("{:?}, shift: {:?}", operand0, operand1),
+            ty => return Err(CompileError::unsupported_type(ty)),
         };
 
-        let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+        let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
         let op0 = op0.into_iter().next().unwrap();
 
         let dest = ScoreHolder::from_local_name(dest.clone(), 4)
@@ -1996,7 +3060,7 @@ fn compile_shl(
             .next()
             .unwrap();
 
-        match eval_maybe_const(operand1, globals, tys) {
+        match eval_maybe_const(operand1, globals, tys)? {
             MaybeConst::Const(c) => {
                 cmds.push(assign(dest.clone(), op0));
                 cmds.push(make_op_lit(dest.clone(), "*=", 1 << c));
@@ -2006,7 +3070,7 @@ fn compile_shl(
                         Type::IntegerType { bits: 8 } => 255,
                         Type::IntegerType { bits: 16 } => 65535,
                         Type::IntegerType { bits: 24 } => 16777216,
-                        ty => todo!("{:?}", ty),
+                        _ => unreachable!(),
                     };
 
                     cmds.push(mark_assertion_matches(false, dest, ..=max_val));
@@ -2031,7 +3095,7 @@ fn compile_shl(
             }
         }
 
-        cmds
+        Ok(cmds)
     }
 }
 
@@ -2040,12 +3104,16 @@ fn compile_lshr(
         operand0,
         operand1,
         dest,
-        debugloc,
+        debugloc: _,
     }: &LShr,
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
-    let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+) -> CompileResult<Vec<Command>> {
+    if matches!(&*operand0.get_type(tys), Type::IntegerType { bits } if *bits > 64) {
+        return compile_shift_multiword(operand0, operand1, dest, ShiftKind::LShr, globals, tys);
+    }
+
+    let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
 
     let op0_type = operand0.get_type(tys);
 
@@ -2059,15 +3127,13 @@ fn compile_lshr(
             dest.clone(),
         ));
 
-        cmds
+        Ok(cmds)
     } else {
-        if let Type::IntegerType { bits } = &*op0_type {
-            // this error was moved down
-        } else {
-            todo!("{:?}", operand0);
+        if !matches!(&*op0_type, Type::IntegerType { .. }) {
+            return Err(CompileError::unsupported_type(&op0_type));
         }
 
-        let (tmp, op1) = eval_operand(operand1, globals, tys);
+        let (tmp, op1) = eval_operand(operand1, globals, tys)?;
         let op1 = op1.into_iter().next().unwrap();
 
         if op0.len() == 1 {
@@ -2102,11 +3168,10 @@ fn compile_lshr(
             cmds.push(assign(dest.next().unwrap(), param(0, 0)));
             cmds.push(assign(dest.next().unwrap(), param(0, 1)));
         } else {
-            dumploc(debugloc);
-            todo!("Logical Shift Right with {} bits",op0.len());
+            return Err(CompileError::unsupported_type(&op0_type));
         }
 
-        cmds
+        Ok(cmds)
     }
 }
 
@@ -2115,27 +3180,36 @@ fn compile_ashr(
         operand0,
         operand1,
         dest,
-        debugloc,
+        debugloc: _,
     }: &AShr,
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
-    let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+) -> CompileResult<Vec<Command>> {
+    if matches!(&*operand0.get_type(tys), Type::IntegerType { bits } if *bits > 64) {
+        return compile_shift_multiword(operand0, operand1, dest, ShiftKind::AShr, globals, tys);
+    }
+
+    let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
 
     let op0_type = operand0.get_type(tys);
 
     if let Some(value) = as_const_64(operand1) {
-        dumploc(debugloc);
-        todo!("[FATAL] 64-bit arithmetic shift right is not supported.");
+        assert!(matches!(&*op0_type, Type::IntegerType { bits: 64 }));
+
+        cmds.extend(ashr_64_bit_const(
+            op0[0].clone(),
+            op0[1].clone(),
+            value as i32,
+            dest.clone(),
+        ));
+
+        Ok(cmds)
     } else {
-        if let Type::IntegerType { bits } = &*op0_type {
-            // this error was moved down
-        } else {
-            dumploc(debugloc);
-            todo!("[FATAL] Arithmetic shift right is only implemented for integers.");
+        if !matches!(&*op0_type, Type::IntegerType { .. }) {
+            return Err(CompileError::unsupported_type(&op0_type));
         }
 
-        let (tmp, op1) = eval_operand(operand1, globals, tys);
+        let (tmp, op1) = eval_operand(operand1, globals, tys)?;
         let op1 = op1.into_iter().next().unwrap();
 
         if op0.len() == 1 {
@@ -2154,12 +3228,26 @@ fn compile_ashr(
                 .into(),
             );
             cmds.push(assign(dest, param(0, 0)));
+        } else if op0.len() == 2 {
+            let mut dest = ScoreHolder::from_local_name(dest.clone(), 8).into_iter();
+
+            cmds.extend(tmp);
+            cmds.push(assign(param(0, 0), op0[0].clone()));
+            cmds.push(assign(param(0, 1), op0[1].clone()));
+            cmds.push(assign(param(1, 0), op1));
+            cmds.push(
+                McFuncCall {
+                    id: McFuncId::new("intrinsic:ashr64"),
+                }
+                .into(),
+            );
+            cmds.push(assign(dest.next().unwrap(), param(0, 0)));
+            cmds.push(assign(dest.next().unwrap(), param(0, 1)));
         } else {
-            dumploc(debugloc);
-            todo!("[FATAL] Arithmetic Shift Right with {} bits is unimplemented", op0.len());
+            return Err(CompileError::unsupported_type(&op0_type));
         }
 
-        cmds
+        Ok(cmds)
     }
 }
 
@@ -2168,21 +3256,26 @@ fn compile_call(
         function,
         arguments,
         dest,
-        debugloc,
         ..
     }: &Call,
     globals: &GlobalVarList,
     tys: &Types,
-) -> (Vec<Command>, Option<Vec<Command>>) {
+) -> CompileResult<(Vec<Command>, Option<Vec<Command>>)> {
     let function = match function {
-        Either::Left(asm) => todo!("inline assembly {:?}", asm),
+        Either::Left(asm) => return Err(CompileError::unsupported_instr(asm)),
         Either::Right(operand) => operand,
     };
 
+    // The number of named parameters, if the callee is variadic
+    let fixed_args = |ty: &Type| match ty {
+        Type::FuncType { param_types, is_var_arg: true, .. } => Some(param_types.len()),
+        _ => None,
+    };
+
     let static_call = if let Operand::ConstantOperand(c) = function {
         if let Constant::GlobalReference { name: Name::Name(name), ty } = &**c {
-            if let Type::FuncType { result_type, is_var_arg: false, .. } = &**ty {
-                Some((name, result_type))
+            if let Type::FuncType { result_type, .. } = &**ty {
+                Some((name, result_type, fixed_args(ty)))
             } else {
                 None
             }
@@ -2190,29 +3283,24 @@ fn compile_call(
             let (ref_name, ref_ty) = if let Constant::GlobalReference { name: Name::Name(name), ty } = &**operand {
                 (name, ty)
             } else {
-                dumploc(debugloc);
-                panic!("[FATAL] Cannot handle this callee. Are you trying to double-cast?");
+                return Err(CompileError::unsupported_instr(c));
             };
 
             if let Type::FuncType { result_type: _, is_var_arg: false, .. } = &**ref_ty {
-
             } else {
-                dumploc(debugloc);
-                panic!("[FATAL] Cannot directly cast a data pointer to a function.");
+                return Err(CompileError::unsupported_instr(c));
             }
 
             let to_inner_ty = if let Type::PointerType { pointee_type, addr_space: _ } = &**to_type {
                 pointee_type
             } else {
-                dumploc(debugloc);
-                panic!("[FATAL] Cannot call a non-pointer");
+                return Err(CompileError::unsupported_instr(c));
             };
 
             if let Type::FuncType { result_type, is_var_arg: false, .. } = &**to_inner_ty {
-                Some((ref_name, result_type))
+                Some((ref_name, result_type, None))
             } else {
-                dumploc(debugloc);
-                panic!("[FATAL] Cannot call a data pointer");
+                return Err(CompileError::unsupported_instr(c));
             }
         } else {
             None
@@ -2231,23 +3319,28 @@ fn compile_call(
         None
     };
 
-    if let Some((name, result_type)) = static_call {
-        let dest_size = type_layout(result_type, tys).size();
+    if let Some((name, result_type, fixed_args)) = static_call {
+        let dest_size = type_layout(result_type, tys)?.size();
         let dest = dest
             .clone()
             .map(|d| ScoreHolder::from_local_name(d, dest_size));
 
+        if inline_asm::is_asm(name) {
+            let cmds = inline_asm::compile_call(name, arguments, dest.as_deref(), result_type, globals, tys)?;
+            return Ok((cmds, None));
+        }
+
         match name.as_str() {
             "llvm.assume" => {
                 assert_eq!(arguments.len(), 1);
                 assert!(dest.is_none());
                 println!("Assumption {:?}", arguments[0]);
 
-                let (mut cmds, op) = eval_operand(&arguments[0].0, globals, tys);
+                let (mut cmds, op) = eval_operand(&arguments[0].0, globals, tys)?;
 
                 cmds.push(mark_assertion_matches(false, op[0].clone(), 1..=1));
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "insert_asm" => {
                 assert_eq!(arguments.len(), 3);
@@ -2263,7 +3356,7 @@ fn compile_call(
                 let addr = if let Operand::ConstantOperand(c) = &ptr.0 {
                     c
                 } else {
-                    todo!("{:?}", ptr)
+                    return Err(CompileError::unsupported_instr(&ptr.0));
                 };
 
                 let addr = if let Constant::GetElementPtr(g) = &**addr {
@@ -2276,7 +3369,7 @@ fn compile_call(
                     let addr = if let Constant::GlobalReference { name, .. } = &**address {
                         name
                     } else {
-                        todo!("{:?}", address)
+                        return Err(CompileError::unsupported_instr(address));
                     };
 
                     let indices_ok =
@@ -2286,12 +3379,12 @@ fn compile_call(
                         *indices[2] == Constant::Int { bits: 32, value: 0 };
 
                     if !indices_ok {
-                        todo!("{:?}", indices)
+                        return Err(CompileError::unsupported_instr(indices));
                     }
 
                     addr
                 } else {
-                    todo!("{:?}", addr)
+                    return Err(CompileError::unsupported_instr(addr));
                 };
 
                 let data = &globals.get(addr).unwrap().1;
@@ -2311,16 +3404,16 @@ fn compile_call(
                             if element_type == &tys.i8() {
                                 elements
                             } else {
-                                todo!("{:?}", element_type)
+                                return Err(CompileError::unsupported_type(element_type));
                             }
                         } else {
-                            todo!("{:?}", c)
+                            return Err(CompileError::unsupported_instr(c));
                         }
                     } else {
-                        todo!("{:?}", values)
+                        return Err(CompileError::unsupported_instr(values));
                     }
                 } else {
-                    todo!("{:?}", data)
+                    return Err(CompileError::unsupported_instr(data));
                 };
 
                 let data = data[..len as usize]
@@ -2336,18 +3429,20 @@ fn compile_call(
 
                 let text = std::str::from_utf8(&data).unwrap();
 
-                let (mut cmds, arg) = eval_operand(&arguments[2].0, globals, tys);
+                let (mut cmds, arg) = eval_operand(&arguments[2].0, globals, tys)?;
                 let arg = arg.into_iter().next().unwrap();
 
                 let interpolated = text
                     .replace("$0", &arg.to_string())
                     .replace("$obj", OBJECTIVE);
 
-                let cmd = interpolated.parse().unwrap();
+                let cmd = interpolated
+                    .parse()
+                    .map_err(|e| CompileError::invalid_asm(&format!("invalid command `{}`: {}", interpolated, e)))?;
 
                 cmds.push(cmd);
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "print_raw" => {
                 assert_eq!(arguments.len(), 2);
@@ -2361,7 +3456,7 @@ fn compile_call(
                 let addr = if let Operand::ConstantOperand(c) = &ptr.0 {
                     c
                 } else {
-                    todo!("{:?}", ptr)
+                    return Err(CompileError::unsupported_instr(&ptr.0));
                 };
 
                 let addr = if let Constant::GetElementPtr(g) = &**addr {
@@ -2374,7 +3469,7 @@ fn compile_call(
                     let addr = if let Constant::GlobalReference { name, .. } = &**address {
                         name
                     } else {
-                        todo!("{:?}", address)
+                        return Err(CompileError::unsupported_instr(address));
                     };
 
                     let indices_ok =
@@ -2384,12 +3479,12 @@ fn compile_call(
                         *indices[2] == Constant::Int { bits: 32, value: 0 };
 
                     if !indices_ok {
-                        todo!("{:?}", indices)
+                        return Err(CompileError::unsupported_instr(indices));
                     }
 
                     addr
                 } else {
-                    todo!("{:?}", ptr)
+                    return Err(CompileError::unsupported_instr(&ptr.0));
                 };
 
                 let data = &globals.get(addr).unwrap().1;
@@ -2409,16 +3504,16 @@ fn compile_call(
                             if element_type == &tys.i8() {
                                 elements
                             } else {
-                                todo!("{:?}", element_type)
+                                return Err(CompileError::unsupported_type(element_type));
                             }
                         } else {
-                            todo!("{:?}", c)
+                            return Err(CompileError::unsupported_instr(c));
                         }
                     } else {
-                        todo!("{:?}", values)
+                        return Err(CompileError::unsupported_instr(values));
                     }
                 } else {
-                    todo!("{:?}", data)
+                    return Err(CompileError::unsupported_instr(data));
                 };
 
                 let data = data[..len as usize]
@@ -2435,10 +3530,10 @@ fn compile_call(
                 let text = std::str::from_utf8(&data).unwrap();
 
                 if text.contains('"') {
-                    todo!("{:?}", text)
+                    return Err(CompileError::unsupported_instr(text));
                 }
 
-                (
+                Ok((
                     vec![Tellraw {
                         target: cir::Selector {
                             var: cir::SelectorVariable::AllPlayers,
@@ -2449,14 +3544,14 @@ fn compile_call(
                     }
                     .into()],
                     None,
-                )
+                ))
             }
             "print" => {
                 assert_eq!(arguments.len(), 1);
 
                 assert!(dest.is_none());
 
-                let (mut cmds, name) = eval_operand(&arguments[0].0, globals, tys);
+                let (mut cmds, name) = eval_operand(&arguments[0].0, globals, tys)?;
 
                 let name = name[0].clone();
 
@@ -2473,7 +3568,7 @@ fn compile_call(
                     .into(),
                 );
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "turtle_set" => {
                 assert_eq!(arguments.len(), 1);
@@ -2482,7 +3577,7 @@ fn compile_call(
 
                 let mut cmds = vec![Command::Comment("call to turtle_set".to_string())];
 
-                match eval_maybe_const(&arguments[0].0, globals, tys) {
+                match eval_maybe_const(&arguments[0].0, globals, tys)? {
                     MaybeConst::Const(mc_block) => {
                         let mc_block = McBlock::try_from(mc_block).unwrap();
 
@@ -2526,7 +3621,7 @@ fn compile_call(
                     }
                 }
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "turtle_check" => {
                 assert_eq!(arguments.len(), 1);
@@ -2536,10 +3631,10 @@ fn compile_call(
                 let dest = dest[0].clone();
 
                 let mc_block =
-                    if let MaybeConst::Const(c) = eval_maybe_const(&arguments[0].0, globals, tys) {
+                    if let MaybeConst::Const(c) = eval_maybe_const(&arguments[0].0, globals, tys)? {
                         c
                     } else {
-                        todo!("non-constant block {:?}", &arguments[0].0)
+                        return Err(CompileError::unsupported_instr(&arguments[0].0));
                     };
 
                 let block = McBlock::try_from(mc_block).unwrap().to_string();
@@ -2561,7 +3656,7 @@ fn compile_call(
                     block,
                 });
 
-                (vec![cmd.into()], None)
+                Ok((vec![cmd.into()], None))
             }
             "turtle_get_char" => {
                 assert_eq!(arguments.len(), 0);
@@ -2623,7 +3718,7 @@ fn compile_call(
                     cmds.push(cmd.into());
                 }
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "turtle_get" => {
                 assert_eq!(arguments.len(), 0);
@@ -2657,16 +3752,16 @@ fn compile_call(
                     cmds.push(cmd.into());
                 }
 
-                (cmds, None)
+                Ok((cmds, None))
             }
-            "llvm.dbg.label" => (vec![], None),
-            "llvm.dbg.declare" => (vec![], None),
-            "llvm.dbg.value" => (vec![], None),
+            "llvm.dbg.label" => Ok((vec![], None)),
+            "llvm.dbg.declare" => Ok((vec![], None)),
+            "llvm.dbg.value" => Ok((vec![], None)),
             "llvm.ctlz.i32" => {
                 let dest = dest.unwrap().into_iter().next().unwrap();
                 let mut cmds = Vec::new();
 
-                match eval_maybe_const(&arguments[0].0, globals, tys) {
+                match eval_maybe_const(&arguments[0].0, globals, tys)? {
                     MaybeConst::Const(c) => {
                         cmds.push(assign_lit(dest, c.leading_zeros() as i32));
                     }
@@ -2684,7 +3779,7 @@ fn compile_call(
                     }
                 }
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "llvm.usub.with.overflow.i32" => {
                 let mut dest_words = dest.unwrap().into_iter();
@@ -2694,7 +3789,7 @@ fn compile_call(
                 let mut cmds = Vec::new();
                 cmds.push(assign_lit(dest_flag.clone(), 0));
 
-                cmds.extend(setup_arguments(arguments, globals, tys));
+                cmds.extend(setup_arguments(arguments, globals, tys)?);
 
                 cmds.push(make_op_lit(param(1, 0), "*=", -1));
 
@@ -2705,11 +3800,11 @@ fn compile_call(
                     assign_lit(dest_flag, 1),
                 ));
 
-                (cmds, None)
+                Ok((cmds, None))
             }
             "llvm.fshr.i32" => {
                 let dest = dest.unwrap().into_iter().next().unwrap();
-                let mut cmds = setup_arguments(arguments, globals, tys);
+                let mut cmds = setup_arguments(arguments, globals, tys)?;
                 cmds.push(
                     McFuncCall {
                         id: McFuncId::new("intrinsic:llvm_fshr_i32"),
@@ -2717,11 +3812,11 @@ fn compile_call(
                     .into(),
                 );
                 cmds.push(assign(dest, return_holder(0)));
-                (cmds, None)
+                Ok((cmds, None))
             }
             "llvm.memset.p0i8.i32" | "llvm.memset.p0i8.i64" => {
                 assert_eq!(dest, None);
-                (compile_memset(arguments, globals, tys), None)
+                Ok((compile_memset(arguments, globals, tys)?, None))
             }
             "llvm.memcpy.p0i8.p0i8.i32" => {
                 assert_eq!(dest, None);
@@ -2731,8 +3826,8 @@ fn compile_call(
                 let dest = dest.unwrap().into_iter().next().unwrap();
 
                 if let [(lhs, _), (rhs, _)] = &arguments[..] {
-                    let (mut cmds, l) = eval_operand(lhs, globals, tys);
-                    let (tmp, r) = eval_operand(rhs, globals, tys);
+                    let (mut cmds, l) = eval_operand(lhs, globals, tys)?;
+                    let (tmp, r) = eval_operand(rhs, globals, tys)?;
                     cmds.extend(tmp);
 
                     let l = l.into_iter().next().unwrap();
@@ -2746,7 +3841,7 @@ fn compile_call(
 
                     cmds.push(assign(dest.clone(), l));
                     cmds.push(make_op(dest, "+=", tmp));
-                    (cmds, None)
+                    Ok((cmds, None))
                 } else {
                     unreachable!()
                 }
@@ -2755,8 +3850,8 @@ fn compile_call(
                 let dest = dest.unwrap().into_iter().next().unwrap();
 
                 if let [(lhs, _), (rhs, _)] = &arguments[..] {
-                    let (mut cmds, l) = eval_operand(lhs, globals, tys);
-                    let (tmp, r) = eval_operand(rhs, globals, tys);
+                    let (mut cmds, l) = eval_operand(lhs, globals, tys)?;
+                    let (tmp, r) = eval_operand(rhs, globals, tys)?;
                     cmds.extend(tmp);
 
                     let l = l.into_iter().next().unwrap();
@@ -2764,18 +3859,82 @@ fn compile_call(
 
                     cmds.push(assign(dest.clone(), l));
                     cmds.push(make_op(dest, "+=", r));
-                    (cmds, None)
+                    Ok((cmds, None))
                 } else {
                     unreachable!()
                 }
             }
             "llvm.lifetime.start.p0i8" => {
                 assert_eq!(dest, None);
-                (vec![], None)
+                Ok((vec![], None))
             }
             "llvm.lifetime.end.p0i8" => {
                 assert_eq!(dest, None);
-                (vec![], None)
+                Ok((vec![], None))
+            }
+            "llvm.va_start" => {
+                assert_eq!(arguments.len(), 1);
+                assert_eq!(dest, None);
+
+                // A `va_list` is just a pointer to the next variadic argument
+                let (mut cmds, list) = eval_operand(&arguments[0].0, globals, tys)?;
+                cmds.push(assign(ptr(), list[0].clone()));
+                cmds.push(
+                    McFuncCall {
+                        id: McFuncId::new("intrinsic:setptr"),
+                    }
+                    .into(),
+                );
+                cmds.push(write_ptr(varargs()));
+
+                Ok((cmds, None))
+            }
+            "llvm.eh.typeid.for" => {
+                assert_eq!(arguments.len(), 1);
+
+                let dest = dest.as_ref().expect("llvm.eh.typeid.for should return a value");
+
+                // The type id is just the address of the type info,
+                // which is also what `__cxa_throw` uses as the selector
+                let (mut cmds, type_info) = eval_operand(&arguments[0].0, globals, tys)?;
+                cmds.push(assign(dest[0].clone(), type_info[0].clone()));
+
+                Ok((cmds, None))
+            }
+            "llvm.va_end" => {
+                assert_eq!(arguments.len(), 1);
+                assert_eq!(dest, None);
+                Ok((vec![], None))
+            }
+            "llvm.va_copy" => {
+                assert_eq!(arguments.len(), 2);
+                assert_eq!(dest, None);
+
+                let (mut cmds, dst) = eval_operand(&arguments[0].0, globals, tys)?;
+                let (tmp, src) = eval_operand(&arguments[1].0, globals, tys)?;
+                cmds.extend(tmp);
+
+                let next_arg = get_unique_holder();
+
+                cmds.push(assign(ptr(), src[0].clone()));
+                cmds.push(
+                    McFuncCall {
+                        id: McFuncId::new("intrinsic:setptr"),
+                    }
+                    .into(),
+                );
+                cmds.push(read_ptr(next_arg.clone()));
+
+                cmds.push(assign(ptr(), dst[0].clone()));
+                cmds.push(
+                    McFuncCall {
+                        id: McFuncId::new("intrinsic:setptr"),
+                    }
+                    .into(),
+                );
+                cmds.push(write_ptr(next_arg));
+
+                Ok((cmds, None))
             }
             "bcmp" => {
                 assert_eq!(arguments.len(), 3);
@@ -2787,7 +3946,7 @@ fn compile_call(
 
                 let mut cmds = Vec::new();
 
-                cmds.extend(setup_arguments(arguments, globals, tys));
+                cmds.extend(setup_arguments(arguments, globals, tys)?);
 
                 cmds.push(
                     McFuncCall {
@@ -2798,22 +3957,22 @@ fn compile_call(
 
                 cmds.push(assign(dest, return_holder(0)));
 
-                (cmds, None)
+                Ok((cmds, None))
             }
+            name if name.starts_with("llvm.") => Err(CompileError::unsupported_intrinsic(name)),
+            name if !globals.contains_key(&Name::from(name)) => Err(CompileError::undefined_symbol(name)),
             _ => {
                 let mut before_cmds = Vec::new();
 
                 before_cmds.push(Command::Comment(format!("Calling {}", name)));
 
-                // Push return address
-                before_cmds.extend(push(ScoreHolder::new("%%fixup_return_addr".to_string()).unwrap()));
-
-                before_cmds.extend(setup_arguments(arguments, globals, tys));
+                let (setup_cmds, cleanup_cmds) = setup_call(arguments, fixed_args, globals, tys)?;
+                before_cmds.extend(setup_cmds);
 
                 // Branch to function
                 before_cmds.push(Command::Comment(format!("!FIXUPCALL {}", name)));
 
-                let after_cmds = if let Some(dest) = dest {
+                let mut after_cmds = if let Some(dest) = dest {
                     dest.into_iter()
                         .enumerate()
                         .map(|(idx, dest)| assign(dest, return_holder(idx)))
@@ -2821,34 +3980,28 @@ fn compile_call(
                 } else {
                     Vec::new()
                 };
+                after_cmds.extend(cleanup_cmds);
 
-                (before_cmds, Some(after_cmds))
+                Ok((before_cmds, Some(after_cmds)))
             }
         }
     } else if let Some(pointee_type) = local_op {
-        let (mut before_cmds, func_ptr) = eval_operand(function, globals, tys);
+        let (mut before_cmds, func_ptr) = eval_operand(function, globals, tys)?;
         assert_eq!(func_ptr.len(), 1);
         let func_ptr = func_ptr.into_iter().next().unwrap();
 
-        if let Type::FuncType {
-            result_type,
-            is_var_arg: false,
-            ..
-        } = &**pointee_type
-        {
-            let dest_size = type_layout(result_type, tys).size();
+        if let Type::FuncType { result_type, .. } = &**pointee_type {
+            let dest_size = type_layout(result_type, tys)?.size();
             let dest = dest
                 .clone()
                 .map(|d| ScoreHolder::from_local_name(d, dest_size));
 
-            // Push return address
-            before_cmds.extend(push(ScoreHolder::new("%%fixup_return_addr".to_string()).unwrap()));
-
-            before_cmds.extend(setup_arguments(arguments, globals, tys));
+            let (setup_cmds, cleanup_cmds) = setup_call(arguments, fixed_args(pointee_type), globals, tys)?;
+            before_cmds.extend(setup_cmds);
 
             before_cmds.push(Command::Comment(format!("!DYNCALL {}", func_ptr)));
 
-            let after_cmds = if let Some(dest) = dest {
+            let mut after_cmds = if let Some(dest) = dest {
                 dest.into_iter()
                     .enumerate()
                     .map(|(idx, dest)| assign(dest, return_holder(idx)))
@@ -2856,33 +4009,35 @@ fn compile_call(
             } else {
                 Vec::new()
             };
+            after_cmds.extend(cleanup_cmds);
 
-            (before_cmds, Some(after_cmds))
+            Ok((before_cmds, Some(after_cmds)))
         } else {
-            todo!("{:?}", pointee_type)
+            Err(CompileError::unsupported_type(pointee_type))
         }
     } else {
-        todo!("{:?}", function)
+        Err(CompileError::unsupported_instr(function))
     }
 }
 
+pub(crate) type BlockDests = Either<Vec<(BlockEdge, McFuncId)>, McFuncId>;
+
 pub(crate) fn compile_terminator(
     parent: &Function,
     term: &Terminator,
-    clobbers: BTreeSet<ScoreHolder>,
     globals: &GlobalVarList,
     tys: &Types,
-) -> (Vec<Command>, Either<Vec<(BlockEdge, McFuncId)>, McFuncId>) {
+) -> CompileResult<(Vec<Command>, BlockDests)> {
     let mut cmds = Vec::new();
 
-    match &term {
+    let result = match &term {
         Terminator::Ret(Ret {
             return_operand: None,
             ..
         }) => {
             cmds.push(Command::Comment("return".to_string()));
 
-            cmds.extend(load_regs(clobbers));
+            cmds.extend(load_frame());
 
             (cmds, Either::Right(McFuncId::new("rust:__langcraft_return")))
         }
@@ -2892,7 +4047,7 @@ pub(crate) fn compile_terminator(
         }) => {
             cmds.push(Command::Comment(format!("return operand {:?}", operand)));
 
-            let (tmp, source) = eval_operand(operand, globals, tys);
+            let (tmp, source) = eval_operand(operand, globals, tys)?;
 
             cmds.extend(tmp);
 
@@ -2900,7 +4055,7 @@ pub(crate) fn compile_terminator(
                 cmds.push(assign(return_holder(idx), word));
             }
 
-            cmds.extend(load_regs(clobbers));
+            cmds.extend(load_frame());
 
             (cmds, Either::Right(McFuncId::new("rust:__langcraft_return")))
         }
@@ -2913,7 +4068,7 @@ pub(crate) fn compile_terminator(
             false_dest,
             ..
         }) => {
-            let (tmp, cond) = eval_operand(condition, globals, tys);
+            let (tmp, cond) = eval_operand(condition, globals, tys)?;
             cmds.extend(tmp);
 
             assert_eq!(cond.len(), 1);
@@ -2956,7 +4111,7 @@ pub(crate) fn compile_terminator(
             default_dest,
             ..
         }) => {
-            let (tmp, op) = eval_operand(operand, globals, tys);
+            let (tmp, op) = eval_operand(operand, globals, tys)?;
             cmds.extend(tmp);
 
             let operand = match &*operand.get_type(tys) {
@@ -2983,7 +4138,7 @@ pub(crate) fn compile_terminator(
 
                     tmp
                 }
-                o => todo!("{:?}", o)
+                o => return Err(CompileError::unsupported_type(o)),
             };
 
 
@@ -2994,7 +4149,7 @@ pub(crate) fn compile_terminator(
                     Type::IntegerType { bits: 32 } |
                     Type::IntegerType { bits: 16 } |
                     Type::IntegerType { bits: 8 } => {
-                        if let MaybeConst::Const(expected) = eval_constant(dest_value, globals, tys) {
+                        if let MaybeConst::Const(expected) = eval_constant(dest_value, globals, tys)? {
                             BlockEdge::SwitchCond { 
                                 value: operand.clone(),
                                 expected,
@@ -3003,11 +4158,11 @@ pub(crate) fn compile_terminator(
                             unreachable!()
                         }
                     }
-                    _ => todo!()
+                    _ => unreachable!()
                 };
 
-                (edge, dest_id)
-            }).collect::<Vec<_>>();
+                Ok((edge, dest_id))
+            }).collect::<CompileResult<Vec<_>>>()?;
 
             let default_dest_id = McFuncId::new_block(&parent.name, default_dest.clone());
 
@@ -3016,15 +4171,15 @@ pub(crate) fn compile_terminator(
                     Type::IntegerType { bits: 32 } |
                     Type::IntegerType { bits: 16 } | 
                     Type::IntegerType { bits: 8 } => {
-                        if let MaybeConst::Const(ne) = eval_constant(dest_value, globals, tys) {
-                            ne
+                        if let MaybeConst::Const(ne) = eval_constant(dest_value, globals, tys)? {
+                            Ok(ne)
                         } else {
                             unreachable!()
                         }
                     }
-                    _ => todo!()
+                    _ => unreachable!()
                 }
-            }).collect();
+            }).collect::<CompileResult<_>>()?;
 
             let default_edge = BlockEdge::SwitchDefault {
                 value: operand,
@@ -3053,73 +4208,97 @@ pub(crate) fn compile_terminator(
 
             (cmds, Either::Left(Vec::new()))
         }
-        Terminator::Resume(_) => {
-            cmds.push(mark_todo());
+        Terminator::Resume(Resume { operand, .. }) => {
+            cmds.push(Command::Comment(format!("resume {:?}", operand)));
+
+            let (tmp, value) = eval_operand(operand, globals, tys)?;
+            cmds.extend(tmp);
 
-            let message = cir::TextBuilder::new()
-                .append_text("OH NO EXCEPTION HANDLING TOOD".into())
-                .build();
+            for (idx, word) in value.into_iter().enumerate() {
+                cmds.push(assign(exception(idx), word));
+            }
 
-            cmds.push(
-                Tellraw {
-                    target: cir::Selector {
-                        var: cir::SelectorVariable::AllPlayers,
-                        args: Vec::new(),
-                    }
-                    .into(),
-                    message,
-                }
-                .into(),
-            );
+            // Returning while this is set makes `rust:__langcraft_return` go
+            // to wherever the call site unwinds to instead
+            cmds.push(assign_lit(unwinding(), 1));
+            cmds.push(Command::Comment(format!("!INTERPRETER: UNWIND {}", parent.name)));
 
-            (cmds, Either::Left(Vec::new()))
+            cmds.extend(load_frame());
+
+            (cmds, Either::Right(McFuncId::new("rust:__langcraft_return")))
         }
-        term => todo!("terminator {:?}", term),
-    }
+        term => return Err(CompileError::unsupported_instr(term)),
+    };
+
+    Ok(result)
 }
 
-#[allow(clippy::reversed_empty_ranges)]
-fn reify_block(AbstractBlock { needs_prolog, mut body, term, parent }: AbstractBlock, clobber_list: &HashMap<String, BTreeSet<ScoreHolder>>, func_starts: &HashMap<String, McFuncId>, globals: &GlobalVarList, tys: &Types) -> McFunction {
-    let mut clobbers = clobber_list.get(&body.id.name).unwrap().clone();
+/// Copies the arguments of `parent` out of the parameter holders, at the end of its prolog
+pub(crate) fn load_params(parent: &Function, tys: &Types) -> CompileResult<Vec<Command>> {
+    let mut cmds = Vec::new();
 
-    for arg in parent.parameters.iter() {
-        let arg_size = type_layout(&arg.ty, tys).size();
-        clobbers.extend(ScoreHolder::from_local_name(arg.name.clone(), arg_size).iter().cloned());
+    for (idx, arg) in parent.parameters.iter().enumerate() {
+        let arg_size = type_layout(&arg.ty, tys)?.size();
+
+        for (arg_word, arg_holder) in
+            ScoreHolder::from_local_name(arg.name.clone(), arg_size)
+                .into_iter()
+                .enumerate()
+        {
+            cmds.push(assign(arg_holder, param(idx, arg_word)));
+        }
     }
 
-    if needs_prolog {
-        let mut prolog = save_regs(clobbers.clone());
+    // The caller passes the address of the variadic arguments after the named ones
+    if parent.is_var_arg {
+        cmds.push(assign(varargs(), param(parent.parameters.len(), 0)));
+    }
 
-        for (idx, arg) in parent.parameters.iter().enumerate() {
-            let arg_size = type_layout(&arg.ty, tys).size();
+    Ok(cmds)
+}
 
-            for (arg_word, arg_holder) in
-                ScoreHolder::from_local_name(arg.name.clone(), arg_size)
-                    .into_iter()
-                    .enumerate()
-            {
-                prolog.push(assign(arg_holder, param(idx, arg_word)));
-            }
+#[allow(clippy::reversed_empty_ranges)]
+fn reify_block(AbstractBlock { needs_prolog, mut body, term, parent }: AbstractBlock, registers: &HashMap<String, Registers>, fused: &HashMap<McFuncId, Vec<McFuncId>>, func_starts: &HashMap<String, McFuncId>, globals: &GlobalVarList, tys: &Types, stack_limit: Option<u32>) -> CompileResult<McFunction> {
+    if needs_prolog {
+        let mut prolog = Vec::new();
+
+        // Checked before saving the frame pointer, so the frame never overwrites the heap
+        if let Some(limit) = stack_limit {
+            prolog.extend(check_stack(&parent.name, limit as i32 - 4));
         }
 
+        prolog.extend(save_frame());
+        prolog.extend(load_params(&parent, tys)?);
+
         body.cmds.splice(0..0, prolog);
 
         // FIXME: When `trace-bbs` is enabled this puts them at the correct place
         // body.cmds.splice(1..1, prolog);
     }
 
-    body.cmds.extend(compile_block_end(&term.unwrap(), body.cmds.len(), &parent, clobbers, &func_starts, globals, tys));
+    let term = term.unwrap();
+
+    if let (BlockEnd::Normal(t), Some(_)) = (&term, &parent.debugloc) {
+        body.cmds.push(loc_marker(t.get_debug_loc().as_ref()));
+    }
+
+    let fused = fused.get(&body.id).map_or(&[][..], |f| &f[..]);
+    body.cmds.extend(compile_block_end(&term, body.cmds.len(), fused, &parent, func_starts, globals, tys)?);
+
+    crate::regalloc::rename(&mut body.cmds, &registers[&body.id.name]);
 
-    body
+    Ok(body)
 }
 
 static RESUME_BLOCK_POS: &str = "-2 1 1";
 static ACTIVATE_BLOCK_POS: &str = "-2 1 0";
 
-fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function, clobbers: BTreeSet<ScoreHolder>, func_starts: &HashMap<String, McFuncId>, globals: &GlobalVarList, tys: &Types) -> Vec<Command> {
+/// Commands that branch to the next block, through the next command block. Branching to any
+/// of the blocks in `fused` calls it directly instead (see `crate::analysis::fuse_blocks`).
+fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, fused: &[McFuncId], parent: &Function, func_starts: &HashMap<String, McFuncId>, globals: &GlobalVarList, tys: &Types) -> CompileResult<Vec<Command>> {
     let mut cmds = Vec::new();
 
-    let dests: Either<Vec<(BlockEdge, McFuncId)>, McFuncId> = match block_end {
+    let dests: BlockDests = match block_end {
         BlockEnd::StaticCall(func_name) => {
             if func_name == "!FIXUPCALL intrinsic:memcpy" {
                 todo!()
@@ -3132,7 +4311,7 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
         }
         BlockEnd::DynCall(func_ptr) => {
             cmds.push(assign(temp_fn_ptr(), func_ptr.clone()));
-            Either::Right(McFuncId::new("rust:__langcraft_call"))
+            Either::Right(McFuncId::new(CALL_FUNC))
 
             /*
             // FIXME: This is identical to the one at the end of `compile_call`
@@ -3154,7 +4333,10 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
             */
         }
         BlockEnd::Normal(t) => {
-            let (tmp, dests) = compile_terminator(&parent, &t, clobbers, globals, tys);
+            let (tmp, dests) = compile_terminator(parent, t, globals, tys).map_err(|err| {
+                let block = parent.basic_blocks.iter().find(|b| &b.term == t).map(|b| &b.name);
+                err.with_context(&parent.name, block, t.get_debug_loc().as_ref())
+            })?;
             cmds.extend(tmp);
             dests
         }
@@ -3175,7 +4357,7 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
                 kind: cir::SetBlockKind::Replace,
             }.into());
 
-            return cmds;
+            return Ok(cmds);
         }
     }
 
@@ -3183,6 +4365,56 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
     // Update command count
     cmds.push(make_op_lit(cmd_count(), "+=", body_cmds as i32 + 10));
 
+    // The fused edges are taken last, and everything before them only happens if none of them are taken,
+    // so the blocks they call can't make any of them happen too
+    let (dests, mut fused) = match dests {
+        Either::Left(dests) => {
+            let (fused, dests) = dests.into_iter().partition::<Vec<_>, _>(|(_, d)| fused.contains(d));
+            (Either::Left(dests), fused)
+        }
+        dests => (dests, Vec::new()),
+    };
+
+    // The block called first could reuse the holder that the next edges check,
+    // so they check a copy of it instead
+    if fused.len() > 1 {
+        let copy = get_unique_holder();
+        let mut copied = false;
+        for (edge, _) in fused.iter_mut() {
+            if let BlockEdge::Cond { value, .. } | BlockEdge::SwitchCond { value, .. } = edge {
+                let value = std::mem::replace(value, copy.clone());
+                if !copied {
+                    cmds.push(assign(copy.clone(), value));
+                    copied = true;
+                }
+            }
+        }
+    }
+
+    let mut not_fused = Vec::new();
+    let mut fused_calls = Vec::new();
+    for (edge, dest) in fused {
+        let conds = edge.into_conds();
+        let mut call = Execute::new();
+        for (cond, is_unless) in conds.iter().cloned() {
+            call.with_subcmd(ExecuteSubCmd::Condition { is_unless, cond });
+        }
+        call.with_run(McFuncCall { id: dest });
+        fused_calls.push(Command::from(call));
+
+        if let [(cond, is_unless)] = &conds[..] {
+            not_fused.push(ExecuteSubCmd::Condition { is_unless: !is_unless, cond: cond.clone() });
+        }
+    }
+
+    if !fused_calls.is_empty() && matches!(&dests, Either::Left(d) if d.is_empty()) {
+        cmds.extend(fused_calls);
+        return Ok(cmds);
+    }
+
+    // Only edges with a single condition get fused when there are others that aren't
+    assert_eq!(not_fused.len(), fused_calls.len());
+
     // All commands used when under the threshold share the same prefix
     let under_thresh_base = {
         let mut tmp = Execute::new();
@@ -3195,6 +4427,7 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
                 source_obj: OBJECTIVE.into(),
             }
         });
+        tmp.subcommands.extend(not_fused.clone());
         tmp
     };
 
@@ -3263,6 +4496,7 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
                 source_obj: OBJECTIVE.into(),
             }
         });
+        tmp.subcommands.extend(not_fused);
         tmp
     };
 
@@ -3317,25 +4551,78 @@ fn compile_block_end(block_end: &BlockEnd, body_cmds: usize, parent: &Function,
     reset_count.with_run(assign_lit(cmd_count(), 0));
     cmds.push(reset_count.into());
 
-    cmds
+    cmds.extend(fused_calls);
+
+    Ok(cmds)
+}
+
+/// Where a call returns to if the callee unwinds: the block for the `invoke`
+/// terminating `invoke_block`, or else the block that keeps unwinding out of the function
+fn unwind_block_id(func_name: &str, invoke_block: Option<&Name>) -> McFuncId {
+    let block = match invoke_block {
+        Some(Name::Name(name)) => format!("__langcraft_unwind_{}", name),
+        Some(Name::Number(num)) => format!("__langcraft_unwind_{}", num),
+        None => "__langcraft_unwind".to_string(),
+    };
+
+    McFuncId::new_block(func_name, Name::from(block))
 }
 
-fn compile_function(
+/// The call that an `invoke` makes, before it either branches or unwinds
+fn invoke_as_call(invoke: &Invoke, tys: &Types) -> Instruction {
+    // `Invoke::get_type` expects the callee to be a function rather than a pointer to one
+    let callee_ty = match &invoke.function {
+        Either::Left(asm) => asm.get_type(tys),
+        Either::Right(operand) => operand.get_type(tys),
+    };
+    let returns_void = match &*callee_ty {
+        Type::PointerType { pointee_type, .. } => matches!(
+            &**pointee_type,
+            Type::FuncType { result_type, .. } if **result_type == Type::VoidType
+        ),
+        _ => false,
+    };
+    let dest = if returns_void { None } else { Some(invoke.result.clone()) };
+
+    Call {
+        function: invoke.function.clone(),
+        arguments: invoke.arguments.clone(),
+        return_attributes: invoke.return_attributes.clone(),
+        dest,
+        function_attributes: invoke.function_attributes.clone(),
+        is_tail_call: false,
+        calling_convention: invoke.calling_convention,
+        debugloc: invoke.debugloc.clone(),
+    }
+    .into()
+}
+
+pub(crate) fn compile_function(
     func: &Function,
     globals: &GlobalVarList,
     tys: &Types,
     options: &BuildOptions,
-) -> (Vec<AbstractBlock>, HashMap<ScoreHolder, cir::HolderUse>) {
-    if func.is_var_arg {
-        todo!("functions with variadic arguments");
-    }
+    inlines: &HashMap<String, Inline>,
+    can_unwind: bool,
+) -> Result<Vec<AbstractBlock>, Vec<CompileError>> {
+    let func_error = |desc: &str| {
+        vec![CompileError::unsupported_instr(desc).with_context(&func.name, None, func.debugloc.as_ref())]
+    };
 
     if func.basic_blocks.is_empty() {
-        todo!("functions with no basic blocks");
+        return Err(func_error("functions with no basic blocks"));
     }
 
     println!("Function {}, {}", func.name, func.basic_blocks.len());
 
+    // Only functions compiled with debug info get source locations
+    let track_locs = func.debugloc.is_some();
+
+    let mut errors = Vec::new();
+
+    // Set if any call unwinds through this function without landing in it
+    let mut propagates = false;
+
     let mut funcs = func
         .basic_blocks
         .iter()
@@ -3352,10 +4639,7 @@ fn compile_function(
                 } else {
                     vec![]
                 };
-                McFunction {
-                    id,
-                    cmds,
-                }
+                McFunction::new(id, cmds)
             };
 
             let mut this = make_new_func(sub);
@@ -3364,8 +4648,35 @@ fn compile_function(
             if idx == 0 {
             }
 
-            for instr in block.instrs.iter() {
-                let (mut before, after) = compile_instr(instr, func, globals, tys, options);
+            // An `invoke` is a call followed by a branch to the normal destination,
+            // except that the call unwinds to a block of its own that leads to the landing pad
+            let invoke = match &block.term {
+                Terminator::Invoke(invoke) => Some(invoke),
+                _ => None,
+            };
+            let invoke_call = invoke.map(|invoke| invoke_as_call(invoke, tys));
+            let mut invoke_unwinds = false;
+
+            // What runs when the call made by the `invoke` returns, which has to happen when it unwinds too
+            let mut invoke_after = Vec::new();
+
+            for (instr_idx, instr) in block.instrs.iter().chain(invoke_call.iter()).enumerate() {
+                let compiled = match inliner::inline_call(instr, inlines, globals, tys) {
+                    Some(cmds) => cmds.map(|cmds| (cmds, None)),
+                    None => compile_instr(instr, func, globals, tys, options),
+                };
+
+                let (mut before, after) = match compiled {
+                    Ok(cmds) => cmds,
+                    Err(err) => {
+                        errors.push(err.with_context(&func.name, Some(&block.name), instr.get_debug_loc().as_ref()));
+                        continue;
+                    }
+                };
+
+                if track_locs {
+                    this.cmds.push(loc_marker(instr.get_debug_loc().as_ref()));
+                }
 
                 if let Some(after) = after {
                     let term = match before.pop().unwrap() {
@@ -3379,8 +4690,14 @@ fn compile_function(
                         b => todo!("{:?}", b),
                     };
 
+                    // Anything needed after the call gets saved before the arguments are set up
+                    this.cmds.push(regalloc::save_marker());
                     this.cmds.extend(before);
 
+                    let call_id = this.id.clone();
+                    let mut after = after;
+                    after.push(regalloc::restore_marker(&call_id));
+
                     result.push(AbstractBlock {
                         parent: func.clone(),
                         needs_prolog: idx == 0 && sub == 1,
@@ -3389,6 +4706,22 @@ fn compile_function(
                     });
                     sub += 1;
 
+                    let is_invoke = instr_idx == block.instrs.len();
+                    if is_invoke {
+                        invoke_after = after.clone();
+                    }
+
+                    if can_unwind {
+                        invoke_unwinds |= is_invoke;
+                        propagates |= !is_invoke;
+
+                        let target = unwind_block_id(&func.name, if is_invoke { Some(&block.name) } else { None });
+                        this.cmds.push(Command::Comment(format!("!UNWIND {}", target)));
+                    }
+
+                    if track_locs {
+                        this.cmds.push(loc_marker(instr.get_debug_loc().as_ref()));
+                    }
                     this.cmds.extend(after);
                 } else {
                     this.cmds.extend(before);
@@ -3400,13 +4733,40 @@ fn compile_function(
                 idx as i32,
             ));
 
+            let term = match invoke {
+                Some(invoke) => Terminator::Br(Br {
+                    dest: invoke.return_label.clone(),
+                    debugloc: invoke.debugloc.clone(),
+                }),
+                None => block.term.clone(),
+            };
+
             result.push(AbstractBlock {
                 parent: func.clone(),
                 needs_prolog: idx == 0 && sub == 1,
                 body: this,
-                term: Some(BlockEnd::Normal(block.term.clone())),
+                term: Some(BlockEnd::Normal(term)),
             });
 
+            if let (Some(invoke), true) = (invoke, invoke_unwinds) {
+                let mut cmds = vec![
+                    Command::Comment(format!("!INTERPRETER: CATCH {}", func.name)),
+                    assign_lit(unwinding(), 0),
+                ];
+                cmds.extend(invoke_after);
+                cmds.push(assign_lit(ScoreHolder::new("%phi".to_string()).unwrap(), idx as i32));
+
+                result.push(AbstractBlock {
+                    parent: func.clone(),
+                    needs_prolog: false,
+                    body: McFunction::new(unwind_block_id(&func.name, Some(&block.name)), cmds),
+                    term: Some(BlockEnd::Normal(Terminator::Br(Br {
+                        dest: invoke.exception_label.clone(),
+                        debugloc: None,
+                    }))),
+                });
+            }
+
             /*for sub_block in result.iter_mut() {
                 sub_block.body.cmds.insert(
                     0,
@@ -3423,6 +4783,27 @@ fn compile_function(
         })
         .collect::<Vec<_>>();
 
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Calls without a landing pad unwind to a block that just returns,
+    // so the epilogue restores the registers and the caller gets a turn
+    if propagates {
+        funcs.push(AbstractBlock {
+            parent: func.clone(),
+            needs_prolog: false,
+            body: McFunction::new(
+                unwind_block_id(&func.name, None),
+                vec![Command::Comment(format!("!INTERPRETER: UNWIND {}", func.name))],
+            ),
+            term: Some(BlockEnd::Normal(Terminator::Ret(Ret {
+                return_operand: None,
+                debugloc: None,
+            }))),
+        });
+    }
+
     /*for (idx, func) in funcs.iter().enumerate() {
         println!("Body command count for {}: {:?}", func.body.id, estimate_body_cmds(&funcs, idx));
     }*/
@@ -3447,19 +4828,7 @@ fn compile_function(
         }
     }
 
-    let mut clobbers = HashMap::new();
-    for f in funcs.iter() {
-        for c in f.body.cmds.iter() {
-            cir::merge_uses(&mut clobbers, &c.holder_uses());
-        }
-    }
-
-    let clobbers = clobbers
-        .into_iter()
-        .map(|(c, u)| ((*c).clone(), u))
-        .collect::<HashMap<_, _>>();
-
-    (funcs, clobbers)
+    Ok(funcs)
 }
 
 pub fn lshr_64_bit_const(
@@ -3474,7 +4843,12 @@ pub fn lshr_64_bit_const(
 
     let mut cmds = Vec::new();
 
-    if amount >= 32 {
+    if amount == 0 {
+        cmds.push(assign(dest_lo, op0_lo));
+        cmds.push(assign(dest_hi, op0_hi));
+
+        cmds
+    } else if amount >= 32 {
         cmds.push(assign_lit(dest_hi, 0));
 
         if amount > 32 {
@@ -3495,9 +4869,9 @@ pub fn lshr_64_bit_const(
     } else {
         let temp = get_unique_holder();
 
-        // temp = (hi & mask) << (32 - amount)
+        // temp = hi << (32 - amount)
+        // (the multiplication wraps, so the high bits just fall off)
         cmds.push(assign(temp.clone(), op0_hi.clone()));
-        cmds.push(make_op_lit(temp.clone(), "%=", 1 << amount));
         cmds.push(make_op_lit(temp.clone(), "*=", 1 << (32 - amount)));
 
         // hi' = hi lshr amount
@@ -3527,6 +4901,77 @@ pub fn lshr_64_bit_const(
     }
 }
 
+pub fn ashr_64_bit_const(
+    op0_lo: ScoreHolder,
+    op0_hi: ScoreHolder,
+    amount: i32,
+    dest: Name,
+) -> Vec<Command> {
+    let mut dest = ScoreHolder::from_local_name(dest, 8).into_iter();
+    let dest_lo = dest.next().unwrap();
+    let dest_hi = dest.next().unwrap();
+
+    let mut cmds = Vec::new();
+
+    if amount == 0 {
+        cmds.push(assign(dest_lo, op0_lo));
+        cmds.push(assign(dest_hi, op0_hi));
+    } else if amount >= 32 {
+        // lo' = hi ashr (amount - 32)
+        cmds.push(assign(param(0, 0), op0_hi.clone()));
+        cmds.push(assign_lit(param(1, 0), amount - 32));
+        cmds.push(
+            McFuncCall {
+                id: McFuncId::new("intrinsic:ashr"),
+            }
+            .into(),
+        );
+        cmds.push(assign(dest_lo, param(0, 0)));
+
+        // hi' = hi ashr 31
+        cmds.push(assign_lit(dest_hi.clone(), 0));
+        let mut is_neg = Execute::new();
+        is_neg.with_if(ExecuteCondition::Score {
+            target: op0_hi.into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((..=-1).into()),
+        });
+        is_neg.with_run(assign_lit(dest_hi, -1));
+        cmds.push(is_neg.into());
+    } else {
+        let temp = get_unique_holder();
+
+        // temp = hi << (32 - amount)
+        cmds.push(assign(temp.clone(), op0_hi.clone()));
+        cmds.push(make_op_lit(temp.clone(), "*=", 1 << (32 - amount)));
+
+        // hi' = hi ashr amount
+        cmds.push(assign(param(0, 0), op0_hi));
+        cmds.push(assign_lit(param(1, 0), amount));
+        cmds.push(
+            McFuncCall {
+                id: McFuncId::new("intrinsic:ashr"),
+            }
+            .into(),
+        );
+        cmds.push(assign(dest_hi, param(0, 0)));
+
+        // lo' = (lo lshr amount) + temp
+        cmds.push(assign(param(0, 0), op0_lo));
+        cmds.push(assign_lit(param(1, 0), amount));
+        cmds.push(
+            McFuncCall {
+                id: McFuncId::new("intrinsic:lshr"),
+            }
+            .into(),
+        );
+        cmds.push(assign(dest_lo.clone(), param(0, 0)));
+        cmds.push(make_op(dest_lo, "+=", temp));
+    }
+
+    cmds
+}
+
 pub fn mul_64_bit(
     op0_lo: ScoreHolder,
     op0_hi: ScoreHolder,
@@ -3562,6 +5007,43 @@ pub fn mul_64_bit(
     cmds
 }
 
+/// Divides two 64-bit integers using long division,
+/// storing either the quotient or the remainder in `dest`
+pub fn div_64_bit(
+    op0: &[ScoreHolder],
+    op1: &[ScoreHolder],
+    dest: Name,
+    signed: bool,
+    remainder: bool,
+) -> Vec<Command> {
+    assert_eq!(op0.len(), 2);
+    assert_eq!(op1.len(), 2);
+
+    let dest = ScoreHolder::from_local_name(dest, 8);
+
+    let mut cmds = Vec::new();
+
+    for (idx, (op0, op1)) in op0.iter().zip(op1.iter()).enumerate() {
+        cmds.push(assign(param(0, idx), op0.clone()));
+        cmds.push(assign(param(1, idx), op1.clone()));
+    }
+
+    let func = if signed { "intrinsic:sdiv64" } else { "intrinsic:udiv64" };
+    cmds.push(
+        McFuncCall {
+            id: McFuncId::new(func),
+        }
+        .into(),
+    );
+
+    let offset = if remainder { 2 } else { 0 };
+    for (idx, dest) in dest.into_iter().enumerate() {
+        cmds.push(assign(dest, return_holder(offset + idx)));
+    }
+
+    cmds
+}
+
 // 64-bit addition:
 // To detect a carry between 32-bit signed integers `a` and `b`:
 // let added = a.wrapping_add(b);
@@ -3594,69 +5076,499 @@ pub fn add_32_with_carry(
     both_neg.with_run(on_carry.clone());
     cmds.push(both_neg.into());
 
-    let mut op0_neg = Execute::new();
-    op0_neg.with_if(ExecuteCondition::Score {
-        target: op0.clone().into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((..=-1).into()),
-    });
-    op0_neg.with_if(ExecuteCondition::Score {
-        target: op1.clone().into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((0..).into()),
-    });
-    op0_neg.with_if(ExecuteCondition::Score {
-        target: dest.clone().into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((0..).into()),
-    });
-    op0_neg.with_run(on_carry.clone());
-    cmds.push(op0_neg.into());
+    let mut op0_neg = Execute::new();
+    op0_neg.with_if(ExecuteCondition::Score {
+        target: op0.clone().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((..=-1).into()),
+    });
+    op0_neg.with_if(ExecuteCondition::Score {
+        target: op1.clone().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((0..).into()),
+    });
+    op0_neg.with_if(ExecuteCondition::Score {
+        target: dest.clone().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((0..).into()),
+    });
+    op0_neg.with_run(on_carry.clone());
+    cmds.push(op0_neg.into());
+
+    let mut op1_neg = Execute::new();
+    op1_neg.with_if(ExecuteCondition::Score {
+        target: op0.into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((0..).into()),
+    });
+    op1_neg.with_if(ExecuteCondition::Score {
+        target: op1.into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((..=-1).into()),
+    });
+    op1_neg.with_if(ExecuteCondition::Score {
+        target: dest.into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((0..).into()),
+    });
+    op1_neg.with_run(on_carry);
+    cmds.push(op1_neg.into());
+
+    cmds
+}
+
+pub fn add_64_bit(
+    op0_lo: ScoreHolder,
+    op0_hi: ScoreHolder,
+    op1_lo: ScoreHolder,
+    op1_hi: ScoreHolder,
+    dest: Name,
+) -> Vec<Command> {
+    let mut dest_iter = ScoreHolder::from_local_name(dest, 8).into_iter();
+    let dest_lo = dest_iter.next().unwrap();
+    let dest_hi = dest_iter.next().unwrap();
+
+    let mut cmds = Vec::new();
+
+    cmds.push(assign(dest_hi.clone(), op0_hi));
+    cmds.push(make_op(dest_hi.clone(), "+=", op1_hi));
+
+    cmds.extend(add_32_with_carry(
+        op0_lo,
+        op1_lo,
+        dest_lo,
+        make_op_lit(dest_hi, "+=", 1),
+    ));
+
+    cmds
+}
+
+// Multiword integers:
+// Wide integers are stored as a little-endian list of words, the same way that
+// `ScoreHolder::from_local_name` splits them, and these helpers work with any number of words.
+
+/// Adds two multiword integers plus `carry_in`, discarding the final carry.
+/// `dest` is allowed to be the same as either operand.
+pub fn add_multiword(
+    op0: &[ScoreHolder],
+    op1: &[ScoreHolder],
+    dest: &[ScoreHolder],
+    carry_in: bool,
+) -> Vec<Command> {
+    assert_eq!(op0.len(), dest.len());
+    assert_eq!(op1.len(), dest.len());
+
+    let carry = get_unique_holder();
+    let next_carry = get_unique_holder();
+    let sum = get_unique_holder();
+
+    let mut cmds = vec![assign_lit(carry.clone(), carry_in as i32)];
+
+    let len = dest.len();
+    for (idx, ((op0, op1), dest)) in op0.iter().zip(op1.iter()).zip(dest.iter()).enumerate() {
+        if idx + 1 == len {
+            // The carry out of the top word is discarded anyway
+            cmds.push(assign(sum.clone(), op0.clone()));
+            cmds.push(make_op(sum.clone(), "+=", op1.clone()));
+        } else {
+            cmds.push(assign_lit(next_carry.clone(), 0));
+            cmds.extend(add_32_with_carry(
+                op0.clone(),
+                op1.clone(),
+                sum.clone(),
+                assign_lit(next_carry.clone(), 1),
+            ));
+
+            // Adding the incoming carry can only overflow if the sum is all ones
+            let mut carry_overflow = Execute::new();
+            carry_overflow.with_if(ExecuteCondition::Score {
+                target: sum.clone().into(),
+                target_obj: OBJECTIVE.into(),
+                kind: ExecuteCondKind::Matches((-1..=-1).into()),
+            });
+            carry_overflow.with_if(ExecuteCondition::Score {
+                target: carry.clone().into(),
+                target_obj: OBJECTIVE.into(),
+                kind: ExecuteCondKind::Matches((1..=1).into()),
+            });
+            carry_overflow.with_run(assign_lit(next_carry.clone(), 1));
+            cmds.push(carry_overflow.into());
+        }
+
+        cmds.push(assign(dest.clone(), sum.clone()));
+        cmds.push(make_op(dest.clone(), "+=", carry.clone()));
+
+        if idx + 1 != len {
+            cmds.push(assign(carry.clone(), next_carry.clone()));
+        }
+    }
+
+    cmds
+}
+
+/// Subtracts `op1` from `op0` by adding its two's complement
+pub fn sub_multiword(op0: &[ScoreHolder], op1: &[ScoreHolder], dest: &[ScoreHolder]) -> Vec<Command> {
+    let mut cmds = Vec::new();
+
+    let op1_inv = op1
+        .iter()
+        .map(|word| {
+            let inv = get_unique_holder();
+            cmds.push(assign(inv.clone(), word.clone()));
+            cmds.extend(invert(inv.clone()));
+            inv
+        })
+        .collect::<Vec<_>>();
+
+    cmds.extend(add_multiword(op0, &op1_inv, dest, true));
+
+    cmds
+}
+
+/// Multiplies two multiword integers, keeping only as many words as `dest` has
+pub fn mul_multiword(op0: &[ScoreHolder], op1: &[ScoreHolder], dest: &[ScoreHolder]) -> Vec<Command> {
+    let len = dest.len();
+    assert_eq!(op0.len(), len);
+    assert_eq!(op1.len(), len);
+
+    let mut cmds = Vec::new();
+
+    let zero = get_unique_holder();
+    cmds.push(assign_lit(zero.clone(), 0));
+
+    let acc = (0..len).map(|_| get_unique_holder()).collect::<Vec<_>>();
+    for word in acc.iter() {
+        cmds.push(assign_lit(word.clone(), 0));
+    }
+
+    for (i, op0_word) in op0.iter().enumerate() {
+        for (j, op1_word) in op1[..len - i].iter().enumerate() {
+            let pos = i + j;
+
+            if pos + 1 == len {
+                // Only the low half of the product ends up in the result
+                let product = get_unique_holder();
+                cmds.push(assign(product.clone(), op0_word.clone()));
+                cmds.push(make_op(product.clone(), "*=", op1_word.clone()));
+                cmds.push(make_op(acc[pos].clone(), "+=", product));
+            } else {
+                cmds.push(assign(param(0, 0), op0_word.clone()));
+                cmds.push(assign(param(1, 0), op1_word.clone()));
+                cmds.push(
+                    McFuncCall {
+                        id: McFuncId::new("intrinsic:mul_32_to_64"),
+                    }
+                    .into(),
+                );
+
+                let mut product = vec![return_holder(0), return_holder(1)];
+                product.resize(len - pos, zero.clone());
+
+                cmds.extend(add_multiword(&acc[pos..], &product, &acc[pos..], false));
+            }
+        }
+    }
+
+    for (dest, acc) in dest.iter().zip(acc) {
+        cmds.push(assign(dest.clone(), acc));
+    }
+
+    cmds
+}
+
+/// Compares two multiword integers, storing 1 in `dest` if the predicate is true and 0 otherwise
+pub fn icmp_multiword(
+    op0: &[ScoreHolder],
+    op1: &[ScoreHolder],
+    predicate: IntPredicate,
+    dest: ScoreHolder,
+) -> Vec<Command> {
+    assert_eq!(op0.len(), op1.len());
+
+    let mut cmds = Vec::new();
+
+    let (relation, signed, or_eq) = match predicate {
+        IntPredicate::EQ | IntPredicate::NE => {
+            let is_eq = predicate == IntPredicate::EQ;
+
+            cmds.push(assign_lit(dest.clone(), !is_eq as i32));
+
+            let mut all_eq = Execute::new();
+            for (l, r) in op0.iter().zip(op1.iter()) {
+                all_eq.with_if(ExecuteCondition::Score {
+                    target: l.clone().into(),
+                    target_obj: OBJECTIVE.into(),
+                    kind: ExecuteCondKind::Relation {
+                        relation: cir::Relation::Eq,
+                        source: r.clone().into(),
+                        source_obj: OBJECTIVE.into(),
+                    },
+                });
+            }
+            all_eq.with_run(assign_lit(dest, is_eq as i32));
+            cmds.push(all_eq.into());
+
+            return cmds;
+        }
+        IntPredicate::ULT => (cir::Relation::LessThan, false, false),
+        IntPredicate::ULE => (cir::Relation::LessThan, false, true),
+        IntPredicate::UGT => (cir::Relation::GreaterThan, false, false),
+        IntPredicate::UGE => (cir::Relation::GreaterThan, false, true),
+        IntPredicate::SLT => (cir::Relation::LessThan, true, false),
+        IntPredicate::SLE => (cir::Relation::LessThan, true, true),
+        IntPredicate::SGT => (cir::Relation::GreaterThan, true, false),
+        IntPredicate::SGE => (cir::Relation::GreaterThan, true, true),
+    };
+
+    // This is the result if every word is equal
+    cmds.push(assign_lit(dest.clone(), or_eq as i32));
+
+    // Otherwise, the most significant word that differs decides the result,
+    // so the words are checked in increasing significance, each one overriding the last.
+    // Only the top word holds a sign.
+    let word_result = get_unique_holder();
+    for (idx, (l, r)) in op0.iter().zip(op1.iter()).enumerate() {
+        if signed && idx + 1 == op0.len() {
+            cmds.push(compile_signed_cmp(l.clone(), r.clone(), word_result.clone(), relation.clone(), true));
+        } else {
+            cmds.extend(compile_unsigned_cmp(l.clone(), r.clone(), word_result.clone(), relation.clone()));
+        }
+
+        let mut differs = Execute::new();
+        differs.with_unless(ExecuteCondition::Score {
+            target: l.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Relation {
+                relation: cir::Relation::Eq,
+                source: r.clone().into(),
+                source_obj: OBJECTIVE.into(),
+            },
+        });
+        differs.with_run(assign(dest.clone(), word_result.clone()));
+        cmds.push(differs.into());
+    }
+
+    cmds
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ShiftKind {
+    Shl,
+    LShr,
+    AShr,
+}
+
+/// Calls a single-word shift intrinsic on `source` by a constant amount, and adds the result to `dest`
+fn shift_word_into(source: ScoreHolder, amount: u32, intrinsic: &str, dest: ScoreHolder) -> Vec<Command> {
+    vec![
+        assign(param(0, 0), source),
+        assign_lit(param(1, 0), amount as i32),
+        McFuncCall {
+            id: McFuncId::new(intrinsic),
+        }
+        .into(),
+        make_op(dest, "+=", param(0, 0)),
+    ]
+}
+
+/// Shifts a multiword integer by a constant amount, which must be less than its width.
+/// `dest` must not be the same as `op`.
+fn shift_multiword_const(op: &[ScoreHolder], amount: u32, kind: ShiftKind, dest: &[ScoreHolder]) -> Vec<Command> {
+    assert_eq!(op.len(), dest.len());
+
+    let len = op.len();
+    let word_shift = (amount / 32) as usize;
+    let bit_shift = amount % 32;
+
+    let mut cmds = Vec::new();
+
+    match kind {
+        ShiftKind::Shl => {
+            for (idx, dest) in dest.iter().enumerate() {
+                cmds.push(assign_lit(dest.clone(), 0));
+
+                if idx < word_shift {
+                    continue;
+                }
+
+                // dest = (op[idx - word_shift] << bit_shift) | (op[idx - word_shift - 1] >> (32 - bit_shift))
+                // The bits never overlap, so the OR can just be an addition
+                let src = idx - word_shift;
+                cmds.push(assign(dest.clone(), op[src].clone()));
+                if bit_shift != 0 {
+                    cmds.push(make_op_lit(dest.clone(), "*=", 1 << bit_shift));
+
+                    if src > 0 {
+                        cmds.extend(shift_word_into(op[src - 1].clone(), 32 - bit_shift, "intrinsic:lshr", dest.clone()));
+                    }
+                }
+            }
+        }
+        ShiftKind::LShr | ShiftKind::AShr => {
+            let is_signed = kind == ShiftKind::AShr;
+
+            for (idx, dest) in dest.iter().enumerate() {
+                let src = idx + word_shift;
+
+                if src >= len {
+                    // Entirely shifted in from above, so this is just the fill value
+                    cmds.push(assign_lit(dest.clone(), 0));
+                    if is_signed {
+                        let mut is_neg = Execute::new();
+                        is_neg.with_if(ExecuteCondition::Score {
+                            target: op[len - 1].clone().into(),
+                            target_obj: OBJECTIVE.into(),
+                            kind: ExecuteCondKind::Matches((..=-1).into()),
+                        });
+                        is_neg.with_run(assign_lit(dest.clone(), -1));
+                        cmds.push(is_neg.into());
+                    }
+                    continue;
+                }
+
+                // dest = (op[src] >> bit_shift) | (op[src + 1] << (32 - bit_shift))
+                // where only the top word is shifted arithmetically
+                if bit_shift == 0 {
+                    cmds.push(assign(dest.clone(), op[src].clone()));
+                } else {
+                    let intrinsic = if is_signed && src + 1 == len {
+                        "intrinsic:ashr"
+                    } else {
+                        "intrinsic:lshr"
+                    };
+
+                    cmds.push(assign_lit(dest.clone(), 0));
+                    cmds.extend(shift_word_into(op[src].clone(), bit_shift, intrinsic, dest.clone()));
+
+                    if src + 1 < len {
+                        let upper = get_unique_holder();
+                        cmds.push(assign(upper.clone(), op[src + 1].clone()));
+                        cmds.push(make_op_lit(upper.clone(), "*=", 1 << (32 - bit_shift)));
+                        cmds.push(make_op(dest.clone(), "+=", upper));
+                    }
+                }
+            }
+        }
+    }
+
+    cmds
+}
+
+/// Shifts a multiword integer by a variable amount,
+/// by conditionally applying a constant shift for each bit of the amount
+fn shift_multiword(op: &[ScoreHolder], amount: ScoreHolder, kind: ShiftKind, dest: &[ScoreHolder]) -> Vec<Command> {
+    let width = op.len() as u32 * 32;
+
+    let mut cmds = Vec::new();
+
+    let current = op.iter().map(|_| get_unique_holder()).collect::<Vec<_>>();
+    for (current, op) in current.iter().zip(op.iter()) {
+        cmds.push(assign(current.clone(), op.clone()));
+    }
+
+    let bit = get_unique_holder();
+
+    let mut step = 1;
+    while step < width {
+        let shifted = op.iter().map(|_| get_unique_holder()).collect::<Vec<_>>();
+        cmds.extend(shift_multiword_const(&current, step, kind, &shifted));
+
+        cmds.push(assign(bit.clone(), amount.clone()));
+        cmds.push(make_op_lit(bit.clone(), "/=", step as i32));
+        cmds.push(make_op_lit(bit.clone(), "%=", 2));
+
+        for (current, shifted) in current.iter().zip(shifted) {
+            let mut exec = Execute::new();
+            exec.with_if(ExecuteCondition::Score {
+                target: bit.clone().into(),
+                target_obj: OBJECTIVE.into(),
+                kind: ExecuteCondKind::Matches((1..=1).into()),
+            });
+            exec.with_run(assign(current.clone(), shifted));
+            cmds.push(exec.into());
+        }
+
+        step *= 2;
+    }
 
-    let mut op1_neg = Execute::new();
-    op1_neg.with_if(ExecuteCondition::Score {
-        target: op0.into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((0..).into()),
-    });
-    op1_neg.with_if(ExecuteCondition::Score {
-        target: op1.into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((..=-1).into()),
-    });
-    op1_neg.with_if(ExecuteCondition::Score {
-        target: dest.into(),
-        target_obj: OBJECTIVE.into(),
-        kind: ExecuteCondKind::Matches((0..).into()),
-    });
-    op1_neg.with_run(on_carry);
-    cmds.push(op1_neg.into());
+    for (dest, current) in dest.iter().zip(current) {
+        cmds.push(assign(dest.clone(), current));
+    }
 
     cmds
 }
 
-pub fn add_64_bit(
-    op0_lo: ScoreHolder,
-    op0_hi: ScoreHolder,
-    op1_lo: ScoreHolder,
-    op1_hi: ScoreHolder,
-    dest: Name,
-) -> Vec<Command> {
-    let mut dest_iter = ScoreHolder::from_local_name(dest, 8).into_iter();
-    let dest_lo = dest_iter.next().unwrap();
-    let dest_hi = dest_iter.next().unwrap();
+/// Lowers a shift whose operand is wider than 64 bits
+fn compile_shift_multiword(
+    operand0: &Operand,
+    operand1: &Operand,
+    dest: &Name,
+    kind: ShiftKind,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+
+    let dest = ScoreHolder::from_local_name(dest.clone(), op0.len() * 4);
+
+    match eval_maybe_const(operand1, globals, tys)? {
+        MaybeConst::Const(amount) => {
+            cmds.extend(shift_multiword_const(&op0, amount as u32, kind, &dest));
+        }
+        MaybeConst::NonConst(tmp, op1) => {
+            // Only the low word of the amount can matter, since the shift must be less than the width
+            cmds.extend(tmp);
+            cmds.extend(shift_multiword(&op0, op1[0].clone(), kind, &dest));
+        }
+    }
+
+    Ok(cmds)
+}
+
+/// Sign extends the `from_bits`-bit integer in `op` to fill all of `dest`
+fn sext_multiword(op: &[ScoreHolder], from_bits: u32, dest: &[ScoreHolder]) -> Vec<Command> {
+    let src_words = from_bits.div_ceil(32) as usize;
+    assert!(src_words <= dest.len());
 
     let mut cmds = Vec::new();
 
-    cmds.push(assign(dest_hi.clone(), op0_hi));
-    cmds.push(make_op(dest_hi.clone(), "+=", op1_hi));
+    for (dest, op) in dest.iter().zip(op[..src_words].iter()) {
+        cmds.push(assign(dest.clone(), op.clone()));
+    }
 
-    cmds.extend(add_32_with_carry(
-        op0_lo,
-        op1_lo,
-        dest_lo,
-        make_op_lit(dest_hi, "+=", 1),
-    ));
+    let top = dest[src_words - 1].clone();
+
+    // The top word may only be partially used, so its own sign bit has to be fixed up first
+    let partial_bits = from_bits % 32;
+    if partial_bits != 0 {
+        cmds.push(make_op_lit(top.clone(), "%=", 1 << partial_bits));
+
+        let mut is_neg = Execute::new();
+        is_neg.with_if(ExecuteCondition::Score {
+            target: top.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((1 << (partial_bits - 1)..).into()),
+        });
+        is_neg.with_run(ScoreAdd {
+            target: top.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            score: -(1 << partial_bits),
+        });
+        cmds.push(is_neg.into());
+    }
+
+    for dest in dest[src_words..].iter() {
+        cmds.push(assign_lit(dest.clone(), 0));
+
+        let mut is_neg = Execute::new();
+        is_neg.with_if(ExecuteCondition::Score {
+            target: top.clone().into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((..=-1).into()),
+        });
+        is_neg.with_run(assign_lit(dest.clone(), -1));
+        cmds.push(is_neg.into());
+    }
 
     cmds
 }
@@ -3668,9 +5580,9 @@ pub fn compile_arithmetic(
     kind: ScoreOpKind,
     globals: &HashMap<&Name, (u32, Option<Constant>)>,
     tys: &Types,
-) -> Vec<Command> {
-    let (mut cmds, source0) = eval_operand(operand0, globals, tys);
-    let (tmp, source1) = eval_operand(operand1, globals, tys);
+) -> CompileResult<Vec<Command>> {
+    let (mut cmds, source0) = eval_operand(operand0, globals, tys)?;
+    let (tmp, source1) = eval_operand(operand1, globals, tys)?;
     cmds.extend(tmp.into_iter());
 
     let op0_type = operand0.get_type(tys);
@@ -3687,49 +5599,45 @@ pub fn compile_arithmetic(
                 cmds.extend(add_64_bit(op0_lo, op0_hi, op1_lo, op1_hi, dest.clone()));
             }
             ScoreOpKind::SubAssign => {
-                let op1_inv_lo = get_unique_holder();
-                let op1_inv_hi = get_unique_holder();
-
-                cmds.push(assign(op1_inv_lo.clone(), op1_lo));
-                cmds.push(assign(op1_inv_hi.clone(), op1_hi));
-
-                cmds.extend(invert(op1_inv_lo.clone()));
-                cmds.extend(invert(op1_inv_hi.clone()));
-
-                let op1_neg_name = Name::from(format!("%%temp_neg_{}", get_unique_num()));
-
-                cmds.extend(add_64_bit(
-                    op1_inv_lo, 
-                    op1_inv_hi,
-                    ScoreHolder::new("%%1".into()).unwrap(),
-                    ScoreHolder::new("%%0".into()).unwrap(),
-                    op1_neg_name.clone()
-                ));
-
-                let mut tmp = ScoreHolder::from_local_name(op1_neg_name, 8).into_iter();
-                let op1_neg_lo = tmp.next().unwrap();
-                let op1_neg_hi = tmp.next().unwrap();
-
-                cmds.extend(add_64_bit(op0_lo, op0_hi, op1_neg_lo, op1_neg_hi, dest.clone()));
+                let dest = ScoreHolder::from_local_name(dest.clone(), 8);
+                cmds.extend(sub_multiword(&[op0_lo, op0_hi], &[op1_lo, op1_hi], &dest));
             }
             ScoreOpKind::MulAssign => {
                 cmds.extend(mul_64_bit(op0_lo, op0_hi, op1_lo, op1_hi, dest.clone()));
             }
-            _ => todo!("{:?}", kind),
+            ScoreOpKind::DivAssign => {
+                cmds.extend(div_64_bit(&source0, &source1, dest.clone(), true, false));
+            }
+            ScoreOpKind::ModAssign => {
+                cmds.extend(div_64_bit(&source0, &source1, dest.clone(), true, true));
+            }
+            _ => return Err(CompileError::unsupported_instr(&kind)),
         }
 
-        cmds
+        Ok(cmds)
+    } else if matches!(&*op0_type, Type::IntegerType { bits } if *bits > 64) {
+        let dest = ScoreHolder::from_local_name(dest.clone(), source0.len() * 4);
+
+        match kind {
+            ScoreOpKind::AddAssign => cmds.extend(add_multiword(&source0, &source1, &dest, false)),
+            ScoreOpKind::SubAssign => cmds.extend(sub_multiword(&source0, &source1, &dest)),
+            ScoreOpKind::MulAssign => cmds.extend(mul_multiword(&source0, &source1, &dest)),
+            _ => return Err(CompileError::unsupported_instr(&kind)),
+        }
+
+        Ok(cmds)
     } else {
         let dest =
-            ScoreHolder::from_local_name(dest.clone(), type_layout(&*op0_type, tys).size());
+            ScoreHolder::from_local_name(dest.clone(), type_layout(&op0_type, tys)?.size());
 
         if let Type::VectorType {
             element_type,
             num_elements,
+            ..
         } = &*op0_type
         {
             if !matches!(&**element_type, Type::IntegerType { bits: 32 }) {
-                todo!("{:?}", element_type)
+                return Err(CompileError::unsupported_type(&op0_type));
             }
 
             assert_eq!(source0.len(), *num_elements);
@@ -3757,7 +5665,7 @@ pub fn compile_arithmetic(
                 .into(),
             );
         }
-        cmds
+        Ok(cmds)
     }
 }
 
@@ -3807,119 +5715,175 @@ pub fn pop(target: ScoreHolder) -> Vec<Command> {
     cmds
 }
 
-pub fn offset_of_array(element_type: &Type, field: u32, tys: &Types) -> usize {
+pub fn offset_of_array(element_type: &Type, field: u32, tys: &Types) -> CompileResult<usize> {
     let mut offset = 0;
     let mut result = Layout::from_size_align(0, 1).unwrap();
     for _ in 0..field + 1 {
-        let (r, o) = result.extend(type_layout(element_type, tys)).unwrap();
+        let (r, o) = result
+            .extend(type_layout(element_type, tys)?)
+            .map_err(|_| CompileError::layout(element_type))?;
         offset = o;
         result = r;
     }
-    offset
+    Ok(offset)
 }
 
-pub fn offset_of(element_types: &[TypeRef], is_packed: bool, field: u32, tys: &Types) -> usize {
+pub fn offset_of(element_types: &[TypeRef], is_packed: bool, field: u32, tys: &Types) -> CompileResult<usize> {
     if is_packed {
-        element_types[0..field as usize]
-            .iter()
-            .map(|t| type_layout(t, tys).size())
-            .sum::<usize>()
+        let mut offset = 0;
+        for t in &element_types[0..field as usize] {
+            offset += type_layout(t, tys)?.size();
+        }
+        Ok(offset)
     } else {
         let mut offset = 0;
         let mut result = Layout::from_size_align(0, 1).unwrap();
         for elem in &element_types[0..field as usize + 1] {
-            let (r, o) = result.extend(type_layout(elem, tys)).unwrap();
+            let (r, o) = result
+                .extend(type_layout(elem, tys)?)
+                .map_err(|_| CompileError::layout(element_types))?;
             offset = o;
             result = r;
         }
-        offset
+        Ok(offset)
     }
 }
 
-pub fn type_layout(ty: &Type, tys: &Types) -> Layout {
+pub fn type_layout(ty: &Type, tys: &Types) -> CompileResult<Layout> {
+    let layout = |size, align| {
+        Layout::from_size_align(size, align).map_err(|_| CompileError::layout(ty))
+    };
+
+    let extend = |result: Layout, next: Layout| {
+        result.extend(next).map(|(l, _)| l).map_err(|_| CompileError::layout(ty))
+    };
+
     match ty {
-        Type::IntegerType { bits: 1 } => Layout::from_size_align(1, 1).unwrap(),
-        Type::IntegerType { bits: 8 } => Layout::from_size_align(1, 1).unwrap(),
-        Type::IntegerType { bits: 16 } => Layout::from_size_align(2, 2).unwrap(),
-        Type::IntegerType { bits: 24 } => Layout::from_size_align(3, 4).unwrap(),
-        Type::IntegerType { bits: 32 } => Layout::from_size_align(4, 4).unwrap(),
-        Type::IntegerType { bits: 48 } => Layout::from_size_align(6, 4).unwrap(),
-        Type::IntegerType { bits: 64 } => Layout::from_size_align(8, 4).unwrap(),
+        Type::IntegerType { bits: 1 } => layout(1, 1),
+        Type::IntegerType { bits: 8 } => layout(1, 1),
+        Type::IntegerType { bits: 16 } => layout(2, 2),
+        Type::IntegerType { bits: 24 } => layout(3, 4),
+        Type::IntegerType { bits: 32 } => layout(4, 4),
+        Type::IntegerType { bits: 48 } => layout(6, 4),
+        Type::IntegerType { bits: 64 } => layout(8, 4),
+        Type::IntegerType { bits } if *bits > 64 => layout((*bits as usize).div_ceil(32) * 4, 4),
         Type::StructType {
             element_types,
             is_packed,
         } => {
             if *is_packed {
                 // TODO: Determine if this applies to inner fields as well
-                Layout::from_size_align(
-                    element_types.iter().map(|e| type_layout(e, tys).size()).sum(),
-                    1,
-                )
-                .unwrap()
+                let mut size = 0;
+                for elem in element_types.iter() {
+                    size += type_layout(elem, tys)?.size();
+                }
+                layout(size, 1)
             } else if element_types.is_empty() {
-                Layout::from_size_align(0, 1).unwrap()
+                layout(0, 1)
             } else {
-                let mut result = type_layout(&element_types[0], tys);
+                let mut result = type_layout(&element_types[0], tys)?;
                 for elem in &element_types[1..] {
-                    result = result.extend(type_layout(elem, tys)).unwrap().0;
+                    result = extend(result, type_layout(elem, tys)?)?;
                 }
-                result
+                Ok(result)
             }
         }
         Type::NamedStructType { name } => {
-            let ty = tys.named_struct_def(name).unwrap();
+            let ty = tys
+                .named_struct_def(name)
+                .and_then(named_as_type)
+                .ok_or_else(|| CompileError::layout(ty))?;
 
-            type_layout(named_as_type(ty).unwrap(), tys)
+            type_layout(ty, tys)
         }
         Type::VectorType {
             element_type,
             num_elements,
+            ..
         } => {
-            let mut result = type_layout(element_type, tys);
+            let mut result = type_layout(element_type, tys)?;
             for _ in 0..num_elements - 1 {
-                result = result.extend(type_layout(element_type, tys)).unwrap().0;
+                result = extend(result, type_layout(element_type, tys)?)?;
             }
-            result.align_to(4).unwrap()
+            result.align_to(4).map_err(|_| CompileError::layout(ty))
         }
         Type::ArrayType {
             element_type,
             num_elements,
         } => {
             if *num_elements == 0 {
-                Layout::from_size_align(0, 1).unwrap()
+                layout(0, 1)
             } else {
-                let mut result = type_layout(element_type, tys);
+                let mut result = type_layout(element_type, tys)?;
                 for _ in 0..num_elements - 1 {
-                    result = result.extend(type_layout(element_type, tys)).unwrap().0;
+                    result = extend(result, type_layout(element_type, tys)?)?;
                 }
-                result
+                Ok(result)
             }
         }
-        Type::PointerType { .. } => Layout::from_size_align(4, 4).unwrap(),
-        Type::VoidType => Layout::from_size_align(0, 4).unwrap(),
-        _ => todo!("size of type {:?}", ty),
+        Type::PointerType { .. } => layout(4, 4),
+        Type::FPType(FPType::Single) => layout(4, 4),
+        Type::FPType(FPType::Double) => layout(8, 4),
+        Type::VoidType => layout(0, 4),
+        _ => Err(CompileError::unsupported_type(ty)),
+    }
+}
+
+/// Creates a marker comment recording which source location the commands after it
+/// were generated from, or that their location is unknown.
+/// These are removed again by `extract_source_locs` once all fixups are done.
+pub fn loc_marker(debugloc: Option<&DebugLoc>) -> Command {
+    match debugloc {
+        Some(DebugLoc { line, col, filename, directory }) => {
+            let col = col.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string());
+            let file = match directory {
+                Some(dir) => format!("{}/{}", dir, filename),
+                None => filename.clone(),
+            };
+            Command::Comment(format!("!LOC {} {} {}", line, col, file))
+        }
+        None => Command::Comment("!LOC".to_string()),
+    }
+}
+
+fn parse_loc_marker(marker: &str) -> Option<Option<SourceLoc>> {
+    let rest = marker.strip_prefix("!LOC")?;
+    if rest.is_empty() {
+        return Some(None);
     }
+
+    let mut parts = rest[1..].splitn(3, ' ');
+    let line = parts.next()?.parse().ok()?;
+    let col = match parts.next()? {
+        "-" => None,
+        col => Some(col.parse().ok()?),
+    };
+    let file = parts.next()?.to_string();
+
+    Some(Some(SourceLoc { file, line, col }))
 }
 
-/*
- * Tells the user where the program experienced the error (if included)
- */
-pub fn dumploc(debugloc: &Option<llvm_ir::DebugLoc>) {
-    if let Some(llvm_ir::DebugLoc {line,col,filename,directory}) = debugloc { // check if the debug location is actually attached
-        eprint!("at {}:{}",filename,line); // this is the base information to give to the user
-        
-        // some compilers can give the column number so print that out if it's included
-        if let Some(column) = col {
-            eprint!(":{}",column);
+/// Removes the location markers from a function
+/// and records the location of each remaining command
+pub fn extract_source_locs(func: &mut McFunction) {
+    let mut current = None;
+    let mut cmds = Vec::with_capacity(func.cmds.len());
+
+    for cmd in std::mem::take(&mut func.cmds) {
+        if let Command::Comment(c) = &cmd {
+            if let Some(loc) = parse_loc_marker(c) {
+                current = loc;
+                continue;
+            }
         }
-        
-        if let Some(dir) = directory {
-            eprint!(" in {}/{}",dir,filename);
+
+        if let Some(loc) = &current {
+            func.locs.insert(cmds.len(), loc.clone());
         }
-        
-        // conclude with a colon and newline
-        eprintln!(":");
+        cmds.push(cmd);
     }
+
+    func.cmds = cmds;
 }
 
 pub fn compile_alloca(
@@ -3927,14 +5891,13 @@ pub fn compile_alloca(
         allocated_type,
         num_elements,
         dest,
-        debugloc,
         ..
     }: &Alloca,
     tys: &Types,
-) -> Vec<Command> {
-    let type_size = type_layout(allocated_type, tys)
+) -> CompileResult<Vec<Command>> {
+    let type_size = type_layout(allocated_type, tys)?
         .align_to(4)
-        .unwrap()
+        .map_err(|_| CompileError::layout(allocated_type))?
         .pad_to_align()
         .size();
 
@@ -3954,19 +5917,15 @@ pub fn compile_alloca(
             for _i in 1..type_size {
                 cmds.push(make_op(stackptr(),"+=",score[0].clone()));
             }
-        } else if let llvm_ir::Type::IntegerType {bits} = &*num_elements.get_type(tys) {
-            dumploc(debugloc);
-            todo!("Allocate with a {}-bit size",bits);
         } else {
-            dumploc(debugloc);
-            todo!("Allocate {:?}",&*num_elements.get_type(tys));
+            return Err(CompileError::unsupported_type(&num_elements.get_type(tys)));
         }
     } else {
-        todo!("{:?}", num_elements);
+        return Err(CompileError::unsupported_instr(num_elements));
     };
     
 
-    cmds
+    Ok(cmds)
 }
 
 // This whole thing could be optimized into a single command with a Predicate but... ugh
@@ -4188,11 +6147,11 @@ fn compile_getelementptr(
         indices,
         dest: dest_all,
         in_bounds: _,
-        debugloc,
+        debugloc: _,
     }: &GetElementPtr,
     globals: &GlobalVarList,
     tys: &Types,
-) -> Vec<Command> {
+) -> CompileResult<Vec<Command>> {
     let dest = ScoreHolder::from_local_name(dest_all.clone(), 4);
     let dest = dest[0].clone();
 
@@ -4206,11 +6165,11 @@ fn compile_getelementptr(
     for index in indices {
         match (*ty).clone() {
             Type::PointerType { pointee_type, .. } => {
-                let pointee_size = type_layout(&pointee_type, tys).pad_to_align().size();
+                let pointee_size = type_layout(&pointee_type, tys)?.pad_to_align().size();
 
                 ty = pointee_type;
 
-                match eval_maybe_const(index, globals, tys) {
+                match eval_maybe_const(index, globals, tys)? {
                     MaybeConst::Const(c) => offset += pointee_size as i32 * c,
                     MaybeConst::NonConst(a, b) => {
                         if b.len() == 1 {
@@ -4234,8 +6193,7 @@ fn compile_getelementptr(
                                 cmds.extend(add64.clone());
                             }
                         } else {
-                            dumploc(debugloc);
-                            unreachable!("[FATAL] Invalid IR: GetElementPtr can only be word-sized (32-bit) or doubleword-sized (64-bit)");
+                            return Err(CompileError::unsupported_instr(index));
                         }
                     }
                 }
@@ -4244,32 +6202,35 @@ fn compile_getelementptr(
                 element_types,
                 is_packed,
             } => {
-                let index = if let MaybeConst::Const(c) = eval_maybe_const(index, globals, tys) {
+                let index = if let MaybeConst::Const(c) = eval_maybe_const(index, globals, tys)? {
                     c
                 } else {
                     unreachable!("attempt to index struct at runtime")
                 };
 
-                offset += offset_of(&element_types, is_packed, index as u32, tys) as i32;
+                offset += offset_of(&element_types, is_packed, index as u32, tys)? as i32;
 
                 ty = element_types.into_iter().nth(index as usize).unwrap().clone();
             }
             Type::NamedStructType { name, .. } => {
-                let index = if let MaybeConst::Const(c) = eval_maybe_const(index, globals, tys) {
+                let index = if let MaybeConst::Const(c) = eval_maybe_const(index, globals, tys)? {
                     c
                 } else {
                     unreachable!("attempt to index named struct at runtime")
                 };
 
-                let (element_types, is_packed) = as_struct_ty(tys.named_struct_def(&name).unwrap()).unwrap();
+                let (element_types, is_packed) = tys
+                    .named_struct_def(&name)
+                    .and_then(as_struct_ty)
+                    .ok_or_else(|| CompileError::layout(&ty))?;
 
-                offset += offset_of(&element_types, is_packed, index as u32, tys) as i32;
+                offset += offset_of(element_types, is_packed, index as u32, tys)? as i32;
                 ty = element_types[index as usize].clone();
             }
             Type::ArrayType { element_type, .. } => {
-                let elem_size = type_layout(&element_type, tys).pad_to_align().size();
+                let elem_size = type_layout(&element_type, tys)?.pad_to_align().size();
 
-                match eval_maybe_const(index, globals, tys) {
+                match eval_maybe_const(index, globals, tys)? {
                     MaybeConst::Const(c) => {
                         offset += c * elem_size as i32;
                     }
@@ -4286,11 +6247,11 @@ fn compile_getelementptr(
 
                 ty = element_type;
             }
-            _ => todo!("{:?}", ty),
+            _ => return Err(CompileError::unsupported_type(&ty)),
         }
     }
 
-    let mut start_cmds = match eval_maybe_const(address, globals, tys) {
+    let mut start_cmds = match eval_maybe_const(address, globals, tys)? {
         MaybeConst::Const(addr) => vec![assign_lit(dest.clone(), addr + offset as i32)],
         MaybeConst::NonConst(mut cmds, addr) => {
             assert_eq!(addr.len(), 1);
@@ -4319,7 +6280,7 @@ fn compile_getelementptr(
 
     start_cmds.extend(cmds);
 
-    start_cmds
+    Ok(start_cmds)
 }
 
 pub fn compile_normal_icmp(
@@ -4386,44 +6347,466 @@ pub fn compile_normal_icmp(
         )),
     }
 
-    cmds
+    cmds
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum BitOp {
+    And,
+    Or,
+    Xor,
+}
+
+fn compile_bitwise_word(operand0: &Operand, operand1: &Operand, dest: Name, op: BitOp, globals: &GlobalVarList, tys: &Types) -> CompileResult<Vec<Command>> {
+    let ty = operand0.get_type(tys);
+    let layout = type_layout(&ty, tys)?;
+
+    let dest = ScoreHolder::from_local_name(dest, layout.size());
+
+    let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+    let (tmp, op1) = eval_operand(operand1, globals, tys)?;
+    cmds.extend(tmp);
+
+    for (dest, (op0, op1)) in dest.into_iter().zip(op0.into_iter().zip(op1.into_iter())) {
+        cmds.push(assign(param(0, 0), op0));
+        cmds.push(assign(param(1, 0), op1));
+
+        let id = match op {
+            BitOp::And => "intrinsic:and",
+            BitOp::Or => "intrinsic:or",
+            BitOp::Xor => "intrinsic:xor",
+        };
+
+        cmds.push(McFuncCall {
+            id: McFuncId::new(id)
+        }.into());
+
+        cmds.push(assign(dest, return_holder(0)));
+    }
+
+    Ok(cmds)
+}
+
+fn float_suffix(ty: &Type) -> CompileResult<&'static str> {
+    match ty {
+        Type::FPType(FPType::Single) => Ok("f32"),
+        Type::FPType(FPType::Double) => Ok("f64"),
+        _ => Err(CompileError::unsupported_type(ty)),
+    }
+}
+
+/// Calls a soft-float intrinsic with each operand as a parameter,
+/// then copies the result words into `dest` if there is one
+fn compile_float_call(
+    operands: &[&Operand],
+    name: &str,
+    dest: Option<(&Name, usize)>,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let mut cmds = Vec::new();
+
+    for (idx, operand) in operands.iter().enumerate() {
+        let (tmp, words) = eval_operand(operand, globals, tys)?;
+        cmds.extend(tmp);
+        for (word_idx, word) in words.into_iter().enumerate() {
+            cmds.push(assign(param(idx, word_idx), word));
+        }
+    }
+
+    cmds.push(
+        McFuncCall {
+            id: McFuncId::new(format!("intrinsic:{}", name)),
+        }
+        .into(),
+    );
+
+    if let Some((dest, dest_size)) = dest {
+        let dest = ScoreHolder::from_local_name(dest.clone(), dest_size);
+        for (word_idx, dest_word) in dest.into_iter().enumerate() {
+            cmds.push(assign(dest_word, return_holder(word_idx)));
+        }
+    }
+
+    Ok(cmds)
+}
+
+fn compile_fcmp(
+    FCmp {
+        predicate,
+        operand0,
+        operand1,
+        dest,
+        ..
+    }: &FCmp,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let dest = ScoreHolder::from_local_name(dest.clone(), 1)
+        .into_iter()
+        .next()
+        .unwrap();
+
+    // The comparison intrinsic returns -1, 0, 1, or 2 for less, equal, greater, or unordered,
+    // so each predicate is a range of those values, possibly inverted
+    let (range, is_unless): (cir::McRange, bool) = match predicate {
+        FPPredicate::False => return Ok(vec![assign_lit(dest, 0)]),
+        FPPredicate::True => return Ok(vec![assign_lit(dest, 1)]),
+        FPPredicate::OEQ => ((0..=0).into(), false),
+        FPPredicate::OGT => ((1..=1).into(), false),
+        FPPredicate::OGE => ((0..=1).into(), false),
+        FPPredicate::OLT => ((-1..=-1).into(), false),
+        FPPredicate::OLE => ((-1..=0).into(), false),
+        FPPredicate::ORD => ((-1..=1).into(), false),
+        FPPredicate::UNO => ((2..=2).into(), false),
+        FPPredicate::UGT => ((-1..=0).into(), true),
+        FPPredicate::UGE => ((-1..=-1).into(), true),
+        FPPredicate::ULT => ((0..=1).into(), true),
+        FPPredicate::ULE => ((1..=1).into(), true),
+        FPPredicate::UNE => ((0..=0).into(), true),
+        // These two are handled below
+        FPPredicate::ONE => ((-1..=1).into(), false),
+        FPPredicate::UEQ => ((0..=2).into(), false),
+    };
+
+    let name = format!("fcmp_{}", float_suffix(&operand0.get_type(tys))?);
+
+    let cmp_result = return_holder(0);
+
+    let mut cmds = compile_float_call(&[operand0, operand1], &name, None, globals, tys)?;
+
+    let cond = ExecuteCondition::Score {
+        target: cmp_result.clone().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches(range),
+    };
+
+    let mut exec = Execute::new();
+    exec.with_subcmd(ExecuteSubCmd::Store {
+        is_success: true,
+        kind: ExecuteStoreKind::Score {
+            target: dest.clone().into(),
+            objective: OBJECTIVE.to_string(),
+        },
+    });
+    if is_unless {
+        exec.with_unless(cond);
+    } else {
+        exec.with_if(cond);
+    }
+    cmds.push(exec.into());
+
+    // Both of these exclude exactly one value from their range
+    let excluded = match predicate {
+        FPPredicate::ONE => Some(0),
+        FPPredicate::UEQ => Some(1),
+        _ => None,
+    };
+
+    if let Some(excluded) = excluded {
+        let mut exec = Execute::new();
+        exec.with_if(ExecuteCondition::Score {
+            target: cmp_result.into(),
+            target_obj: OBJECTIVE.into(),
+            kind: ExecuteCondKind::Matches((excluded..=excluded).into()),
+        });
+        exec.with_run(assign_lit(dest, 0));
+        cmds.push(exec.into());
+    }
+
+    Ok(cmds)
+}
+
+/// Converts an integer operand into the two-word form expected by the `*itofp` intrinsics
+fn compile_int_to_float(
+    operand: &Operand,
+    to_type: &Type,
+    dest: &Name,
+    signed: bool,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let bits = if let Type::IntegerType { bits } = &*operand.get_type(tys) {
+        *bits
+    } else {
+        return Err(CompileError::unsupported_type(&operand.get_type(tys)));
+    };
+
+    let (mut cmds, op) = eval_operand(operand, globals, tys)?;
+
+    let lo_word = param(0, 0);
+    let hi_word = param(0, 1);
+
+    match bits {
+        64 => {
+            cmds.push(assign(lo_word, op[0].clone()));
+            cmds.push(assign(hi_word, op[1].clone()));
+        }
+        1..=32 => {
+            cmds.push(assign(lo_word.clone(), op[0].clone()));
+
+            // The high bits of narrow integers aren't guaranteed to be clear
+            if bits < 32 {
+                cmds.push(make_op_lit(lo_word.clone(), "%=", 1 << bits));
+                if signed {
+                    let mut exec = Execute::new();
+                    exec.with_if(ExecuteCondition::Score {
+                        target: lo_word.clone().into(),
+                        target_obj: OBJECTIVE.into(),
+                        kind: ExecuteCondKind::Matches((1 << (bits - 1)..).into()),
+                    });
+                    exec.with_run(ScoreAdd {
+                        target: lo_word.clone().into(),
+                        target_obj: OBJECTIVE.into(),
+                        score: -(1 << bits),
+                    });
+                    cmds.push(exec.into());
+                }
+            }
+
+            cmds.push(assign_lit(hi_word.clone(), 0));
+            if signed {
+                let mut exec = Execute::new();
+                exec.with_if(ExecuteCondition::Score {
+                    target: lo_word.into(),
+                    target_obj: OBJECTIVE.into(),
+                    kind: ExecuteCondKind::Matches((..=-1).into()),
+                });
+                exec.with_run(assign_lit(hi_word, -1));
+                cmds.push(exec.into());
+            }
+        }
+        _ => return Err(CompileError::unsupported_type(&operand.get_type(tys))),
+    }
+
+    let name = format!(
+        "{}_{}",
+        if signed { "sitofp" } else { "uitofp" },
+        float_suffix(to_type)?
+    );
+
+    cmds.push(
+        McFuncCall {
+            id: McFuncId::new(format!("intrinsic:{}", name)),
+        }
+        .into(),
+    );
+
+    let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(to_type, tys)?.size());
+    for (word_idx, dest_word) in dest.into_iter().enumerate() {
+        cmds.push(assign(dest_word, return_holder(word_idx)));
+    }
+
+    Ok(cmds)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum BitOp {
-    And,
-    Or,
-    Xor,
+/// Compiles instructions made up by the compiler in place of another one,
+/// none of which may end the block
+fn compile_replacement(
+    instrs: &[Instruction],
+    parent: &Function,
+    globals: &GlobalVarList,
+    tys: &Types,
+    options: &BuildOptions,
+) -> CompileResult<Vec<Command>> {
+    let mut cmds = Vec::new();
+    for instr in instrs.iter() {
+        let (before, after) = compile_instr(instr, parent, globals, tys, options)?;
+        assert!(after.is_none(), "{} ended the block", instr);
+        cmds.extend(before);
+    }
+    Ok(cmds)
 }
 
-fn compile_bitwise_word(operand0: &Operand, operand1: &Operand, dest: Name, op: BitOp, globals: &GlobalVarList, tys: &Types) -> Vec<Command> {
-    let ty = operand0.get_type(tys);
-    let layout = type_layout(&ty, tys);
+fn atomic_temp(dest: &Name, kind: &str) -> Name {
+    Name::from(format!("{}%atomic_{}", dest.to_string().trim_start_matches('%'), kind))
+}
 
-    let dest = ScoreHolder::from_local_name(dest, layout.size());
+/// Lowers an `atomicrmw` to a plain load, operation and store.
+/// Minecraft only ever runs one command at a time, so nothing can happen in between.
+fn compile_atomicrmw(
+    rmw: &AtomicRMW,
+    parent: &Function,
+    globals: &GlobalVarList,
+    tys: &Types,
+    options: &BuildOptions,
+) -> CompileResult<Vec<Command>> {
+    let AtomicRMW { operation, address, value, dest, volatile, debugloc, .. } = rmw;
+
+    let ty = value.get_type(tys);
+    let alignment = type_layout(&ty, tys)?.align() as u32;
+
+    // The old value ends up in `dest` right away
+    let old = Operand::LocalOperand { name: dest.clone(), ty: ty.clone() };
+    let new_name = atomic_temp(dest, "new");
+    let new = Operand::LocalOperand { name: new_name.clone(), ty: ty.clone() };
+
+    let mut instrs = vec![Instruction::Load(Load {
+        address: address.clone(),
+        dest: dest.clone(),
+        volatile: *volatile,
+        atomicity: None,
+        alignment,
+        debugloc: debugloc.clone(),
+    })];
+
+    let binop = |operand0: Operand, operand1: Operand, dest: Name| -> Instruction {
+        let debugloc = debugloc.clone();
+        match operation {
+            RMWBinOp::Add => Add { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::Sub => Sub { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::And | RMWBinOp::Nand => And { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::Or => Or { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::Xor => Xor { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::FAdd => FAdd { operand0, operand1, dest, debugloc }.into(),
+            RMWBinOp::FSub => FSub { operand0, operand1, dest, debugloc }.into(),
+            _ => unreachable!(),
+        }
+    };
 
-    let (mut cmds, op0) = eval_operand(operand0, globals, tys);
-    let (tmp, op1) = eval_operand(operand1, globals, tys);
-    cmds.extend(tmp);
+    let new = match operation {
+        RMWBinOp::Xchg => value.clone(),
+        RMWBinOp::Nand => {
+            let bits = match &*ty {
+                Type::IntegerType { bits } => *bits,
+                _ => return Err(CompileError::unsupported_type(&ty)),
+            };
 
-    for (dest, (op0, op1)) in dest.into_iter().zip(op0.into_iter().zip(op1.into_iter())) {
-        cmds.push(assign(param(0, 0), op0));
-        cmds.push(assign(param(1, 0), op1));
+            let and_name = atomic_temp(dest, "and");
+            instrs.push(binop(old, value.clone(), and_name.clone()));
+            instrs.push(Xor {
+                operand0: Operand::LocalOperand { name: and_name, ty: ty.clone() },
+                // All ones, which `wide_int::words` sign extends to the full width of anything wider
+                operand1: Operand::ConstantOperand(ConstantRef::new(Constant::Int {
+                    bits,
+                    value: if bits >= 64 { u64::MAX } else { (1 << bits) - 1 },
+                })),
+                dest: new_name,
+                debugloc: debugloc.clone(),
+            }.into());
+            new
+        }
+        RMWBinOp::Max | RMWBinOp::Min | RMWBinOp::UMax | RMWBinOp::UMin => {
+            let predicate = match operation {
+                RMWBinOp::Max => IntPredicate::SGT,
+                RMWBinOp::Min => IntPredicate::SLT,
+                RMWBinOp::UMax => IntPredicate::UGT,
+                RMWBinOp::UMin => IntPredicate::ULT,
+                _ => unreachable!(),
+            };
 
-        let id = match op {
-            BitOp::And => "intrinsic:and",
-            BitOp::Or => "intrinsic:or",
-            BitOp::Xor => "intrinsic:xor",
-        };
+            let keep_name = atomic_temp(dest, "keep");
+            instrs.push(ICmp {
+                predicate,
+                operand0: old.clone(),
+                operand1: value.clone(),
+                dest: keep_name.clone(),
+                debugloc: debugloc.clone(),
+            }.into());
+            instrs.push(Select {
+                condition: Operand::LocalOperand { name: keep_name, ty: tys.bool() },
+                true_value: old,
+                false_value: value.clone(),
+                dest: new_name,
+                debugloc: debugloc.clone(),
+            }.into());
+            new
+        }
+        _ => {
+            instrs.push(binop(old, value.clone(), new_name));
+            new
+        }
+    };
 
-        cmds.push(McFuncCall {
-            id: McFuncId::new(id)
-        }.into());
+    instrs.push(Instruction::Store(Store {
+        address: address.clone(),
+        value: new,
+        volatile: *volatile,
+        atomicity: None,
+        alignment,
+        debugloc: debugloc.clone(),
+    }));
 
-        cmds.push(assign(dest, return_holder(0)));
+    compile_replacement(&instrs, parent, globals, tys, options)
+}
+
+/// Lowers a `cmpxchg` to a plain load, comparison and store,
+/// which can't fail spuriously even if it's marked `weak`
+fn compile_cmpxchg(
+    cmpxchg: &CmpXchg,
+    parent: &Function,
+    globals: &GlobalVarList,
+    tys: &Types,
+    options: &BuildOptions,
+) -> CompileResult<Vec<Command>> {
+    let CmpXchg { address, expected, replacement, dest, volatile, debugloc, .. } = cmpxchg;
+
+    let ty = expected.get_type(tys);
+    let alignment = type_layout(&ty, tys)?.align() as u32;
+
+    let old_name = atomic_temp(dest, "old");
+    let success_name = atomic_temp(dest, "success");
+    let new_name = atomic_temp(dest, "new");
+    let old = Operand::LocalOperand { name: old_name.clone(), ty: ty.clone() };
+
+    let instrs = [
+        Instruction::Load(Load {
+            address: address.clone(),
+            dest: old_name.clone(),
+            volatile: *volatile,
+            atomicity: None,
+            alignment,
+            debugloc: debugloc.clone(),
+        }),
+        ICmp {
+            predicate: IntPredicate::EQ,
+            operand0: old.clone(),
+            operand1: expected.clone(),
+            dest: success_name.clone(),
+            debugloc: debugloc.clone(),
+        }.into(),
+        Select {
+            condition: Operand::LocalOperand { name: success_name.clone(), ty: tys.bool() },
+            true_value: replacement.clone(),
+            false_value: old,
+            dest: new_name.clone(),
+            debugloc: debugloc.clone(),
+        }.into(),
+        Instruction::Store(Store {
+            address: address.clone(),
+            value: Operand::LocalOperand { name: new_name, ty: ty.clone() },
+            volatile: *volatile,
+            atomicity: None,
+            alignment,
+            debugloc: debugloc.clone(),
+        }),
+    ];
+
+    let mut cmds = compile_replacement(&instrs, parent, globals, tys, options)?;
+
+    // The result is `{ ty, i1 }`, holding the old value and whether it was replaced
+    let result_ty = tys.struct_of(vec![ty.clone(), tys.bool()], false);
+    let success_offset = offset_of(&[ty.clone(), tys.bool()], false, 1, tys)?;
+
+    let value_size = type_layout(&ty, tys)?.size();
+    let result = ScoreHolder::from_local_name(dest.clone(), type_layout(&result_ty, tys)?.size());
+    let old = ScoreHolder::from_local_name(old_name, value_size);
+    let success = ScoreHolder::from_local_name(success_name, 1)[0].clone();
+
+    for (result_word, old_word) in result.iter().zip(old) {
+        cmds.push(assign(result_word.clone(), old_word));
     }
 
-    cmds
+    let success_word = result[success_offset / 4].clone();
+    if success_offset % 4 == 0 {
+        cmds.push(assign(success_word, success));
+    } else {
+        // The old value was zero-extended, so the flag can just be added above it
+        cmds.push(make_op_lit(success.clone(), "*=", 1 << (8 * (success_offset % 4))));
+        cmds.push(make_op(success_word, "+=", success));
+    }
+
+    Ok(cmds)
 }
 
 pub fn compile_instr(
@@ -4431,12 +6814,18 @@ pub fn compile_instr(
     parent: &Function,
     globals: &HashMap<&Name, (u32, Option<Constant>)>,
     tys: &Types,
-    _options: &BuildOptions,
-) -> (Vec<Command>, Option<Vec<Command>>) {
+    options: &BuildOptions,
+) -> CompileResult<(Vec<Command>, Option<Vec<Command>>)> {
     let result = match instr {
         // We use an empty stack
-        Instruction::Alloca(alloca) => compile_alloca(alloca, tys),
-        Instruction::GetElementPtr(gep) => compile_getelementptr(gep, globals, tys),
+        Instruction::Alloca(alloca) => {
+            let mut cmds = compile_alloca(alloca, tys)?;
+            if let Some(limit) = options.stack_limit() {
+                cmds.extend(check_stack(&parent.name, limit as i32));
+            }
+            cmds
+        }
+        Instruction::GetElementPtr(gep) => compile_getelementptr(gep, globals, tys)?,
         Instruction::Select(Select {
             condition,
             true_value,
@@ -4444,18 +6833,18 @@ pub fn compile_instr(
             dest,
             ..
         }) => {
-            let (mut cmds, true_val) = eval_operand(true_value, globals, tys);
-            let (tmp, false_val) = eval_operand(false_value, globals, tys);
+            let (mut cmds, true_val) = eval_operand(true_value, globals, tys)?;
+            let (tmp, false_val) = eval_operand(false_value, globals, tys)?;
             cmds.extend(tmp);
-            let (tmp, cond) = eval_operand(condition, globals, tys);
+            let (tmp, cond) = eval_operand(condition, globals, tys)?;
             cmds.extend(tmp);
 
-            let dest_size = type_layout(&true_value.get_type(tys), tys).size();
+            let dest_size = type_layout(&true_value.get_type(tys), tys)?.size();
 
             let dest = ScoreHolder::from_local_name(dest.clone(), dest_size);
 
             if cond.len() != 1 {
-                todo!()
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             let cond = cond[0].clone();
@@ -4491,17 +6880,39 @@ pub fn compile_instr(
             alignment,
             ..
         }) => {
-            let (mut cmds, addr) = eval_operand(address, globals, tys);
+            if let Some(port) = crate::mmio::port_at(address, options.mmio)? {
+                let (mut cmds, value) = eval_operand(value, globals, tys)?;
+                cmds.extend(crate::mmio::store(port, &value)?);
+                return Ok((cmds, None));
+            }
+
+            let value_size = type_layout(&value.get_type(tys), tys)?.size() as u32;
+            if let MaybeConst::Const(addr) = eval_maybe_const(address, globals, tys)? {
+                if (value_size <= 4 || value_size.is_multiple_of(4)) && is_direct_access(addr, value_size, &options.memory) {
+                    let cmds = match eval_maybe_const(value, globals, tys)? {
+                        MaybeConst::Const(value) if addr % 4 == 0 && value_size == 4 => vec![set_memory(value, addr, &options.memory)],
+                        value => {
+                            let (mut cmds, words) = value.force_eval();
+                            for (idx, word) in words.into_iter().enumerate() {
+                                cmds.extend(store_const_addr(word, addr + 4 * idx as i32, value_size.min(4), &options.memory));
+                            }
+                            cmds
+                        }
+                    };
+                    return Ok((cmds, None));
+                }
+            }
+
+            let (mut cmds, addr) = eval_operand(address, globals, tys)?;
 
             assert_eq!(addr.len(), 1, "multiword addr {:?}", address);
 
             let addr = addr[0].clone();
 
-            let value_size = type_layout(&value.get_type(tys), tys).size();
             if value_size % 4 == 0 && alignment % 4 == 0 {
                 // If we're directly storing a constant,
                 // we can skip writing to a temporary value
-                let write_cmds = match eval_maybe_const(value, globals, tys) {
+                let write_cmds = match eval_maybe_const(value, globals, tys)? {
                     MaybeConst::Const(value) => vec![write_ptr_const(value)],
                     MaybeConst::NonConst(eval_cmds, ids) => {
                         cmds.extend(eval_cmds);
@@ -4534,7 +6945,7 @@ pub fn compile_instr(
                     cmds.push(write_cmd);
                 }
             } else if value_size == 1 {
-                let (eval_cmds, value) = eval_operand(value, globals, tys);
+                let (eval_cmds, value) = eval_operand(value, globals, tys)?;
                 let value = value.into_iter().next().unwrap();
 
                 cmds.extend(eval_cmds);
@@ -4547,7 +6958,7 @@ pub fn compile_instr(
                     .into(),
                 )
             } else if value_size == 2 {
-                let (eval_cmds, value) = eval_operand(value, globals, tys);
+                let (eval_cmds, value) = eval_operand(value, globals, tys)?;
                 cmds.extend(eval_cmds);
                 let value = value.into_iter().next().unwrap();
 
@@ -4589,7 +7000,7 @@ pub fn compile_instr(
                     }.into());
                 }
                 */
-                let (tmp, val) = eval_operand(value, globals, tys);
+                let (tmp, val) = eval_operand(value, globals, tys)?;
                 cmds.extend(tmp);
 
                 for (word_idx, val) in val.into_iter().enumerate() {
@@ -4611,7 +7022,7 @@ pub fn compile_instr(
                     );
                 }
             } else if value_size < 4 {
-                let (tmp, val) = eval_operand(value, globals, tys);
+                let (tmp, val) = eval_operand(value, globals, tys)?;
                 cmds.extend(tmp);
 
                 let val = val.into_iter().next().unwrap();
@@ -4647,7 +7058,7 @@ pub fn compile_instr(
                     cmds.push(make_op_lit(ptr(), "+=", 1));
                 }
             } else {
-                todo!("{:?} {}", value, alignment)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             cmds
@@ -4665,14 +7076,29 @@ pub fn compile_instr(
                 unreachable!()
             };
 
-            let (mut cmds, addr) = eval_operand(address, globals, tys);
+            let pointee_layout = type_layout(pointee_type, tys)?;
 
-            assert_eq!(addr.len(), 1, "multiword address {:?}", address);
-            let addr = addr[0].clone();
+            let dest = ScoreHolder::from_local_name(dest.clone(), pointee_layout.size());
 
-            let pointee_layout = type_layout(&pointee_type, tys);
+            if let Some(port) = crate::mmio::port_at(address, options.mmio)? {
+                return Ok((crate::mmio::load(port, &dest)?, None));
+            }
 
-            let dest = ScoreHolder::from_local_name(dest.clone(), pointee_layout.size());
+            let size = pointee_layout.size() as u32;
+            if let MaybeConst::Const(addr) = eval_maybe_const(address, globals, tys)? {
+                if (size <= 4 || size.is_multiple_of(4)) && is_direct_access(addr, size, &options.memory) {
+                    let mut cmds = Vec::new();
+                    for (idx, word) in dest.into_iter().enumerate() {
+                        cmds.extend(load_const_addr(word, addr + 4 * idx as i32, size.min(4), &options.memory));
+                    }
+                    return Ok((cmds, None));
+                }
+            }
+
+            let (mut cmds, addr) = eval_operand(address, globals, tys)?;
+
+            assert_eq!(addr.len(), 1, "multiword address {:?}", address);
+            let addr = addr[0].clone();
 
             if pointee_layout.size() % 4 == 0 && pointee_layout.align() == 4 && alignment % 4 == 0 {
                 for (word_idx, dest_word) in dest.into_iter().enumerate() {
@@ -4735,15 +7161,10 @@ pub fn compile_instr(
                 if pointee_layout.size() == 3 {
                     cmds.push(make_op_lit(dest[0].clone(), "%=", 16777216));
                 } else {
-                    todo!("{:?}", pointee_layout)
+                    return Err(CompileError::unsupported_instr(instr))
                 }
             } else {
-                todo!(
-                    "{:?} with layout {:?} and pointer alignment {}",
-                    pointee_type,
-                    pointee_layout,
-                    alignment
-                )
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             cmds
@@ -4753,41 +7174,46 @@ pub fn compile_instr(
             operand1,
             dest,
             ..
-        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::AddAssign, globals, tys),
+        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::AddAssign, globals, tys)?,
         Instruction::Sub(Sub {
             operand0,
             operand1,
             dest,
             ..
-        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::SubAssign, globals, tys),
+        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::SubAssign, globals, tys)?,
         Instruction::Mul(Mul {
             operand0,
             operand1,
             dest,
             ..
-        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::MulAssign, globals, tys),
+        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::MulAssign, globals, tys)?,
         Instruction::SDiv(SDiv {
             operand0,
             operand1,
             dest,
             ..
-        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::DivAssign, globals, tys),
+        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::DivAssign, globals, tys)?,
         Instruction::SRem(SRem {
             operand0,
             operand1,
             dest,
             ..
-        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::ModAssign, globals, tys),
+        }) => compile_arithmetic(operand0, operand1, dest, ScoreOpKind::ModAssign, globals, tys)?,
         Instruction::UDiv(UDiv {
             operand0,
             operand1,
             dest,
             ..
         }) => {
-            let (mut cmds, source0) = eval_operand(operand0, globals, tys);
-            let (tmp, source1) = eval_operand(operand1, globals, tys);
+            let (mut cmds, source0) = eval_operand(operand0, globals, tys)?;
+            let (tmp, source1) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp.into_iter());
 
+            if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 64 }) {
+                cmds.extend(div_64_bit(&source0, &source1, dest.clone(), false, false));
+                return Ok((cmds, None));
+            }
+
             // FIXME: THIS DOES AN SREM
             for s in source0.iter().cloned() {
                 cmds.push(mark_assertion_matches(true, s, ..=-1));
@@ -4799,16 +7225,17 @@ pub fn compile_instr(
 
             let dest = ScoreHolder::from_local_name(
                 dest.clone(),
-                type_layout(&operand0.get_type(tys), tys).size(),
+                type_layout(&operand0.get_type(tys), tys)?.size(),
             );
 
             if let Type::VectorType {
                 element_type,
                 num_elements,
+                ..
             } = &*operand0.get_type(tys)
             {
                 if !matches!(&**element_type, Type::IntegerType { bits: 32 }) {
-                    todo!("{:?}", element_type)
+                    return Err(CompileError::unsupported_type(&element_type))
                 }
 
                 assert_eq!(source0.len(), *num_elements);
@@ -4844,10 +7271,15 @@ pub fn compile_instr(
             dest,
             ..
         }) => {
-            let (mut cmds, source0) = eval_operand(operand0, globals, tys);
-            let (tmp, source1) = eval_operand(operand1, globals, tys);
+            let (mut cmds, source0) = eval_operand(operand0, globals, tys)?;
+            let (tmp, source1) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp.into_iter());
 
+            if matches!(&*operand0.get_type(tys), Type::IntegerType { bits: 64 }) {
+                cmds.extend(div_64_bit(&source0, &source1, dest.clone(), false, true));
+                return Ok((cmds, None));
+            }
+
             // FIXME: THIS DOES AN SREM
             for s in source0.iter().cloned() {
                 cmds.push(mark_assertion_matches(true, s, ..=-1));
@@ -4859,16 +7291,17 @@ pub fn compile_instr(
 
             let dest = ScoreHolder::from_local_name(
                 dest.clone(),
-                type_layout(&operand0.get_type(tys), tys).size(),
+                type_layout(&operand0.get_type(tys), tys)?.size(),
             );
 
             if let Type::VectorType {
                 element_type,
                 num_elements,
+                ..
             } = &*operand0.get_type(tys)
             {
                 if !matches!(&**element_type, Type::IntegerType { bits: 32 }) {
-                    todo!("{:?}", element_type)
+                    return Err(CompileError::unsupported_type(&element_type))
                 }
 
                 assert_eq!(source0.len(), *num_elements);
@@ -4915,8 +7348,8 @@ pub fn compile_instr(
             let is_eq = pred == &IntPredicate::EQ;
 
             // TODO: When operand1 is a constant, we can optimize the direct comparison into a `matches`
-            let (mut cmds, op0) = eval_operand(operand0, globals, tys);
-            let (tmp_cmds, op1) = eval_operand(operand1, globals, tys);
+            let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+            let (tmp_cmds, op1) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp_cmds);
 
             let dest = ScoreHolder::from_local_name(dest.clone(), 1)
@@ -4959,8 +7392,8 @@ pub fn compile_instr(
             ..
         }) => {
             // TODO: When operand1 is a constant, we can optimize the direct comparison into a `matches`
-            let (mut cmds, target) = eval_operand(operand0, globals, tys);
-            let (tmp_cmds, source) = eval_operand(operand1, globals, tys);
+            let (mut cmds, target) = eval_operand(operand0, globals, tys)?;
+            let (tmp_cmds, source) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp_cmds);
 
             match &*operand0.get_type(tys) {
@@ -5044,7 +7477,14 @@ pub fn compile_instr(
                             lo_lt_check.with_run(assign_lit(dest, 1));
                             cmds.push(lo_lt_check.into());
                         }
-                        p => todo!("{:?}", p),
+                        _ => {
+                            cmds.extend(icmp_multiword(
+                                &[target_lo, target_hi],
+                                &[source_lo, source_hi],
+                                *predicate,
+                                dest,
+                            ));
+                        }
                     }
 
                     cmds
@@ -5052,9 +7492,10 @@ pub fn compile_instr(
                 Type::VectorType {
                     element_type,
                     num_elements,
+                    ..
                 } if matches!(&**element_type, Type::IntegerType { bits: 32 }) => {
                     if *num_elements > 4 {
-                        todo!()
+                        return Err(CompileError::unsupported_instr(instr))
                     }
 
                     let dest = ScoreHolder::from_local_name(dest.clone(), *num_elements)
@@ -5074,6 +7515,7 @@ pub fn compile_instr(
                 Type::VectorType {
                     element_type,
                     num_elements: 4,
+                    ..
                 } if matches!(&**element_type, Type::IntegerType { bits: 8 }) => {
                     let dest = ScoreHolder::from_local_name(dest.clone(), 4)
                         .into_iter()
@@ -5116,7 +7558,7 @@ pub fn compile_instr(
 
                     cmds
                 }
-                ty @ Type::VectorType { .. } => todo!("{:?}", ty),
+                ty @ Type::VectorType { .. } => return Err(CompileError::unsupported_type(&ty)),
                 ty => {
                     let dest = ScoreHolder::from_local_name(dest.clone(), 1)
                         .into_iter()
@@ -5124,34 +7566,11 @@ pub fn compile_instr(
                         .unwrap();
 
                     if target.len() != 1 || source.len() != 1 {
-                        if target.len() == 2 && source.len() == 2 {
-                            cmds.extend(compile_normal_icmp(target[1].clone(), source[1].clone(),predicate, dest.clone()));
-
-                            let conditionals = compile_normal_icmp(target[0].clone(), source[0].clone(), predicate, dest);
-
-                            cmds.extend(
-                                conditionals
-                                .into_iter()
-                                .map(|current_command| -> Command {
-                                    let mut out_command = Execute::new();
-
-                                    out_command.with_if(ExecuteCondition::Score {
-                                        target: Target::Uuid(target[1].clone()),
-                                        target_obj: OBJECTIVE.to_string(),
-                                        kind: ExecuteCondKind::Relation {
-                                            relation: cir::Relation::Eq,
-                                            source: Target::Uuid(source[1].clone()),
-                                            source_obj: OBJECTIVE.to_string()
-                                        }
-                                    });
-                                    out_command.with_run::<cir::Command>(current_command.clone());
-
-                                    out_command.into()
-                                })
-                            );
+                        if target.len() == source.len() {
+                            cmds.extend(icmp_multiword(&target, &source, *predicate, dest));
                         } else {
                             println!("Target len is {}, source len is {} and ty is {:?} and predicate is {:?}",target.len(),source.len(), ty, predicate);
-                            todo!()
+                            return Err(CompileError::unsupported_instr(instr))
                         }
                     } else {
                         let target = target.into_iter().next().unwrap();
@@ -5170,7 +7589,7 @@ pub fn compile_instr(
             to_type,
             ..
         }) => {
-            let to_type_size = type_layout(to_type, tys).size();
+            let to_type_size = type_layout(to_type, tys)?.size();
 
             let dst = ScoreHolder::from_local_name(dest.clone(), to_type_size);
 
@@ -5188,7 +7607,7 @@ pub fn compile_instr(
                     block, value
                 )));
 
-                let (tmp, val) = eval_operand(value, globals, tys);
+                let (tmp, val) = eval_operand(value, globals, tys)?;
                 cmds.extend(tmp);
 
                 assert_eq!(val.len(), dst.len());
@@ -5214,18 +7633,18 @@ pub fn compile_instr(
             to_type,
             ..
         }) => {
-            let (mut cmds, source) = eval_operand(operand, globals, tys);
+            let (mut cmds, source) = eval_operand(operand, globals, tys)?;
 
             if source.len() != 1 {
-                todo!("multiword source {:?}", source);
+                return Err(CompileError::unsupported_instr(instr));
             }
 
             let source = source[0].clone();
 
-            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(to_type, tys).size());
+            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(to_type, tys)?.size());
 
             if dest.len() != 1 {
-                todo!("multiword dest {:?}", dest);
+                return Err(CompileError::unsupported_instr(instr));
             }
 
             let dest = dest[0].clone();
@@ -5240,11 +7659,11 @@ pub fn compile_instr(
             dest,
             ..
         }) if to_type.as_ref() == &Type::IntegerType { bits: 32 } => {
-            if !matches!(&*operand.get_type(tys), Type::IntegerType { bits: 64 }) {
-                todo!("{:?}", operand);
+            if !matches!(&*operand.get_type(tys), Type::IntegerType { bits } if *bits >= 64) {
+                return Err(CompileError::unsupported_instr(instr));
             }
 
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
 
             let dest = ScoreHolder::from_local_name(dest.clone(), 4)[0].clone();
 
@@ -5252,13 +7671,39 @@ pub fn compile_instr(
 
             cmds
         }
+        Instruction::Trunc(Trunc {
+            operand,
+            to_type,
+            dest,
+            ..
+        }) if matches!(&**to_type, Type::IntegerType { bits } if *bits > 32) => {
+            let bits = if let Type::IntegerType { bits } = &**to_type {
+                *bits
+            } else {
+                unreachable!()
+            };
+
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
+
+            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(to_type, tys)?.size());
+
+            for (dest, op) in dest.iter().zip(op) {
+                cmds.push(assign(dest.clone(), op));
+            }
+
+            if bits % 32 != 0 {
+                cmds.push(make_op_lit(dest.last().unwrap().clone(), "%=", 1 << (bits % 32)));
+            }
+
+            cmds
+        }
         Instruction::Trunc(Trunc {
             operand,
             to_type,
             dest,
             ..
         }) => {
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
 
             let dest = ScoreHolder::from_local_name(dest.clone(), 1)
                 .into_iter()
@@ -5270,11 +7715,11 @@ pub fn compile_instr(
             let bits = if let Type::IntegerType { bits } = &**to_type {
                 *bits
             } else {
-                todo!("{:?}", to_type)
+                return Err(CompileError::unsupported_type(&to_type))
             };
 
             if bits >= 31 {
-                todo!()
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             // FIXME: Is this (and the other one) valid?
@@ -5288,10 +7733,10 @@ pub fn compile_instr(
             dest,
             ..
         }) => {
-            let (mut cmds, aggr) = eval_operand(aggregate, globals, tys);
+            let (mut cmds, aggr) = eval_operand(aggregate, globals, tys)?;
 
             if indices.len() != 1 {
-                todo!("{:?}", indices)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             if let Type::StructType {
@@ -5300,40 +7745,35 @@ pub fn compile_instr(
             } = &*aggregate.get_type(tys)
             {
                 let result_type = &element_types[indices[0] as usize];
-                let size = type_layout(result_type, tys).size();
+                let size = type_layout(result_type, tys)?.size();
 
-                let offset = offset_of(element_types, *is_packed, indices[0], tys);
+                let offset = offset_of(element_types, *is_packed, indices[0], tys)?;
 
                 let dest = ScoreHolder::from_local_name(dest.clone(), size);
 
                 if size == 4 {
                     if dest.len() != 1 {
-                        todo!()
+                        return Err(CompileError::unsupported_instr(instr))
                     }
 
                     let dest = dest[0].clone();
 
                     if offset % 4 != 0 {
-                        todo!()
+                        return Err(CompileError::unsupported_instr(instr))
                     }
 
                     cmds.push(assign(dest, aggr[offset as usize / 4].clone()))
                 } else if size == 1 {
                     let dest = dest[0].clone();
+                    let word = aggr[offset / 4].clone();
 
-                    // FIXME: THIS DOES NOT WORK
-                    // Shift over to the relevant byte
-                    for _ in 0..offset {
-                        cmds.push(make_op_lit(dest.clone(), "/=", 256));
-                    }
-
-                    cmds.extend(truncate_to(dest, 1));
+                    cmds.extend(extract_bytes(dest, word, offset as u32 % 4, 1));
                 } else {
                     println!("{:?}", aggregate);
-                    todo!("multiword extract value {:?}", result_type);
+                    return Err(CompileError::unsupported_type(&result_type));
                 }
             } else {
-                todo!("{:?}", aggregate)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             cmds
@@ -5346,10 +7786,10 @@ pub fn compile_instr(
             ..
         }) => {
             let aggr_ty = aggregate.get_type(tys);
-            let aggr_layout = type_layout(&aggr_ty, tys);
+            let aggr_layout = type_layout(&aggr_ty, tys)?;
 
             if indices.len() != 1 {
-                todo!("indices {:?}", indices)
+                return Err(CompileError::unsupported_instr(instr))
             }
             let index = indices[0];
 
@@ -5360,19 +7800,19 @@ pub fn compile_instr(
             {
                 (element_types, *is_packed)
             } else {
-                todo!("{:?}", aggr_ty)
+                return Err(CompileError::unsupported_type(&aggr_ty))
             };
 
-            let (mut cmds, aggr) = eval_operand(aggregate, globals, tys);
-            let (tmp, elem) = eval_operand(element, globals, tys);
+            let (mut cmds, aggr) = eval_operand(aggregate, globals, tys)?;
+            let (tmp, elem) = eval_operand(element, globals, tys)?;
             cmds.extend(tmp);
 
             let elem = elem[0].clone();
 
-            let offset = offset_of(&element_types, is_packed, index, tys);
+            let offset = offset_of(element_types, is_packed, index, tys)?;
 
             if offset % 4 != 0 {
-                todo!("{:?}", offset);
+                return Err(CompileError::unsupported_instr(instr));
             }
 
             let dest = ScoreHolder::from_local_name(dest.clone(), aggr_layout.size());
@@ -5387,9 +7827,9 @@ pub fn compile_instr(
                 cmds.push(assign(dest_word.clone(), aggr_word.clone()));
             }
 
-            if type_layout(&element.get_type(tys), tys).size() == 4 && offset % 4 == 0 {
+            if type_layout(&element.get_type(tys), tys)?.size() == 4 && offset % 4 == 0 {
                 cmds.push(assign(dest[insert_idx].clone(), elem));
-            } else if type_layout(&element.get_type(tys), tys).size() == 1 {
+            } else if type_layout(&element.get_type(tys), tys)?.size() == 1 {
                 cmds.push(mark_assertion_matches(true, dest[insert_idx].clone(), 0..=255));
 
                 if index == 0 {
@@ -5401,10 +7841,10 @@ pub fn compile_instr(
                     cmds.extend(shift_left_bytes(elem.clone(), trunc_len as u32));
                     cmds.push(make_op(dest[insert_idx].clone(), "+=", elem));
                 } else {
-                    todo!()
+                    return Err(CompileError::unsupported_instr(instr))
                 }
             } else {
-                todo!();
+                return Err(CompileError::unsupported_instr(instr));
             }
 
             cmds
@@ -5415,7 +7855,7 @@ pub fn compile_instr(
             dest,
             ..
         }) => {
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
 
             if matches!(&**to_type, Type::IntegerType { bits: 32 }) {
                 let op = op.into_iter().next().unwrap();
@@ -5450,7 +7890,7 @@ pub fn compile_instr(
                         } else if matches!(&*operand.get_type(tys), Type::IntegerType { bits: 16 }) {
                             (32768..=65535, -65536, 65536)
                         } else {
-                            todo!("{:?}", operand.get_type(tys));
+                            return Err(CompileError::unsupported_type(&operand.get_type(tys)));
                         };
 
                     cmds.push(assign(dest.clone(), op));
@@ -5514,12 +7954,22 @@ pub fn compile_instr(
                     exec.with_run(assign_lit(dest[1].clone(), u32::MAX as i32));
                     cmds.push(exec.into());
 
+                    cmds
+                } else if let Type::IntegerType { bits } = &*operand.get_type(tys) {
+                    cmds.extend(sext_multiword(&op, *bits, &dest));
+
                     cmds
                 } else {
-                    todo!("{:?}", operand)
+                    return Err(CompileError::unsupported_instr(instr))
                 }
+            } else if let (Type::IntegerType { bits }, Type::IntegerType { .. }) = (&*operand.get_type(tys), &**to_type) {
+                let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(to_type, tys)?.size());
+
+                cmds.extend(sext_multiword(&op, *bits, &dest));
+
+                cmds
             } else {
-                todo!("sign extend to {:?}", to_type)
+                return Err(CompileError::unsupported_type(&to_type))
             }
         }
         Instruction::ZExt(ZExt {
@@ -5528,9 +7978,9 @@ pub fn compile_instr(
             dest,
             ..
         }) => {
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
 
-            let to_size = type_layout(to_type, tys).size();
+            let to_size = type_layout(to_type, tys)?.size();
 
             let dst = ScoreHolder::from_local_name(dest.clone(), to_size);
 
@@ -5543,11 +7993,11 @@ pub fn compile_instr(
                             cmds.push(make_op_lit(dst[0].clone(), "%=", 1 << bits));
                         }
                     }
-                    Type::VectorType { element_type, num_elements: 4 } if matches!(&**element_type, Type::IntegerType { bits: 1 }) => {
-                        let elem_ty = if let Type::VectorType { element_type, num_elements: 4 } = &**to_type {
+                    Type::VectorType { element_type, num_elements: 4, .. } if matches!(&**element_type, Type::IntegerType { bits: 1 }) => {
+                        let elem_ty = if let Type::VectorType { element_type, num_elements: 4, .. } = &**to_type {
                             element_type
                         } else {
-                            panic!("{:?}", to_type)
+                            return Err(CompileError::unsupported_type(&to_type))
                         };
 
                         if matches!(&**elem_ty, Type::IntegerType { bits: 32 }) {
@@ -5559,35 +8009,35 @@ pub fn compile_instr(
                                 cmds.push(make_op_lit(tmp.clone(), "/=", 256));
                             }
 
-                            return (cmds, None);
+                            return Ok((cmds, None));
                         } else {
-                            todo!("{:?}", elem_ty)
+                            return Err(CompileError::unsupported_type(&elem_ty))
                         }
                     }
-                    _ => todo!("{:?} -> {:?}", operand, to_type)
+                    _ => return Err(CompileError::unsupported_type(&to_type))
                 }
 
                 for dst in dst[1..].iter().cloned() {
                     cmds.push(assign_lit(dst, 0));
                 }
-            } else if op.len() == 2 {
-                cmds.push(assign(dst[0].clone(), op[0].clone()));
-                cmds.push(assign(dst[1].clone(), op[1].clone()));
+            } else if op.len() >= 2 {
+                for (dst, op) in dst.iter().zip(op.iter()) {
+                    cmds.push(assign(dst.clone(), op.clone()));
+                }
 
                 if let Type::IntegerType { bits } = &*operand.get_type(tys) {
-                    if *bits < 64 {
-                        assert!(*bits > 32);
-                        cmds.push(make_op_lit(dst[1].clone(), "%=", 1 << (bits - 32)));
+                    if bits % 32 != 0 {
+                        cmds.push(make_op_lit(dst[op.len() - 1].clone(), "%=", 1 << (bits % 32)));
                     }
                 } else {
-                    todo!("{:?}", operand)
+                    return Err(CompileError::unsupported_instr(instr))
                 };
 
-                for dst in dst[2..].iter().cloned() {
+                for dst in dst[op.len()..].iter().cloned() {
                     cmds.push(assign_lit(dst, 0));
                 }
             } else {
-                todo!("{:?} -> {:?}", operand, to_type)
+                return Err(CompileError::unsupported_type(&to_type))
             }
 
             cmds
@@ -5600,9 +8050,9 @@ pub fn compile_instr(
         }) => {
             assert_eq!(operand0.get_type(tys), operand1.get_type(tys));
 
-            let (mut cmds, op0) = eval_operand(operand0, globals, tys);
+            let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
 
-            let (tmp, op1) = eval_operand(operand1, globals, tys);
+            let (tmp, op1) = eval_operand(operand1, globals, tys)?;
 
             cmds.extend(tmp);
 
@@ -5635,7 +8085,7 @@ pub fn compile_instr(
                     cmds
                 }
                 _ => {
-                    compile_bitwise_word(operand0, operand1, dest.clone(), BitOp::Or, globals, tys)
+                    compile_bitwise_word(operand0, operand1, dest.clone(), BitOp::Or, globals, tys)?
                 }
             }
         }
@@ -5647,11 +8097,11 @@ pub fn compile_instr(
         }) => {
             assert_eq!(operand0.get_type(tys), operand1.get_type(tys));
 
-            let (mut cmds, op0) = eval_operand(operand0, globals, tys);
-            let (tmp, op1) = eval_operand(operand1, globals, tys);
+            let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+            let (tmp, op1) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp);
 
-            let layout = type_layout(&operand0.get_type(tys), tys);
+            let layout = type_layout(&operand0.get_type(tys), tys)?;
 
             match &*operand0.get_type(tys) {
                 Type::IntegerType { bits: 1 } => {
@@ -5678,14 +8128,15 @@ pub fn compile_instr(
                     cmds
                 }
                 _ => {
-                    compile_bitwise_word(operand0, operand1, dest.clone(), BitOp::And, globals, tys)
+                    compile_bitwise_word(operand0, operand1, dest.clone(), BitOp::And, globals, tys)?
                 }
             }
         }
-        Instruction::Xor(xor) => compile_xor(xor, globals, tys),
-        Instruction::Shl(shl) => compile_shl(shl, globals, tys),
-        Instruction::LShr(lshr) => compile_lshr(lshr, globals, tys),
-        Instruction::AShr(ashr) => compile_ashr(ashr, globals, tys),
+        Instruction::Xor(xor) => compile_xor(xor, globals, tys)?,
+        Instruction::VAArg(va_arg) => compile_va_arg(va_arg, globals, tys)?,
+        Instruction::Shl(shl) => compile_shl(shl, globals, tys)?,
+        Instruction::LShr(lshr) => compile_lshr(lshr, globals, tys)?,
+        Instruction::AShr(ashr) => compile_ashr(ashr, globals, tys)?,
         Instruction::PtrToInt(PtrToInt {
             operand,
             to_type,
@@ -5693,10 +8144,10 @@ pub fn compile_instr(
             ..
         }) if to_type.as_ref() == &Type::IntegerType { bits: 32 } => {
             if !matches!(&*operand.get_type(tys), Type::PointerType{ .. }) {
-                todo!("{:?}", operand)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
             let op = op.into_iter().next().unwrap();
 
             let dest = ScoreHolder::from_local_name(dest.clone(), 4)
@@ -5717,10 +8168,10 @@ pub fn compile_instr(
             assert_eq!(operand.get_type(tys).as_ref(), &Type::IntegerType { bits: 32 });
 
             if !matches!(&*to_type.get_type(tys), Type::PointerType{ .. }) {
-                todo!("{:?}", operand)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
-            let (mut cmds, op) = eval_operand(operand, globals, tys);
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
             let op = op.into_iter().next().unwrap();
 
             let dest = ScoreHolder::from_local_name(dest.clone(), 4)
@@ -5739,8 +8190,8 @@ pub fn compile_instr(
             mask,
             ..
         }) => {
-            let (mut cmds, op0) = eval_operand(operand0, globals, tys);
-            let (tmp, op1) = eval_operand(operand1, globals, tys);
+            let (mut cmds, op0) = eval_operand(operand0, globals, tys)?;
+            let (tmp, op1) = eval_operand(operand1, globals, tys)?;
             cmds.extend(tmp);
 
             let op0_ty = operand0.get_type(tys);
@@ -5758,7 +8209,7 @@ pub fn compile_instr(
 
             let mask_vals = match &**mask {
                 Constant::AggregateZero(t) => {
-                    if let Type::VectorType { element_type: _, num_elements } = &**t {
+                    if let Type::VectorType { num_elements, .. } = &**t {
                         vec![ConstantRef::new(Constant::Int { bits: 32, value: 0 }); *num_elements]
                     } else {
                         unreachable!()
@@ -5771,9 +8222,11 @@ pub fn compile_instr(
             let dest_type = Type::VectorType {
                 element_type: element_type.clone(),
                 num_elements: mask_vals.len(),
+                #[cfg(not(feature = "llvm-10"))]
+                scalable: false,
             };
 
-            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(&dest_type, tys).size());
+            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(&dest_type, tys)?.size());
 
             match &**element_type {
                 Type::IntegerType { bits: 32 } => {
@@ -5799,7 +8252,7 @@ pub fn compile_instr(
                 // This does deal with signs, do not use with 8 bits!
                 Type::IntegerType { bits: 1 } if mask_vals.len() == 4 => {
                     if mask_vals.len() != 4 {
-                        todo!()
+                        return Err(CompileError::unsupported_instr(instr))
                     }
 
                     let dest = dest.into_iter().next().unwrap();
@@ -5814,7 +8267,7 @@ pub fn compile_instr(
                                 let value = *value as usize;
 
                                 if op0_len != 4 {
-                                    todo!()
+                                    return Err(CompileError::unsupported_instr(instr))
                                 }
 
                                 let (source, byte_idx) = if value > op0_len {
@@ -5838,7 +8291,7 @@ pub fn compile_instr(
                         }
                     }
                 }
-                _ => todo!("{:?}", element_type),
+                _ => return Err(CompileError::unsupported_type(&element_type)),
             }
 
             cmds
@@ -5856,11 +8309,11 @@ pub fn compile_instr(
             };
 
             let dest =
-                ScoreHolder::from_local_name(dest.clone(), type_layout(&element_type, tys).size());
+                ScoreHolder::from_local_name(dest.clone(), type_layout(&element_type, tys)?.size());
 
-            let (mut cmds, vec) = eval_operand(vector, globals, tys);
+            let (mut cmds, vec) = eval_operand(vector, globals, tys)?;
 
-            match eval_maybe_const(index, globals, tys) {
+            match eval_maybe_const(index, globals, tys)? {
                 MaybeConst::Const(c) => {
                     let c = c as usize;
                     match &*element_type {
@@ -5875,10 +8328,10 @@ pub fn compile_instr(
                             cmds.push(assign(dest.clone(), vec[c / 4].clone()));
                             cmds.push(make_op_lit(dest, "/=", 1 << (8 * (c % 4))));
                         }
-                        ty => todo!("{:?}", ty),
+                        ty => return Err(CompileError::unsupported_type(&ty)),
                     }
                 }
-                MaybeConst::NonConst(_, _) => todo!("{:?}", index),
+                MaybeConst::NonConst(_, _) => return Err(CompileError::unsupported_instr(instr)),
             }
 
             cmds
@@ -5897,21 +8350,21 @@ pub fn compile_instr(
             };
 
             if !matches!(&*element_type, Type::IntegerType { bits: 32 }) {
-                todo!("{:?}", vector)
+                return Err(CompileError::unsupported_instr(instr))
             }
 
             let dest =
-                ScoreHolder::from_local_name(dest.clone(), type_layout(&vector.get_type(tys), tys).size());
+                ScoreHolder::from_local_name(dest.clone(), type_layout(&vector.get_type(tys), tys)?.size());
 
-            let (mut cmds, vec) = eval_operand(vector, globals, tys);
+            let (mut cmds, vec) = eval_operand(vector, globals, tys)?;
 
-            let (tmp, elem) = eval_operand(element, globals, tys);
+            let (tmp, elem) = eval_operand(element, globals, tys)?;
             cmds.extend(tmp);
 
             assert_eq!(elem.len(), 1);
             let elem = elem.into_iter().next().unwrap();
 
-            match eval_maybe_const(index, globals, tys) {
+            match eval_maybe_const(index, globals, tys)? {
                 MaybeConst::Const(c) => {
                     for (word_idx, (src_word, dest_word)) in
                         vec.into_iter().zip(dest.into_iter()).enumerate()
@@ -5923,15 +8376,107 @@ pub fn compile_instr(
                         }
                     }
                 }
-                MaybeConst::NonConst(_, _) => todo!("{:?}", index),
+                MaybeConst::NonConst(_, _) => return Err(CompileError::unsupported_instr(instr)),
+            }
+
+            cmds
+        }
+        Instruction::FAdd(FAdd { operand0, operand1, dest, .. })
+        | Instruction::FSub(FSub { operand0, operand1, dest, .. })
+        | Instruction::FMul(FMul { operand0, operand1, dest, .. })
+        | Instruction::FDiv(FDiv { operand0, operand1, dest, .. }) => {
+            let op_name = match instr {
+                Instruction::FAdd(_) => "fadd",
+                Instruction::FSub(_) => "fsub",
+                Instruction::FMul(_) => "fmul",
+                Instruction::FDiv(_) => "fdiv",
+                _ => unreachable!(),
+            };
+
+            let ty = operand0.get_type(tys);
+            let name = format!("{}_{}", op_name, float_suffix(&ty)?);
+            let size = type_layout(&ty, tys)?.size();
+
+            compile_float_call(&[operand0, operand1], &name, Some((dest, size)), globals, tys)?
+        }
+        Instruction::FNeg(FNeg { operand, dest, .. }) => {
+            let size = type_layout(&operand.get_type(tys), tys)?.size();
+
+            let (mut cmds, op) = eval_operand(operand, globals, tys)?;
+
+            let dest = ScoreHolder::from_local_name(dest.clone(), size);
+
+            for (dest_word, op_word) in dest.iter().zip(op) {
+                cmds.push(assign(dest_word.clone(), op_word));
             }
 
+            // Flip the sign bit, which is the top bit of the last word
+            let top = dest.last().unwrap().clone();
+            cmds.push(ScoreAdd {
+                target: top.clone().into(),
+                target_obj: OBJECTIVE.into(),
+                score: -i32::MAX,
+            }.into());
+            cmds.push(ScoreAdd {
+                target: top.into(),
+                target_obj: OBJECTIVE.into(),
+                score: -1,
+            }.into());
+
             cmds
         }
-        _ => todo!("instruction {:?}", instr),
+        Instruction::FCmp(fcmp) => compile_fcmp(fcmp, globals, tys)?,
+        Instruction::FPExt(FPExt { operand, to_type, dest, .. }) => {
+            if float_suffix(&operand.get_type(tys))? != "f32" || float_suffix(to_type)? != "f64" {
+                return Err(CompileError::unsupported_instr(instr))
+            }
+
+            compile_float_call(&[operand], "fpext", Some((dest, 8)), globals, tys)?
+        }
+        Instruction::FPTrunc(FPTrunc { operand, to_type, dest, .. }) => {
+            if float_suffix(&operand.get_type(tys))? != "f64" || float_suffix(to_type)? != "f32" {
+                return Err(CompileError::unsupported_instr(instr))
+            }
+
+            compile_float_call(&[operand], "fptrunc", Some((dest, 4)), globals, tys)?
+        }
+        Instruction::FPToSI(FPToSI { operand, to_type, dest, .. })
+        | Instruction::FPToUI(FPToUI { operand, to_type, dest, .. }) => {
+            // The intrinsic always produces a 64-bit result, so narrower integers just use the low word
+            let op_name = if matches!(instr, Instruction::FPToSI(_)) { "fptosi" } else { "fptoui" };
+            let name = format!("{}_{}", op_name, float_suffix(&operand.get_type(tys))?);
+
+            let size = match &**to_type {
+                Type::IntegerType { bits } if *bits <= 32 => 4,
+                Type::IntegerType { bits: 64 } => 8,
+                _ => return Err(CompileError::unsupported_type(&to_type)),
+            };
+
+            compile_float_call(&[operand], &name, Some((dest, size)), globals, tys)?
+        }
+        Instruction::SIToFP(SIToFP { operand, to_type, dest, .. }) => {
+            compile_int_to_float(operand, to_type, dest, true, globals, tys)?
+        }
+        Instruction::UIToFP(UIToFP { operand, to_type, dest, .. }) => {
+            compile_int_to_float(operand, to_type, dest, false, globals, tys)?
+        }
+        Instruction::LandingPad(LandingPad { result_type, dest, .. }) => {
+            // Clauses aren't checked here, since the selector is the type id of the exception
+            // and the code after the landing pad compares it against each of them anyway
+            let dest = ScoreHolder::from_local_name(dest.clone(), type_layout(result_type, tys)?.size());
+            dest.into_iter()
+                .enumerate()
+                .map(|(idx, dest)| assign(dest, exception(idx)))
+                .collect()
+        }
+        Instruction::AtomicRMW(rmw) => compile_atomicrmw(rmw, parent, globals, tys, options)?,
+        Instruction::CmpXchg(cmpxchg) => compile_cmpxchg(cmpxchg, parent, globals, tys, options)?,
+        // There's only ever one thread
+        Instruction::Fence(_) => Vec::new(),
+        _ => return Err(CompileError::unsupported_instr(instr)),
     };
 
-    (result, None)
+    Ok((result, None))
 }
 
 pub enum MaybeConst {
@@ -5951,23 +8496,48 @@ impl MaybeConst {
     }
 }
 
+/// Puts each word of an integer constant wider than 64 bits in a temporary
+fn wide_int_constant(con: &Constant) -> CompileResult<MaybeConst> {
+    let words = crate::wide_int::words(con).ok_or_else(|| CompileError::unsupported_instr(con))?;
+
+    let num = get_unique_num();
+
+    let (cmds, holders) = words
+        .into_iter()
+        .enumerate()
+        .map(|(idx, word)| {
+            let holder = ScoreHolder::new(format!("%temp{}%{}", num, idx)).unwrap();
+            (assign_lit(holder.clone(), word), holder)
+        })
+        .unzip();
+
+    Ok(MaybeConst::NonConst(cmds, holders))
+}
+
 pub fn eval_constant(
     con: &Constant,
     globals: &GlobalVarList,
     tys: &Types,
-) -> MaybeConst {
-    match con {
+) -> CompileResult<MaybeConst> {
+    let global_addr = |name: &Name| {
+        globals
+            .get(name)
+            .map(|g| g.0)
+            .ok_or_else(|| match name {
+                Name::Name(n) => CompileError::undefined_symbol(n),
+                Name::Number(n) => CompileError::undefined_symbol(&n.to_string()),
+            })
+    };
+
+    let result = match con {
         Constant::GlobalReference { name, .. } => {
-            let addr = globals
-                .get(name)
-                .unwrap_or_else(|| panic!("failed to get {:?}", name))
-                .0;
+            let addr = global_addr(name)?;
 
             if addr == u32::MAX {
                 let mut name = if let Name::Name(name) = name {
                     (**name).clone()
                 } else {
-                    todo!()
+                    return Err(CompileError::unsupported_instr(con));
                 };
                 name.push_str("%%fixup_func_ref");
                 // FIXME: ew ew ew ew
@@ -5976,6 +8546,8 @@ pub fn eval_constant(
                 MaybeConst::Const(addr as i32)
             }
         }
+        Constant::Int { bits, .. } if *bits > 64 => wide_int_constant(con)?,
+        Constant::PtrToInt(_) if crate::wide_int::words(con).is_some() => wide_int_constant(con)?,
         Constant::PtrToInt(tmp) => {
             let llvm_ir::constant::PtrToInt {
                 operand,
@@ -5983,13 +8555,21 @@ pub fn eval_constant(
             } = &*tmp;
 
             if let Constant::GlobalReference { name, .. } = &**operand {
-                let addr = globals
-                    .get(name)
-                    .unwrap_or_else(|| panic!("failed to get {:?}", name))
-                    .0;
-                MaybeConst::Const(addr as i32)
+                MaybeConst::Const(global_addr(name)? as i32)
+            } else {
+                return Err(CompileError::unsupported_instr(operand));
+            }
+        }
+        Constant::IntToPtr(tmp) => {
+            let llvm_ir::constant::IntToPtr {
+                operand,
+                ..
+            } = tmp;
+
+            if let Constant::Int { bits: 32, value } = &**operand {
+                MaybeConst::Const(*value as i32)
             } else {
-                todo!("{:?}", operand)
+                return Err(CompileError::unsupported_instr(operand));
             }
         }
         Constant::Int { bits: 1, value } => MaybeConst::Const(*value as i32),
@@ -6013,28 +8593,44 @@ pub fn eval_constant(
 
             MaybeConst::NonConst(cmds, vec![lo_word, hi_word])
         }
+        Constant::Float(Float::Single(value)) => MaybeConst::Const(value.to_bits() as i32),
+        Constant::Float(Float::Double(value)) => {
+            let num = get_unique_num();
+
+            let lo_word = ScoreHolder::new(format!("%temp{}%0", num)).unwrap();
+            let hi_word = ScoreHolder::new(format!("%temp{}%1", num)).unwrap();
+
+            let bits = value.to_bits();
+
+            let cmds = vec![
+                assign_lit(lo_word.clone(), bits as i32),
+                assign_lit(hi_word.clone(), (bits >> 32) as i32),
+            ];
+
+            MaybeConst::NonConst(cmds, vec![lo_word, hi_word])
+        }
         Constant::Struct { values, is_packed: _, name: _ } => {
-            if values.iter().all(|v| matches!(&*v.get_type(tys), Type::IntegerType { bits: 32 })) {
-                let (cmds, words) = values
-                    .iter()
-                    .map(|v| eval_constant(v, globals, tys).force_eval())
-                    .map(|(c, w)| {
-                        assert_eq!(c.len(), 1);
-                        assert_eq!(w.len(), 1);
-                        (c.into_iter().next().unwrap(), w.into_iter().next().unwrap())
-                    })
-                    .unzip();
+            if !values.iter().all(|v| matches!(&*v.get_type(tys), Type::IntegerType { bits: 32 })) {
+                return Err(CompileError::unsupported_instr(con));
+            }
 
-                MaybeConst::NonConst(cmds, words)
-            } else {
-                todo!("{:?}", values);
+            let mut cmds = Vec::new();
+            let mut words = Vec::new();
+            for v in values.iter() {
+                let (c, w) = eval_constant(v, globals, tys)?.force_eval();
+                assert_eq!(c.len(), 1);
+                assert_eq!(w.len(), 1);
+                cmds.extend(c);
+                words.extend(w);
             }
+
+            MaybeConst::NonConst(cmds, words)
         }
-        Constant::BitCast(bitcast) => eval_constant(&bitcast.operand, globals, tys),
+        Constant::BitCast(bitcast) => eval_constant(&bitcast.operand, globals, tys)?,
         Constant::Undef(ty) => {
             // TODO: This can literally be *anything* you want it to be
 
-            let len = type_layout(ty, tys).size();
+            let len = type_layout(ty, tys)?.size();
 
             let num = get_unique_num();
 
@@ -6047,11 +8643,11 @@ pub fn eval_constant(
 
             MaybeConst::NonConst(cmds, holders)
         }
-        Constant::GetElementPtr(g) => MaybeConst::Const(getelementptr_const(&g, globals, tys) as i32),
+        Constant::GetElementPtr(g) => MaybeConst::Const(getelementptr_const(g, globals, tys)? as i32),
         Constant::Null(_) => MaybeConst::Const(0),
         Constant::AggregateZero(t) => {
-            if let Type::VectorType { element_type, num_elements } = &**t {
-                let size = type_layout(&element_type, tys).size() * num_elements;
+            if let Type::VectorType { element_type, num_elements, .. } = &**t {
+                let size = type_layout(element_type, tys)?.size() * num_elements;
                 if size % 4 == 0 {
                     let num = get_unique_num();
 
@@ -6064,59 +8660,61 @@ pub fn eval_constant(
 
                     MaybeConst::NonConst(cmds, holders)
                 } else {
-                    todo!("{:?} {}", element_type, num_elements)
+                    return Err(CompileError::unsupported_type(t));
                 }
             } else {
-                todo!("{:?}", t)
+                return Err(CompileError::unsupported_type(t));
             }
         }
         Constant::Vector(elems) => {
             // TODO: This is ugly, please fix all of this
 
+            let unsupported = || CompileError::unsupported_instr(con);
+
             let as_8 = elems
                 .iter()
                 .map(|e| {
                     if e.get_type(tys) == tys.i8() {
                         if let Constant::Int { bits: 8, value } = &**e {
-                            Some(*value as u8)
+                            Ok(Some(*value as u8))
                         } else {
-                            todo!()
+                            Err(unsupported())
                         }
                     } else {
-                        None
+                        Ok(None)
                     }
                 })
-                .collect::<Option<Vec<u8>>>();
+                .collect::<CompileResult<Option<Vec<u8>>>>()?;
 
             let as_32 = elems
                 .iter()
                 .map(|e| {
                     if e.get_type(tys) == tys.i32() {
-                        if let MaybeConst::Const(c) = eval_constant(e, globals, tys) {
-                            Some(c)
+                        if let MaybeConst::Const(c) = eval_constant(e, globals, tys)? {
+                            Ok(Some(c))
                         } else {
-                            todo!("{:?}", e)
+                            Err(unsupported())
                         }
                     } else {
-                        None
+                        Ok(None)
                     }
                 })
-                .collect::<Option<Vec<i32>>>();
+                .collect::<CompileResult<Option<Vec<i32>>>>()?;
 
             let as_64 = elems
                 .iter()
                 .map(|e| {
                     if e.get_type(tys) == tys.i64() {
                         if let Constant::Int { bits: 64, value } = &**e {
-                            Some(*value)
+                            Ok(Some(*value))
                         } else {
-                            todo!()
+                            Err(unsupported())
                         }
                     } else {
-                        None
+                        Ok(None)
                     }
                 })
-                .collect::<Option<Vec<u64>>>();
+                .collect::<CompileResult<Option<Vec<u64>>>>()?;
 
             let as_64 = as_64.map(|vec| {
                 vec.into_iter()
@@ -6132,7 +8730,7 @@ pub fn eval_constant(
                         val0 as u8, val1 as u8, val2 as u8, val3 as u8,
                     ]))
                 } else {
-                    todo!()
+                    return Err(unsupported());
                 }
             } else if let Some(as_32) = as_32 {
                 let num = get_unique_num();
@@ -6165,7 +8763,7 @@ pub fn eval_constant(
 
                 MaybeConst::NonConst(cmds, holders)
             } else {
-                todo!("{:?}", elems);
+                return Err(unsupported());
             }
         }
         Constant::ICmp(icmp) => {
@@ -6174,18 +8772,17 @@ pub fn eval_constant(
                 operand0,
                 operand1,
             } = &*icmp;
-            if let MaybeConst::Const(op0) = eval_constant(&operand0, globals, tys) {
-                if let MaybeConst::Const(op1) = eval_constant(&operand1, globals, tys) {
-                    let result = match predicate {
-                        IntPredicate::NE => op0 != op1,
-                        _ => todo!("{:?}", predicate),
-                    };
-                    MaybeConst::Const(result as i32)
-                } else {
-                    todo!("{:?}", operand1)
-                }
+            if let (MaybeConst::Const(op0), MaybeConst::Const(op1)) = (
+                eval_constant(operand0, globals, tys)?,
+                eval_constant(operand1, globals, tys)?,
+            ) {
+                let result = match predicate {
+                    IntPredicate::NE => op0 != op1,
+                    _ => return Err(CompileError::unsupported_instr(con)),
+                };
+                MaybeConst::Const(result as i32)
             } else {
-                todo!("{:?}", operand0)
+                return Err(CompileError::unsupported_instr(con));
             }
         }
         Constant::Select(s) => {
@@ -6201,53 +8798,52 @@ pub fn eval_constant(
                         operand0,
                         operand1,
                     } = &*i;
-                    if let Constant::BitCast(BitCastConst { operand, .. }) = &**operand0 {
-                        if let Constant::GlobalReference { name, .. } = &**operand {
-                            let value = globals.get(name).unwrap().0;
-                            if let Constant::Null(_) = &**operand1 {
+                    match (&**operand0, &**operand1) {
+                        (Constant::BitCast(BitCastConst { operand, .. }), Constant::Null(_)) => {
+                            if let Constant::GlobalReference { name, .. } = &**operand {
+                                let value = global_addr(name)?;
                                 #[allow(clippy::absurd_extreme_comparisons)]
                                 match predicate {
-                                    IntPredicate::ULE => (value as u32) <= 0,
-                                    IntPredicate::NE => (value as u32) != 0,
-                                    _ => todo!("{:?}", predicate),
+                                    IntPredicate::ULE => value <= 0,
+                                    IntPredicate::NE => value != 0,
+                                    _ => return Err(CompileError::unsupported_instr(con)),
                                 }
                             } else {
-                                todo!("{:?}", operand1)
+                                return Err(CompileError::unsupported_instr(con));
                             }
-                        } else {
-                            todo!("{:?}", operand)
                         }
-                    } else {
-                        todo!("{:?}", operand0)
+                        _ => return Err(CompileError::unsupported_instr(con)),
                     }
                 }
-                _ => todo!("{:?}", condition),
+                _ => return Err(CompileError::unsupported_instr(con)),
             };
             if condition {
-                eval_constant(&true_value, globals, tys)
+                eval_constant(true_value, globals, tys)?
             } else {
-                eval_constant(&false_value, globals, tys)
+                eval_constant(false_value, globals, tys)?
             }
         }
-        _ => todo!("evaluate constant {:?}", con),
-    }
+        _ => return Err(CompileError::unsupported_instr(con)),
+    };
+
+    Ok(result)
 }
 
 pub fn eval_maybe_const(
     op: &Operand,
     globals: &GlobalVarList,
     tys: &Types,
-) -> MaybeConst {
+) -> CompileResult<MaybeConst> {
     match op {
         Operand::LocalOperand { name, ty } => {
-            let len = type_layout(ty, tys).size();
+            let len = type_layout(ty, tys)?.size();
 
             let holders = ScoreHolder::from_local_name(name.clone(), len);
 
-            MaybeConst::NonConst(Vec::new(), holders)
+            Ok(MaybeConst::NonConst(Vec::new(), holders))
         }
         Operand::ConstantOperand(con) => eval_constant(con, globals, tys),
-        _ => todo!("operand {:?}", op),
+        Operand::MetadataOperand => Err(CompileError::unsupported_instr(op)),
     }
 }
 
@@ -6255,8 +8851,8 @@ pub fn eval_operand(
     op: &Operand,
     globals: &GlobalVarList,
     tys: &Types,
-) -> (Vec<Command>, Vec<ScoreHolder>) {
-    eval_maybe_const(op, globals, tys).force_eval()
+) -> CompileResult<(Vec<Command>, Vec<ScoreHolder>)> {
+    Ok(eval_maybe_const(op, globals, tys)?.force_eval())
 }
 
 lazy_static! {
@@ -6264,17 +8860,32 @@ lazy_static! {
     pub static ref FREE_PTR: Mutex<u32> = Mutex::new(4);
 }
 
-struct StaticAllocator(u32);
+struct StaticAllocator {
+    next: u32,
+    limit: u32,
+}
 
 impl StaticAllocator {
-    pub fn reserve(&mut self, mut amount: u32) -> u32 {
+    pub fn new(layout: &MemoryLayout) -> Self {
+        // Address 0 is left unused so it can be the null pointer
+        StaticAllocator { next: 4, limit: layout.bytes() }
+    }
+
+    /// Keeps everything from `end` onwards free, e.g. for the heap
+    pub fn limit_to(&mut self, end: u32) {
+        self.limit = self.limit.min(end);
+    }
+
+    pub fn reserve(&mut self, mut amount: u32) -> CompileResult<u32> {
         if amount % 4 != 0 {
             amount += 4 - (amount % 4);
         }
 
-        let result = self.0;
-        self.0 += amount;
-        result
+        let result = self.next;
+        self.next = self.next.checked_add(amount)
+            .filter(|&next| next <= self.limit)
+            .ok_or_else(|| CompileError::out_of_memory(result as u64 + amount as u64, self.limit))?;
+        Ok(result)
     }
 }
 
@@ -6285,7 +8896,7 @@ fn get_unique_num() -> u32 {
     result
 }
 
-fn get_unique_holder() -> ScoreHolder {
+pub(crate) fn get_unique_holder() -> ScoreHolder {
     ScoreHolder::new(format!("%temp{}", get_unique_num())).unwrap()
 }
 
@@ -6336,9 +8947,11 @@ mod test {
         let ty = Type::VectorType {
             element_type: tys.get_for_type(&Type::IntegerType { bits: 8 }),
             num_elements: 4,
+            #[cfg(not(feature = "llvm-10"))]
+            scalable: false,
         };
 
-        assert_eq!(type_layout(&ty, &tys), Layout::from_size_align(4, 4).unwrap());
+        assert_eq!(type_layout(&ty, &tys).unwrap(), Layout::from_size_align(4, 4).unwrap());
     }
 
     #[test]
@@ -6351,9 +8964,102 @@ mod test {
             tys.get_for_type(&Type::IntegerType { bits: 32 }),
         ];
 
-        assert_eq!(offset_of(&element_types, false, 0, &tys), 0);
-        assert_eq!(offset_of(&element_types, false, 1, &tys), 4);
-        assert_eq!(offset_of(&element_types, true, 0, &tys), 0);
-        assert_eq!(offset_of(&element_types, true, 1, &tys), 4);
+        assert_eq!(offset_of(&element_types, false, 0, &tys).unwrap(), 0);
+        assert_eq!(offset_of(&element_types, false, 1, &tys).unwrap(), 4);
+        assert_eq!(offset_of(&element_types, true, 0, &tys).unwrap(), 0);
+        assert_eq!(offset_of(&element_types, true, 1, &tys).unwrap(), 4);
+    }
+
+    #[test]
+    fn error_context() {
+        let debugloc = DebugLoc {
+            line: 12,
+            col: Some(5),
+            filename: "main.c".to_string(),
+            directory: None,
+        };
+
+        let err = CompileError::unsupported_intrinsic("llvm.foo")
+            .with_context("main", Some(&Name::from("entry")), Some(&debugloc))
+            .with_context("other", None, None);
+
+        assert_eq!(err.context().function.as_deref(), Some("main"));
+        assert_eq!(err.to_string(), "unsupported intrinsic llvm.foo in function `main`, block %entry at main.c:12:5");
+    }
+
+    #[test]
+    fn memory_layout() {
+        let layout: MemoryLayout = "300x8x32".parse().unwrap();
+
+        for &addr in [4, 124, 128, 1020, 1024, 300 * 8 * 32 * 4 - 4].iter() {
+            let (x, y, z) = layout.get_address(addr);
+            assert_eq!(layout.get_index(x, y, z).unwrap(), addr);
+        }
+        assert!(layout.get_index(300, 0, 0).is_err());
+
+        assert!("128x16".parse::<MemoryLayout>().is_err());
+        assert!("128x512x16".parse::<MemoryLayout>().is_err());
+
+        // Every command has to stay within the limits of `fill` and `clone`
+        let cmds = layout.build_cmds();
+        assert_eq!(cmds.iter().filter(|c| matches!(c, Command::Fill(_))).count(), 3);
+        assert_eq!(cmds.last().unwrap().to_string(), "clone 0 0 0 43 7 31 256 0 0");
+
+        // With storage, each slice along X is one page
+        let layout = layout.with_backend("storage".parse().unwrap());
+        assert_eq!(set_memory(7, 4 * (2 * 256 + 33), &layout).to_string(), "data modify storage langcraft:memory pages[2][33] set value 7");
+        assert_eq!(layout.build_cmds().len(), 2 + 300);
+        assert!("blocks".parse::<MemoryBackend>().is_err());
+    }
+
+    #[test]
+    fn static_allocator_limit() {
+        let mut alloc = StaticAllocator::new(&MemoryLayout::new(1, 1, 4).unwrap());
+
+        assert_eq!(alloc.reserve(5).unwrap(), 4);
+        assert_eq!(alloc.reserve(4).unwrap(), 12);
+        assert!(matches!(alloc.reserve(1), Err(CompileError::OutOfMemory(..))));
+    }
+
+    #[test]
+    fn call_dispatch() {
+        let others = (0..1000).map(|i| McFunction::new(McFuncId::new(format!("rust:f{}", i)), Vec::new())).collect::<Vec<_>>();
+        let funcs = create_call_funcs(&others);
+        assert_eq!(funcs[0].id.name, CALL_FUNC);
+
+        // Every function is reached by exactly one leaf
+        let leaves = funcs.iter().flat_map(|f| f.cmds.iter()).filter(|c| c.to_string().contains("set value \"function rust:f")).count();
+        assert_eq!(leaves, others.len());
+
+        let list = funcs.iter().map(|f| (f.id.clone(), f)).collect::<HashMap<_, _>>();
+        let count = crate::analysis::estimate_total_count(&list, &HashMap::new(), &funcs[0]).unwrap();
+        assert!(count < 50, "dispatch takes {} commands", count);
+    }
+
+    #[test]
+    fn unwind_dispatch() {
+        // Every third function is the continuation of a call that unwinds to the one before it
+        let mut funcs = (0..1000)
+            .map(|i| {
+                let cmds = if i % 3 == 1 {
+                    vec![Command::Comment(format!("!UNWIND rust:f{}", i - 1))]
+                } else {
+                    Vec::new()
+                };
+                McFunction::new(McFuncId::new(format!("rust:f{}", i)), cmds)
+            })
+            .collect::<Vec<_>>();
+        let unwind = create_unwind_funcs(&mut funcs);
+        assert_eq!(unwind[0].id.name, UNWIND_FUNC);
+        assert!(funcs.iter().all(|f| f.cmds.is_empty()));
+
+        let leaves = unwind.iter().flat_map(|f| f.cmds.iter()).map(|c| c.to_string()).filter(|c| c.contains("players set %%unwindptr")).collect::<Vec<_>>();
+        assert_eq!(leaves.len(), 333);
+        assert!(leaves.contains(&"execute if score %%tempfuncptr rust matches 997..997 run scoreboard players set %%unwindptr rust 996".to_string()), "{:?}", leaves);
+
+        let list = unwind.iter().map(|f| (f.id.clone(), f)).collect::<HashMap<_, _>>();
+        let count = crate::analysis::estimate_total_count(&list, &HashMap::new(), &unwind[0]).unwrap();
+        // Both halves of the tree get counted, since the root runs more than just the dispatch
+        assert!(count < 100, "unwinding takes {} commands", count);
     }
 }
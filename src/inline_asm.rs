@@ -0,0 +1,303 @@
+//! Inline assembly, where each line of the template is a command.
+//!
+//! Neither `llvm_ir` nor the LLVM C API can read the template or constraints of an
+//! inline assembly value, but LLVM can still print one. So while the input is being linked,
+//! `name_inline_asm` replaces the callee of each `asm` call with a declaration named after
+//! its printed form (`__langcraft_asm.N "template", "constraints"`), and `compile_call`
+//! turns calls to those back into commands.
+//!
+//! Operands are numbered like LLVM does, outputs first, and `$N` (or `${N}`) expands to
+//! operand `N`, while `$$` is a plain `$`. The supported constraints are:
+//!  - `r`: an input passed in a score holder, which `$N` is the name of
+//!  - `i` or `n`: a constant input, which `$N` is the value of
+//!  - `=r`: an output in a score holder, which the commands have to set
+//!  - `N`: an input that starts out in the score holder of output `N`
+//!
+//! Clobbers are ignored. Every operand has to fit in a single word.
+//!
+//! With clang, `asm("scoreboard players add %0 rust %1" : "+r"(x) : "i"(5))` adds 5 to `x`.
+
+use crate::cir::{Command, ScoreHolder};
+use crate::compile_ir::{assign, eval_operand, type_layout, CompileError, CompileResult, GlobalVarList};
+use crate::llvm_sys::core::{
+    LLVMAddFunction, LLVMDisposeMessage, LLVMGetCalledFunctionType, LLVMGetCalledValue,
+    LLVMGetFirstBasicBlock, LLVMGetFirstFunction, LLVMGetFirstInstruction, LLVMGetNextBasicBlock,
+    LLVMGetNextFunction, LLVMGetNextInstruction, LLVMGetNumOperands, LLVMIsACallInst,
+    LLVMIsAInlineAsm, LLVMIsAInvokeInst, LLVMPrintValueToString, LLVMSetOperand,
+};
+use crate::llvm_sys::prelude::{LLVMModuleRef, LLVMValueRef};
+use llvm_ir::function::ParameterAttribute;
+use llvm_ir::types::Types;
+use llvm_ir::{Constant, Operand, Type};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+
+/// The start of the name of every declaration that stands in for inline assembly
+static ASM_PREFIX: &str = "__langcraft_asm.";
+
+/// Replaces the callee of every call to inline assembly in `module` with a declaration
+/// named after the assembly, which `llvm_ir` can see
+///
+/// # Safety
+/// `module` must be a valid module
+pub(crate) unsafe fn name_inline_asm(module: LLVMModuleRef) {
+    let mut decls = HashMap::<String, LLVMValueRef>::new();
+
+    let mut func = LLVMGetFirstFunction(module);
+    while !func.is_null() {
+        let mut block = LLVMGetFirstBasicBlock(func);
+        while !block.is_null() {
+            let mut instr = LLVMGetFirstInstruction(block);
+            while !instr.is_null() {
+                let is_call = !LLVMIsACallInst(instr).is_null() || !LLVMIsAInvokeInst(instr).is_null();
+                if is_call && !LLVMIsAInlineAsm(LLVMGetCalledValue(instr)).is_null() {
+                    let text = LLVMPrintValueToString(LLVMGetCalledValue(instr));
+                    let printed = CStr::from_ptr(text).to_string_lossy().into_owned();
+                    LLVMDisposeMessage(text);
+
+                    // This is something like `void (i32)* asm sideeffect "template", "constraints"`,
+                    // and the type makes sure each declaration is only used with one signature
+                    let next = decls.len();
+                    let decl = *decls.entry(printed.clone()).or_insert_with(|| {
+                        let strings = &printed[printed.find('"').unwrap_or(printed.len())..];
+                        let name = CString::new(format!("{}{} {}", ASM_PREFIX, next, strings)).unwrap();
+                        LLVMAddFunction(module, name.as_ptr(), LLVMGetCalledFunctionType(instr))
+                    });
+
+                    // The callee is always the last operand
+                    LLVMSetOperand(instr, LLVMGetNumOperands(instr) as u32 - 1, decl);
+                }
+
+                instr = LLVMGetNextInstruction(instr);
+            }
+
+            block = LLVMGetNextBasicBlock(block);
+        }
+
+        func = LLVMGetNextFunction(func);
+    }
+}
+
+/// Returns true if `name` is a declaration created by `name_inline_asm`
+pub fn is_asm(name: &str) -> bool {
+    name.starts_with(ASM_PREFIX)
+}
+
+/// Reads a string written the way LLVM prints it from the start of `s`,
+/// returning it and the rest of `s`
+fn parse_llvm_string(s: &str) -> Option<(String, &str)> {
+    let s = s.trim_start().strip_prefix('"')?;
+
+    // Quotes are always escaped
+    let end = s.find('"')?;
+
+    let mut bytes = Vec::new();
+    let mut rest = &s.as_bytes()[..end];
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'\\' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+
+    Some((String::from_utf8(bytes).ok()?, &s[end + 1..]))
+}
+
+/// Returns the template and constraints of the assembly that the declaration `name` stands in for
+fn parse_name(name: &str) -> Option<(String, String)> {
+    let strings = &name[name.find(' ')?..];
+    let (template, rest) = parse_llvm_string(strings)?;
+    let (constraints, _) = parse_llvm_string(rest.trim_start().strip_prefix(',')?)?;
+    Some((template, constraints))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constraint {
+    Output,
+    Input,
+    Immediate,
+    Tied(usize),
+}
+
+/// Parses a constraint string, leaving out the clobbers
+fn parse_constraints(constraints: &str) -> CompileResult<Vec<Constraint>> {
+    constraints
+        .split(',')
+        .filter(|c| !c.is_empty() && !c.starts_with('~'))
+        .map(|c| match c {
+            "=r" | "=&r" => Ok(Constraint::Output),
+            "r" => Ok(Constraint::Input),
+            "i" | "n" => Ok(Constraint::Immediate),
+            _ => c
+                .parse()
+                .map(Constraint::Tied)
+                .map_err(|_| CompileError::invalid_asm(&format!("unsupported constraint `{}`", c))),
+        })
+        .collect()
+}
+
+/// Replaces each operand reference in `template` with the text for that operand
+fn substitute(template: &str, operands: &[String]) -> CompileResult<String> {
+    let operand = |index: &str| {
+        index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| operands.get(i))
+            .ok_or_else(|| CompileError::invalid_asm(&format!("no operand `${}`", index)))
+    };
+
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+
+        if let Some(tail) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('{') {
+            let end = tail.find('}').ok_or_else(|| CompileError::invalid_asm("unterminated `${`"))?;
+            if tail[..end].contains(':') {
+                return Err(CompileError::invalid_asm(&format!("unsupported operand modifier in `${{{}}}`", &tail[..end])));
+            }
+            result.push_str(operand(&tail[..end])?);
+            rest = &tail[end + 1..];
+        } else {
+            let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            result.push_str(operand(&rest[..end])?);
+            rest = &rest[end..];
+        }
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// The value of a constant integer, sign-extended
+fn const_value(operand: &Operand) -> Option<i64> {
+    if let Operand::ConstantOperand(c) = operand {
+        if let Constant::Int { bits, value } = &**c {
+            let shift = 64 - (*bits).clamp(1, 64);
+            return Some(((*value << shift) as i64) >> shift);
+        }
+    }
+
+    None
+}
+
+/// The score holders that each output of the assembly goes in
+fn output_holders(count: usize, dest: Option<&[ScoreHolder]>, result_type: &Type, tys: &Types) -> CompileResult<Vec<ScoreHolder>> {
+    let dest = dest.unwrap_or(&[]);
+
+    match count {
+        0 => Ok(Vec::new()),
+        1 if dest.len() == 1 => Ok(dest.to_vec()),
+        // Several outputs are returned as a struct, which has one word per field if they all fit
+        _ => match result_type {
+            Type::StructType { element_types, .. } if element_types.len() == count && dest.len() == count => {
+                for ty in element_types.iter() {
+                    if type_layout(ty, tys)?.size() != 4 {
+                        return Err(CompileError::invalid_asm(&format!("output of type {} isn't a word", ty)));
+                    }
+                }
+                Ok(dest.to_vec())
+            }
+            _ => Err(CompileError::invalid_asm(&format!("outputs of type {} don't fit in a word each", result_type))),
+        },
+    }
+}
+
+/// Compiles a call to the declaration `name` that stands in for inline assembly
+pub fn compile_call(
+    name: &str,
+    arguments: &[(Operand, Vec<ParameterAttribute>)],
+    dest: Option<&[ScoreHolder]>,
+    result_type: &Type,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Command>> {
+    let (template, constraints) = parse_name(name).ok_or_else(|| CompileError::invalid_asm(name))?;
+    let constraints = parse_constraints(&constraints)?;
+
+    let output_count = constraints.iter().filter(|c| **c == Constraint::Output).count();
+    let outputs = output_holders(output_count, dest, result_type, tys)?;
+
+    let mut cmds = Vec::new();
+    let mut operands = outputs.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+
+    let inputs = constraints.iter().filter(|c| **c != Constraint::Output);
+    if inputs.clone().count() != arguments.len() {
+        return Err(CompileError::invalid_asm(&format!("{} arguments for {} inputs", arguments.len(), inputs.count())));
+    }
+
+    for (constraint, (arg, _)) in inputs.zip(arguments.iter()) {
+        let text = match constraint {
+            Constraint::Immediate => const_value(arg)
+                .ok_or_else(|| CompileError::invalid_asm(&format!("{} isn't a constant", arg)))?
+                .to_string(),
+            Constraint::Input | Constraint::Tied(_) => {
+                let (tmp, value) = eval_operand(arg, globals, tys)?;
+                cmds.extend(tmp);
+
+                let value = match &value[..] {
+                    [value] => value.clone(),
+                    _ => return Err(CompileError::invalid_asm(&format!("{} doesn't fit in a word", arg))),
+                };
+
+                if let Constraint::Tied(output) = constraint {
+                    let output = outputs
+                        .get(*output)
+                        .ok_or_else(|| CompileError::invalid_asm(&format!("input tied to missing output {}", output)))?;
+                    cmds.push(assign(output.clone(), value));
+                    output.to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+            Constraint::Output => unreachable!(),
+        };
+
+        operands.push(text);
+    }
+
+    for line in substitute(&template, &operands)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let cmd = line
+            .parse()
+            .map_err(|e| CompileError::invalid_asm(&format!("invalid command `{}`: {}", line, e)))?;
+        cmds.push(cmd);
+    }
+
+    Ok(cmds)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_name() {
+        let name = r#"__langcraft_asm.0 "tellraw @a \22$$$0\5C\22\0Akill @e", "=r,r,~{dirflag}""#;
+        let (template, constraints) = parse_name(name).unwrap();
+        assert_eq!(template, "tellraw @a \"$$$0\\\"\nkill @e");
+        assert_eq!(constraints, "=r,r,~{dirflag}");
+        assert_eq!(parse_constraints(&constraints).unwrap(), vec![Constraint::Output, Constraint::Input]);
+        assert!(parse_constraints("=*m").is_err());
+    }
+
+    #[test]
+    fn substitute_operands() {
+        let operands = vec!["%x%0".to_string(), "5".to_string()];
+        assert_eq!(substitute("add $0 ${1} $$1", &operands).unwrap(), "add %x%0 5 $1");
+        assert!(substitute("$2", &operands).is_err());
+        assert!(substitute("${0:h}", &operands).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Inlining of small functions, enabled by `BuildOptions::opt_level`.
+//!
+//! A call splits its block in two and goes through `rust:__langcraft_call` and
+//! `rust:__langcraft_return`, which costs a lot of commands and might even take a tick.
+//! Calls to a function that's just one basic block with no calls of its own
+//! get the commands of that block instead, with its holders renamed to fresh temporaries.
+//! Such a function can't be recursive, and it never touches the stack,
+//! so there's no frame to set up either. It still gets compiled on its own as well.
+//!
+//! Functions with more than one basic block are never inlined, even if they're small and call
+//! nothing, since their blocks would have to become blocks of the caller at each call site.
+//! Anything with a branch or a loop in it is always called.
+
+use crate::cir::Function as McFunction;
+use crate::cir::FunctionId as McFuncId;
+use crate::cir::{Command, FuncCall as McFuncCall, ScoreHolder};
+use crate::compile_ir::{
+    assign, compile_instr, eval_operand, get_unique_holder, type_layout, BuildOptions,
+    CompileResult, GlobalVarList,
+};
+use crate::regalloc::{self, Registers};
+use either::Either;
+use llvm_ir::function::FunctionAttribute;
+use llvm_ir::instruction::Call;
+use llvm_ir::terminator::Ret;
+use llvm_ir::types::Types;
+use llvm_ir::{Constant, Function, Instruction, Module, Name, Operand, Terminator};
+use std::collections::HashMap;
+
+/// Functions whose body takes more commands than this are always called,
+/// which is roughly what the call itself costs
+const INLINE_LIMIT: usize = 100;
+
+/// The commands of a function that can be inlined, in terms of its own holders
+pub(crate) struct Inline {
+    /// The holders of each parameter
+    params: Vec<Vec<ScoreHolder>>,
+    body: Vec<Command>,
+    /// The holders of the returned value, if there is one
+    result: Vec<ScoreHolder>,
+    /// The holders that get renamed at each call, so that they can't clash with the caller's
+    locals: Vec<ScoreHolder>,
+}
+
+/// Finds every function in `module` that's small enough to be inlined
+pub(crate) fn find_inlines(module: &Module, globals: &GlobalVarList, options: &BuildOptions) -> HashMap<String, Inline> {
+    module
+        .functions
+        .iter()
+        .filter_map(|func| {
+            // Anything that can't be compiled here gets reported when the function is compiled on its own
+            let inline = make_inline(func, globals, &module.types, options).ok()??;
+            Some((func.name.clone(), inline))
+        })
+        .collect()
+}
+
+fn make_inline(func: &Function, globals: &GlobalVarList, tys: &Types, options: &BuildOptions) -> CompileResult<Option<Inline>> {
+    if func.is_var_arg || func.function_attributes.contains(&FunctionAttribute::NoInline) {
+        return Ok(None);
+    }
+
+    let block = match &func.basic_blocks[..] {
+        [block] => block,
+        _ => return Ok(None),
+    };
+
+    let return_operand = match &block.term {
+        Terminator::Ret(Ret { return_operand, .. }) => return_operand,
+        _ => return Ok(None),
+    };
+
+    let params = func
+        .parameters
+        .iter()
+        .map(|p| Ok(ScoreHolder::from_local_name(p.name.clone(), type_layout(&p.ty, tys)?.size())))
+        .collect::<CompileResult<Vec<_>>>()?;
+
+    let mut body = Vec::new();
+    for instr in block.instrs.iter() {
+        match compile_instr(instr, func, globals, tys, options)? {
+            (cmds, None) => body.extend(cmds),
+            (_, Some(_)) => return Ok(None),
+        }
+    }
+
+    let result = match return_operand {
+        Some(operand) => {
+            let (tmp, result) = eval_operand(operand, globals, tys)?;
+            body.extend(tmp);
+            result
+        }
+        None => Vec::new(),
+    };
+
+    for cmd in body.iter() {
+        // Setting up a stack frame is the one thing a call does that this doesn't
+        if cmd.holder_uses().keys().any(|h| ["%stackptr", "%stackbaseptr"].contains(&h.as_ref())) {
+            return Ok(None);
+        }
+
+        // Anything else that gets called has to be counted
+        if let Command::FuncCall(McFuncCall { id }) = cmd {
+            if !id.name.starts_with("intrinsic:") && id.name != "stdout:putc" {
+                return Ok(None);
+            }
+        }
+    }
+
+    let count = crate::analysis::estimate_total_count(&HashMap::new(), &HashMap::new(), &McFunction::new(McFuncId::new(&func.name), body.clone()));
+    if count.is_none_or(|c| c > INLINE_LIMIT) {
+        return Ok(None);
+    }
+
+    let prefixes = regalloc::local_prefixes(func);
+    let mut locals = params.iter().flatten().chain(result.iter()).cloned().collect::<Vec<_>>();
+    for cmd in body.iter() {
+        locals.extend(cmd.holder_uses().into_keys().cloned());
+    }
+    locals.retain(|h| regalloc::is_local(h, &prefixes));
+    locals.sort();
+    locals.dedup();
+
+    Ok(Some(Inline { params, body, result, locals }))
+}
+
+/// If `instr` calls one of `inlines`, returns the commands that do the same thing without calling it
+pub(crate) fn inline_call(instr: &Instruction, inlines: &HashMap<String, Inline>, globals: &GlobalVarList, tys: &Types) -> Option<CompileResult<Vec<Command>>> {
+    let Call { function, arguments, dest, .. } = match instr {
+        Instruction::Call(call) => call,
+        _ => return None,
+    };
+
+    let inline = match function {
+        Either::Right(Operand::ConstantOperand(c)) => match &**c {
+            Constant::GlobalReference { name: Name::Name(name), .. } => inlines.get(name.as_str())?,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    if arguments.len() != inline.params.len() {
+        return None;
+    }
+
+    let renamed = inline.locals.iter().map(|h| (h.clone(), get_unique_holder())).collect::<Registers>();
+    let rename = |h: &ScoreHolder| renamed.get(h).cloned().unwrap_or_else(|| h.clone());
+
+    let mut cmds = Vec::new();
+    for ((arg, _), param) in arguments.iter().zip(inline.params.iter()) {
+        let (tmp, arg) = match eval_operand(arg, globals, tys) {
+            Ok(arg) => arg,
+            Err(err) => return Some(Err(err)),
+        };
+        cmds.extend(tmp);
+        cmds.extend(param.iter().zip(arg).map(|(param, word)| assign(rename(param), word)));
+    }
+
+    let mut body = inline.body.clone();
+    regalloc::rename(&mut body, &renamed);
+    cmds.extend(body);
+
+    if let Some(dest) = dest {
+        let dest = ScoreHolder::from_local_name(dest.clone(), 4 * inline.result.len());
+        cmds.extend(dest.into_iter().zip(inline.result.iter()).map(|(dest, word)| assign(dest, rename(word))));
+    }
+
+    Some(Ok(cmds))
+}
@@ -1,5 +1,7 @@
 use crate::cir::*;
-use crate::compile_ir::{get_index, pos_to_func_idx, func_idx_to_pos, OBJECTIVE};
+use crate::compile_ir::{pos_to_func_idx, func_idx_to_pos, ptr, stackptr, temp_fn_ptr, MemoryLayout, OBJECTIVE};
+use crate::sanitizer::ptr_check;
+use crate::storage::STORAGE;
 use crate::Datapack;
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -14,6 +16,10 @@ pub enum InterpError {
     EnteredUnreachable,
     EnteredTodo,
     AssertionFailed,
+    StackOverflow(String),
+    InvalidPointer(i32, String),
+    InvalidStorageAccess(String),
+    UncaughtException,
     BreakpointHit,
     InvalidBranch(usize),
     MultiBranch(FunctionId, Option<FunctionId>),
@@ -29,6 +35,10 @@ impl std::fmt::Display for InterpError {
             InterpError::EnteredUnreachable => write!(f, "entered unreachable code"),
             InterpError::EnteredTodo => write!(f, "entered code not yet implemented"),
             InterpError::AssertionFailed => write!(f, "assertion failed"),
+            InterpError::StackOverflow(func) => write!(f, "stack overflow in {}", func),
+            InterpError::InvalidPointer(addr, func) => write!(f, "invalid pointer {:#X} in {}", *addr as u32, func),
+            InterpError::InvalidStorageAccess(access) => write!(f, "invalid storage access {}", access),
+            InterpError::UncaughtException => write!(f, "uncaught exception"),
             InterpError::BreakpointHit => write!(f, "breakpoint hit"),
             InterpError::InvalidBranch(b) => write!(f, "invalid branch to {}", b),
             InterpError::MultiBranch(prev, att) => {
@@ -44,6 +54,24 @@ impl std::fmt::Display for InterpError {
     }
 }
 
+/// A value in the memory storage of the storage backend
+#[derive(Debug, Clone, PartialEq)]
+enum StorageValue {
+    Int(i32),
+    IntArray(Vec<i32>),
+}
+
+/// Splits a path like `pages[3][5]` into `pages` and its indices
+fn parse_storage_path(path: &str) -> Result<(&str, Vec<usize>), InterpError> {
+    let (name, indices) = path.split_at(path.find('[').unwrap_or(path.len()));
+    let indices = indices
+        .split_terminator(']')
+        .map(|idx| idx.strip_prefix('[').and_then(|idx| idx.parse().ok()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| InterpError::InvalidStorageAccess(path.to_string()))?;
+    Ok((name, indices))
+}
+
 type RelPos = ((i32, bool), (i32, bool), (i32, bool));
 
 fn add_rel_pos(base: (i32, i32, i32), rel: RelPos) -> (i32, i32, i32) {
@@ -90,6 +118,19 @@ fn parse_rel_coords(pos: &str) -> Result<RelPos, String> {
 
 impl std::error::Error for InterpError {}
 
+/// The states of a heap block, as written by `runtime/alloc.ll`
+const HEAP_FREE: i32 = 0x6672_6565;
+const HEAP_USED: i32 = 0x7573_6564;
+
+/// A summary of the blocks in the heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeapUsage {
+    pub used_blocks: usize,
+    /// Includes the headers of the blocks
+    pub used_bytes: u32,
+    pub free_bytes: u32,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BreakKind {
     Read,
@@ -117,12 +158,21 @@ pub struct Interpreter {
     pub rust_scores: HashMap<ScoreHolder, i32>,
     pub(crate) call_stack: Vec<(usize, usize, (i32, i32, i32))>,
     program: Vec<Function>,
-    pub memory: [i32; 128 * 16 * 16],
+    pub memory: Vec<i32>,
+    memory_layout: MemoryLayout,
+    /// The scratch page and word used by the storage backend,
+    /// while `pages` is backed by `memory`
+    storage_page: Vec<i32>,
+    storage_word: i32,
+    heap: std::ops::Range<u32>,
     ptr_pos: (i32, i32, i32),
     turtle_pos: (i32, i32, i32),
     run_state: RunState,
     letters: HashMap<(i32, i32, i32), char>,
     pub output: Vec<String>,
+    /// Each step taken by unwinding exceptions: `unwind f` when one leaves a frame of `f`,
+    /// and `catch f` when it reaches a landing pad in `f`
+    pub unwind_trace: Vec<String>,
     pub tick: usize,
     commands_run: usize,
     memory_points: HashMap<usize, BreakKind>,
@@ -145,7 +195,11 @@ impl Interpreter {
         Interpreter {
             program,
             call_stack: vec![(func_idx, 0, (0, 0, 0))],
-            memory: [0; 128 * 16 * 16],
+            memory: vec![0; MemoryLayout::default().words()],
+            memory_layout: MemoryLayout::default(),
+            storage_page: vec![0; MemoryLayout::default().page_words() as usize],
+            storage_word: 0,
+            heap: 0..0,
             rust_scores: HashMap::new(),
             ptr_pos: (0, 0, 0),
             turtle_pos: (0, 0, 0),
@@ -153,6 +207,7 @@ impl Interpreter {
             commands_run: 0,
             tick: 0,
             output: Vec::new(),
+            unwind_trace: Vec::new(),
             memory_points: HashMap::new(),
             letters,
             stdout_buffer: String::new(),
@@ -195,7 +250,11 @@ impl Interpreter {
         Interpreter {
             program: datapack.functions,
             call_stack: vec![(start_idx, 0, (0, 0, 0))],
-            memory: [0x55_55_55_55; 128 * 16 * 16],
+            memory: vec![0x55_55_55_55; datapack.memory.words()],
+            memory_layout: datapack.memory,
+            storage_page: vec![0; datapack.memory.page_words() as usize],
+            storage_word: 0,
+            heap: datapack.heap,
             rust_scores,
             ptr_pos: (0, 0, 0),
             turtle_pos: (0, 0, 0),
@@ -203,6 +262,7 @@ impl Interpreter {
             tick: 0,
             commands_run: 0,
             output: Vec::new(),
+            unwind_trace: Vec::new(),
             memory_points: HashMap::new(),
             letters,
             stdout_buffer: String::new(),
@@ -221,6 +281,51 @@ impl Interpreter {
             .collect()
     }
 
+    /// Describes each frame of the call stack, using the original source location
+    /// when the function has debug info and the mcfunction line number otherwise
+    pub fn backtrace(&self) -> Vec<String> {
+        self.call_stack()
+            .into_iter()
+            .map(|(f, c)| match f.source_loc(c) {
+                Some(loc) => format!("{} at {}", f.id, loc),
+                None => format!("{} line {}", f.id, f.get_line(c)),
+            })
+            .collect()
+    }
+
+    /// Walks the blocks in the heap, or returns `None` if the program never used it.
+    /// Anything still in use after the program halts was leaked.
+    pub fn heap_usage(&self) -> Result<Option<HeapUsage>, String> {
+        let mut usage = HeapUsage::default();
+
+        let mut addr = self.heap.start;
+        while addr < self.heap.end {
+            let size = self.memory[addr as usize / 4];
+            let state = self.memory[addr as usize / 4 + 1];
+
+            if addr == self.heap.start && state != HEAP_FREE && state != HEAP_USED {
+                return Ok(None);
+            }
+
+            if size < 8 || size % 8 != 0 || size as u32 > self.heap.end - addr {
+                return Err(format!("corrupted heap block at {:#X} with size {}", addr, size));
+            }
+
+            match state {
+                HEAP_FREE => usage.free_bytes += size as u32,
+                HEAP_USED => {
+                    usage.used_blocks += 1;
+                    usage.used_bytes += size as u32;
+                }
+                _ => return Err(format!("corrupted heap block at {:#X} with state {:#X}", addr, state)),
+            }
+
+            addr += size as u32;
+        }
+
+        Ok(Some(usage))
+    }
+
     /// `word_start` is in bytes, must be aligned to a multiple of 4
     pub fn set_mem_breakpoint(&mut self, word_start: usize, kind: BreakKind) {
         assert_eq!(word_start % 4, 0);
@@ -454,8 +559,74 @@ impl Interpreter {
         }
     }
 
+    /// Returns the range of `memory` that `pages[page]` (or `pages[page][word]`) is backed by,
+    /// if there's such a page and word
+    fn storage_range(&self, page: usize, word: Option<usize>) -> Option<std::ops::Range<usize>> {
+        let page_words = self.memory_layout.page_words() as usize;
+        if page >= self.memory.len() / page_words {
+            return None;
+        }
+
+        match word {
+            Some(word) if word < page_words => Some(page * page_words + word..page * page_words + word + 1),
+            Some(_) => None,
+            None => Some(page * page_words..(page + 1) * page_words),
+        }
+    }
+
+    fn get_storage(&self, path: &str) -> Result<StorageValue, InterpError> {
+        let invalid = || InterpError::InvalidStorageAccess(path.to_string());
+
+        match parse_storage_path(path)? {
+            ("word", idx) if idx.is_empty() => Ok(StorageValue::Int(self.storage_word)),
+            ("page", idx) if idx.is_empty() => Ok(StorageValue::IntArray(self.storage_page.clone())),
+            ("page", idx) if idx.len() == 1 => {
+                Ok(StorageValue::Int(*self.storage_page.get(idx[0]).ok_or_else(invalid)?))
+            }
+            ("pages", idx) if idx.len() == 1 => {
+                let range = self.storage_range(idx[0], None).ok_or_else(invalid)?;
+                Ok(StorageValue::IntArray(self.memory[range].to_vec()))
+            }
+            ("pages", idx) if idx.len() == 2 => {
+                let range = self.storage_range(idx[0], Some(idx[1])).ok_or_else(invalid)?;
+                Ok(StorageValue::Int(self.get_word(range.start * 4)?))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn set_storage(&mut self, path: &str, value: StorageValue) -> Result<(), InterpError> {
+        let invalid = || InterpError::InvalidStorageAccess(path.to_string());
+
+        match (parse_storage_path(path)?, value) {
+            (("word", idx), StorageValue::Int(v)) if idx.is_empty() => self.storage_word = v,
+            (("page", idx), StorageValue::IntArray(v)) if idx.is_empty() => self.storage_page = v,
+            (("page", idx), StorageValue::Int(v)) if idx.len() == 1 => {
+                *self.storage_page.get_mut(idx[0]).ok_or_else(invalid)? = v;
+            }
+            (("pages", idx), StorageValue::IntArray(v)) if idx.len() == 1 => {
+                // Pages always keep their size
+                let range = self.storage_range(idx[0], None).filter(|r| r.len() == v.len()).ok_or_else(invalid)?;
+
+                // Only the changed words count as written, so breakpoints still work
+                for (addr, word) in range.zip(v) {
+                    if self.memory[addr] != word {
+                        self.set_word(word, addr * 4)?;
+                    }
+                }
+            }
+            (("pages", idx), StorageValue::Int(v)) if idx.len() == 2 => {
+                let range = self.storage_range(idx[0], Some(idx[1])).ok_or_else(invalid)?;
+                self.set_word(v, range.start * 4)?;
+            }
+            _ => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+
     fn read_mem(&self) -> Result<i32, InterpError> {
-        let index = get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
+        let index = self.memory_layout.get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
         self.get_word(index as usize)
     }
 
@@ -557,7 +728,8 @@ impl Interpreter {
                     todo!("{:?}", target_obj)
                 }
 
-                let mut lhs = self.get_rust_score(target).unwrap();
+                // Like in Minecraft, adding to an unset score starts it from 0
+                let mut lhs = self.rust_scores.get(target).copied().unwrap_or(0);
                 lhs = lhs.wrapping_add(*score);
                 self.rust_scores.insert(target.clone(), lhs);
             }
@@ -651,7 +823,7 @@ impl Interpreter {
 
                         if path == "RecordItem.tag.Memory" {
                             if let DataModifySource::Value(score) = source {
-                                self.set_word(*score, get_index(target_pos.0, target_pos.1, target_pos.2)? as usize)?;
+                                self.set_word(*score, self.memory_layout.get_index(target_pos.0, target_pos.1, target_pos.2)? as usize)?;
                             } else {
                                 todo!()
                             }
@@ -680,6 +852,17 @@ impl Interpreter {
                             todo!("{}", path)
                         }
                     }
+                    (DataTarget::Storage(id), DataKind::Modify { path, kind: DataModifyKind::Set, source }) if id == STORAGE => {
+                        let value = match source {
+                            DataModifySource::Value(v) => StorageValue::Int(*v),
+                            DataModifySource::From(DataTarget::Storage(id), source_path) if id == STORAGE => {
+                                self.get_storage(source_path)?
+                            }
+                            _ => return Err(InterpError::InvalidStorageAccess(cmd.to_string())),
+                        };
+
+                        self.set_storage(path, value)?;
+                    }
                     _ => todo!("{:?} {:?}", target, kind),
                 }
             }
@@ -800,10 +983,10 @@ impl Interpreter {
                 if let Command::Data(Data { target: DataTarget::Block(block), kind: DataKind::Modify { path, kind: DataModifyKind::Set, source: DataModifySource::Value(v) } }) = run {
                     let index = match block.as_str() {
                         "~ ~ ~" => {
-                            get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)
+                            self.memory_layout.get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)
                         }
                         "~-2 1 ~" => {
-                            get_index(self.ptr_pos.0 - 2, 1, self.ptr_pos.2)
+                            self.memory_layout.get_index(self.ptr_pos.0 - 2, 1, self.ptr_pos.2)
                         }
                         _ => todo!("{:?}", block)
                     };
@@ -812,7 +995,7 @@ impl Interpreter {
                         todo!("{:?}", path);
                     }
 
-                    self.set_word(*v, index.unwrap() as usize)?;
+                    self.set_word(*v, index? as usize)?;
                 } else {
                     todo!("{:?}", cmd)
                 }
@@ -834,7 +1017,7 @@ impl Interpreter {
                     }
 
                     if run.to_string() == "data get block ~ ~ ~ RecordItem.tag.Memory 1" {
-                        let index = get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
+                        let index = self.memory_layout.get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
                         let word = self.get_word(index as usize)?;
                         self.rust_scores.insert(target.clone(), word);
                     } else {
@@ -847,7 +1030,7 @@ impl Interpreter {
                         }
 
                         let val = *self.rust_scores.get(target).unwrap_or_else(|| panic!("read from uninitialized variable {}", target));
-                        let index = get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
+                        let index = self.memory_layout.get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
                         self.set_word(val, index as usize)?;
                     }
                 } else {
@@ -883,12 +1066,63 @@ impl Interpreter {
                     }
 
                     let val = *self.rust_scores.get(target).unwrap_or_else(|| panic!("read from uninitialized variable {}", target));
-                    let index = get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
+                    let index = self.memory_layout.get_index(self.ptr_pos.0, self.ptr_pos.1, self.ptr_pos.2)?;
                     self.set_word(val, index as usize)?;
                 } else {
                     todo!("{:?}", sg)
                 }
             }
+            Command::Execute(Execute { run: Some(run), subcommands }) if matches!(&subcommands[..], [ExecuteSubCmd::Store { is_success: false, .. }]) => {
+                match (&subcommands[0], &**run) {
+                    (
+                        ExecuteSubCmd::Store { kind: ExecuteStoreKind::Score { target: Target::Uuid(target), objective }, .. },
+                        Command::Data(Data { target: DataTarget::Storage(id), kind: DataKind::Get { path, .. } }),
+                    ) if objective == OBJECTIVE && id == STORAGE => {
+                        // Like in the game, getting an array gives its length
+                        let v = match self.get_storage(path)? {
+                            StorageValue::Int(v) => v,
+                            StorageValue::IntArray(v) => v.len() as i32,
+                        };
+                        self.rust_scores.insert(target.clone(), v);
+                    }
+                    (
+                        ExecuteSubCmd::Store { kind: ExecuteStoreKind::Data { target: DataTarget::Storage(id), path, ty, .. }, .. },
+                        Command::ScoreGet(ScoreGet { target: Target::Uuid(source), target_obj }),
+                    ) if target_obj == OBJECTIVE && id == STORAGE && ty == "int" => {
+                        let v = *self.rust_scores.get(source).unwrap_or_else(|| panic!("read from uninitialized variable {}", source));
+                        self.set_storage(path, StorageValue::Int(v))?;
+                    }
+                    (
+                        ExecuteSubCmd::Store { kind: ExecuteStoreKind::Score { target: Target::Uuid(target), objective }, .. },
+                        Command::Data(Data { target: DataTarget::Block(block), kind: DataKind::Get { path, .. } }),
+                    ) if objective == OBJECTIVE && path == "RecordItem.tag.Memory" => {
+                        let (x, y, z) = add_rel_pos(pos, parse_rel_coords(block).unwrap());
+                        let word = self.get_word(self.memory_layout.get_index(x, y, z)? as usize)?;
+                        self.rust_scores.insert(target.clone(), word);
+                    }
+                    (
+                        ExecuteSubCmd::Store { kind: ExecuteStoreKind::Data { target: DataTarget::Block(block), path, ty, .. }, .. },
+                        Command::ScoreGet(ScoreGet { target: Target::Uuid(source), target_obj }),
+                    ) if target_obj == OBJECTIVE && path == "RecordItem.tag.Memory" && ty == "int" => {
+                        let v = *self.rust_scores.get(source).unwrap_or_else(|| panic!("read from uninitialized variable {}", source));
+                        let (x, y, z) = add_rel_pos(pos, parse_rel_coords(block).unwrap());
+                        self.set_word(v, self.memory_layout.get_index(x, y, z)? as usize)?;
+                    }
+                    (
+                        ExecuteSubCmd::Store { kind: ExecuteStoreKind::Score { target: Target::Uuid(target), objective }, .. },
+                        Command::TimeQuery(query),
+                    ) if objective == OBJECTIVE => {
+                        // The world is treated as if it was created when the program started
+                        let time = match query {
+                            TimeQuery::GameTime => self.tick,
+                            TimeQuery::DayTime => self.tick % 24000,
+                            TimeQuery::Day => self.tick / 24000,
+                        };
+                        self.rust_scores.insert(target.clone(), time as i32);
+                    }
+                    _ => todo!("{}", cmd),
+                }
+            }
             Command::Execute(Execute { run: Some(run), subcommands }) => {
                 if cmd.to_string().starts_with("execute at @e[tag=turtle] run setblock ~ ~ ~") {
                     if let Command::SetBlock(SetBlock { pos: _, block, kind: _ }) = &**run {
@@ -1005,6 +1239,32 @@ impl Interpreter {
             Command::Comment(c) if c == "!INTERPRETER: UNREACHABLE" => {
                 return Err(InterpError::EnteredUnreachable);
             }
+            Command::Comment(c) if c.starts_with("!INTERPRETER: STACK CHECK ") => {
+                let mut parts = c["!INTERPRETER: STACK CHECK ".len()..].splitn(2, ' ');
+                let max = parts.next().unwrap().parse::<i32>().unwrap();
+                let func = parts.next().unwrap();
+
+                if self.get_rust_score(&stackptr()).unwrap() > max {
+                    return Err(InterpError::StackOverflow(func.to_string()));
+                }
+            }
+            Command::Comment(c) if c.starts_with("!INTERPRETER: CHECK PTR ") => {
+                if self.get_rust_score(&ptr_check()).unwrap() != 0 {
+                    let func = &c["!INTERPRETER: CHECK PTR ".len()..];
+                    return Err(InterpError::InvalidPointer(self.get_rust_score(&ptr()).unwrap(), func.to_string()));
+                }
+            }
+            Command::Comment(c) if c.starts_with("!INTERPRETER: UNWIND ") => {
+                self.unwind_trace.push(format!("unwind {}", &c["!INTERPRETER: UNWIND ".len()..]));
+            }
+            Command::Comment(c) if c.starts_with("!INTERPRETER: CATCH ") => {
+                self.unwind_trace.push(format!("catch {}", &c["!INTERPRETER: CATCH ".len()..]));
+            }
+            Command::Comment(c) if c == "!INTERPRETER: UNCAUGHT EXCEPTION" => {
+                if self.get_rust_score(&temp_fn_ptr()).unwrap() == -1 {
+                    return Err(InterpError::UncaughtException);
+                }
+            }
             Command::Comment(c) if c.starts_with("!INTERPRETER: ASSERT ") => {
                 let c = &c["!INTERPRETER: ASSERT ".len()..];
                 let (c, is_unless) = if c.starts_with("unless ") {
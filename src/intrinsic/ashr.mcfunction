@@ -1,7 +1,13 @@
-# This code was derived from <top>/src/intrinsics/lshr/inner.mcfunction.
+# Arguments:
+# %param0%0 - The value to be shifted (also the output)
+# %param1%0 - The amount to shift by, is clobbered
+#
+# For negative values, `x >> s` is the same as `!(!x >>> s)`,
+# and `!x` is just `-1 - x`, so this only ever has to shift non-negative values.
 
-function intrinsic:lshr/getshift
-scoreboard players remove %%temp1_lshr_inner rust 1
-execute if score %param0%0 rust matches ..-1 run scoreboard players operation %param0%0 rust -= %%temp1_lshr_inner rust
-scoreboard players add %%temp1_lshr_inner rust 1
-scoreboard players operation %param0%0 rust /= %%temp1_lshr_inner rust
+execute store success score %%temp0_ashr rust if score %param0%0 rust matches ..-1
+execute if score %%temp0_ashr rust matches 1..1 run scoreboard players operation %param0%0 rust *= %%-1 rust
+execute if score %%temp0_ashr rust matches 1..1 run scoreboard players remove %param0%0 rust 1
+function intrinsic:lshr
+execute if score %%temp0_ashr rust matches 1..1 run scoreboard players operation %param0%0 rust *= %%-1 rust
+execute if score %%temp0_ashr rust matches 1..1 run scoreboard players remove %param0%0 rust 1
@@ -0,0 +1,7 @@
+# Arguments:
+# %param0%0, %param0%1 - The value to be shifted (also the output)
+# %param1%0 - The amount to shift by, from 0 to 63, is clobbered
+
+scoreboard players operation %temp2_ashr64 rust = %param1%0 rust
+execute if score %temp2_ashr64 rust matches 32.. run function intrinsic:ashr64/big
+execute if score %temp2_ashr64 rust matches ..31 run function intrinsic:ashr64/small
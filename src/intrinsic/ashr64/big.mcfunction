@@ -0,0 +1,8 @@
+# l = h >> (s - 32)
+# h = h >> 31
+
+scoreboard players operation %param0%0 rust = %param0%1 rust
+scoreboard players remove %param1%0 rust 32
+function intrinsic:ashr
+execute if score %param0%1 rust matches 0.. run scoreboard players set %param0%1 rust 0
+execute if score %param0%1 rust matches ..-1 run scoreboard players set %param0%1 rust -1
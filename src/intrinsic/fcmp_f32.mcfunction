@@ -0,0 +1,18 @@
+# Arguments:
+# %param0%0 - Left operand
+# %param1%0 - Right operand
+# %return%0 - -1 if less, 0 if equal, 1 if greater, and 2 if unordered
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+function intrinsic:float/key32
+scoreboard players operation %%float_a_lo rust = %%float_w0 rust
+scoreboard players operation %%float_a_nan rust = %%float_nan rust
+
+scoreboard players operation %%float_w0 rust = %param1%0 rust
+function intrinsic:float/key32
+
+scoreboard players set %return%0 rust 0
+execute if score %%float_a_lo rust < %%float_w0 rust run scoreboard players set %return%0 rust -1
+execute if score %%float_a_lo rust > %%float_w0 rust run scoreboard players set %return%0 rust 1
+execute if score %%float_a_nan rust matches 1..1 run scoreboard players set %return%0 rust 2
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %return%0 rust 2
@@ -0,0 +1,23 @@
+# Arguments:
+# %param0%0, %param0%1 - Left operand
+# %param1%0, %param1%1 - Right operand
+# %return%0 - -1 if less, 0 if equal, 1 if greater, and 2 if unordered
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+function intrinsic:float/key64
+scoreboard players operation %%float_a_lo rust = %%float_w0 rust
+scoreboard players operation %%float_a_hi rust = %%float_w1 rust
+scoreboard players operation %%float_a_nan rust = %%float_nan rust
+
+scoreboard players operation %%float_w0 rust = %param1%0 rust
+scoreboard players operation %%float_w1 rust = %param1%1 rust
+function intrinsic:float/key64
+
+scoreboard players set %return%0 rust 0
+execute if score %%float_a_hi rust < %%float_w1 rust run scoreboard players set %return%0 rust -1
+execute if score %%float_a_hi rust > %%float_w1 rust run scoreboard players set %return%0 rust 1
+execute if score %%float_a_hi rust = %%float_w1 rust if score %%float_a_lo rust < %%float_w0 rust run scoreboard players set %return%0 rust -1
+execute if score %%float_a_hi rust = %%float_w1 rust if score %%float_a_lo rust > %%float_w0 rust run scoreboard players set %return%0 rust 1
+execute if score %%float_a_nan rust matches 1..1 run scoreboard players set %return%0 rust 2
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %return%0 rust 2
@@ -0,0 +1,8 @@
+# Arguments:
+# %param0%0, %param0%1 - Left operand
+# %param1%0, %param1%1 - Right operand
+# %return%0, %return%1 - Quotient
+
+function intrinsic:float/load_args64
+function intrinsic:float/div
+function intrinsic:float/pack64
@@ -0,0 +1,15 @@
+# Adds the `a` and `b` operands, leaving the unrounded sum as the unpacked value
+
+scoreboard players set %%float_done rust 0
+
+scoreboard players operation %%float_nan rust = %%float_a_nan rust
+execute if score %%float_b_nan rust matches 1..1 run scoreboard players set %%float_nan rust 1
+execute if score %%float_a_exp rust matches 2047..2047 if score %%float_b_exp rust matches 2047..2047 unless score %%float_a_sign rust = %%float_b_sign rust run scoreboard players set %%float_nan rust 1
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %%float_done rust 1
+
+execute if score %%float_done rust matches 0..0 if score %%float_a_exp rust matches 2047..2047 run scoreboard players set %%float_done rust 2
+execute if score %%float_done rust matches 0..0 if score %%float_b_exp rust matches 2047..2047 run scoreboard players set %%float_done rust 3
+execute if score %%float_done rust matches 2..2 run function intrinsic:float/load_a
+execute if score %%float_done rust matches 3..3 run function intrinsic:float/load_b
+
+execute if score %%float_done rust matches 0..0 run function intrinsic:float/add/finite
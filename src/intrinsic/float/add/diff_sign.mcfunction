@@ -0,0 +1,15 @@
+# a - b, which can't be negative
+scoreboard players operation %%float_tmp rust = %%float_a_lo rust
+scoreboard players operation %%float_tmp rust -= %%float_lo rust
+scoreboard players operation %%float_lo rust = %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a_hi rust
+scoreboard players operation %%float_tmp rust -= %%float_hi rust
+scoreboard players operation %%float_hi rust = %%float_tmp rust
+execute if score %%float_lo rust matches ..-1 run scoreboard players remove %%float_hi rust 1
+execute if score %%float_lo rust matches ..-1 run scoreboard players add %%float_lo rust 268435456
+
+# An exact zero is positive when rounding to nearest
+execute if score %%float_hi rust matches 0..0 if score %%float_lo rust matches 0..0 run scoreboard players set %%float_sign rust 0
+
+scoreboard players set %%float_min_exp rust 1
+function intrinsic:float/normalize
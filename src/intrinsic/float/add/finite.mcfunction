@@ -0,0 +1,18 @@
+# Make sure that |a| >= |b|
+scoreboard players set %%float_tmp rust 0
+execute if score %%float_b_exp rust > %%float_a_exp rust run scoreboard players set %%float_tmp rust 1
+execute if score %%float_b_exp rust = %%float_a_exp rust if score %%float_b_hi rust > %%float_a_hi rust run scoreboard players set %%float_tmp rust 1
+execute if score %%float_b_exp rust = %%float_a_exp rust if score %%float_b_hi rust = %%float_a_hi rust if score %%float_b_lo rust > %%float_a_lo rust run scoreboard players set %%float_tmp rust 1
+execute if score %%float_tmp rust matches 1..1 run function intrinsic:float/swap
+
+# Line up b with a
+function intrinsic:float/load_b
+scoreboard players operation %%float_shift rust = %%float_a_exp rust
+scoreboard players operation %%float_shift rust -= %%float_b_exp rust
+function intrinsic:float/shift_right
+function intrinsic:float/or_sticky
+
+scoreboard players operation %%float_sign rust = %%float_a_sign rust
+scoreboard players operation %%float_exp rust = %%float_a_exp rust
+execute if score %%float_a_sign rust = %%float_b_sign rust run function intrinsic:float/add/same_sign
+execute unless score %%float_a_sign rust = %%float_b_sign rust run function intrinsic:float/add/diff_sign
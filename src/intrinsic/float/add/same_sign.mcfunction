@@ -0,0 +1,5 @@
+scoreboard players operation %%float_hi rust += %%float_a_hi rust
+scoreboard players operation %%float_lo rust += %%float_a_lo rust
+execute if score %%float_lo rust matches 268435456.. run scoreboard players add %%float_hi rust 1
+execute if score %%float_lo rust matches 268435456.. run scoreboard players remove %%float_lo rust 268435456
+execute if score %%float_hi rust matches 268435456.. run function intrinsic:float/shift_right_one
@@ -0,0 +1,11 @@
+# Sets %%float_nan and %%float_zero from an unpacked value,
+# and gives subnormals an exponent of 1
+
+scoreboard players set %%float_nan rust 0
+execute if score %%float_exp rust matches 2047..2047 unless score %%float_hi rust matches 0..0 run scoreboard players set %%float_nan rust 1
+execute if score %%float_exp rust matches 2047..2047 unless score %%float_lo rust matches 0..0 run scoreboard players set %%float_nan rust 1
+
+scoreboard players set %%float_zero rust 0
+execute if score %%float_exp rust matches 0..0 if score %%float_hi rust matches 0..0 if score %%float_lo rust matches 0..0 run scoreboard players set %%float_zero rust 1
+
+execute if score %%float_exp rust matches 0..0 run scoreboard players set %%float_exp rust 1
@@ -0,0 +1,8 @@
+# Shifts the significand right so that the exponent becomes 1,
+# used when a result is too small to be a normal number
+
+scoreboard players set %%float_shift rust 1
+scoreboard players operation %%float_shift rust -= %%float_exp rust
+function intrinsic:float/shift_right
+function intrinsic:float/or_sticky
+scoreboard players set %%float_exp rust 1
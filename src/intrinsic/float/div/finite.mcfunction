@@ -0,0 +1,23 @@
+function intrinsic:float/normalize_operands
+scoreboard players operation %%float_sign rust = %%float_a_sign rust
+scoreboard players operation %%float_sign rust += %%float_b_sign rust
+scoreboard players operation %%float_sign rust %= %%2 rust
+
+# Restoring division, one quotient bit at a time.
+# The quotient is floor(a * 2^56 / b), which has 56 or 57 bits.
+scoreboard players operation %%float_rem_hi rust = %%float_a_hi rust
+scoreboard players operation %%float_rem_lo rust = %%float_a_lo rust
+scoreboard players set %%float_hi rust 0
+scoreboard players set %%float_lo rust 0
+scoreboard players set %%float_count rust 57
+function intrinsic:float/div/step
+
+scoreboard players set %%float_sticky rust 0
+execute unless score %%float_rem_hi rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+execute unless score %%float_rem_lo rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+function intrinsic:float/or_sticky
+
+scoreboard players operation %%float_exp rust = %%float_a_exp rust
+scoreboard players operation %%float_exp rust -= %%float_b_exp rust
+scoreboard players add %%float_exp rust 1022
+execute if score %%float_hi rust matches 268435456.. run function intrinsic:float/shift_right_one
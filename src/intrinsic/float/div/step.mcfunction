@@ -0,0 +1,28 @@
+# quotient <<= 1
+scoreboard players operation %%float_hi rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust /= %%134217728 rust
+scoreboard players operation %%float_hi rust += %%float_tmp rust
+scoreboard players operation %%float_lo rust %= %%134217728 rust
+scoreboard players operation %%float_lo rust *= %%2 rust
+
+# if remainder >= b { remainder -= b; quotient += 1 }
+scoreboard players set %%float_tmp rust 0
+execute if score %%float_rem_hi rust > %%float_b_hi rust run scoreboard players set %%float_tmp rust 1
+execute if score %%float_rem_hi rust = %%float_b_hi rust if score %%float_rem_lo rust >= %%float_b_lo rust run scoreboard players set %%float_tmp rust 1
+execute if score %%float_tmp rust matches 1..1 run scoreboard players operation %%float_rem_hi rust -= %%float_b_hi rust
+execute if score %%float_tmp rust matches 1..1 run scoreboard players operation %%float_rem_lo rust -= %%float_b_lo rust
+execute if score %%float_rem_lo rust matches ..-1 run scoreboard players remove %%float_rem_hi rust 1
+execute if score %%float_rem_lo rust matches ..-1 run scoreboard players add %%float_rem_lo rust 268435456
+execute if score %%float_tmp rust matches 1..1 run scoreboard players add %%float_lo rust 1
+
+# remainder <<= 1
+scoreboard players operation %%float_rem_hi rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_rem_lo rust
+scoreboard players operation %%float_tmp rust /= %%134217728 rust
+scoreboard players operation %%float_rem_hi rust += %%float_tmp rust
+scoreboard players operation %%float_rem_lo rust %= %%134217728 rust
+scoreboard players operation %%float_rem_lo rust *= %%2 rust
+
+scoreboard players remove %%float_count rust 1
+execute if score %%float_count rust matches 1.. run function intrinsic:float/div/step
@@ -0,0 +1,42 @@
+# Converts an unsigned 64-bit integer to an unrounded unpacked value
+#
+# Arguments:
+# %%float_w0 - Low word of the integer, is clobbered
+# %%float_w1 - High word of the integer, is clobbered
+
+scoreboard players set %%float_nan rust 0
+
+# Split the integer into 28-bit limbs
+scoreboard players operation %%float_c0 rust = %%float_w0 rust
+scoreboard players operation %%float_c0 rust %= %%268435456 rust
+
+execute store success score %%float_tmp rust if score %%float_w0 rust matches ..-1
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w0 rust 2147483647
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w0 rust 1
+scoreboard players operation %%float_c1 rust = %%float_w0 rust
+scoreboard players operation %%float_c1 rust /= %%268435456 rust
+execute if score %%float_tmp rust matches 1..1 run scoreboard players add %%float_c1 rust 8
+scoreboard players operation %%float_tmp rust = %%float_w1 rust
+scoreboard players operation %%float_tmp rust %= %%16777216 rust
+scoreboard players operation %%float_tmp rust *= %%16 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+
+execute store success score %%float_tmp rust if score %%float_w1 rust matches ..-1
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w1 rust 2147483647
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w1 rust 1
+scoreboard players operation %%float_c2 rust = %%float_w1 rust
+scoreboard players operation %%float_c2 rust /= %%16777216 rust
+execute if score %%float_tmp rust matches 1..1 run scoreboard players add %%float_c2 rust 128
+
+# The value is (c2 * 2^28 + c1) * 2^(exp - 1078) plus the bits in c0
+scoreboard players set %%float_exp rust 1106
+execute if score %%float_c2 rust matches 0..0 run function intrinsic:float/from_int/shift_limb
+execute if score %%float_c2 rust matches 0..0 run function intrinsic:float/from_int/shift_limb
+execute if score %%float_c2 rust matches 0..0 run scoreboard players set %%float_exp rust 1
+execute if score %%float_c2 rust matches 1..134217727 run function intrinsic:float/from_int/normalize
+
+scoreboard players operation %%float_hi rust = %%float_c2 rust
+scoreboard players operation %%float_lo rust = %%float_c1 rust
+scoreboard players set %%float_sticky rust 0
+execute unless score %%float_c0 rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+function intrinsic:float/or_sticky
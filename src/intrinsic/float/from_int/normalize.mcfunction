@@ -0,0 +1,15 @@
+# Shifts all three limbs left until the implicit bit is set
+
+scoreboard players operation %%float_c2 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c1 rust
+scoreboard players operation %%float_tmp rust /= %%134217728 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_c1 rust %= %%134217728 rust
+scoreboard players operation %%float_c1 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c0 rust
+scoreboard players operation %%float_tmp rust /= %%134217728 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_c0 rust %= %%134217728 rust
+scoreboard players operation %%float_c0 rust *= %%2 rust
+scoreboard players remove %%float_exp rust 1
+execute if score %%float_c2 rust matches ..134217727 run function intrinsic:float/from_int/normalize
@@ -0,0 +1,4 @@
+scoreboard players operation %%float_c2 rust = %%float_c1 rust
+scoreboard players operation %%float_c1 rust = %%float_c0 rust
+scoreboard players set %%float_c0 rust 0
+scoreboard players remove %%float_exp rust 28
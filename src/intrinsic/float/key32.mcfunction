@@ -0,0 +1,11 @@
+# Turns a float into a key that compares like the float does
+#
+# Arguments:
+# %%float_w0 - The float (mutated into the key)
+# %%float_nan - Set to 1 if the value is a NaN, otherwise 0
+
+execute store success score %%float_sign rust if score %%float_w0 rust matches ..-1
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w0 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w0 rust 1
+execute store success score %%float_nan rust if score %%float_w0 rust matches 2139095041..
+execute if score %%float_sign rust matches 1..1 run scoreboard players operation %%float_w0 rust *= %%-1 rust
@@ -0,0 +1,23 @@
+# Turns a double into a key that compares like the double does,
+# where the low words are compared as signed integers
+#
+# Arguments:
+# %%float_w0 - Low word of the double (mutated into the low word of the key)
+# %%float_w1 - High word of the double (mutated into the high word of the key)
+# %%float_nan - Set to 1 if the value is a NaN, otherwise 0
+
+execute store success score %%float_sign rust if score %%float_w1 rust matches ..-1
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w1 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w1 rust 1
+
+execute store success score %%float_nan rust if score %%float_w1 rust matches 2146435073..
+execute if score %%float_w1 rust matches 2146435072..2146435072 unless score %%float_w0 rust matches 0..0 run scoreboard players set %%float_nan rust 1
+
+# Negate the 64-bit magnitude
+execute if score %%float_sign rust matches 1..1 run scoreboard players operation %%float_w1 rust *= %%-1 rust
+execute if score %%float_sign rust matches 1..1 unless score %%float_w0 rust matches 0..0 run scoreboard players remove %%float_w1 rust 1
+execute if score %%float_sign rust matches 1..1 run scoreboard players operation %%float_w0 rust *= %%-1 rust
+
+# Flip the top bit of the low word so that a signed comparison works
+scoreboard players remove %%float_w0 rust 2147483647
+scoreboard players remove %%float_w0 rust 1
@@ -0,0 +1,8 @@
+# Copies the `a` operand into the unpacked value
+
+scoreboard players operation %%float_sign rust = %%float_a_sign rust
+scoreboard players operation %%float_exp rust = %%float_a_exp rust
+scoreboard players operation %%float_hi rust = %%float_a_hi rust
+scoreboard players operation %%float_lo rust = %%float_a_lo rust
+scoreboard players operation %%float_nan rust = %%float_a_nan rust
+scoreboard players operation %%float_zero rust = %%float_a_zero rust
@@ -0,0 +1,9 @@
+# Unpacks the floats in %param0 and %param1 into the `a` and `b` operands
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+function intrinsic:float/unpack32
+function intrinsic:float/save_a
+
+scoreboard players operation %%float_w0 rust = %param1%0 rust
+function intrinsic:float/unpack32
+function intrinsic:float/save_b
@@ -0,0 +1,11 @@
+# Unpacks the doubles in %param0 and %param1 into the `a` and `b` operands
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+function intrinsic:float/unpack64
+function intrinsic:float/save_a
+
+scoreboard players operation %%float_w0 rust = %param1%0 rust
+scoreboard players operation %%float_w1 rust = %param1%1 rust
+function intrinsic:float/unpack64
+function intrinsic:float/save_b
@@ -0,0 +1,8 @@
+# Copies the `b` operand into the unpacked value
+
+scoreboard players operation %%float_sign rust = %%float_b_sign rust
+scoreboard players operation %%float_exp rust = %%float_b_exp rust
+scoreboard players operation %%float_hi rust = %%float_b_hi rust
+scoreboard players operation %%float_lo rust = %%float_b_lo rust
+scoreboard players operation %%float_nan rust = %%float_b_nan rust
+scoreboard players operation %%float_zero rust = %%float_b_zero rust
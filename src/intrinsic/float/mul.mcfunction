@@ -0,0 +1,26 @@
+# Multiplies the `a` and `b` operands, leaving the unrounded product as the unpacked value
+
+scoreboard players operation %%float_sign rust = %%float_a_sign rust
+scoreboard players operation %%float_sign rust += %%float_b_sign rust
+scoreboard players operation %%float_sign rust %= %%2 rust
+
+scoreboard players set %%float_done rust 0
+
+# NaN, including infinity times zero
+scoreboard players operation %%float_nan rust = %%float_a_nan rust
+execute if score %%float_b_nan rust matches 1..1 run scoreboard players set %%float_nan rust 1
+execute if score %%float_a_exp rust matches 2047..2047 if score %%float_b_zero rust matches 1..1 run scoreboard players set %%float_nan rust 1
+execute if score %%float_b_exp rust matches 2047..2047 if score %%float_a_zero rust matches 1..1 run scoreboard players set %%float_nan rust 1
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %%float_done rust 1
+
+# Infinity
+execute if score %%float_done rust matches 0..0 if score %%float_a_exp rust matches 2047..2047 run scoreboard players set %%float_done rust 2
+execute if score %%float_done rust matches 0..0 if score %%float_b_exp rust matches 2047..2047 run scoreboard players set %%float_done rust 2
+
+# Zero
+execute if score %%float_done rust matches 0..0 if score %%float_a_zero rust matches 1..1 run scoreboard players set %%float_done rust 3
+execute if score %%float_done rust matches 0..0 if score %%float_b_zero rust matches 1..1 run scoreboard players set %%float_done rust 3
+
+execute if score %%float_done rust matches 2..2 run function intrinsic:float/set_inf
+execute if score %%float_done rust matches 3..3 run function intrinsic:float/set_zero
+execute if score %%float_done rust matches 0..0 run function intrinsic:float/mul/finite
@@ -0,0 +1,127 @@
+function intrinsic:float/normalize_operands
+scoreboard players operation %%float_sign rust = %%float_a_sign rust
+scoreboard players operation %%float_sign rust += %%float_b_sign rust
+scoreboard players operation %%float_sign rust %= %%2 rust
+
+# Split both significands into 14-bit limbs
+scoreboard players operation %%float_a0 rust = %%float_a_lo rust
+scoreboard players operation %%float_a0 rust %= %%16384 rust
+scoreboard players operation %%float_a1 rust = %%float_a_lo rust
+scoreboard players operation %%float_a1 rust /= %%16384 rust
+scoreboard players operation %%float_a2 rust = %%float_a_hi rust
+scoreboard players operation %%float_a2 rust %= %%16384 rust
+scoreboard players operation %%float_a3 rust = %%float_a_hi rust
+scoreboard players operation %%float_a3 rust /= %%16384 rust
+scoreboard players operation %%float_b0 rust = %%float_b_lo rust
+scoreboard players operation %%float_b0 rust %= %%16384 rust
+scoreboard players operation %%float_b1 rust = %%float_b_lo rust
+scoreboard players operation %%float_b1 rust /= %%16384 rust
+scoreboard players operation %%float_b2 rust = %%float_b_hi rust
+scoreboard players operation %%float_b2 rust %= %%16384 rust
+scoreboard players operation %%float_b3 rust = %%float_b_hi rust
+scoreboard players operation %%float_b3 rust /= %%16384 rust
+
+# Long multiplication, each column fits in a word before carrying
+scoreboard players set %%float_c0 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a0 rust
+scoreboard players operation %%float_tmp rust *= %%float_b0 rust
+scoreboard players operation %%float_c0 rust += %%float_tmp rust
+scoreboard players set %%float_c1 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a0 rust
+scoreboard players operation %%float_tmp rust *= %%float_b1 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a1 rust
+scoreboard players operation %%float_tmp rust *= %%float_b0 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c0 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_c0 rust %= %%16384 rust
+scoreboard players set %%float_c2 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a0 rust
+scoreboard players operation %%float_tmp rust *= %%float_b2 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a1 rust
+scoreboard players operation %%float_tmp rust *= %%float_b1 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a2 rust
+scoreboard players operation %%float_tmp rust *= %%float_b0 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c1 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_c1 rust %= %%16384 rust
+scoreboard players set %%float_c3 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a0 rust
+scoreboard players operation %%float_tmp rust *= %%float_b3 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a1 rust
+scoreboard players operation %%float_tmp rust *= %%float_b2 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a2 rust
+scoreboard players operation %%float_tmp rust *= %%float_b1 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a3 rust
+scoreboard players operation %%float_tmp rust *= %%float_b0 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c2 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_c2 rust %= %%16384 rust
+scoreboard players set %%float_c4 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a1 rust
+scoreboard players operation %%float_tmp rust *= %%float_b3 rust
+scoreboard players operation %%float_c4 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a2 rust
+scoreboard players operation %%float_tmp rust *= %%float_b2 rust
+scoreboard players operation %%float_c4 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a3 rust
+scoreboard players operation %%float_tmp rust *= %%float_b1 rust
+scoreboard players operation %%float_c4 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c3 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c4 rust += %%float_tmp rust
+scoreboard players operation %%float_c3 rust %= %%16384 rust
+scoreboard players set %%float_c5 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a2 rust
+scoreboard players operation %%float_tmp rust *= %%float_b3 rust
+scoreboard players operation %%float_c5 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_a3 rust
+scoreboard players operation %%float_tmp rust *= %%float_b2 rust
+scoreboard players operation %%float_c5 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c4 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c5 rust += %%float_tmp rust
+scoreboard players operation %%float_c4 rust %= %%16384 rust
+scoreboard players set %%float_c6 rust 0
+scoreboard players operation %%float_tmp rust = %%float_a3 rust
+scoreboard players operation %%float_tmp rust *= %%float_b3 rust
+scoreboard players operation %%float_c6 rust += %%float_tmp rust
+scoreboard players operation %%float_tmp rust = %%float_c5 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c6 rust += %%float_tmp rust
+scoreboard players operation %%float_c5 rust %= %%16384 rust
+scoreboard players set %%float_c7 rust 0
+scoreboard players operation %%float_tmp rust = %%float_c6 rust
+scoreboard players operation %%float_tmp rust /= %%16384 rust
+scoreboard players operation %%float_c7 rust += %%float_tmp rust
+scoreboard players operation %%float_c6 rust %= %%16384 rust
+
+# The product is at least 2^110, so it needs at most one bit of normalization
+scoreboard players operation %%float_exp rust = %%float_a_exp rust
+scoreboard players operation %%float_exp rust += %%float_b_exp rust
+scoreboard players remove %%float_exp rust 1022
+execute if score %%float_c7 rust matches ..8191 run function intrinsic:float/mul/shift_product
+
+scoreboard players operation %%float_hi rust = %%float_c7 rust
+scoreboard players operation %%float_hi rust *= %%16384 rust
+scoreboard players operation %%float_hi rust += %%float_c6 rust
+scoreboard players operation %%float_lo rust = %%float_c5 rust
+scoreboard players operation %%float_lo rust *= %%16384 rust
+scoreboard players operation %%float_lo rust += %%float_c4 rust
+scoreboard players set %%float_sticky rust 0
+execute unless score %%float_c0 rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+execute unless score %%float_c1 rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+execute unless score %%float_c2 rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+execute unless score %%float_c3 rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+function intrinsic:float/or_sticky
@@ -0,0 +1,39 @@
+# Shifts the product left by one bit
+
+scoreboard players operation %%float_c7 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c6 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c7 rust += %%float_tmp rust
+scoreboard players operation %%float_c6 rust %= %%8192 rust
+scoreboard players operation %%float_c6 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c5 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c6 rust += %%float_tmp rust
+scoreboard players operation %%float_c5 rust %= %%8192 rust
+scoreboard players operation %%float_c5 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c4 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c5 rust += %%float_tmp rust
+scoreboard players operation %%float_c4 rust %= %%8192 rust
+scoreboard players operation %%float_c4 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c3 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c4 rust += %%float_tmp rust
+scoreboard players operation %%float_c3 rust %= %%8192 rust
+scoreboard players operation %%float_c3 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c2 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c3 rust += %%float_tmp rust
+scoreboard players operation %%float_c2 rust %= %%8192 rust
+scoreboard players operation %%float_c2 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c1 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c2 rust += %%float_tmp rust
+scoreboard players operation %%float_c1 rust %= %%8192 rust
+scoreboard players operation %%float_c1 rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_c0 rust
+scoreboard players operation %%float_tmp rust /= %%8192 rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_c0 rust %= %%8192 rust
+scoreboard players operation %%float_c0 rust *= %%2 rust
+scoreboard players remove %%float_exp rust 1
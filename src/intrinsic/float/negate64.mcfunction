@@ -0,0 +1,5 @@
+# Negates the 64-bit integer in %%float_w0 (low word) and %%float_w1 (high word)
+
+scoreboard players operation %%float_w1 rust *= %%-1 rust
+execute unless score %%float_w0 rust matches 0..0 run scoreboard players remove %%float_w1 rust 1
+scoreboard players operation %%float_w0 rust *= %%-1 rust
@@ -0,0 +1,11 @@
+# Shifts the significand left until the implicit bit is set,
+# without letting the exponent go below %%float_min_exp.
+# Zero is left untouched.
+#
+# Arguments:
+# %%float_exp, %%float_hi, %%float_lo - Value to normalize (mutated)
+# %%float_min_exp - Lowest exponent allowed
+
+scoreboard players set %%float_tmp rust 1
+execute if score %%float_hi rust matches 0..0 if score %%float_lo rust matches 0..0 run scoreboard players set %%float_tmp rust 0
+execute if score %%float_tmp rust matches 1..1 run function intrinsic:float/normalize/step
@@ -0,0 +1,8 @@
+scoreboard players operation %%float_hi rust *= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust /= %%134217728 rust
+scoreboard players operation %%float_hi rust += %%float_tmp rust
+scoreboard players operation %%float_lo rust %= %%134217728 rust
+scoreboard players operation %%float_lo rust *= %%2 rust
+scoreboard players remove %%float_exp rust 1
+function intrinsic:float/normalize/step
@@ -0,0 +1,12 @@
+scoreboard players operation %%float_tmp rust = %%float_exp rust
+scoreboard players operation %%float_tmp rust -= %%float_min_exp rust
+
+# Shift by an entire limb at once if possible
+scoreboard players set %%float_tmp2 rust 0
+execute if score %%float_hi rust matches 0..0 if score %%float_tmp rust matches 28.. run scoreboard players set %%float_tmp2 rust 1
+execute if score %%float_tmp2 rust matches 1..1 run scoreboard players operation %%float_hi rust = %%float_lo rust
+execute if score %%float_tmp2 rust matches 1..1 run scoreboard players set %%float_lo rust 0
+execute if score %%float_tmp2 rust matches 1..1 run scoreboard players remove %%float_exp rust 28
+execute if score %%float_tmp2 rust matches 1..1 run scoreboard players remove %%float_tmp rust 28
+
+execute if score %%float_hi rust matches ..134217727 if score %%float_tmp rust matches 1.. run function intrinsic:float/normalize/shift
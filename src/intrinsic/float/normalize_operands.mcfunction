@@ -0,0 +1,12 @@
+# Normalizes subnormal `a` and `b` operands, letting their exponents go below 1.
+# Neither operand may be zero.
+
+scoreboard players set %%float_min_exp rust -1000
+
+function intrinsic:float/load_a
+function intrinsic:float/normalize
+function intrinsic:float/save_a
+
+function intrinsic:float/load_b
+function intrinsic:float/normalize
+function intrinsic:float/save_b
@@ -0,0 +1,5 @@
+# Sets the lowest bit of %%float_lo if %%float_sticky is 1
+
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust %= %%2 rust
+execute if score %%float_sticky rust matches 1..1 if score %%float_tmp rust matches 0..0 run scoreboard players add %%float_lo rust 1
@@ -0,0 +1,40 @@
+# Rounds an unpacked value to the nearest float, with ties going to even.
+# The exponent is still biased like a double's.
+#
+# Arguments:
+# %%float_sign, %%float_exp, %%float_hi, %%float_lo, %%float_nan - Value to pack (clobbered)
+#
+# Results:
+# %return%0 - The float
+
+execute if score %%float_hi rust matches 0..0 if score %%float_lo rust matches 0..0 if score %%float_exp rust matches ..2046 run scoreboard players set %%float_exp rust 897
+scoreboard players remove %%float_exp rust 896
+execute if score %%float_exp rust matches ..0 run function intrinsic:float/denormalize
+
+# Put the top 28 bits of the significand in the top of one word,
+# keeping the rest as a sticky bit
+scoreboard players operation %%float_round rust = %%float_hi rust
+scoreboard players operation %%float_round rust *= %%4 rust
+execute unless score %%float_lo rust matches 0..0 run scoreboard players add %%float_round rust 1
+
+# Drop the guard bits
+scoreboard players operation %%float_lo rust = %%float_round rust
+scoreboard players operation %%float_lo rust /= %%64 rust
+scoreboard players operation %%float_round rust %= %%64 rust
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust %= %%2 rust
+scoreboard players operation %%float_round rust += %%float_tmp rust
+execute if score %%float_round rust matches 33.. run scoreboard players add %%float_lo rust 1
+
+scoreboard players operation %return%0 rust = %%float_exp rust
+scoreboard players remove %return%0 rust 1
+scoreboard players operation %return%0 rust *= %%8388608 rust
+scoreboard players operation %return%0 rust += %%float_lo rust
+
+execute if score %%float_exp rust matches 255.. run scoreboard players set %return%0 rust 2139095040
+
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %%float_sign rust 0
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %return%0 rust 2143289344
+
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %return%0 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %return%0 rust 1
@@ -0,0 +1,46 @@
+# Rounds an unpacked value to the nearest double, with ties going to even.
+# Exponents of 2047 or above become infinity.
+#
+# Arguments:
+# %%float_sign, %%float_exp, %%float_hi, %%float_lo, %%float_nan - Value to pack (clobbered)
+#
+# Results:
+# %return%0 - Low word of the double
+# %return%1 - High word of the double
+
+execute if score %%float_hi rust matches 0..0 if score %%float_lo rust matches 0..0 if score %%float_exp rust matches ..2046 run scoreboard players set %%float_exp rust 1
+execute if score %%float_exp rust matches ..0 run function intrinsic:float/denormalize
+
+# Drop the guard bits
+scoreboard players operation %%float_round rust = %%float_lo rust
+scoreboard players operation %%float_round rust %= %%8 rust
+scoreboard players operation %%float_lo rust /= %%8 rust
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust %= %%2 rust
+scoreboard players operation %%float_round rust += %%float_tmp rust
+execute if score %%float_round rust matches 5.. run scoreboard players add %%float_lo rust 1
+execute if score %%float_lo rust matches 33554432.. run scoreboard players add %%float_hi rust 1
+execute if score %%float_lo rust matches 33554432.. run scoreboard players set %%float_lo rust 0
+
+# The significand is now hi * 2^25 + lo. Adding it to (exp - 1) * 2^52 gives the right
+# result even when rounding carries into the next exponent or the value is subnormal.
+scoreboard players operation %return%1 rust = %%float_exp rust
+scoreboard players remove %return%1 rust 1
+scoreboard players operation %return%1 rust *= %%1048576 rust
+scoreboard players operation %%float_tmp rust = %%float_hi rust
+scoreboard players operation %%float_tmp rust /= %%128 rust
+scoreboard players operation %return%1 rust += %%float_tmp rust
+scoreboard players operation %return%0 rust = %%float_hi rust
+scoreboard players operation %return%0 rust %= %%128 rust
+scoreboard players operation %return%0 rust *= %%33554432 rust
+scoreboard players operation %return%0 rust += %%float_lo rust
+
+execute if score %%float_exp rust matches 2047.. run scoreboard players set %return%1 rust 2146435072
+execute if score %%float_exp rust matches 2047.. run scoreboard players set %return%0 rust 0
+
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %%float_sign rust 0
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %return%1 rust 2146959360
+execute if score %%float_nan rust matches 1..1 run scoreboard players set %return%0 rust 0
+
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %return%1 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %return%1 rust 1
@@ -0,0 +1,35 @@
+# Arguments:
+# %%float_shift - Exponent, from 0 to 30
+# %%float_pow - Set to 2 to the power of %%float_shift
+
+execute if score %%float_shift rust matches 0..0 run scoreboard players set %%float_pow rust 1
+execute if score %%float_shift rust matches 1..1 run scoreboard players set %%float_pow rust 2
+execute if score %%float_shift rust matches 2..2 run scoreboard players set %%float_pow rust 4
+execute if score %%float_shift rust matches 3..3 run scoreboard players set %%float_pow rust 8
+execute if score %%float_shift rust matches 4..4 run scoreboard players set %%float_pow rust 16
+execute if score %%float_shift rust matches 5..5 run scoreboard players set %%float_pow rust 32
+execute if score %%float_shift rust matches 6..6 run scoreboard players set %%float_pow rust 64
+execute if score %%float_shift rust matches 7..7 run scoreboard players set %%float_pow rust 128
+execute if score %%float_shift rust matches 8..8 run scoreboard players set %%float_pow rust 256
+execute if score %%float_shift rust matches 9..9 run scoreboard players set %%float_pow rust 512
+execute if score %%float_shift rust matches 10..10 run scoreboard players set %%float_pow rust 1024
+execute if score %%float_shift rust matches 11..11 run scoreboard players set %%float_pow rust 2048
+execute if score %%float_shift rust matches 12..12 run scoreboard players set %%float_pow rust 4096
+execute if score %%float_shift rust matches 13..13 run scoreboard players set %%float_pow rust 8192
+execute if score %%float_shift rust matches 14..14 run scoreboard players set %%float_pow rust 16384
+execute if score %%float_shift rust matches 15..15 run scoreboard players set %%float_pow rust 32768
+execute if score %%float_shift rust matches 16..16 run scoreboard players set %%float_pow rust 65536
+execute if score %%float_shift rust matches 17..17 run scoreboard players set %%float_pow rust 131072
+execute if score %%float_shift rust matches 18..18 run scoreboard players set %%float_pow rust 262144
+execute if score %%float_shift rust matches 19..19 run scoreboard players set %%float_pow rust 524288
+execute if score %%float_shift rust matches 20..20 run scoreboard players set %%float_pow rust 1048576
+execute if score %%float_shift rust matches 21..21 run scoreboard players set %%float_pow rust 2097152
+execute if score %%float_shift rust matches 22..22 run scoreboard players set %%float_pow rust 4194304
+execute if score %%float_shift rust matches 23..23 run scoreboard players set %%float_pow rust 8388608
+execute if score %%float_shift rust matches 24..24 run scoreboard players set %%float_pow rust 16777216
+execute if score %%float_shift rust matches 25..25 run scoreboard players set %%float_pow rust 33554432
+execute if score %%float_shift rust matches 26..26 run scoreboard players set %%float_pow rust 67108864
+execute if score %%float_shift rust matches 27..27 run scoreboard players set %%float_pow rust 134217728
+execute if score %%float_shift rust matches 28..28 run scoreboard players set %%float_pow rust 268435456
+execute if score %%float_shift rust matches 29..29 run scoreboard players set %%float_pow rust 536870912
+execute if score %%float_shift rust matches 30..30 run scoreboard players set %%float_pow rust 1073741824
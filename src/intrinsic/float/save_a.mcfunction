@@ -0,0 +1,8 @@
+# Copies the unpacked value into the `a` operand
+
+scoreboard players operation %%float_a_sign rust = %%float_sign rust
+scoreboard players operation %%float_a_exp rust = %%float_exp rust
+scoreboard players operation %%float_a_hi rust = %%float_hi rust
+scoreboard players operation %%float_a_lo rust = %%float_lo rust
+scoreboard players operation %%float_a_nan rust = %%float_nan rust
+scoreboard players operation %%float_a_zero rust = %%float_zero rust
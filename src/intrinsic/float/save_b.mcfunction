@@ -0,0 +1,8 @@
+# Copies the unpacked value into the `b` operand
+
+scoreboard players operation %%float_b_sign rust = %%float_sign rust
+scoreboard players operation %%float_b_exp rust = %%float_exp rust
+scoreboard players operation %%float_b_hi rust = %%float_hi rust
+scoreboard players operation %%float_b_lo rust = %%float_lo rust
+scoreboard players operation %%float_b_nan rust = %%float_nan rust
+scoreboard players operation %%float_b_zero rust = %%float_zero rust
@@ -0,0 +1,5 @@
+# Makes the unpacked value an infinity, keeping its sign
+
+scoreboard players set %%float_exp rust 2047
+scoreboard players set %%float_hi rust 0
+scoreboard players set %%float_lo rust 0
@@ -0,0 +1,11 @@
+# Shifts the significand right, remembering whether any set bits were lost
+#
+# Arguments:
+# %%float_hi, %%float_lo - Significand (mutated)
+# %%float_shift - Amount to shift by, is clobbered
+# %%float_sticky - Set to 1 if any set bits were shifted out, otherwise 0
+
+scoreboard players set %%float_sticky rust 0
+execute if score %%float_shift rust matches 56.. run function intrinsic:float/shift_right/all
+execute if score %%float_shift rust matches 28..55 run function intrinsic:float/shift_right/limb
+execute if score %%float_shift rust matches 1..27 run function intrinsic:float/shift_right/bits
@@ -0,0 +1,5 @@
+execute unless score %%float_hi rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+execute unless score %%float_lo rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+scoreboard players set %%float_hi rust 0
+scoreboard players set %%float_lo rust 0
+scoreboard players set %%float_shift rust 0
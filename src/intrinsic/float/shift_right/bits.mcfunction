@@ -0,0 +1,19 @@
+# lo = (lo >> shift) | ((hi % 2^shift) << (28 - shift))
+# hi = hi >> shift
+
+function intrinsic:float/pow2
+
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust %= %%float_pow rust
+execute unless score %%float_tmp rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+scoreboard players operation %%float_lo rust /= %%float_pow rust
+
+scoreboard players operation %%float_tmp rust = %%float_hi rust
+scoreboard players operation %%float_tmp rust %= %%float_pow rust
+scoreboard players operation %%float_hi rust /= %%float_pow rust
+
+scoreboard players operation %%float_shift rust *= %%-1 rust
+scoreboard players add %%float_shift rust 28
+function intrinsic:float/pow2
+scoreboard players operation %%float_tmp rust *= %%float_pow rust
+scoreboard players operation %%float_lo rust += %%float_tmp rust
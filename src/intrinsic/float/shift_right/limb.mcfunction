@@ -0,0 +1,4 @@
+execute unless score %%float_lo rust matches 0..0 run scoreboard players set %%float_sticky rust 1
+scoreboard players operation %%float_lo rust = %%float_hi rust
+scoreboard players set %%float_hi rust 0
+scoreboard players remove %%float_shift rust 28
@@ -0,0 +1,13 @@
+# Shifts the significand right by one bit and increments the exponent,
+# keeping the shifted out bit as a sticky bit
+
+scoreboard players operation %%float_sticky rust = %%float_lo rust
+scoreboard players operation %%float_sticky rust %= %%2 rust
+scoreboard players operation %%float_lo rust /= %%2 rust
+scoreboard players operation %%float_tmp rust = %%float_hi rust
+scoreboard players operation %%float_tmp rust %= %%2 rust
+scoreboard players operation %%float_tmp rust *= %%134217728 rust
+scoreboard players operation %%float_lo rust += %%float_tmp rust
+scoreboard players operation %%float_hi rust /= %%2 rust
+scoreboard players add %%float_exp rust 1
+function intrinsic:float/or_sticky
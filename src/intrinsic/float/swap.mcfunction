@@ -0,0 +1,10 @@
+# Swaps the `a` and `b` operands, clobbering the unpacked value
+
+function intrinsic:float/load_a
+scoreboard players operation %%float_a_sign rust = %%float_b_sign rust
+scoreboard players operation %%float_a_exp rust = %%float_b_exp rust
+scoreboard players operation %%float_a_hi rust = %%float_b_hi rust
+scoreboard players operation %%float_a_lo rust = %%float_b_lo rust
+scoreboard players operation %%float_a_nan rust = %%float_b_nan rust
+scoreboard players operation %%float_a_zero rust = %%float_b_zero rust
+function intrinsic:float/save_b
@@ -0,0 +1,14 @@
+# Converts the unpacked value to a 64-bit integer, rounding towards zero.
+# Values that are out of range give an unspecified result.
+#
+# Results:
+# %return%0 - Low word of the integer
+# %return%1 - High word of the integer
+
+scoreboard players set %%float_w0 rust 0
+scoreboard players set %%float_w1 rust 0
+execute if score %%float_exp rust matches 1023.. run function intrinsic:float/to_int/nonzero
+execute if score %%float_sign rust matches 1..1 run function intrinsic:float/negate64
+
+scoreboard players operation %return%0 rust = %%float_w0 rust
+scoreboard players operation %return%1 rust = %%float_w1 rust
@@ -0,0 +1,20 @@
+# The value is the significand times 2^(exp - 1078)
+execute if score %%float_exp rust matches 1087.. run scoreboard players set %%float_exp rust 1086
+scoreboard players operation %%float_shift rust = %%float_exp rust
+scoreboard players remove %%float_shift rust 1078
+
+scoreboard players set %%float_c2 rust 0
+scoreboard players operation %%float_c1 rust = %%float_hi rust
+scoreboard players operation %%float_c0 rust = %%float_lo rust
+execute if score %%float_shift rust matches 1.. run function intrinsic:float/to_int/shift_left
+execute if score %%float_shift rust matches ..-1 run function intrinsic:float/to_int/shift_right
+
+# Repack the 28-bit limbs into two words
+scoreboard players operation %%float_w0 rust = %%float_c1 rust
+scoreboard players operation %%float_w0 rust %= %%16 rust
+scoreboard players operation %%float_w0 rust *= %%268435456 rust
+scoreboard players operation %%float_w0 rust += %%float_c0 rust
+scoreboard players operation %%float_w1 rust = %%float_c2 rust
+scoreboard players operation %%float_w1 rust *= %%16777216 rust
+scoreboard players operation %%float_c1 rust /= %%16 rust
+scoreboard players operation %%float_w1 rust += %%float_c1 rust
@@ -0,0 +1,21 @@
+# c2 = hi >> (28 - shift)
+# c1 = ((hi % 2^(28 - shift)) << shift) | (lo >> (28 - shift))
+# c0 = (lo % 2^(28 - shift)) << shift
+
+function intrinsic:float/pow2
+scoreboard players operation %%float_tmp2 rust = %%float_pow rust
+scoreboard players operation %%float_shift rust *= %%-1 rust
+scoreboard players add %%float_shift rust 28
+function intrinsic:float/pow2
+
+scoreboard players operation %%float_c2 rust = %%float_hi rust
+scoreboard players operation %%float_c2 rust /= %%float_pow rust
+scoreboard players operation %%float_c1 rust = %%float_hi rust
+scoreboard players operation %%float_c1 rust %= %%float_pow rust
+scoreboard players operation %%float_c1 rust *= %%float_tmp2 rust
+scoreboard players operation %%float_tmp rust = %%float_lo rust
+scoreboard players operation %%float_tmp rust /= %%float_pow rust
+scoreboard players operation %%float_c1 rust += %%float_tmp rust
+scoreboard players operation %%float_c0 rust = %%float_lo rust
+scoreboard players operation %%float_c0 rust %= %%float_pow rust
+scoreboard players operation %%float_c0 rust *= %%float_tmp2 rust
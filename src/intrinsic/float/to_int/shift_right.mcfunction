@@ -0,0 +1,4 @@
+scoreboard players operation %%float_shift rust *= %%-1 rust
+function intrinsic:float/shift_right
+scoreboard players operation %%float_c1 rust = %%float_hi rust
+scoreboard players operation %%float_c0 rust = %%float_lo rust
@@ -0,0 +1,30 @@
+# Splits a float into the same form that `intrinsic:float/unpack64` uses.
+# Every float is exactly representable as a normal double, so subnormal
+# floats get normalized here.
+#
+# Arguments:
+# %%float_w0 - The float, is clobbered
+#
+# Results are the same as `intrinsic:float/unpack64`
+
+execute store success score %%float_sign rust if score %%float_w0 rust matches ..-1
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w0 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w0 rust 1
+
+scoreboard players operation %%float_exp rust = %%float_w0 rust
+scoreboard players operation %%float_exp rust /= %%8388608 rust
+scoreboard players operation %%float_hi rust = %%float_w0 rust
+scoreboard players operation %%float_hi rust %= %%8388608 rust
+execute unless score %%float_exp rust matches 0..0 unless score %%float_exp rust matches 255..255 run scoreboard players add %%float_hi rust 8388608
+scoreboard players operation %%float_hi rust *= %%16 rust
+scoreboard players set %%float_lo rust 0
+
+# Rebias the exponent, keeping subnormals as an exponent of 0 for now
+execute if score %%float_exp rust matches 255..255 run scoreboard players set %%float_exp rust 2047
+execute if score %%float_exp rust matches 1..254 run scoreboard players add %%float_exp rust 896
+
+function intrinsic:float/classify
+
+execute if score %%float_exp rust matches 1..1 if score %%float_zero rust matches 0..0 run scoreboard players set %%float_exp rust 897
+scoreboard players set %%float_min_exp rust 1
+function intrinsic:float/normalize
@@ -0,0 +1,40 @@
+# Splits a double into its parts. The significand is kept in two 28-bit
+# limbs with three extra guard bits, so the implicit bit of a normal number
+# is bit 27 of the high limb. Subnormals get an exponent of 1 and no implicit
+# bit, while infinities and NaNs get an exponent of 2047 and no implicit bit.
+#
+# Arguments:
+# %%float_w0 - Low word of the double, is clobbered
+# %%float_w1 - High word of the double, is clobbered
+#
+# Results:
+# %%float_sign - 1 if negative, otherwise 0
+# %%float_exp - Biased exponent
+# %%float_hi, %%float_lo - Significand
+# %%float_nan - 1 if the value is a NaN, otherwise 0
+# %%float_zero - 1 if the value is zero, otherwise 0
+
+execute store success score %%float_sign rust if score %%float_w1 rust matches ..-1
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w1 rust 2147483647
+execute if score %%float_sign rust matches 1..1 run scoreboard players remove %%float_w1 rust 1
+
+scoreboard players operation %%float_exp rust = %%float_w1 rust
+scoreboard players operation %%float_exp rust /= %%1048576 rust
+scoreboard players operation %%float_hi rust = %%float_w1 rust
+scoreboard players operation %%float_hi rust %= %%1048576 rust
+execute unless score %%float_exp rust matches 0..0 unless score %%float_exp rust matches 2047..2047 run scoreboard players add %%float_hi rust 1048576
+scoreboard players operation %%float_hi rust *= %%128 rust
+
+# The top 7 bits of the low word belong to the high limb
+execute store success score %%float_tmp rust if score %%float_w0 rust matches ..-1
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w0 rust 2147483647
+execute if score %%float_tmp rust matches 1..1 run scoreboard players remove %%float_w0 rust 1
+scoreboard players operation %%float_lo rust = %%float_w0 rust
+scoreboard players operation %%float_lo rust /= %%33554432 rust
+execute if score %%float_tmp rust matches 1..1 run scoreboard players add %%float_lo rust 64
+scoreboard players operation %%float_hi rust += %%float_lo rust
+scoreboard players operation %%float_lo rust = %%float_w0 rust
+scoreboard players operation %%float_lo rust %= %%33554432 rust
+scoreboard players operation %%float_lo rust *= %%8 rust
+
+function intrinsic:float/classify
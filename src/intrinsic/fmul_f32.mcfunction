@@ -0,0 +1,8 @@
+# Arguments:
+# %param0%0 - Left operand
+# %param1%0 - Right operand
+# %return%0 - Product
+
+function intrinsic:float/load_args32
+function intrinsic:float/mul
+function intrinsic:float/pack32
@@ -0,0 +1,7 @@
+# Arguments:
+# %param0%0 - Float to extend
+# %return%0, %return%1 - Resulting double
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+function intrinsic:float/unpack32
+function intrinsic:float/pack64
@@ -0,0 +1,8 @@
+# Arguments:
+# %param0%0, %param0%1 - Double to convert
+# %return%0, %return%1 - Resulting integer, rounded towards zero
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+function intrinsic:float/unpack64
+function intrinsic:float/to_int
@@ -0,0 +1,10 @@
+# Arguments:
+# %param0%0, %param0%1 - Double to convert
+# %return%0, %return%1 - Resulting unsigned integer, rounded towards zero
+
+# The conversion never clamps below 2^64, so values that don't fit in
+# a signed integer still come out with the right bits
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+function intrinsic:float/unpack64
+function intrinsic:float/to_int
@@ -0,0 +1,9 @@
+# Arguments:
+# %param0%0, %param0%1 - Left operand
+# %param1%0, %param1%1 - Right operand
+# %return%0, %return%1 - Difference
+
+function intrinsic:float/load_args64
+execute store success score %%float_b_sign rust if score %%float_b_sign rust matches 0..0
+function intrinsic:float/add
+function intrinsic:float/pack64
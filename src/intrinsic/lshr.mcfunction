@@ -30,4 +30,6 @@
 # %param1%0 : shift (clobbered)
 
 execute if score %param1%0 rust matches 32.. run scoreboard players set %param0%0 rust 0
+execute if score %param1%0 rust matches 32.. run scoreboard players set %param1%0 rust 0
+execute if score %param1%0 rust matches 31..31 run function intrinsic:lshr/sign_bit
 execute if score %param1%0 rust matches 1.. run function intrinsic:lshr/inner
\ No newline at end of file
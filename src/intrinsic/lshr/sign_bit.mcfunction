@@ -0,0 +1,5 @@
+# A shift by 31 would have to divide by i32::MIN, which rounds the wrong way in-game,
+# so just extract the sign bit directly
+
+execute store success score %param0%0 rust if score %param0%0 rust matches ..-1
+scoreboard players set %param1%0 rust 0
@@ -0,0 +1,7 @@
+# l = h >> (s - 32)
+# h = 0
+
+scoreboard players operation %param0%0 rust = %param0%1 rust
+scoreboard players remove %param1%0 rust 32
+function intrinsic:lshr
+scoreboard players set %param0%1 rust 0
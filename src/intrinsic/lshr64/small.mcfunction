@@ -0,0 +1,18 @@
+# l = (l >> s) | (h << (32 - s))
+# h = h >> s
+
+function intrinsic:lshr
+scoreboard players operation %temp1_lshr64 rust = %param0%0 rust
+
+scoreboard players operation %param0%0 rust = %param0%1 rust
+scoreboard players set %param1%0 rust 32
+scoreboard players operation %param1%0 rust -= %temp2_lshr64 rust
+function intrinsic:shl
+scoreboard players operation %temp1_lshr64 rust += %param0%0 rust
+
+scoreboard players operation %param0%0 rust = %param0%1 rust
+scoreboard players operation %param1%0 rust = %temp2_lshr64 rust
+function intrinsic:lshr
+
+scoreboard players operation %param0%1 rust = %param0%0 rust
+scoreboard players operation %param0%0 rust = %temp1_lshr64 rust
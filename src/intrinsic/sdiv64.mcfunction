@@ -0,0 +1,19 @@
+# Arguments:
+# %param0%0, %param0%1 - Dividend (clobbered)
+# %param1%0, %param1%1 - Divisor (clobbered)
+# %return%0, %return%1 - Quotient, rounded towards zero
+# %return%2, %return%3 - Remainder, with the same sign as the dividend
+
+scoreboard players set %%sdiv64_quot_neg rust 0
+scoreboard players set %%sdiv64_rem_neg rust 0
+execute if score %param0%1 rust matches ..-1 run scoreboard players set %%sdiv64_rem_neg rust 1
+execute if score %param0%1 rust matches ..-1 run scoreboard players add %%sdiv64_quot_neg rust 1
+execute if score %param1%1 rust matches ..-1 run scoreboard players add %%sdiv64_quot_neg rust 1
+
+execute if score %param0%1 rust matches ..-1 run function intrinsic:sdiv64/negate_dividend
+execute if score %param1%1 rust matches ..-1 run function intrinsic:sdiv64/negate_divisor
+
+function intrinsic:udiv64
+
+execute if score %%sdiv64_quot_neg rust matches 1..1 run function intrinsic:sdiv64/negate_quotient
+execute if score %%sdiv64_rem_neg rust matches 1..1 run function intrinsic:sdiv64/negate_remainder
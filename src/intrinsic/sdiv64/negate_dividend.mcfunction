@@ -0,0 +1,3 @@
+scoreboard players operation %param0%1 rust *= %%-1 rust
+execute unless score %param0%0 rust matches 0..0 run scoreboard players remove %param0%1 rust 1
+scoreboard players operation %param0%0 rust *= %%-1 rust
@@ -0,0 +1,3 @@
+scoreboard players operation %param1%1 rust *= %%-1 rust
+execute unless score %param1%0 rust matches 0..0 run scoreboard players remove %param1%1 rust 1
+scoreboard players operation %param1%0 rust *= %%-1 rust
@@ -0,0 +1,3 @@
+scoreboard players operation %return%1 rust *= %%-1 rust
+execute unless score %return%0 rust matches 0..0 run scoreboard players remove %return%1 rust 1
+scoreboard players operation %return%0 rust *= %%-1 rust
@@ -0,0 +1,3 @@
+scoreboard players operation %return%3 rust *= %%-1 rust
+execute unless score %return%2 rust matches 0..0 run scoreboard players remove %return%3 rust 1
+scoreboard players operation %return%2 rust *= %%-1 rust
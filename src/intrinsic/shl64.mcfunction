@@ -1,21 +1,7 @@
-# l = l << s
-# h = (h << s) | (l >> (32 - s))
+# Arguments:
+# %param0%0, %param0%1 - The value to be shifted (also the output)
+# %param1%0 - The amount to shift by, from 0 to 63, is clobbered
 
-scoreboard players operation %temp0_shl64 rust = %param0%0 rust
 scoreboard players operation %temp2_shl64 rust = %param1%0 rust
-
-scoreboard players operation %param0%0 rust = %param0%1 rust
-function intrinsic:shl
-scoreboard players operation %temp1_shl64 rust = %param0%0 rust
-
-scoreboard players operation %param0%0 rust = %temp0_shl64 rust
-scoreboard players set %param1%0 rust 32
-scoreboard players operation %param1%0 rust -= %temp2_shl64 rust
-function intrinsic:lshr
-scoreboard players operation %temp1_shl64 rust += %param0%0 rust
-
-scoreboard players operation %param0%0 rust = %temp0_shl64 rust
-scoreboard players operation %param1%0 rust = %temp2_shl64 rust
-function intrinsic:shl
-
-scoreboard players operation %param0%1 rust = %temp1_shl64 rust
+execute if score %temp2_shl64 rust matches 32.. run function intrinsic:shl64/big
+execute if score %temp2_shl64 rust matches ..31 run function intrinsic:shl64/small
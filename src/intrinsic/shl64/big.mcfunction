@@ -0,0 +1,7 @@
+# h = l << (s - 32)
+# l = 0
+
+scoreboard players remove %param1%0 rust 32
+function intrinsic:shl
+scoreboard players operation %param0%1 rust = %param0%0 rust
+scoreboard players set %param0%0 rust 0
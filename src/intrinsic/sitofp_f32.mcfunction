@@ -0,0 +1,10 @@
+# Arguments:
+# %param0%0, %param0%1 - Signed 64-bit integer to convert
+# %return%0 - Resulting float
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+execute store success score %%float_sign rust if score %%float_w1 rust matches ..-1
+execute if score %%float_sign rust matches 1..1 run function intrinsic:float/negate64
+function intrinsic:float/from_int
+function intrinsic:float/pack32
@@ -0,0 +1,33 @@
+# Arguments:
+# %param0%0, %param0%1 - Dividend
+# %param1%0, %param1%1 - Divisor
+# %return%0, %return%1 - Quotient
+# %return%2, %return%3 - Remainder
+#
+# Restoring long division: the dividend is shifted into the remainder one bit
+# at a time, and the low bits it frees up collect the quotient.
+
+scoreboard players operation %%udiv64_n0 rust = %param0%0 rust
+scoreboard players operation %%udiv64_n1 rust = %param0%1 rust
+scoreboard players set %%udiv64_r0 rust 0
+scoreboard players set %%udiv64_r1 rust 0
+
+# Divisor biased by 2^31 so that unsigned comparisons can use signed scores
+scoreboard players operation %%udiv64_d0 rust = %param1%0 rust
+scoreboard players operation %%udiv64_d0 rust += %%-2147483648 rust
+scoreboard players operation %%udiv64_d1 rust = %param1%1 rust
+scoreboard players operation %%udiv64_d1 rust += %%-2147483648 rust
+
+# Negated divisor words, for subtracting without overflow
+scoreboard players operation %%udiv64_neg0 rust = %param1%0 rust
+scoreboard players operation %%udiv64_neg0 rust *= %%-1 rust
+scoreboard players operation %%udiv64_neg1 rust = %param1%1 rust
+scoreboard players operation %%udiv64_neg1 rust *= %%-1 rust
+
+scoreboard players set %%udiv64_count rust 64
+function intrinsic:udiv64/step
+
+scoreboard players operation %return%0 rust = %%udiv64_n0 rust
+scoreboard players operation %return%1 rust = %%udiv64_n1 rust
+scoreboard players operation %return%2 rust = %%udiv64_r0 rust
+scoreboard players operation %return%3 rust = %%udiv64_r1 rust
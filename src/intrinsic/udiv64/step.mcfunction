@@ -0,0 +1,22 @@
+# (remainder, dividend) <<= 1
+scoreboard players set %%udiv64_fits rust 0
+execute if score %%udiv64_r1 rust matches ..-1 run scoreboard players set %%udiv64_fits rust 1
+scoreboard players operation %%udiv64_r1 rust *= %%2 rust
+execute if score %%udiv64_r0 rust matches ..-1 run scoreboard players add %%udiv64_r1 rust 1
+scoreboard players operation %%udiv64_r0 rust *= %%2 rust
+execute if score %%udiv64_n1 rust matches ..-1 run scoreboard players add %%udiv64_r0 rust 1
+scoreboard players operation %%udiv64_n1 rust *= %%2 rust
+execute if score %%udiv64_n0 rust matches ..-1 run scoreboard players add %%udiv64_n1 rust 1
+scoreboard players operation %%udiv64_n0 rust *= %%2 rust
+
+# if remainder >= divisor (or the shift carried out of it) { remainder -= divisor; quotient += 1 }
+scoreboard players operation %%udiv64_t0 rust = %%udiv64_r0 rust
+scoreboard players operation %%udiv64_t0 rust += %%-2147483648 rust
+scoreboard players operation %%udiv64_t1 rust = %%udiv64_r1 rust
+scoreboard players operation %%udiv64_t1 rust += %%-2147483648 rust
+execute if score %%udiv64_t1 rust > %%udiv64_d1 rust run scoreboard players set %%udiv64_fits rust 1
+execute if score %%udiv64_t1 rust = %%udiv64_d1 rust if score %%udiv64_t0 rust >= %%udiv64_d0 rust run scoreboard players set %%udiv64_fits rust 1
+execute if score %%udiv64_fits rust matches 1..1 run function intrinsic:udiv64/subtract
+
+scoreboard players remove %%udiv64_count rust 1
+execute if score %%udiv64_count rust matches 1.. run function intrinsic:udiv64/step
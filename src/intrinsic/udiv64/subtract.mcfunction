@@ -0,0 +1,5 @@
+# Borrow from the high word if the low word of the remainder is below the divisor's
+execute if score %%udiv64_t0 rust < %%udiv64_d0 rust run scoreboard players remove %%udiv64_r1 rust 1
+scoreboard players operation %%udiv64_r0 rust += %%udiv64_neg0 rust
+scoreboard players operation %%udiv64_r1 rust += %%udiv64_neg1 rust
+scoreboard players add %%udiv64_n0 rust 1
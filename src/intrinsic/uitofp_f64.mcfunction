@@ -0,0 +1,9 @@
+# Arguments:
+# %param0%0, %param0%1 - Unsigned 64-bit integer to convert
+# %return%0, %return%1 - Resulting double
+
+scoreboard players operation %%float_w0 rust = %param0%0 rust
+scoreboard players operation %%float_w1 rust = %param0%1 rust
+scoreboard players set %%float_sign rust 0
+function intrinsic:float/from_int
+function intrinsic:float/pack64
@@ -6,6 +6,23 @@ static INTRINSIC_STRS: &[(&str, &str)] = &[
     ("intrinsic:lshr", include_str!("intrinsic/lshr.mcfunction")),
     ("intrinsic:lshr64", include_str!("intrinsic/lshr64.mcfunction")),
     ("intrinsic:ashr", include_str!("intrinsic/ashr.mcfunction")),
+    ("intrinsic:ashr64", include_str!("intrinsic/ashr64.mcfunction")),
+    (
+        "intrinsic:ashr64/big",
+        include_str!("intrinsic/ashr64/big.mcfunction"),
+    ),
+    (
+        "intrinsic:ashr64/small",
+        include_str!("intrinsic/ashr64/small.mcfunction"),
+    ),
+    (
+        "intrinsic:lshr64/big",
+        include_str!("intrinsic/lshr64/big.mcfunction"),
+    ),
+    (
+        "intrinsic:lshr64/small",
+        include_str!("intrinsic/lshr64/small.mcfunction"),
+    ),
     (
         "intrinsic:lshr/getshift",
         include_str!("intrinsic/lshr/getshift.mcfunction"),
@@ -14,6 +31,10 @@ static INTRINSIC_STRS: &[(&str, &str)] = &[
         "intrinsic:lshr/inner",
         include_str!("intrinsic/lshr/inner.mcfunction"),
     ),
+    (
+        "intrinsic:lshr/sign_bit",
+        include_str!("intrinsic/lshr/sign_bit.mcfunction"),
+    ),
     (
         "intrinsic:memcpy",
         include_str!("intrinsic/memcpy.mcfunction"),
@@ -47,6 +68,32 @@ static INTRINSIC_STRS: &[(&str, &str)] = &[
         "intrinsic:shift_from_ptr_inner",
         include_str!("intrinsic/shift_from_ptr_inner.mcfunction"),
     ),
+    ("intrinsic:udiv64", include_str!("intrinsic/udiv64.mcfunction")),
+    (
+        "intrinsic:udiv64/step",
+        include_str!("intrinsic/udiv64/step.mcfunction"),
+    ),
+    (
+        "intrinsic:udiv64/subtract",
+        include_str!("intrinsic/udiv64/subtract.mcfunction"),
+    ),
+    ("intrinsic:sdiv64", include_str!("intrinsic/sdiv64.mcfunction")),
+    (
+        "intrinsic:sdiv64/negate_dividend",
+        include_str!("intrinsic/sdiv64/negate_dividend.mcfunction"),
+    ),
+    (
+        "intrinsic:sdiv64/negate_divisor",
+        include_str!("intrinsic/sdiv64/negate_divisor.mcfunction"),
+    ),
+    (
+        "intrinsic:sdiv64/negate_quotient",
+        include_str!("intrinsic/sdiv64/negate_quotient.mcfunction"),
+    ),
+    (
+        "intrinsic:sdiv64/negate_remainder",
+        include_str!("intrinsic/sdiv64/negate_remainder.mcfunction"),
+    ),
     ("intrinsic:xor", include_str!("intrinsic/xor.mcfunction")),
     (
         "intrinsic:xor_inner",
@@ -126,6 +173,278 @@ static INTRINSIC_STRS: &[(&str, &str)] = &[
         "intrinsic:shl64",
         include_str!("intrinsic/shl64.mcfunction")
     ),
+    (
+        "intrinsic:shl64/big",
+        include_str!("intrinsic/shl64/big.mcfunction"),
+    ),
+    (
+        "intrinsic:shl64/small",
+        include_str!("intrinsic/shl64/small.mcfunction"),
+    ),
+    (
+        "intrinsic:fadd_f32",
+        include_str!("intrinsic/fadd_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fadd_f64",
+        include_str!("intrinsic/fadd_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fcmp_f32",
+        include_str!("intrinsic/fcmp_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fcmp_f64",
+        include_str!("intrinsic/fcmp_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fdiv_f32",
+        include_str!("intrinsic/fdiv_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fdiv_f64",
+        include_str!("intrinsic/fdiv_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fmul_f32",
+        include_str!("intrinsic/fmul_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fmul_f64",
+        include_str!("intrinsic/fmul_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fpext",
+        include_str!("intrinsic/fpext.mcfunction"),
+    ),
+    (
+        "intrinsic:fptosi_f32",
+        include_str!("intrinsic/fptosi_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fptosi_f64",
+        include_str!("intrinsic/fptosi_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fptoui_f32",
+        include_str!("intrinsic/fptoui_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fptoui_f64",
+        include_str!("intrinsic/fptoui_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:fptrunc",
+        include_str!("intrinsic/fptrunc.mcfunction"),
+    ),
+    (
+        "intrinsic:fsub_f32",
+        include_str!("intrinsic/fsub_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:fsub_f64",
+        include_str!("intrinsic/fsub_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:sitofp_f32",
+        include_str!("intrinsic/sitofp_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:sitofp_f64",
+        include_str!("intrinsic/sitofp_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:uitofp_f32",
+        include_str!("intrinsic/uitofp_f32.mcfunction"),
+    ),
+    (
+        "intrinsic:uitofp_f64",
+        include_str!("intrinsic/uitofp_f64.mcfunction"),
+    ),
+    (
+        "intrinsic:float/add",
+        include_str!("intrinsic/float/add.mcfunction"),
+    ),
+    (
+        "intrinsic:float/add/diff_sign",
+        include_str!("intrinsic/float/add/diff_sign.mcfunction"),
+    ),
+    (
+        "intrinsic:float/add/finite",
+        include_str!("intrinsic/float/add/finite.mcfunction"),
+    ),
+    (
+        "intrinsic:float/add/same_sign",
+        include_str!("intrinsic/float/add/same_sign.mcfunction"),
+    ),
+    (
+        "intrinsic:float/classify",
+        include_str!("intrinsic/float/classify.mcfunction"),
+    ),
+    (
+        "intrinsic:float/denormalize",
+        include_str!("intrinsic/float/denormalize.mcfunction"),
+    ),
+    (
+        "intrinsic:float/div",
+        include_str!("intrinsic/float/div.mcfunction"),
+    ),
+    (
+        "intrinsic:float/div/finite",
+        include_str!("intrinsic/float/div/finite.mcfunction"),
+    ),
+    (
+        "intrinsic:float/div/step",
+        include_str!("intrinsic/float/div/step.mcfunction"),
+    ),
+    (
+        "intrinsic:float/from_int",
+        include_str!("intrinsic/float/from_int.mcfunction"),
+    ),
+    (
+        "intrinsic:float/from_int/normalize",
+        include_str!("intrinsic/float/from_int/normalize.mcfunction"),
+    ),
+    (
+        "intrinsic:float/from_int/shift_limb",
+        include_str!("intrinsic/float/from_int/shift_limb.mcfunction"),
+    ),
+    (
+        "intrinsic:float/key32",
+        include_str!("intrinsic/float/key32.mcfunction"),
+    ),
+    (
+        "intrinsic:float/key64",
+        include_str!("intrinsic/float/key64.mcfunction"),
+    ),
+    (
+        "intrinsic:float/load_a",
+        include_str!("intrinsic/float/load_a.mcfunction"),
+    ),
+    (
+        "intrinsic:float/load_args32",
+        include_str!("intrinsic/float/load_args32.mcfunction"),
+    ),
+    (
+        "intrinsic:float/load_args64",
+        include_str!("intrinsic/float/load_args64.mcfunction"),
+    ),
+    (
+        "intrinsic:float/load_b",
+        include_str!("intrinsic/float/load_b.mcfunction"),
+    ),
+    (
+        "intrinsic:float/mul",
+        include_str!("intrinsic/float/mul.mcfunction"),
+    ),
+    (
+        "intrinsic:float/mul/finite",
+        include_str!("intrinsic/float/mul/finite.mcfunction"),
+    ),
+    (
+        "intrinsic:float/mul/shift_product",
+        include_str!("intrinsic/float/mul/shift_product.mcfunction"),
+    ),
+    (
+        "intrinsic:float/negate64",
+        include_str!("intrinsic/float/negate64.mcfunction"),
+    ),
+    (
+        "intrinsic:float/normalize",
+        include_str!("intrinsic/float/normalize.mcfunction"),
+    ),
+    (
+        "intrinsic:float/normalize/shift",
+        include_str!("intrinsic/float/normalize/shift.mcfunction"),
+    ),
+    (
+        "intrinsic:float/normalize/step",
+        include_str!("intrinsic/float/normalize/step.mcfunction"),
+    ),
+    (
+        "intrinsic:float/normalize_operands",
+        include_str!("intrinsic/float/normalize_operands.mcfunction"),
+    ),
+    (
+        "intrinsic:float/or_sticky",
+        include_str!("intrinsic/float/or_sticky.mcfunction"),
+    ),
+    (
+        "intrinsic:float/pack32",
+        include_str!("intrinsic/float/pack32.mcfunction"),
+    ),
+    (
+        "intrinsic:float/pack64",
+        include_str!("intrinsic/float/pack64.mcfunction"),
+    ),
+    (
+        "intrinsic:float/pow2",
+        include_str!("intrinsic/float/pow2.mcfunction"),
+    ),
+    (
+        "intrinsic:float/save_a",
+        include_str!("intrinsic/float/save_a.mcfunction"),
+    ),
+    (
+        "intrinsic:float/save_b",
+        include_str!("intrinsic/float/save_b.mcfunction"),
+    ),
+    (
+        "intrinsic:float/set_inf",
+        include_str!("intrinsic/float/set_inf.mcfunction"),
+    ),
+    (
+        "intrinsic:float/set_zero",
+        include_str!("intrinsic/float/set_zero.mcfunction"),
+    ),
+    (
+        "intrinsic:float/shift_right",
+        include_str!("intrinsic/float/shift_right.mcfunction"),
+    ),
+    (
+        "intrinsic:float/shift_right/all",
+        include_str!("intrinsic/float/shift_right/all.mcfunction"),
+    ),
+    (
+        "intrinsic:float/shift_right/bits",
+        include_str!("intrinsic/float/shift_right/bits.mcfunction"),
+    ),
+    (
+        "intrinsic:float/shift_right/limb",
+        include_str!("intrinsic/float/shift_right/limb.mcfunction"),
+    ),
+    (
+        "intrinsic:float/shift_right_one",
+        include_str!("intrinsic/float/shift_right_one.mcfunction"),
+    ),
+    (
+        "intrinsic:float/swap",
+        include_str!("intrinsic/float/swap.mcfunction"),
+    ),
+    (
+        "intrinsic:float/to_int",
+        include_str!("intrinsic/float/to_int.mcfunction"),
+    ),
+    (
+        "intrinsic:float/to_int/nonzero",
+        include_str!("intrinsic/float/to_int/nonzero.mcfunction"),
+    ),
+    (
+        "intrinsic:float/to_int/shift_left",
+        include_str!("intrinsic/float/to_int/shift_left.mcfunction"),
+    ),
+    (
+        "intrinsic:float/to_int/shift_right",
+        include_str!("intrinsic/float/to_int/shift_right.mcfunction"),
+    ),
+    (
+        "intrinsic:float/unpack32",
+        include_str!("intrinsic/float/unpack32.mcfunction"),
+    ),
+    (
+        "intrinsic:float/unpack64",
+        include_str!("intrinsic/float/unpack64.mcfunction"),
+    ),
 ];
 
 lazy_static! {
@@ -203,16 +522,19 @@ mod test {
 
         interp.call_stack = vec![(idx, 0, (0, 0, 0))];
 
-        for i in 0..31 {
+        for i in 0..32 {
             interp.rust_scores.insert(
-                cir::ScoreHolder::new(format!("%%{}", 1 << i)).unwrap(),
+                cir::ScoreHolder::new(format!("%%{}", 1_i32 << i)).unwrap(),
                 1 << i,
             );
         }
 
         interp
             .rust_scores
-            .insert(cir::ScoreHolder::new("%%SIXTEEN".into()).unwrap(), 16);
+            .insert(cir::ScoreHolder::new("%%MEM_Y".into()).unwrap(), 16);
+        interp
+            .rust_scores
+            .insert(cir::ScoreHolder::new("%%MEM_Z".into()).unwrap(), 16);
         interp
             .rust_scores
             .insert(cir::ScoreHolder::new("%%-1".into()).unwrap(), -1);
@@ -604,6 +926,140 @@ mod test {
         }
     }
 
+    fn test_ashr(a: i32, shift: i32) {
+        let expected = a >> shift;
+        let mut interp = create_interp("intrinsic:ashr");
+        interp.rust_scores.insert(param(0, 0), a);
+        interp.rust_scores.insert(param(1, 0), shift);
+        interp.run_to_end().unwrap();
+        let actual = *interp.rust_scores.get(&param(0, 0)).unwrap();
+
+        if expected != actual {
+            println!("Shift: {}", shift);
+            println!("Input:    {:>10} ({:#010X})", a, a);
+            println!("Expected: {:>10} ({:#010X})", expected, expected);
+            println!("Actual:   {:>10} ({:#010X})", actual, actual);
+            panic!();
+        }
+    }
+
+    #[test]
+    fn ashr() {
+        for &a in &[0, 1, -1, i32::MIN, i32::MAX, 1234567890, -1234567890] {
+            for shift in 0..32 {
+                test_ashr(a, shift);
+            }
+        }
+    }
+
+    const SHIFT64_VALUES: &[u64] = &[
+        0,
+        1,
+        u64::MAX,
+        0x8000_0000_0000_0000,
+        0x7FFF_FFFF_FFFF_FFFF,
+        0x0000_0000_8000_0000,
+        0x0000_0001_0000_0000,
+        0x1234_5678_9ABC_DEF0,
+        0xFEDC_BA98_7654_3210,
+    ];
+
+    fn test_shift64(name: &str, op: fn(u64, u32) -> u64) {
+        for &a in SHIFT64_VALUES {
+            for shift in 0..64 {
+                let expected = op(a, shift);
+
+                let mut interp = create_interp(name);
+                interp.rust_scores.insert(param(0, 0), a as i32);
+                interp.rust_scores.insert(param(0, 1), (a >> 32) as i32);
+                interp.rust_scores.insert(param(1, 0), shift as i32);
+                interp.run_to_end().unwrap();
+
+                let actual_lo = *interp.rust_scores.get(&param(0, 0)).unwrap() as u32;
+                let actual_hi = *interp.rust_scores.get(&param(0, 1)).unwrap() as u32;
+                let actual = actual_lo as u64 | ((actual_hi as u64) << 32);
+
+                if expected != actual {
+                    println!("Shift: {}", shift);
+                    println!("Input:    {:>20} ({:#018X})", a, a);
+                    println!("Expected: {:>20} ({:#018X})", expected, expected);
+                    println!("Actual:   {:>20} ({:#018X})", actual, actual);
+                    panic!();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shl64() {
+        test_shift64("intrinsic:shl64", |a, shift| a << shift);
+    }
+
+    #[test]
+    fn lshr64() {
+        test_shift64("intrinsic:lshr64", |a, shift| a >> shift);
+    }
+
+    #[test]
+    fn ashr64() {
+        test_shift64("intrinsic:ashr64", |a, shift| ((a as i64) >> shift) as u64);
+    }
+
+    const DIV64_VALUES: &[u64] = &[
+        1,
+        2,
+        3,
+        10,
+        u64::MAX,
+        0x8000_0000_0000_0000,
+        0x7FFF_FFFF_FFFF_FFFF,
+        0x0000_0000_8000_0000,
+        0x0000_0001_0000_0000,
+        0x0000_0000_FFFF_FFFF,
+        0x1234_5678_9ABC_DEF0,
+        0xFEDC_BA98_7654_3210,
+        (-10_i64) as u64,
+    ];
+
+    fn test_div64(name: &str, op: fn(u64, u64) -> (u64, u64)) {
+        for &a in DIV64_VALUES.iter().chain(std::iter::once(&0)) {
+            for &b in DIV64_VALUES {
+                let (expected_quot, expected_rem) = op(a, b);
+
+                let mut interp = create_interp(name);
+                interp.rust_scores.insert(param(0, 0), a as i32);
+                interp.rust_scores.insert(param(0, 1), (a >> 32) as i32);
+                interp.rust_scores.insert(param(1, 0), b as i32);
+                interp.rust_scores.insert(param(1, 1), (b >> 32) as i32);
+                interp.run_to_end().unwrap();
+
+                let get = |idx| *interp.rust_scores.get(&return_holder(idx)).unwrap() as u32 as u64;
+                let actual_quot = get(0) | (get(1) << 32);
+                let actual_rem = get(2) | (get(3) << 32);
+
+                if (expected_quot, expected_rem) != (actual_quot, actual_rem) {
+                    println!("Inputs:    {:#018X} {:#018X}", a, b);
+                    println!("Expected:  {:#018X} {:#018X}", expected_quot, expected_rem);
+                    println!("Actual:    {:#018X} {:#018X}", actual_quot, actual_rem);
+                    panic!();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn udiv64() {
+        test_div64("intrinsic:udiv64", |a, b| (a / b, a % b));
+    }
+
+    #[test]
+    fn sdiv64() {
+        test_div64("intrinsic:sdiv64", |a, b| {
+            let (a, b) = (a as i64, b as i64);
+            (a.wrapping_div(b) as u64, a.wrapping_rem(b) as u64)
+        });
+    }
+
     fn test_shift_from_ptr(a: i32, ptr: i32) {
         let expected = (a as u32 >> (8 * (ptr % 4))) as i32;
         let mut interp = create_interp("intrinsic:shift_from_ptr");
@@ -702,4 +1158,351 @@ mod test {
         test_and(-52, -123561);
         test_and(-23566, 1352);
     }
+
+    const F64_VALUES: &[f64] = &[
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        0.1,
+        -3.5,
+        1.000_000_000_000_000_2,
+        123_456_789.123,
+        1e300,
+        -1e-300,
+        f64::MIN_POSITIVE,
+        // The largest subnormal
+        f64::from_bits(0x000F_FFFF_FFFF_FFFF),
+        5e-324,
+        f64::MAX,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+    ];
+
+    const F32_VALUES: &[f32] = &[
+        0.0,
+        -0.0,
+        1.0,
+        -1.0,
+        0.1,
+        -3.5,
+        1.000_000_1,
+        16_777_217.0,
+        1e30,
+        -1e-30,
+        f32::MIN_POSITIVE,
+        1.175_494_2e-38,
+        1e-45,
+        f32::MAX,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ];
+
+    /// A simple xorshift generator so the float tests cover more than the hand-picked values
+    struct FloatRng(u64);
+
+    impl FloatRng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn f64(&mut self) -> f64 {
+            // Keep exponents close enough together that operations usually interact
+            let bits = self.next();
+            let exp = 1023 - 40 + (bits >> 52) % 80;
+            f64::from_bits((bits & 0x800F_FFFF_FFFF_FFFF) | (exp << 52))
+        }
+
+        fn f32(&mut self) -> f32 {
+            let bits = self.next() as u32;
+            let exp = 127 - 20 + (bits >> 23) % 40;
+            f32::from_bits((bits & 0x807F_FFFF) | (exp << 23))
+        }
+    }
+
+    fn f64_pairs() -> Vec<(f64, f64)> {
+        let mut pairs = Vec::new();
+        for &a in F64_VALUES {
+            for &b in F64_VALUES {
+                pairs.push((a, b));
+            }
+        }
+
+        let mut rng = FloatRng(0x1234_5678_9ABC_DEF0);
+        for _ in 0..50 {
+            pairs.push((rng.f64(), rng.f64()));
+        }
+        pairs
+    }
+
+    fn f32_pairs() -> Vec<(f32, f32)> {
+        let mut pairs = Vec::new();
+        for &a in F32_VALUES {
+            for &b in F32_VALUES {
+                pairs.push((a, b));
+            }
+        }
+
+        let mut rng = FloatRng(0x0FED_CBA9_8765_4321);
+        for _ in 0..50 {
+            pairs.push((rng.f32(), rng.f32()));
+        }
+        pairs
+    }
+
+    fn set_f64_param(interp: &mut Interpreter, idx: usize, value: f64) {
+        let bits = value.to_bits();
+        interp.rust_scores.insert(param(idx, 0), bits as u32 as i32);
+        interp.rust_scores.insert(param(idx, 1), (bits >> 32) as u32 as i32);
+    }
+
+    fn get_return_u64(interp: &Interpreter) -> u64 {
+        let lo = interp.get_rust_score(&return_holder(0)).unwrap() as u32 as u64;
+        let hi = interp.get_rust_score(&return_holder(1)).unwrap() as u32 as u64;
+        lo | (hi << 32)
+    }
+
+    fn check_f64(what: &str, expected: f64, actual: f64) {
+        if expected.is_nan() && actual.is_nan() {
+            return;
+        }
+
+        if expected.to_bits() != actual.to_bits() {
+            println!("Operation: {}", what);
+            println!("Expected: {:e} ({:#018X})", expected, expected.to_bits());
+            println!("Actual:   {:e} ({:#018X})", actual, actual.to_bits());
+            panic!();
+        }
+    }
+
+    fn check_f32(what: &str, expected: f32, actual: f32) {
+        if expected.is_nan() && actual.is_nan() {
+            return;
+        }
+
+        if expected.to_bits() != actual.to_bits() {
+            println!("Operation: {}", what);
+            println!("Expected: {:e} ({:#010X})", expected, expected.to_bits());
+            println!("Actual:   {:e} ({:#010X})", actual, actual.to_bits());
+            panic!();
+        }
+    }
+
+    fn test_f64_binop(name: &str, op: fn(f64, f64) -> f64) {
+        for (a, b) in f64_pairs() {
+            let mut interp = create_interp(name);
+            set_f64_param(&mut interp, 0, a);
+            set_f64_param(&mut interp, 1, b);
+            interp.run_to_end().unwrap();
+
+            let actual = f64::from_bits(get_return_u64(&interp));
+            check_f64(&format!("{} {:e} {:e}", name, a, b), op(a, b), actual);
+        }
+    }
+
+    fn test_f32_binop(name: &str, op: fn(f32, f32) -> f32) {
+        for (a, b) in f32_pairs() {
+            let mut interp = create_interp(name);
+            interp.rust_scores.insert(param(0, 0), a.to_bits() as i32);
+            interp.rust_scores.insert(param(1, 0), b.to_bits() as i32);
+            interp.run_to_end().unwrap();
+
+            let actual = f32::from_bits(interp.get_rust_score(&return_holder(0)).unwrap() as u32);
+            check_f32(&format!("{} {:e} {:e}", name, a, b), op(a, b), actual);
+        }
+    }
+
+    fn expected_cmp<T: PartialOrd>(a: T, b: T) -> i32 {
+        match a.partial_cmp(&b) {
+            Some(std::cmp::Ordering::Less) => -1,
+            Some(std::cmp::Ordering::Equal) => 0,
+            Some(std::cmp::Ordering::Greater) => 1,
+            None => 2,
+        }
+    }
+
+    #[test]
+    fn fadd_f64() {
+        test_f64_binop("intrinsic:fadd_f64", |a, b| a + b);
+    }
+
+    #[test]
+    fn fsub_f64() {
+        test_f64_binop("intrinsic:fsub_f64", |a, b| a - b);
+    }
+
+    #[test]
+    fn fmul_f64() {
+        test_f64_binop("intrinsic:fmul_f64", |a, b| a * b);
+    }
+
+    #[test]
+    fn fdiv_f64() {
+        test_f64_binop("intrinsic:fdiv_f64", |a, b| a / b);
+    }
+
+    #[test]
+    fn fadd_f32() {
+        test_f32_binop("intrinsic:fadd_f32", |a, b| a + b);
+    }
+
+    #[test]
+    fn fsub_f32() {
+        test_f32_binop("intrinsic:fsub_f32", |a, b| a - b);
+    }
+
+    #[test]
+    fn fmul_f32() {
+        test_f32_binop("intrinsic:fmul_f32", |a, b| a * b);
+    }
+
+    #[test]
+    fn fdiv_f32() {
+        test_f32_binop("intrinsic:fdiv_f32", |a, b| a / b);
+    }
+
+    #[test]
+    fn fcmp_f64() {
+        for (a, b) in f64_pairs() {
+            let mut interp = create_interp("intrinsic:fcmp_f64");
+            set_f64_param(&mut interp, 0, a);
+            set_f64_param(&mut interp, 1, b);
+            interp.run_to_end().unwrap();
+
+            let actual = interp.get_rust_score(&return_holder(0)).unwrap();
+            assert_eq!(actual, expected_cmp(a, b), "comparing {:e} and {:e}", a, b);
+        }
+    }
+
+    #[test]
+    fn fcmp_f32() {
+        for (a, b) in f32_pairs() {
+            let mut interp = create_interp("intrinsic:fcmp_f32");
+            interp.rust_scores.insert(param(0, 0), a.to_bits() as i32);
+            interp.rust_scores.insert(param(1, 0), b.to_bits() as i32);
+            interp.run_to_end().unwrap();
+
+            let actual = interp.get_rust_score(&return_holder(0)).unwrap();
+            assert_eq!(actual, expected_cmp(a, b), "comparing {:e} and {:e}", a, b);
+        }
+    }
+
+    #[test]
+    fn fpext() {
+        for (a, _) in f32_pairs() {
+            let mut interp = create_interp("intrinsic:fpext");
+            interp.rust_scores.insert(param(0, 0), a.to_bits() as i32);
+            interp.run_to_end().unwrap();
+
+            let actual = f64::from_bits(get_return_u64(&interp));
+            check_f64(&format!("fpext {:e}", a), a as f64, actual);
+        }
+    }
+
+    #[test]
+    fn fptrunc() {
+        let mut values = f64_pairs().into_iter().map(|(a, _)| a).collect::<Vec<_>>();
+        // Halfway cases and values near the edges of the float range
+        values.extend_from_slice(&[
+            1.0 + f32::EPSILON as f64 / 2.0,
+            1.0 + f32::EPSILON as f64 * 1.5,
+            f32::MAX as f64 * 1.000_000_1,
+            1e-40,
+            1e-46,
+            -7e-46,
+        ]);
+
+        for a in values {
+            let mut interp = create_interp("intrinsic:fptrunc");
+            set_f64_param(&mut interp, 0, a);
+            interp.run_to_end().unwrap();
+
+            let actual = f32::from_bits(interp.get_rust_score(&return_holder(0)).unwrap() as u32);
+            check_f32(&format!("fptrunc {:e}", a), a as f32, actual);
+        }
+    }
+
+    #[test]
+    fn fptosi() {
+        let values = [
+            0.0, -0.0, 0.5, -0.99, 1.0, -1.0, 42.9, -42.9, 65536.5, 2_147_483_647.0,
+            -2_147_483_648.0, 1e15 + 0.5, -4.5e18, 9.2e18, 5e-324,
+        ];
+
+        for &a in values.iter() {
+            let mut interp = create_interp("intrinsic:fptosi_f64");
+            set_f64_param(&mut interp, 0, a);
+            interp.run_to_end().unwrap();
+            assert_eq!(get_return_u64(&interp) as i64, a as i64, "converting {:e}", a);
+
+            let a = a as f32;
+            let mut interp = create_interp("intrinsic:fptosi_f32");
+            interp.rust_scores.insert(param(0, 0), a.to_bits() as i32);
+            interp.run_to_end().unwrap();
+            assert_eq!(get_return_u64(&interp) as i64, a as i64, "converting {:e}", a);
+        }
+    }
+
+    #[test]
+    fn fptoui() {
+        let values = [
+            0.0, 0.5, 42.9, 2_147_483_648.0, 3e9, 4_294_967_295.0, 9_223_372_036_854_775_808.0,
+            1.5e19, 1.8e19,
+        ];
+
+        for &a in values.iter() {
+            let mut interp = create_interp("intrinsic:fptoui_f64");
+            set_f64_param(&mut interp, 0, a);
+            interp.run_to_end().unwrap();
+            assert_eq!(get_return_u64(&interp), a as u64, "converting {:e}", a);
+
+            let a = a as f32;
+            let mut interp = create_interp("intrinsic:fptoui_f32");
+            interp.rust_scores.insert(param(0, 0), a.to_bits() as i32);
+            interp.run_to_end().unwrap();
+            assert_eq!(get_return_u64(&interp), a as u64, "converting {:e}", a);
+        }
+    }
+
+    #[test]
+    fn sitofp() {
+        let mut values = vec![
+            0, 1, -1, 42, -42, i32::MAX as i64, i32::MIN as i64, 1 << 53, (1 << 53) + 1,
+            (1 << 24) + 1, -(1 << 60) - 12345, i64::MAX, i64::MIN,
+        ];
+        let mut rng = FloatRng(0xDEAD_BEEF_1234_5678);
+        for _ in 0..20 {
+            values.push(rng.next() as i64 >> (rng.next() % 63));
+        }
+
+        for value in values {
+            let mut interp = create_interp("intrinsic:sitofp_f64");
+            set_f64_param(&mut interp, 0, f64::from_bits(value as u64));
+            interp.run_to_end().unwrap();
+            check_f64(&format!("sitofp {}", value), value as f64, f64::from_bits(get_return_u64(&interp)));
+
+            let mut interp = create_interp("intrinsic:sitofp_f32");
+            set_f64_param(&mut interp, 0, f64::from_bits(value as u64));
+            interp.run_to_end().unwrap();
+            let actual = f32::from_bits(interp.get_rust_score(&return_holder(0)).unwrap() as u32);
+            check_f32(&format!("sitofp {}", value), value as f32, actual);
+
+            let value = value as u64;
+            let mut interp = create_interp("intrinsic:uitofp_f64");
+            set_f64_param(&mut interp, 0, f64::from_bits(value));
+            interp.run_to_end().unwrap();
+            check_f64(&format!("uitofp {}", value), value as f64, f64::from_bits(get_return_u64(&interp)));
+
+            let mut interp = create_interp("intrinsic:uitofp_f32");
+            set_f64_param(&mut interp, 0, f64::from_bits(value));
+            interp.run_to_end().unwrap();
+            let actual = f32::from_bits(interp.get_rust_score(&return_holder(0)).unwrap() as u32);
+            check_f32(&format!("uitofp {}", value), value as f32, actual);
+        }
+    }
 }
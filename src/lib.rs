@@ -1,22 +1,90 @@
 use cir::{Function, FunctionId};
-pub use compile_ir::BuildOptions;
+use optimize::OptReport;
+pub use compile_ir::{BuildOptions, CompileError, MemoryBackend, MemoryLayout};
 pub use interpreter::Interpreter;
 use serde_json::json;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "llvm-10")]
+use llvm_sys_100 as llvm_sys;
+#[cfg(feature = "llvm-11")]
+use llvm_sys_110 as llvm_sys;
+#[cfg(feature = "llvm-12")]
+use llvm_sys_120 as llvm_sys;
+#[cfg(feature = "llvm-13")]
+use llvm_sys_130 as llvm_sys;
+#[cfg(feature = "llvm-14")]
+use llvm_sys_140 as llvm_sys;
 
 pub mod cir;
 pub mod compile_ir;
 pub mod interpreter;
 pub mod analysis;
+mod inline_asm;
+mod inliner;
 mod intrinsics;
+pub mod mmio;
+pub mod optimize;
+mod regalloc;
+mod sanitizer;
+mod storage;
+mod wide_int;
 
 static SETUP_STR: &str = include_str!("setup.mcfunction");
-static PUTC_STR: &str = include_str!("stdout/putc.mcfunction");
-static FLUSH_STR: &str = include_str!("stdout/flush.mcfunction");
+pub(crate) static PUTC_STR: &str = include_str!("stdout/putc.mcfunction");
+pub(crate) static FLUSH_STR: &str = include_str!("stdout/flush.mcfunction");
+
+/// The heap allocator, which gets linked in when a program uses one of `ALLOC_SYMBOLS`
+static ALLOC_RUNTIME_STR: &str = include_str!("runtime/alloc.ll");
+static ALLOC_SYMBOLS: &[&str] = &[
+    "malloc",
+    "free",
+    "realloc",
+    "calloc",
+    "__rust_alloc",
+    "__rust_dealloc",
+    "__rust_realloc",
+    "__rust_alloc_zeroed",
+];
+
+/// Throwing and catching exceptions, which gets linked in when a program uses one of `UNWIND_SYMBOLS`
+static UNWIND_RUNTIME_STR: &str = include_str!("runtime/unwind.ll");
+static UNWIND_SYMBOLS: &[&str] = &[
+    "_Unwind_RaiseException",
+    "_Unwind_Resume",
+    "_Unwind_DeleteException",
+    "_Unwind_GetLanguageSpecificData",
+    "_Unwind_GetRegionStart",
+    "_Unwind_GetIP",
+    "_Unwind_GetIPInfo",
+    "_Unwind_SetGR",
+    "_Unwind_SetIP",
+    "_Unwind_GetTextRelBase",
+    "_Unwind_GetDataRelBase",
+    "__cxa_allocate_exception",
+    "__cxa_free_exception",
+    "__cxa_throw",
+    "__cxa_rethrow",
+    "__cxa_begin_catch",
+    "__cxa_end_catch",
+];
+
+/// Each runtime's name, source and symbols. The exception runtime allocates exceptions
+/// on the heap, so it comes first in case that pulls in the allocator too.
+static RUNTIMES: &[(&str, &str, &[&str])] = &[
+    ("runtime/unwind.ll", UNWIND_RUNTIME_STR, UNWIND_SYMBOLS),
+    ("runtime/alloc.ll", ALLOC_RUNTIME_STR, ALLOC_SYMBOLS),
+];
 
 pub struct Datapack {
     pub functions: Vec<Function>,
     pub description: String,
+    pub memory: MemoryLayout,
+    /// The addresses used by the heap
+    pub heap: std::ops::Range<u32>,
+    /// What the optimizer did, if `BuildOptions::opt_level` was above 0
+    pub opt_report: Option<OptReport>,
 }
 
 impl Datapack {
@@ -29,12 +97,27 @@ impl Datapack {
     }
 
     pub fn from_bc(path: &Path, build_opts: &BuildOptions) -> Result<Self, String> {
-        let mut functions = compile_bc(path, build_opts)?;
+        Self::from_files(&[path], build_opts)
+    }
 
-        functions.extend(intrinsics::INTRINSICS.iter().cloned());
+    /// Links several bitcode or textual IR files into one datapack
+    pub fn from_files<P: AsRef<Path>>(paths: &[P], build_opts: &BuildOptions) -> Result<Self, String> {
+        let (mut functions, opt_report) = compile_with_report(paths, build_opts)?;
+
+        // The compiled module already has (possibly rewritten) copies of these,
+        // and saving a second copy would overwrite them
+        let missing = intrinsics::INTRINSICS
+            .iter()
+            .filter(|intr| functions.iter().all(|f| f.id != intr.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        functions.extend(missing);
         Ok(Datapack {
             functions,
             description: "Compiled datapack from Langcraft".into(),
+            memory: build_opts.memory,
+            heap: build_opts.heap_region(),
+            opt_report,
         })
     }
 
@@ -60,9 +143,16 @@ impl Datapack {
         )?;
 
         std::fs::create_dir_all(output_folder.join(Path::new("data/setup/functions/")))?;
+        let setup = self.memory
+            .build_cmds()
+            .iter()
+            .map(|cmd| cmd.to_string())
+            .chain(std::iter::once(SETUP_STR.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n");
         std::fs::write(
             output_folder.join(Path::new("data/setup/functions/setup.mcfunction")),
-            SETUP_STR,
+            setup,
         )?;
 
         std::fs::create_dir_all(output_folder.join(Path::new("data/stdout/functions/")))?;
@@ -110,13 +200,197 @@ impl Datapack {
             std::fs::write(full_path, contents.as_bytes())?
         }
 
+        std::fs::write(
+            output_folder.join("source_map.json"),
+            self.source_map().to_string(),
+        )?;
+
         Ok(())
     }
+
+    /// Maps each function with debug info to the source locations of its commands
+    pub fn source_map(&self) -> serde_json::Value {
+        let map = self.functions
+            .iter()
+            .filter(|func| !func.locs.is_empty())
+            .map(|func| {
+                let locs = func.locs
+                    .iter()
+                    .map(|(idx, loc)| (idx.to_string(), json!(loc)))
+                    .collect::<serde_json::Map<_, _>>();
+
+                (func.id.to_string(), serde_json::Value::Object(locs))
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        serde_json::Value::Object(map)
+    }
 }
 
+/// Compiles either an LLVM bitcode file or, if the extension is `.ll`, a textual IR file
 pub fn compile_bc(path: &Path, build_opts: &BuildOptions) -> Result<Vec<Function>, String> {
-    Ok(compile_ir::compile_module(
-        &llvm_ir::Module::from_bc_path(path)?,
-        build_opts,
-    ))
+    compile_files(&[path], build_opts)
+}
+
+/// Links the given bitcode or textual IR files together and compiles them as one program
+pub fn compile_files<P: AsRef<Path>>(paths: &[P], build_opts: &BuildOptions) -> Result<Vec<Function>, String> {
+    compile_with_report(paths, build_opts).map(|(functions, _)| functions)
+}
+
+fn compile_with_report<P: AsRef<Path>>(paths: &[P], build_opts: &BuildOptions) -> Result<(Vec<Function>, Option<OptReport>), String> {
+    compile_ir::compile_module(&load_modules(paths)?, build_opts).map_err(|errs| {
+        errs.iter()
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+pub fn load_modules<P: AsRef<Path>>(paths: &[P]) -> Result<llvm_ir::Module, String> {
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    if paths.is_empty() {
+        return Err("no input files were given".to_string());
+    }
+
+    // `llvm_ir` can only read a single bitcode file, so the inputs
+    // (and the runtime, if they need it) get linked into a temporary one first
+    let bc_path = std::env::temp_dir().join(format!(
+        "langcraft-{}-{}.bc",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ));
+
+    let result = link_to_bitcode(paths, &bc_path).and_then(|()| llvm_ir::Module::from_bc_path(&bc_path));
+    let _ = std::fs::remove_file(&bc_path);
+    result
+}
+
+/// Parses each of the files in `paths`, links them together (along with each runtime
+/// that has any of its symbols left undefined), and writes the resulting module out as bitcode to `bc_path`
+fn link_to_bitcode<P: AsRef<Path>>(paths: &[P], bc_path: &Path) -> Result<(), String> {
+    use llvm_sys::bit_writer::LLVMWriteBitcodeToFile;
+    use llvm_sys::core::{
+        LLVMContextCreate, LLVMContextDispose, LLVMContextSetDiagnosticHandler,
+        LLVMCreateMemoryBufferWithContentsOfFile, LLVMCreateMemoryBufferWithMemoryRangeCopy,
+        LLVMDisposeMessage, LLVMDisposeModule, LLVMGetDiagInfoDescription, LLVMGetNamedFunction,
+        LLVMIsDeclaration,
+    };
+    use llvm_sys::prelude::LLVMMemoryBufferRef;
+    use llvm_sys::ir_reader::LLVMParseIRInContext;
+    use llvm_sys::linker::LLVMLinkModules2;
+    use llvm_sys::prelude::{LLVMContextRef, LLVMDiagnosticInfoRef, LLVMModuleRef};
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_void};
+
+    unsafe fn take_message(msg: *mut c_char) -> String {
+        let result = CStr::from_ptr(msg).to_string_lossy().into_owned();
+        LLVMDisposeMessage(msg);
+        result
+    }
+
+    extern "C" fn collect_diagnostic(info: LLVMDiagnosticInfoRef, diagnostics: *mut c_void) {
+        unsafe {
+            let diagnostics = &mut *(diagnostics as *mut Vec<String>);
+            diagnostics.push(take_message(LLVMGetDiagInfoDescription(info)));
+        }
+    }
+
+    fn to_cstring(path: &Path) -> Result<CString, String> {
+        path.to_str()
+            .and_then(|p| CString::new(p).ok())
+            .ok_or_else(|| format!("invalid path {}", path.display()))
+    }
+
+    /// Reads either bitcode or textual IR
+    unsafe fn parse(context: LLVMContextRef, path: &Path) -> Result<LLVMModuleRef, String> {
+        let c_path = to_cstring(path)?;
+
+        let mut buffer = std::ptr::null_mut();
+        let mut err = std::ptr::null_mut();
+        if LLVMCreateMemoryBufferWithContentsOfFile(c_path.as_ptr(), &mut buffer, &mut err) != 0 {
+            return Err(format!("{}: {}", path.display(), take_message(err)));
+        }
+
+        parse_buffer(context, buffer, &path.display().to_string())
+    }
+
+    unsafe fn parse_buffer(context: LLVMContextRef, buffer: LLVMMemoryBufferRef, name: &str) -> Result<LLVMModuleRef, String> {
+        // This takes ownership of the buffer
+        let mut module = std::ptr::null_mut();
+        let mut err = std::ptr::null_mut();
+        if LLVMParseIRInContext(context, buffer, &mut module, &mut err) != 0 {
+            return Err(format!("{}: {}", name, take_message(err)));
+        }
+
+        Ok(module)
+    }
+
+    unsafe fn needs_runtime(module: LLVMModuleRef, symbols: &[&str]) -> bool {
+        symbols.iter().any(|symbol| {
+            let symbol = CString::new(*symbol).unwrap();
+            let func = LLVMGetNamedFunction(module, symbol.as_ptr());
+            !func.is_null() && LLVMIsDeclaration(func) != 0
+        })
+    }
+
+    let bc_path = to_cstring(bc_path)?;
+
+    unsafe {
+        let context = LLVMContextCreate();
+
+        let mut diagnostics = Vec::<String>::new();
+        LLVMContextSetDiagnosticHandler(
+            context,
+            Some(collect_diagnostic),
+            &mut diagnostics as *mut Vec<String> as *mut c_void,
+        );
+
+        let result = (|| {
+            let dest = parse(context, paths[0].as_ref())?;
+
+            for path in paths[1..].iter() {
+                let src = parse(context, path.as_ref())?;
+
+                // Duplicate definitions are reported through the diagnostic handler
+                if LLVMLinkModules2(dest, src) != 0 {
+                    return Err(format!("failed to link {}: {}", path.as_ref().display(), diagnostics.join("\n")));
+                }
+            }
+
+            for (runtime, source, symbols) in RUNTIMES.iter() {
+                if !needs_runtime(dest, symbols) {
+                    continue;
+                }
+
+                let name = CString::new(*runtime).unwrap();
+                let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(
+                    source.as_ptr() as *const c_char,
+                    source.len(),
+                    name.as_ptr(),
+                );
+                let src = parse_buffer(context, buffer, runtime)?;
+
+                if LLVMLinkModules2(dest, src) != 0 {
+                    return Err(format!("failed to link {}: {}", runtime, diagnostics.join("\n")));
+                }
+            }
+
+            inline_asm::name_inline_asm(dest);
+            wide_int::name_wide_ints(dest)?;
+
+            let failed = LLVMWriteBitcodeToFile(dest, bc_path.as_ptr()) != 0;
+            LLVMDisposeModule(dest);
+
+            if failed {
+                Err(format!("failed to write bitcode to {:?}", bc_path))
+            } else {
+                Ok(())
+            }
+        })();
+
+        LLVMContextDispose(context);
+
+        result
+    }
 }
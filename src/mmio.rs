@@ -0,0 +1,113 @@
+//! Memory-mapped I/O ports, enabled by `BuildOptions::mmio`.
+//!
+//! Loads and stores whose address is a constant inside the MMIO region never touch memory,
+//! and are compiled straight into the commands for the port they hit. This is how C code
+//! written like `*(volatile char *)0xFFFF0000 = c` ends up talking to the game.
+//! Accesses through a pointer that's only known at runtime always go to memory.
+
+use crate::cir::FuncCall as McFuncCall;
+use crate::cir::FunctionId as McFuncId;
+use crate::cir::{Command, Execute, ExecuteStoreKind, ExecuteSubCmd, ScoreHolder, TimeQuery};
+use crate::compile_ir::{assign, make_op_lit, CompileError, CompileResult, MemoryLayout, OBJECTIVE};
+use llvm_ir::{Constant, Operand};
+
+/// Where the region starts if no other address is given
+pub const DEFAULT_BASE: u32 = 0xFFFF_0000;
+
+/// The number of bytes reserved for ports, most of which are still unused
+pub const REGION_SIZE: u32 = 0x100;
+
+/// A port in the MMIO region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmioPort {
+    /// Writing a byte prints it as a character with `stdout:putc`, where a newline flushes the line
+    Stdout,
+    /// Reading a word returns the current game tick
+    Tick,
+}
+
+impl MmioPort {
+    /// The offset of the port from the start of the region
+    pub fn offset(self) -> u32 {
+        match self {
+            MmioPort::Stdout => 0x0,
+            MmioPort::Tick => 0x4,
+        }
+    }
+
+    fn at_offset(offset: u32) -> Option<Self> {
+        [MmioPort::Stdout, MmioPort::Tick].iter().copied().find(|p| p.offset() == offset)
+    }
+}
+
+/// Makes sure the region doesn't overlap memory (or wrap around the address space)
+pub fn check_region(base: u32, layout: &MemoryLayout) -> CompileResult<()> {
+    if base < layout.bytes() || base.checked_add(REGION_SIZE).is_none() {
+        Err(CompileError::layout(&format!("MMIO region at {:#X}", base)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the port that `address` refers to, if it's a constant in the region at `base`
+pub fn port_at(address: &Operand, base: Option<u32>) -> CompileResult<Option<MmioPort>> {
+    let base = match base {
+        Some(base) => base,
+        None => return Ok(None),
+    };
+
+    let address = match address {
+        Operand::ConstantOperand(c) => match &**c {
+            Constant::IntToPtr(cast) => match &*cast.operand {
+                Constant::Int { bits: 32, value } => *value as u32,
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+
+    if (base..base + REGION_SIZE).contains(&address) {
+        MmioPort::at_offset(address - base)
+            .map(Some)
+            .ok_or_else(|| CompileError::unsupported_instr(&format!("access to unmapped MMIO address {:#X}", address)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Reads from `port` into `dest`
+pub fn load(port: MmioPort, dest: &[ScoreHolder]) -> CompileResult<Vec<Command>> {
+    match (port, dest) {
+        (MmioPort::Tick, [dest]) => {
+            let mut cmd = Execute::new();
+            cmd.with_subcmd(ExecuteSubCmd::Store {
+                is_success: false,
+                kind: ExecuteStoreKind::Score {
+                    target: dest.clone().into(),
+                    objective: OBJECTIVE.to_string(),
+                },
+            });
+            cmd.with_run(TimeQuery::GameTime);
+
+            Ok(vec![cmd.into()])
+        }
+        _ => Err(CompileError::unsupported_instr(&format!("load of {} words from MMIO port {:?}", dest.len(), port))),
+    }
+}
+
+/// Writes `value` to `port`
+pub fn store(port: MmioPort, value: &[ScoreHolder]) -> CompileResult<Vec<Command>> {
+    match (port, value) {
+        (MmioPort::Stdout, [value]) => {
+            let c = ScoreHolder::new("%%temp0_putc".to_string()).unwrap();
+
+            Ok(vec![
+                assign(c.clone(), value.clone()),
+                make_op_lit(c, "%=", 256),
+                McFuncCall { id: McFuncId::new("stdout:putc") }.into(),
+            ])
+        }
+        _ => Err(CompileError::unsupported_instr(&format!("store of {} words to MMIO port {:?}", value.len(), port))),
+    }
+}
@@ -0,0 +1,527 @@
+//! Optimizations on the generated commands, enabled by `BuildOptions::opt_level`.
+//!
+//! Score holders are global and a function can be followed by almost anything,
+//! so each pass only looks at the commands of one function, in order. At the end of a function,
+//! and at anything whose effects aren't known (like a marker that the interpreter acts on
+//! or that a fixup replaces later), every score holder is assumed to be used.
+//! Calling a function only uses the holders that it (and anything it calls) mentions,
+//! unless it does something unknown too.
+//!
+//! Level 1 runs each pass once, and level 2 keeps running them until nothing changes.
+
+use crate::cir::FuncCall as McFuncCall;
+use crate::cir::Function as McFunction;
+use crate::cir::FunctionId as McFuncId;
+use crate::cir::{
+    Command, Execute, ExecuteStoreKind, ExecuteSubCmd, HolderUse, ScoreAdd, ScoreHolder, ScoreOp,
+    ScoreOpKind, ScoreSet, Target, merge_uses,
+};
+use crate::compile_ir::OBJECTIVE;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+type Pass = fn(&mut Vec<Command>, &Summaries) -> bool;
+
+/// The holders that each function might use, including through the functions it calls,
+/// for every function that doesn't do anything unknown
+type Summaries = HashMap<McFuncId, HashSet<ScoreHolder>>;
+
+/// Every pass, in the order they run in
+static PASSES: &[(&str, Pass)] = &[
+    ("constant folding", fold_constants),
+    ("copy propagation", propagate_copies),
+    ("dead store elimination", remove_dead_stores),
+];
+
+/// Level 2 gives up after this many rounds, in case the passes keep undoing each other
+const MAX_ROUNDS: usize = 8;
+
+/// The number of commands before and after optimizing, and how many each pass removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptReport {
+    pub before: usize,
+    pub after: usize,
+    pub removed: Vec<(&'static str, usize)>,
+}
+
+impl fmt::Display for OptReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Optimized {} commands down to {}", self.before, self.after)?;
+        for (pass, removed) in self.removed.iter() {
+            write!(f, "\n    {}: {} removed", pass, removed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the commands that actually run, which leaves out comments
+fn count_cmds(funcs: &[McFunction]) -> usize {
+    funcs
+        .iter()
+        .flat_map(|f| f.cmds.iter())
+        .filter(|c| !matches!(c, Command::Comment(_)))
+        .count()
+}
+
+/// Runs the passes for `level` over every function
+pub fn optimize(funcs: &mut [McFunction], level: u8) -> OptReport {
+    let before = count_cmds(funcs);
+
+    // The passes never make a function use a holder it didn't already use, so these stay correct
+    let summaries = summarize(funcs);
+
+    let mut removed = PASSES.iter().map(|(name, _)| (*name, 0)).collect::<Vec<_>>();
+
+    let rounds = match level {
+        0 => 0,
+        1 => 1,
+        _ => MAX_ROUNDS,
+    };
+
+    for _ in 0..rounds {
+        let mut changed = false;
+
+        for ((_, pass), (_, count)) in PASSES.iter().zip(removed.iter_mut()) {
+            for func in funcs.iter_mut() {
+                let len = func.cmds.len();
+                changed |= pass(&mut func.cmds, &summaries);
+                *count += len - func.cmds.len();
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    OptReport { before, after: count_cmds(funcs), removed }
+}
+
+/// What a command does to score holders
+enum Effect<'a> {
+    /// It might read or write any of them
+    Barrier,
+    /// It only touches these, and the writes always happen if they're `definite`
+    Uses { uses: HashMap<&'a ScoreHolder, HolderUse>, definite: bool },
+}
+
+/// Returns true if `target` is a single score holder in the objective everything else uses
+fn is_holder(target: &Target, objective: &str) -> bool {
+    matches!(target, Target::Uuid(_)) && objective == OBJECTIVE
+}
+
+/// Returns true if `execute` always runs its command exactly once. There's always
+/// exactly one pointer entity, so running at it doesn't make anything conditional.
+pub(crate) fn always_runs(execute: &Execute) -> bool {
+    execute.subcommands.iter().all(|subcmd| match subcmd {
+        ExecuteSubCmd::Store { .. } | ExecuteSubCmd::Positioned { .. } => true,
+        ExecuteSubCmd::At { target } | ExecuteSubCmd::As { target } => target.to_string() == "@e[tag=ptr]",
+        ExecuteSubCmd::Condition { .. } => false,
+    })
+}
+
+fn effect<'a>(cmd: &'a Command, summaries: &'a Summaries) -> Effect<'a> {
+    match cmd {
+        Command::FuncCall(McFuncCall { id }) => match summaries.get(id) {
+            Some(holders) => Effect::Uses {
+                uses: holders.iter().map(|h| (h, HolderUse::ReadWrite)).collect(),
+                definite: false,
+            },
+            None => Effect::Barrier,
+        },
+        Command::ObjAdd(_) | Command::ObjRemove(_) => Effect::Barrier,
+        // Location markers are the only ones that do nothing
+        Command::Comment(c) if c.starts_with('%') || (c.starts_with('!') && !c.starts_with("!LOC")) => Effect::Barrier,
+        Command::Execute(execute @ Execute { subcommands, run }) => {
+            let definite = always_runs(execute);
+
+            let mut uses = HashMap::new();
+            for subcmd in subcommands.iter() {
+                if let ExecuteSubCmd::Store { kind: ExecuteStoreKind::Score { target, objective }, .. } = subcmd {
+                    if !is_holder(target, objective) {
+                        return Effect::Barrier;
+                    }
+                }
+                merge_uses(&mut uses, &subcmd.holder_uses());
+            }
+
+            let mut run_definite = true;
+            if let Some(run) = run {
+                match effect(run, summaries) {
+                    Effect::Barrier => return Effect::Barrier,
+                    Effect::Uses { uses: run_uses, definite } => {
+                        merge_uses(&mut uses, &run_uses);
+                        run_definite = definite;
+                    }
+                }
+            }
+
+            Effect::Uses { uses, definite: definite && run_definite }
+        }
+        Command::ScoreSet(ScoreSet { target, target_obj, .. })
+        | Command::ScoreAdd(ScoreAdd { target, target_obj, .. })
+            if !is_holder(target, target_obj) => Effect::Barrier,
+        Command::ScoreGet(get) if !is_holder(&get.target, &get.target_obj) => Effect::Barrier,
+        Command::ScoreOp(op) if !is_holder(&op.target, &op.target_obj) || !is_holder(&op.source, &op.source_obj) => Effect::Barrier,
+        _ => Effect::Uses { uses: cmd.holder_uses(), definite: true },
+    }
+}
+
+/// Finds the functions that only use score holders in ways the passes understand.
+/// A function that calls itself (even indirectly) is left out.
+fn summarize(funcs: &[McFunction]) -> Summaries {
+    let mut summaries = Summaries::new();
+
+    // Each round adds the functions whose callees were all added in an earlier one
+    loop {
+        let mut changed = false;
+
+        for func in funcs.iter() {
+            if summaries.contains_key(&func.id) {
+                continue;
+            }
+
+            let mut holders = HashSet::new();
+            let known = func.cmds.iter().all(|cmd| match effect(cmd, &summaries) {
+                Effect::Barrier => false,
+                Effect::Uses { uses, .. } => {
+                    holders.extend(uses.keys().map(|h| (*h).clone()));
+                    true
+                }
+            });
+
+            if known {
+                summaries.insert(func.id.clone(), holders);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return summaries;
+        }
+    }
+}
+
+/// The holders that `cmd` might write to, or `None` if it might write to any of them
+fn written(cmd: &Command, summaries: &Summaries) -> Option<Vec<ScoreHolder>> {
+    match effect(cmd, summaries) {
+        Effect::Barrier => None,
+        Effect::Uses { uses, .. } => Some(
+            uses.into_iter()
+                .filter(|(_, u)| *u != HolderUse::ReadOnly)
+                .map(|(h, _)| h.clone())
+                .collect(),
+        ),
+    }
+}
+
+/// The value of `holder`, if it's known. Holders named after a number
+/// (like `%%4`) are constants that `make_op_lit` relies on.
+fn value(holder: &ScoreHolder, known: &HashMap<ScoreHolder, i32>) -> Option<i32> {
+    known
+        .get(holder)
+        .copied()
+        .or_else(|| holder.as_ref().strip_prefix("%%")?.parse().ok())
+}
+
+fn set(target: &ScoreHolder, score: i32) -> Command {
+    ScoreSet { target: target.clone().into(), target_obj: OBJECTIVE.to_string(), score }.into()
+}
+
+fn add(target: &ScoreHolder, score: i32) -> Command {
+    ScoreAdd { target: target.clone().into(), target_obj: OBJECTIVE.to_string(), score }.into()
+}
+
+fn apply(kind: ScoreOpKind, lhs: i32, rhs: i32) -> Option<i32> {
+    match kind {
+        ScoreOpKind::AddAssign => Some(lhs.wrapping_add(rhs)),
+        ScoreOpKind::SubAssign => Some(lhs.wrapping_sub(rhs)),
+        ScoreOpKind::MulAssign => Some(lhs.wrapping_mul(rhs)),
+        // Minecraft rounds down and the interpreter rounds toward zero, so these are only folded where they agree
+        ScoreOpKind::DivAssign if lhs >= 0 && rhs > 0 => Some(lhs / rhs),
+        ScoreOpKind::ModAssign if lhs >= 0 && rhs > 0 => Some(lhs % rhs),
+        ScoreOpKind::Min => Some(lhs.min(rhs)),
+        ScoreOpKind::Max => Some(lhs.max(rhs)),
+        _ => None,
+    }
+}
+
+/// Returns what `cmd` can be replaced with (`Some(None)` to remove it),
+/// or `None` if it can't be simplified
+fn fold(cmd: &Command, known: &HashMap<ScoreHolder, i32>) -> Option<Option<Command>> {
+    match cmd {
+        Command::ScoreAdd(ScoreAdd { target: Target::Uuid(target), target_obj, score }) if target_obj == OBJECTIVE => {
+            if *score == 0 {
+                Some(None)
+            } else {
+                value(target, known).map(|v| Some(set(target, v.wrapping_add(*score))))
+            }
+        }
+        Command::ScoreOp(ScoreOp { target: Target::Uuid(target), target_obj, kind, source: Target::Uuid(source), source_obj })
+            if target_obj == OBJECTIVE && source_obj == OBJECTIVE =>
+        {
+            match (*kind, value(target, known), value(source, known)) {
+                (ScoreOpKind::Assign, _, _) if target == source => Some(None),
+                (ScoreOpKind::Assign, _, Some(rhs)) => Some(Some(set(target, rhs))),
+                (kind, Some(lhs), Some(rhs)) => apply(kind, lhs, rhs).map(|v| Some(set(target, v))),
+                (ScoreOpKind::AddAssign, None, Some(0))
+                | (ScoreOpKind::SubAssign, None, Some(0))
+                | (ScoreOpKind::MulAssign, None, Some(1))
+                | (ScoreOpKind::DivAssign, None, Some(1)) => Some(None),
+                // `ScoreAdd` can't hold the negation of `i32::MIN`
+                (ScoreOpKind::AddAssign, None, Some(rhs)) if rhs != i32::MIN => Some(Some(add(target, rhs))),
+                (ScoreOpKind::SubAssign, None, Some(rhs)) if rhs != i32::MIN => Some(Some(add(target, -rhs))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Replaces operations on holders with known values by the result
+fn fold_constants(cmds: &mut Vec<Command>, summaries: &Summaries) -> bool {
+    let mut known = HashMap::<ScoreHolder, i32>::new();
+    let mut changed = false;
+
+    let mut result = Vec::with_capacity(cmds.len());
+    for cmd in cmds.drain(..) {
+        let cmd = match fold(&cmd, &known) {
+            None => cmd,
+            Some(replacement) => {
+                changed = true;
+                match replacement {
+                    Some(replacement) => replacement,
+                    None => continue,
+                }
+            }
+        };
+
+        match written(&cmd, summaries) {
+            None => known.clear(),
+            Some(holders) => {
+                for holder in holders.iter() {
+                    known.remove(holder);
+                }
+            }
+        }
+
+        if let Command::ScoreSet(ScoreSet { target: Target::Uuid(target), target_obj, score }) = &cmd {
+            if target_obj == OBJECTIVE {
+                known.insert(target.clone(), *score);
+            }
+        }
+
+        result.push(cmd);
+    }
+
+    *cmds = result;
+    changed
+}
+
+/// Reads from the copy made by `a = b` read from `b` instead, as long as neither has changed,
+/// and removes assignments of a holder to itself
+fn propagate_copies(cmds: &mut Vec<Command>, summaries: &Summaries) -> bool {
+    // Each copy, and where it came from
+    let mut copies = HashMap::<ScoreHolder, ScoreHolder>::new();
+    let mut changed = false;
+
+    let mut result = Vec::with_capacity(cmds.len());
+    for mut cmd in cmds.drain(..) {
+        if let Command::ScoreOp(op) = &mut cmd {
+            if op.kind != ScoreOpKind::Swap && op.source_obj == OBJECTIVE {
+                if let Target::Uuid(source) = &op.source {
+                    if let Some(original) = copies.get(source) {
+                        op.source = original.clone().into();
+                        changed = true;
+                    }
+                }
+            }
+
+            if op.kind == ScoreOpKind::Assign && op.target == op.source && op.target_obj == op.source_obj {
+                changed = true;
+                continue;
+            }
+        }
+
+        match written(&cmd, summaries) {
+            None => copies.clear(),
+            Some(holders) => {
+                for holder in holders.iter() {
+                    copies.remove(holder);
+                    copies.retain(|_, original| original != holder);
+                }
+            }
+        }
+
+        if let Command::ScoreOp(ScoreOp { target: Target::Uuid(target), target_obj, kind: ScoreOpKind::Assign, source: Target::Uuid(source), source_obj }) = &cmd {
+            if target_obj == OBJECTIVE && source_obj == OBJECTIVE {
+                copies.insert(target.clone(), source.clone());
+            }
+        }
+
+        result.push(cmd);
+    }
+
+    *cmds = result;
+    changed
+}
+
+/// The holder that `cmd` computes a new value for, if that's all it does
+fn store_target(cmd: &Command) -> Option<&ScoreHolder> {
+    match cmd {
+        Command::ScoreSet(ScoreSet { target: Target::Uuid(target), .. })
+        | Command::ScoreAdd(ScoreAdd { target: Target::Uuid(target), .. }) => Some(target),
+        Command::ScoreOp(ScoreOp { target: Target::Uuid(target), kind, .. }) if *kind != ScoreOpKind::Swap => Some(target),
+        _ => None,
+    }
+}
+
+/// Removes writes to holders that are always overwritten before they're read
+fn remove_dead_stores(cmds: &mut Vec<Command>, summaries: &Summaries) -> bool {
+    // Holders whose current value will never be read
+    let mut dead = HashSet::<ScoreHolder>::new();
+    let mut keep = vec![true; cmds.len()];
+
+    for (idx, cmd) in cmds.iter().enumerate().rev() {
+        let (uses, definite) = match effect(cmd, summaries) {
+            Effect::Barrier => {
+                dead.clear();
+                continue;
+            }
+            Effect::Uses { uses, definite } => (uses, definite),
+        };
+
+        if store_target(cmd).is_some_and(|target| dead.contains(target)) {
+            keep[idx] = false;
+            continue;
+        }
+
+        for (holder, holder_use) in uses.iter() {
+            match holder_use {
+                HolderUse::WriteOnly if definite => {
+                    dead.insert((*holder).clone());
+                }
+                // A write that might not happen doesn't make the old value any more or less dead
+                HolderUse::WriteOnly => {}
+                HolderUse::ReadOnly | HolderUse::ReadWrite => {
+                    dead.remove(*holder);
+                }
+            }
+        }
+    }
+
+    let changed = keep.contains(&false);
+    let mut keep = keep.into_iter();
+    cmds.retain(|_| keep.next().unwrap());
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(cmds: &[&str]) -> Vec<Command> {
+        cmds.iter().map(|c| c.parse().unwrap()).collect()
+    }
+
+    fn run(pass: Pass, cmds: &[&str]) -> Vec<String> {
+        let mut cmds = parse(cmds);
+        pass(&mut cmds, &Summaries::new());
+        cmds.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn folds_constants() {
+        let cmds = run(fold_constants, &[
+            "scoreboard players set %x rust 6",
+            "scoreboard players operation %x rust *= %%4 rust",
+            "scoreboard players add %x rust 1",
+            "scoreboard players operation %y rust += %%2 rust",
+            "scoreboard players operation %y rust *= %%1 rust",
+            "function rust:foo",
+            "scoreboard players add %x rust 1",
+        ]);
+
+        assert_eq!(cmds, vec![
+            "scoreboard players set %x rust 6",
+            "scoreboard players set %x rust 24",
+            "scoreboard players set %x rust 25",
+            "scoreboard players add %y rust 2",
+            "function rust:foo",
+            "scoreboard players add %x rust 1",
+        ]);
+    }
+
+    #[test]
+    fn propagates_copies() {
+        let cmds = run(propagate_copies, &[
+            "scoreboard players operation %a rust = %b rust",
+            "scoreboard players operation %c rust += %a rust",
+            "scoreboard players operation %b rust = %a rust",
+            "scoreboard players set %b rust 1",
+            "scoreboard players operation %d rust = %a rust",
+        ]);
+
+        assert_eq!(cmds, vec![
+            "scoreboard players operation %a rust = %b rust",
+            "scoreboard players operation %c rust += %b rust",
+            "scoreboard players set %b rust 1",
+            "scoreboard players operation %d rust = %a rust",
+        ]);
+    }
+
+    #[test]
+    fn removes_dead_stores() {
+        let cmds = run(remove_dead_stores, &[
+            "scoreboard players set %a rust 1",
+            "scoreboard players add %a rust 1",
+            "scoreboard players set %b rust 1",
+            "execute if score %b rust matches 1..2 run scoreboard players set %a rust 2",
+            "scoreboard players set %a rust 3",
+            "scoreboard players set %c rust 1",
+            "function rust:foo",
+            "scoreboard players set %c rust 2",
+        ]);
+
+        assert_eq!(cmds, vec![
+            "scoreboard players set %b rust 1",
+            "execute if score %b rust matches 1..2 run scoreboard players set %a rust 2",
+            "scoreboard players set %a rust 3",
+            "scoreboard players set %c rust 1",
+            "function rust:foo",
+            "scoreboard players set %c rust 2",
+        ]);
+    }
+
+    #[test]
+    fn calls_to_known_functions() {
+        let funcs = vec![
+            McFunction::new(McFuncId::new("rust:leaf"), parse(&["scoreboard players operation %b rust += %%1 rust"])),
+            McFunction::new(McFuncId::new("rust:middle"), parse(&["function rust:leaf"])),
+            McFunction::new(McFuncId::new("rust:unknown"), parse(&["# !INTERPRETER: TODO"])),
+        ];
+        let summaries = summarize(&funcs);
+        assert!(summaries.contains_key(&McFuncId::new("rust:middle")));
+        assert!(!summaries.contains_key(&McFuncId::new("rust:unknown")));
+
+        let mut cmds = parse(&[
+            "scoreboard players set %a rust 1",
+            "scoreboard players set %b rust 1",
+            "function rust:middle",
+            "scoreboard players set %a rust 2",
+            "scoreboard players set %b rust 2",
+            "function rust:unknown",
+            "scoreboard players set %b rust 3",
+        ]);
+        remove_dead_stores(&mut cmds, &summaries);
+
+        assert_eq!(cmds.iter().map(|c| c.to_string()).collect::<Vec<_>>(), vec![
+            "scoreboard players set %b rust 1",
+            "function rust:middle",
+            "scoreboard players set %a rust 2",
+            "scoreboard players set %b rust 2",
+            "function rust:unknown",
+            "scoreboard players set %b rust 3",
+        ]);
+    }
+}
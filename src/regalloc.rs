@@ -0,0 +1,553 @@
+//! Register allocation, over the abstract blocks of one function at a time.
+//!
+//! Score holders are global, so whatever a function still needs after a call has to be kept
+//! somewhere the callee won't touch. Each call pushes the holders that are live once it returns
+//! and pops them again afterwards, and the callee only saves the frame pointer.
+//!
+//! Every LLVM value (and every temporary) starts out in a holder of its own. Those are then
+//! packed into a pool of registers (`%%reg0`, `%%reg1`, ...) shared by every function,
+//! where two of them only get the same register if neither is ever written while the other is live.
+//! Liveness is worked out from the commands themselves, except that a `phi` only counts as
+//! reading its incoming value along the edge that value comes from.
+
+use crate::analysis::{AbstractBlock, BlockEdge, BlockEnd};
+use crate::cir::FunctionId as McFuncId;
+use crate::cir::{
+    Command, Execute, ExecuteCondKind, ExecuteCondition, ExecuteSubCmd, HolderUse, McRange,
+    ScoreHolder, ScoreOp, ScoreOpKind, ScoreSet, Target,
+};
+use crate::compile_ir::{
+    assign, check_stack, compile_terminator, is_saved, load_params, mark_assertion, pop, push,
+    temp_fn_ptr, CompileResult, GlobalVarList,
+};
+use crate::optimize::always_runs;
+use either::Either;
+use llvm_ir::types::Types;
+use llvm_ir::{Function, Terminator};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
+
+/// The register that each holder of a function was given
+pub(crate) type Registers = HashMap<ScoreHolder, ScoreHolder>;
+
+static SAVE_MARKER: &str = "!SAVEREGS";
+static RESTORE_MARKER: &str = "!LOADREGS ";
+static ASSERT_MARKER: &str = "!INTERPRETER: ASSERT ";
+
+/// Marks where a call starts, which is where the holders it has to preserve get pushed
+pub(crate) fn save_marker() -> Command {
+    Command::Comment(SAVE_MARKER.to_string())
+}
+
+/// Marks where the holders pushed for the call at the end of `call` get popped again,
+/// after its results have been copied out
+pub(crate) fn restore_marker(call: &McFuncId) -> Command {
+    Command::Comment(format!("{}{}", RESTORE_MARKER, call))
+}
+
+fn register(idx: usize) -> ScoreHolder {
+    ScoreHolder::new(format!("%%reg{}", idx)).unwrap()
+}
+
+/// Numbers the holders of a function, so the sets below stay cheap
+#[derive(Default)]
+struct Holders {
+    ids: HashMap<ScoreHolder, usize>,
+    names: Vec<ScoreHolder>,
+}
+
+impl Holders {
+    fn id(&mut self, holder: &ScoreHolder) -> usize {
+        if let Some(id) = self.ids.get(holder) {
+            return *id;
+        }
+
+        self.names.push(holder.clone());
+        self.ids.insert(holder.clone(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+}
+
+/// What a single command does to holders
+struct Step {
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+    /// False if the writes might not happen
+    definite: bool,
+    /// For a `phi`, the index of the block that the value it reads comes from
+    phi_edge: Option<i32>,
+    /// For `a = b`, the `b`, which can share a register with `a`
+    copy_of: Option<usize>,
+}
+
+/// Splits an assertion marker into its condition, and whether it's an `unless`
+fn parse_assertion(comment: &str) -> Option<(bool, ExecuteCondition)> {
+    let cond = comment.strip_prefix(ASSERT_MARKER)?;
+    let (is_unless, cond) = match cond.strip_prefix("unless ") {
+        Some(cond) => (true, cond),
+        None => (false, cond.strip_prefix("if ")?),
+    };
+
+    Some((is_unless, ExecuteCondition::from_str(cond).ok()?))
+}
+
+/// Returns true if every write in `cmd` always happens
+fn always_writes(cmd: &Command) -> bool {
+    match cmd {
+        Command::Execute(execute) => always_runs(execute) && execute.run.as_deref().is_none_or(always_writes),
+        _ => true,
+    }
+}
+
+/// If `cmd` is part of a `phi`, returns the index of the block it takes its value from
+fn phi_edge(cmd: &Command) -> Option<i32> {
+    let (subcommands, run) = match cmd {
+        Command::Execute(Execute { subcommands, run: Some(run) }) => (subcommands, run),
+        _ => return None,
+    };
+
+    match (&subcommands[..], &**run) {
+        (
+            [ExecuteSubCmd::Condition {
+                is_unless: false,
+                cond: ExecuteCondition::Score {
+                    target: Target::Uuid(target),
+                    kind: ExecuteCondKind::Matches(McRange::Between(range)),
+                    ..
+                },
+            }],
+            Command::ScoreOp(ScoreOp { kind: ScoreOpKind::Assign, .. }),
+        ) if target.as_ref() == "%phi" && range.start() == range.end() => Some(*range.start()),
+        _ => None,
+    }
+}
+
+fn step(cmd: &Command, holders: &mut Holders) -> Step {
+    let uses = match cmd {
+        Command::Comment(c) => match parse_assertion(c) {
+            Some((is_unless, cond)) => ExecuteSubCmd::Condition { is_unless, cond }
+                .holder_uses()
+                .into_iter()
+                .map(|(h, u)| (h.clone(), u))
+                .collect(),
+            None => Vec::new(),
+        },
+        _ => cmd.holder_uses().into_iter().map(|(h, u)| (h.clone(), u)).collect::<Vec<_>>(),
+    };
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    for (holder, holder_use) in uses {
+        let id = holders.id(&holder);
+        if holder_use != HolderUse::WriteOnly {
+            reads.push(id);
+        }
+        if holder_use != HolderUse::ReadOnly {
+            writes.push(id);
+        }
+    }
+
+    let copy_of = match cmd {
+        Command::ScoreOp(ScoreOp { target: Target::Uuid(_), kind: ScoreOpKind::Assign, source: Target::Uuid(source), .. }) => {
+            Some(holders.id(source))
+        }
+        _ => None,
+    };
+
+    Step { reads, writes, definite: always_writes(cmd), phi_edge: phi_edge(cmd), copy_of }
+}
+
+/// A block, as far as liveness is concerned
+struct Node {
+    steps: Vec<Step>,
+    succs: Vec<usize>,
+    /// The index of the LLVM block this one ends, which picks what each `phi` in a successor reads
+    phi: Option<i32>,
+}
+
+/// The blocks that run after `block` returns from the call at its end
+fn call_succs(block: &AbstractBlock, blocks: &[AbstractBlock], index: &HashMap<McFuncId, usize>) -> Vec<usize> {
+    let mut next = block.body.id.clone();
+    next.sub += 1;
+
+    let next = match index.get(&next) {
+        Some(next) => *next,
+        None => return Vec::new(),
+    };
+
+    let mut succs = vec![next];
+
+    // If the callee unwinds, the call returns to wherever the `!UNWIND` marker says instead
+    for cmd in blocks[next].body.cmds.iter() {
+        if let Command::Comment(c) = cmd {
+            if let Some(target) = c.strip_prefix("!UNWIND ") {
+                succs.extend(target.parse::<McFuncId>().ok().and_then(|t| index.get(&t)));
+            }
+        }
+    }
+
+    succs
+}
+
+fn build_nodes(
+    blocks: &[AbstractBlock],
+    index: &HashMap<McFuncId, usize>,
+    holders: &mut Holders,
+    globals: &GlobalVarList,
+    tys: &Types,
+) -> CompileResult<Vec<Node>> {
+    let mut nodes = Vec::new();
+
+    for block in blocks.iter() {
+        let mut cmds = Vec::new();
+        if block.needs_prolog {
+            cmds.extend(load_params(&block.parent, tys)?);
+        }
+        cmds.extend(block.body.cmds.iter().cloned());
+
+        // The conditions of a branch are read by the commands that pick the next block
+        let mut edge_reads = Vec::new();
+
+        let succs = match block.term.as_ref() {
+            // An error here gets reported when the block is reified
+            Some(BlockEnd::Normal(term)) => match compile_terminator(&block.parent, term, globals, tys) {
+                Ok((tail, dests)) => {
+                    cmds.extend(tail);
+                    match dests {
+                        Either::Left(dests) => {
+                            for (edge, _) in dests.iter() {
+                                match edge {
+                                    BlockEdge::None => {}
+                                    BlockEdge::Cond { value, .. } | BlockEdge::SwitchCond { value, .. } | BlockEdge::SwitchDefault { value, .. } => {
+                                        edge_reads.push(holders.id(value))
+                                    }
+                                }
+                            }
+                            dests.iter().filter_map(|(_, d)| index.get(d).copied()).collect()
+                        }
+                        Either::Right(_) => Vec::new(),
+                    }
+                }
+                Err(_) => Vec::new(),
+            },
+            Some(BlockEnd::DynCall(func_ptr)) => {
+                cmds.push(assign(temp_fn_ptr(), func_ptr.clone()));
+                call_succs(block, blocks, index)
+            }
+            Some(BlockEnd::StaticCall(_)) => call_succs(block, blocks, index),
+            None => Vec::new(),
+        };
+
+        let phi = block.body.cmds.iter().rev().find_map(|cmd| match cmd {
+            Command::ScoreSet(ScoreSet { target: Target::Uuid(target), score, .. }) if target.as_ref() == "%phi" => Some(*score),
+            _ => None,
+        });
+
+        let mut steps = cmds.iter().map(|cmd| step(cmd, holders)).collect::<Vec<_>>();
+        steps.push(Step { reads: edge_reads, writes: Vec::new(), definite: true, phi_edge: None, copy_of: None });
+
+        nodes.push(Node {
+            steps,
+            succs,
+            phi,
+        });
+    }
+
+    Ok(nodes)
+}
+
+fn apply(step: &Step, live: &mut HashSet<usize>) {
+    if step.definite {
+        for write in step.writes.iter() {
+            live.remove(write);
+        }
+    }
+    live.extend(step.reads.iter().copied());
+}
+
+/// Walks backwards through `steps` from what's live after them. Coming from
+/// the block with index `edge`, a `phi` that reads from some other block never runs.
+fn live_before(steps: &[Step], mut live: HashSet<usize>, edge: Option<i32>) -> HashSet<usize> {
+    for step in steps.iter().rev() {
+        if edge.is_some() && step.phi_edge.is_some() && step.phi_edge != edge {
+            continue;
+        }
+        apply(step, &mut live);
+    }
+    live
+}
+
+/// The holders live at the end of each node
+fn live_out(nodes: &[Node]) -> Vec<HashSet<usize>> {
+    let mut out = vec![HashSet::new(); nodes.len()];
+
+    loop {
+        let mut changed = false;
+
+        for idx in (0..nodes.len()).rev() {
+            let mut live = HashSet::new();
+            for &succ in nodes[idx].succs.iter() {
+                live.extend(live_before(&nodes[succ].steps, out[succ].clone(), nodes[idx].phi));
+            }
+
+            if live != out[idx] {
+                out[idx] = live;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return out;
+        }
+    }
+}
+
+/// The prefixes of the holders that LLVM values of `func` get
+pub(crate) fn local_prefixes(func: &Function) -> HashSet<String> {
+    let names = func
+        .parameters
+        .iter()
+        .map(|p| &p.name)
+        .chain(func.basic_blocks.iter().flat_map(|b| b.instrs.iter()).filter_map(|i| i.try_get_result()))
+        .chain(func.basic_blocks.iter().filter_map(|b| match &b.term {
+            Terminator::Invoke(invoke) => Some(&invoke.result),
+            _ => None,
+        }));
+
+    names
+        .map(|name| {
+            let holder = ScoreHolder::from_local_name(name.clone(), 4).remove(0);
+            holder.as_ref().strip_suffix("%0").unwrap().to_string()
+        })
+        .collect()
+}
+
+/// Returns true if `holder` belongs to a single function, so it can go in a register
+pub(crate) fn is_local(holder: &ScoreHolder, prefixes: &HashSet<String>) -> bool {
+    holder.as_ref().starts_with("%temp")
+        || holder
+            .as_ref()
+            .rsplit_once('%')
+            .is_some_and(|(prefix, word)| prefixes.contains(prefix) && word.parse::<usize>().is_ok())
+}
+
+/// Replaces the markers left by `compile_function` around each call with the commands that
+/// save and restore what's needed afterwards, and returns the registers for the holders of the function
+pub(crate) fn allocate(blocks: &mut [AbstractBlock], globals: &GlobalVarList, tys: &Types, stack_limit: Option<u32>) -> CompileResult<Registers> {
+    let index = blocks.iter().enumerate().map(|(idx, b)| (b.body.id.clone(), idx)).collect::<HashMap<_, _>>();
+    let mut holders = Holders::default();
+
+    // Step 1: Find what each call has to save
+    let nodes = build_nodes(blocks, &index, &mut holders, globals, tys)?;
+    let out = live_out(&nodes);
+
+    // Holders that are never written are constants, or belong to the calling convention
+    let written = nodes.iter().flat_map(|n| n.steps.iter()).flat_map(|s| s.writes.iter().copied()).collect::<HashSet<_>>();
+
+    let mut saved = HashMap::new();
+    for (block, live) in blocks.iter().zip(out.iter()) {
+        if matches!(block.term, Some(BlockEnd::StaticCall(_)) | Some(BlockEnd::DynCall(_))) {
+            let regs = live
+                .iter()
+                .filter(|h| written.contains(h))
+                .map(|h| holders.names[*h].clone())
+                .filter(is_saved)
+                .collect::<BTreeSet<_>>();
+            saved.insert(block.body.id.clone(), regs);
+        }
+    }
+
+    // Step 2: Save and restore them
+    for block in blocks.iter_mut() {
+        let mut cmds = Vec::with_capacity(block.body.cmds.len());
+        for cmd in block.body.cmds.drain(..) {
+            match &cmd {
+                Command::Comment(c) if c == SAVE_MARKER => {
+                    let regs = &saved[&block.body.id];
+                    if let (Some(limit), false) = (stack_limit, regs.is_empty()) {
+                        cmds.extend(check_stack(&block.parent.name, limit as i32 - 4 * regs.len() as i32));
+                    }
+                    cmds.extend(regs.iter().cloned().flat_map(push));
+                }
+                Command::Comment(c) if c.starts_with(RESTORE_MARKER) => {
+                    let call = c[RESTORE_MARKER.len()..].parse::<McFuncId>().unwrap();
+                    cmds.extend(saved[&call].iter().rev().cloned().flat_map(pop));
+                }
+                _ => cmds.push(cmd),
+            }
+        }
+        block.body.cmds = cmds;
+    }
+
+    // Step 3: Find which holders can't share a register, now that the values saved
+    // across a call aren't live during it
+    let nodes = build_nodes(blocks, &index, &mut holders, globals, tys)?;
+    let out = live_out(&nodes);
+
+    let prefixes = local_prefixes(&blocks[0].parent);
+    let written = nodes.iter().flat_map(|n| n.steps.iter()).flat_map(|s| s.writes.iter().copied()).collect::<HashSet<_>>();
+    let local = (0..holders.names.len())
+        .map(|h| written.contains(&h) && is_local(&holders.names[h], &prefixes))
+        .collect::<Vec<_>>();
+
+    let mut interference = vec![HashSet::new(); holders.names.len()];
+    let mut add_edge = |a: usize, b: usize| {
+        if a != b && local[a] && local[b] {
+            interference[a].insert(b);
+            interference[b].insert(a);
+        }
+    };
+
+    for ((block, node), live) in blocks.iter().zip(nodes.iter()).zip(out) {
+        let mut live = live;
+        for step in node.steps.iter().rev() {
+            for &write in step.writes.iter() {
+                for &other in live.iter() {
+                    if step.copy_of != Some(other) {
+                        add_edge(write, other);
+                    }
+                }
+            }
+            apply(step, &mut live);
+        }
+
+        // Anything still live at the very start has no value yet, so it can't share with anything there either
+        if block.needs_prolog {
+            for &a in live.iter() {
+                for &b in live.iter() {
+                    add_edge(a, b);
+                }
+            }
+        }
+    }
+
+    // Step 4: Give each holder the first register its neighbors don't have,
+    // preferring the one of a holder it's copied to or from
+    let mut hints = vec![Vec::new(); holders.names.len()];
+    let mut order = Vec::new();
+    let mut seen = HashSet::new();
+    for step in nodes.iter().flat_map(|n| n.steps.iter()) {
+        if let (Some(source), [target]) = (step.copy_of, &step.writes[..]) {
+            hints[*target].push(source);
+            hints[source].push(*target);
+        }
+
+        for &h in step.writes.iter().chain(step.reads.iter()) {
+            if local[h] && seen.insert(h) {
+                order.push(h);
+            }
+        }
+    }
+
+    let mut colors = HashMap::<usize, usize>::new();
+    for h in order {
+        let taken = interference[h].iter().filter_map(|n| colors.get(n)).copied().collect::<HashSet<_>>();
+        let color = hints[h]
+            .iter()
+            .filter_map(|n| colors.get(n))
+            .copied()
+            .find(|c| !taken.contains(c))
+            .unwrap_or_else(|| (0..).find(|c| !taken.contains(c)).unwrap());
+        colors.insert(h, color);
+    }
+
+    Ok(colors.into_iter().map(|(h, color)| (holders.names[h].clone(), register(color))).collect())
+}
+
+/// Replaces each holder in `cmds` with its register
+pub(crate) fn rename(cmds: &mut [Command], registers: &Registers) {
+    let rename_one = |holder: &mut ScoreHolder| {
+        if let Some(register) = registers.get(holder) {
+            *holder = register.clone();
+        }
+    };
+
+    for cmd in cmds.iter_mut() {
+        if let Command::Comment(c) = cmd {
+            if let Some((is_unless, mut cond)) = parse_assertion(c) {
+                cond.holders_mut().into_iter().for_each(rename_one);
+                *cmd = mark_assertion(is_unless, &cond);
+            }
+        } else {
+            cmd.holders_mut().into_iter().for_each(rename_one);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile_ir::{compile_function, read_ptr, write_ptr};
+    use crate::BuildOptions;
+    use llvm_ir::{Module, Name};
+
+    fn steps(cmds: &[&str], holders: &mut Holders) -> Vec<Step> {
+        cmds.iter().map(|c| step(&c.parse().unwrap(), holders)).collect()
+    }
+
+    #[test]
+    fn phi_reads_along_edge() {
+        let mut holders = Holders::default();
+        let steps = steps(
+            &[
+                "execute if score %phi rust matches 1..1 run scoreboard players operation %x%0 rust = %a%0 rust",
+                "execute if score %phi rust matches 2..2 run scoreboard players operation %x%0 rust = %b%0 rust",
+                "scoreboard players operation %y%0 rust = %x%0 rust",
+            ],
+            &mut holders,
+        );
+        let names = |live: HashSet<usize>| {
+            let mut names = live.into_iter().map(|h| holders.names[h].to_string()).collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(live_before(&steps, HashSet::new(), Some(1))), vec!["%a%0", "%phi", "%x%0"]);
+        assert_eq!(names(live_before(&steps, HashSet::new(), None)), vec!["%a%0", "%b%0", "%phi", "%x%0"]);
+    }
+
+    #[test]
+    fn assertions_are_renamed() {
+        let mut registers = Registers::new();
+        registers.insert(ScoreHolder::new("%x%0".into()).unwrap(), register(3));
+
+        let mut cmds = vec![
+            mark_assertion(true, &ExecuteCondition::from_str("score %x%0 rust matches ..-1").unwrap()),
+            "scoreboard players operation %x%0 rust += %y%0 rust".parse().unwrap(),
+        ];
+        rename(&mut cmds, &registers);
+
+        assert_eq!(cmds[0].to_string(), "# !INTERPRETER: ASSERT unless score %%reg3 rust matches ..-1");
+        assert_eq!(cmds[1].to_string(), "scoreboard players operation %%reg3 rust += %y%0 rust");
+    }
+
+    #[test]
+    fn only_live_values_are_saved() {
+        let module = Module::from_bc_path("tests/regalloc.bc").unwrap();
+        let names = module.functions.iter().map(|f| Name::from(f.name.as_str())).collect::<Vec<_>>();
+        let globals = names.iter().map(|n| (n, (u32::MAX, None))).collect::<GlobalVarList>();
+        let main = module.get_func_by_name("main").unwrap();
+
+        let mut blocks = compile_function(main, &globals, &module.types, &BuildOptions::default(), &HashMap::new(), false).unwrap();
+        let registers = allocate(&mut blocks, &globals, &module.types, None).unwrap();
+
+        // The holders each block pushes and pops, leaving out the frame pointer
+        let moved = |block: &AbstractBlock, make: fn(ScoreHolder) -> Command| {
+            block.body.cmds.iter()
+                .filter_map(|c| c.holder_uses().into_keys().find(|h| is_saved(h) && *c == make((*h).clone())).map(|h| h.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        // `%b` and `%e` are only passed to `twice`, so `%a` and `%c` are all either call has to keep
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(moved(&blocks[0], write_ptr), vec!["a%0", "c%0"]);
+        assert_eq!(moved(&blocks[1], read_ptr), vec!["c%0", "a%0"]);
+        assert_eq!(moved(&blocks[1], write_ptr), vec!["a%0", "c%0"]);
+        assert_eq!(moved(&blocks[2], read_ptr), vec!["c%0", "a%0"]);
+        assert_eq!(moved(&blocks[2], write_ptr), Vec::<String>::new());
+
+        let locals = main.basic_blocks.iter().flat_map(|b| b.instrs.iter()).filter(|i| i.try_get_result().is_some()).count();
+        let used = registers.values().collect::<HashSet<_>>().len();
+        assert!(used < locals, "{} registers for {} locals", used, locals);
+    }
+}
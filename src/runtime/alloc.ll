@@ -0,0 +1,293 @@
+; The heap allocator, linked into any program that uses `malloc` and friends without defining them.
+;
+; The heap is the region from `__langcraft_heap_start` to `__langcraft_heap_end`,
+; which the compiler reserves at the top of memory. It's a list of blocks laid out
+; back to back, each starting with an 8 byte header: the size of the whole block
+; in bytes (a multiple of 8), and then FREE (0x66726565) or USED (0x75736564).
+; The interpreter walks the same list to report heap usage and leaks.
+;
+; Freeing only marks the block, and neighbouring free blocks are merged while `malloc` searches.
+; Everything is defined `weak`, so a program can still bring its own versions.
+
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@__langcraft_heap_start = external global i8
+@__langcraft_heap_end = external global i8
+
+@heap_ready = internal global i32 0
+
+; Turns the whole heap into one free block the first time it's used
+define internal void @heap_init() {
+entry:
+  %ready = load i32, i32* @heap_ready
+  %is_ready = icmp ne i32 %ready, 0
+  br i1 %is_ready, label %done, label %init
+
+init:
+  %start = ptrtoint i8* @__langcraft_heap_start to i32
+  %end = ptrtoint i8* @__langcraft_heap_end to i32
+  %size = sub i32 %end, %start
+  %hdr = inttoptr i32 %start to i32*
+  store i32 %size, i32* %hdr
+  %state = getelementptr i32, i32* %hdr, i32 1
+  store i32 1718773093, i32* %state
+  store i32 1, i32* @heap_ready
+  br label %done
+
+done:
+  ret void
+}
+
+define weak i8* @malloc(i32 %n) {
+entry:
+  call void @heap_init()
+  %n7 = add i32 %n, 7
+  %rounded = and i32 %n7, -8
+  %needed = add i32 %rounded, 8
+  ; Sizes close to 4 GiB wrap around, either when rounding or when adding the header
+  %round_overflow = icmp ult i32 %rounded, %n
+  %header_overflow = icmp ult i32 %needed, %rounded
+  %overflow = or i1 %round_overflow, %header_overflow
+  %start = ptrtoint i8* @__langcraft_heap_start to i32
+  %end = ptrtoint i8* @__langcraft_heap_end to i32
+  br i1 %overflow, label %fail, label %loop
+
+loop:
+  %block = phi i32 [ %start, %entry ], [ %next, %advance ]
+  %at_end = icmp uge i32 %block, %end
+  br i1 %at_end, label %fail, label %check
+
+check:
+  %hdr = inttoptr i32 %block to i32*
+  %size = load i32, i32* %hdr
+  %state_ptr = getelementptr i32, i32* %hdr, i32 1
+  %state = load i32, i32* %state_ptr
+  %is_free = icmp eq i32 %state, 1718773093
+  br i1 %is_free, label %coalesce, label %advance
+
+; Merges any free blocks right after this one into it
+coalesce:
+  %merged_size = phi i32 [ %size, %check ], [ %merged, %merge ]
+  %following = add i32 %block, %merged_size
+  %has_following = icmp ult i32 %following, %end
+  br i1 %has_following, label %check_following, label %fit
+
+check_following:
+  %following_hdr = inttoptr i32 %following to i32*
+  %following_state_ptr = getelementptr i32, i32* %following_hdr, i32 1
+  %following_state = load i32, i32* %following_state_ptr
+  %following_free = icmp eq i32 %following_state, 1718773093
+  br i1 %following_free, label %merge, label %fit
+
+merge:
+  %following_size = load i32, i32* %following_hdr
+  %merged = add i32 %merged_size, %following_size
+  store i32 %merged, i32* %hdr
+  br label %coalesce
+
+fit:
+  %big_enough = icmp uge i32 %merged_size, %needed
+  br i1 %big_enough, label %take, label %advance
+
+advance:
+  %skip = phi i32 [ %size, %check ], [ %merged_size, %fit ]
+  %next = add i32 %block, %skip
+  br label %loop
+
+take:
+  %rest = sub i32 %merged_size, %needed
+  ; The rest only becomes its own block if it has room for more than a header
+  %split = icmp uge i32 %rest, 16
+  br i1 %split, label %do_split, label %mark
+
+do_split:
+  store i32 %needed, i32* %hdr
+  %rest_block = add i32 %block, %needed
+  %rest_hdr = inttoptr i32 %rest_block to i32*
+  store i32 %rest, i32* %rest_hdr
+  %rest_state = getelementptr i32, i32* %rest_hdr, i32 1
+  store i32 1718773093, i32* %rest_state
+  br label %mark
+
+mark:
+  store i32 1970496868, i32* %state_ptr
+  %payload = add i32 %block, 8
+  %result = inttoptr i32 %payload to i8*
+  ret i8* %result
+
+fail:
+  ret i8* null
+}
+
+define weak void @free(i8* %p) {
+entry:
+  %is_null = icmp eq i8* %p, null
+  br i1 %is_null, label %done, label %release
+
+release:
+  %addr = ptrtoint i8* %p to i32
+  %block = sub i32 %addr, 8
+  %hdr = inttoptr i32 %block to i32*
+  %state = getelementptr i32, i32* %hdr, i32 1
+  store i32 1718773093, i32* %state
+  br label %done
+
+done:
+  ret void
+}
+
+; The number of bytes that can be used in the allocation at `p`
+define internal i32 @usable_size(i8* %p) {
+entry:
+  %addr = ptrtoint i8* %p to i32
+  %block = sub i32 %addr, 8
+  %hdr = inttoptr i32 %block to i32*
+  %size = load i32, i32* %hdr
+  %usable = sub i32 %size, 8
+  ret i32 %usable
+}
+
+; Copies `n` bytes (a multiple of 4) between word-aligned pointers
+define internal void @copy_words(i8* %dest, i8* %src, i32 %n) {
+entry:
+  %dest_addr = ptrtoint i8* %dest to i32
+  %src_addr = ptrtoint i8* %src to i32
+  br label %loop
+
+loop:
+  %offset = phi i32 [ 0, %entry ], [ %next, %body ]
+  %done = icmp uge i32 %offset, %n
+  br i1 %done, label %exit, label %body
+
+body:
+  %from_addr = add i32 %src_addr, %offset
+  %from = inttoptr i32 %from_addr to i32*
+  %word = load i32, i32* %from
+  %to_addr = add i32 %dest_addr, %offset
+  %to = inttoptr i32 %to_addr to i32*
+  store i32 %word, i32* %to
+  %next = add i32 %offset, 4
+  br label %loop
+
+exit:
+  ret void
+}
+
+define weak i8* @realloc(i8* %p, i32 %n) {
+entry:
+  %is_null = icmp eq i8* %p, null
+  br i1 %is_null, label %fresh, label %resize
+
+fresh:
+  %fresh_result = call i8* @malloc(i32 %n)
+  ret i8* %fresh_result
+
+resize:
+  %old = call i32 @usable_size(i8* %p)
+  %fits = icmp uge i32 %old, %n
+  br i1 %fits, label %same, label %move
+
+same:
+  ret i8* %p
+
+move:
+  %new = call i8* @malloc(i32 %n)
+  %failed = icmp eq i8* %new, null
+  br i1 %failed, label %fail, label %copy
+
+copy:
+  call void @copy_words(i8* %new, i8* %p, i32 %old)
+  call void @free(i8* %p)
+  ret i8* %new
+
+fail:
+  ret i8* null
+}
+
+define weak i8* @calloc(i32 %count, i32 %size) {
+entry:
+  %total = mul i32 %count, %size
+  %size_is_zero = icmp eq i32 %size, 0
+  br i1 %size_is_zero, label %alloc, label %check
+
+check:
+  %quotient = udiv i32 %total, %size
+  %overflow = icmp ne i32 %quotient, %count
+  br i1 %overflow, label %fail, label %alloc
+
+alloc:
+  %p = call i8* @malloc(i32 %total)
+  %failed = icmp eq i8* %p, null
+  br i1 %failed, label %fail, label %zero
+
+zero:
+  %usable = call i32 @usable_size(i8* %p)
+  %addr = ptrtoint i8* %p to i32
+  br label %loop
+
+loop:
+  %offset = phi i32 [ 0, %zero ], [ %next, %body ]
+  %done = icmp uge i32 %offset, %usable
+  br i1 %done, label %exit, label %body
+
+body:
+  %word_addr = add i32 %addr, %offset
+  %word = inttoptr i32 %word_addr to i32*
+  store i32 0, i32* %word
+  %next = add i32 %offset, 4
+  br label %loop
+
+exit:
+  ret i8* %p
+
+fail:
+  ret i8* null
+}
+
+; Every block is 8-byte aligned, so larger alignments can't be satisfied
+
+define weak i8* @__rust_alloc(i32 %size, i32 %align) {
+entry:
+  %too_aligned = icmp ugt i32 %align, 8
+  br i1 %too_aligned, label %fail, label %alloc
+
+alloc:
+  %p = call i8* @malloc(i32 %size)
+  ret i8* %p
+
+fail:
+  ret i8* null
+}
+
+define weak i8* @__rust_alloc_zeroed(i32 %size, i32 %align) {
+entry:
+  %too_aligned = icmp ugt i32 %align, 8
+  br i1 %too_aligned, label %fail, label %alloc
+
+alloc:
+  %p = call i8* @calloc(i32 1, i32 %size)
+  ret i8* %p
+
+fail:
+  ret i8* null
+}
+
+define weak void @__rust_dealloc(i8* %p, i32 %size, i32 %align) {
+entry:
+  call void @free(i8* %p)
+  ret void
+}
+
+define weak i8* @__rust_realloc(i8* %p, i32 %old_size, i32 %align, i32 %new_size) {
+entry:
+  %too_aligned = icmp ugt i32 %align, 8
+  br i1 %too_aligned, label %fail, label %resize
+
+resize:
+  %new = call i8* @realloc(i8* %p, i32 %new_size)
+  ret i8* %new
+
+fail:
+  ret i8* null
+}
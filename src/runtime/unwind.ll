@@ -0,0 +1,213 @@
+; The exception handling runtime, linked into any program that throws exceptions without defining these itself.
+;
+; Unwinding is built into the compiler: `resume` leaves each frame in turn until it reaches a call
+; made with `invoke`, whose landing pad gets the same `{ i8*, i32 }` that was resumed.
+; So throwing is just resuming with the exception, and personality functions are never called.
+;
+; A C++ exception is the thrown object itself, and its selector is the address of its type info,
+; which is also what `llvm.eh.typeid.for` returns. Catching by a base class doesn't work,
+; and the destructor of a thrown object is never run.
+;
+; Each object is preceded by a header, so that `throw;` knows what type it's rethrowing,
+; and so that the exceptions being handled form a stack for nested `catch` blocks:
+;   { i8* type_info, i8* next caught exception, i32 handlers, i32 padding }
+; `handlers` counts the `catch` blocks handling it, and is negated while it's being rethrown,
+; so that leaving the `catch` block it was rethrown from doesn't free it.
+;
+; Everything but `@header` is defined `weak`, so a program can still bring its own versions.
+
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+declare i32 @__gxx_personality_v0(...)
+declare i8* @malloc(i32)
+declare void @free(i8*)
+
+; The exception being handled by the innermost `catch`, which links to the next one out
+@caught = internal global i8* null
+
+; Type infos for the fundamental types, which would come from the C++ runtime library.
+; Only their addresses are ever used.
+@_ZTIb = weak constant i8* null
+@_ZTIc = weak constant i8* null
+@_ZTIi = weak constant i8* null
+@_ZTIj = weak constant i8* null
+@_ZTIl = weak constant i8* null
+@_ZTIm = weak constant i8* null
+@_ZTIx = weak constant i8* null
+@_ZTIy = weak constant i8* null
+@_ZTIf = weak constant i8* null
+@_ZTId = weak constant i8* null
+@_ZTIPKc = weak constant i8* null
+
+define weak i32 @_Unwind_RaiseException(i8* %exception) personality i32 (...)* @__gxx_personality_v0 {
+entry:
+  %value = insertvalue { i8*, i32 } undef, i8* %exception, 0
+  %with_selector = insertvalue { i8*, i32 } %value, i32 0, 1
+  resume { i8*, i32 } %with_selector
+}
+
+define weak void @_Unwind_Resume(i8* %exception) personality i32 (...)* @__gxx_personality_v0 {
+entry:
+  %value = insertvalue { i8*, i32 } undef, i8* %exception, 0
+  %with_selector = insertvalue { i8*, i32 } %value, i32 0, 1
+  resume { i8*, i32 } %with_selector
+}
+
+; Nothing owns the exception afterwards, so it's leaked
+define weak void @_Unwind_DeleteException(i8* %exception) {
+entry:
+  ret void
+}
+
+; Used by personality functions, which never run
+
+define weak i8* @_Unwind_GetLanguageSpecificData(i8* %context) {
+entry:
+  ret i8* null
+}
+
+define weak i32 @_Unwind_GetRegionStart(i8* %context) {
+entry:
+  ret i32 0
+}
+
+define weak i32 @_Unwind_GetIP(i8* %context) {
+entry:
+  ret i32 0
+}
+
+define weak i32 @_Unwind_GetIPInfo(i8* %context, i32* %before_insn) {
+entry:
+  store i32 0, i32* %before_insn
+  ret i32 0
+}
+
+define weak void @_Unwind_SetGR(i8* %context, i32 %index, i32 %value) {
+entry:
+  ret void
+}
+
+define weak void @_Unwind_SetIP(i8* %context, i32 %value) {
+entry:
+  ret void
+}
+
+define weak i32 @_Unwind_GetTextRelBase(i8* %context) {
+entry:
+  ret i32 0
+}
+
+define weak i32 @_Unwind_GetDataRelBase(i8* %context) {
+entry:
+  ret i32 0
+}
+
+; The C++ ABI
+
+define internal { i8*, i8*, i32, i32 }* @header(i8* %object) {
+entry:
+  %start = getelementptr i8, i8* %object, i32 -16
+  %header = bitcast i8* %start to { i8*, i8*, i32, i32 }*
+  ret { i8*, i8*, i32, i32 }* %header
+}
+
+define weak i8* @__cxa_allocate_exception(i32 %size) {
+entry:
+  %with_header = add i32 %size, 16
+  %start = call i8* @malloc(i32 %with_header)
+  %object = getelementptr i8, i8* %start, i32 16
+  %header = call { i8*, i8*, i32, i32 }* @header(i8* %object)
+  %handlers_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 2
+  store i32 0, i32* %handlers_ptr
+  ret i8* %object
+}
+
+define weak void @__cxa_free_exception(i8* %object) {
+entry:
+  %start = getelementptr i8, i8* %object, i32 -16
+  call void @free(i8* %start)
+  ret void
+}
+
+define weak void @__cxa_throw(i8* %object, i8* %type_info, i8* %destructor) personality i32 (...)* @__gxx_personality_v0 {
+entry:
+  %header = call { i8*, i8*, i32, i32 }* @header(i8* %object)
+  %type_info_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 0
+  store i8* %type_info, i8** %type_info_ptr
+  %selector = ptrtoint i8* %type_info to i32
+  %value = insertvalue { i8*, i32 } undef, i8* %object, 0
+  %with_selector = insertvalue { i8*, i32 } %value, i32 %selector, 1
+  resume { i8*, i32 } %with_selector
+}
+
+; Throws the exception being handled by the innermost `catch` again
+define weak void @__cxa_rethrow() personality i32 (...)* @__gxx_personality_v0 {
+entry:
+  %object = load i8*, i8** @caught
+  %header = call { i8*, i8*, i32, i32 }* @header(i8* %object)
+  %handlers_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 2
+  %handlers = load i32, i32* %handlers_ptr
+  %rethrown = sub i32 0, %handlers
+  store i32 %rethrown, i32* %handlers_ptr
+  %type_info_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 0
+  %type_info = load i8*, i8** %type_info_ptr
+  %selector = ptrtoint i8* %type_info to i32
+  %value = insertvalue { i8*, i32 } undef, i8* %object, 0
+  %with_selector = insertvalue { i8*, i32 } %value, i32 %selector, 1
+  resume { i8*, i32 } %with_selector
+}
+
+define weak i8* @__cxa_begin_catch(i8* %object) {
+entry:
+  %header = call { i8*, i8*, i32, i32 }* @header(i8* %object)
+  %handlers_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 2
+  %handlers = load i32, i32* %handlers_ptr
+  %negative = icmp slt i32 %handlers, 0
+  %negated = sub i32 0, %handlers
+  %count = select i1 %negative, i32 %negated, i32 %handlers
+  %new_count = add i32 %count, 1
+  store i32 %new_count, i32* %handlers_ptr
+
+  ; A rethrown exception caught again is already on the stack
+  %innermost = load i8*, i8** @caught
+  %on_stack = icmp eq i8* %innermost, %object
+  br i1 %on_stack, label %done, label %push
+
+push:
+  %next_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 1
+  store i8* %innermost, i8** %next_ptr
+  store i8* %object, i8** @caught
+  br label %done
+
+done:
+  ret i8* %object
+}
+
+define weak void @__cxa_end_catch() {
+entry:
+  %object = load i8*, i8** @caught
+  %header = call { i8*, i8*, i32, i32 }* @header(i8* %object)
+  %handlers_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 2
+  %handlers = load i32, i32* %handlers_ptr
+  %rethrown = icmp slt i32 %handlers, 0
+  %toward_zero = select i1 %rethrown, i32 1, i32 -1
+  %new_count = add i32 %handlers, %toward_zero
+  store i32 %new_count, i32* %handlers_ptr
+  %last = icmp eq i32 %new_count, 0
+  br i1 %last, label %pop, label %done
+
+pop:
+  %next_ptr = getelementptr { i8*, i8*, i32, i32 }, { i8*, i8*, i32, i32 }* %header, i32 0, i32 1
+  %next = load i8*, i8** %next_ptr
+  store i8* %next, i8** @caught
+  ; A rethrown exception is still being unwound, and gets freed by whichever `catch` ends up handling it
+  br i1 %rethrown, label %done, label %free
+
+free:
+  call void @__cxa_free_exception(i8* %object)
+  br label %done
+
+done:
+  ret void
+}
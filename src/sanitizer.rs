@@ -0,0 +1,108 @@
+//! Runtime checks for the pointers used by memory accesses, enabled by `BuildOptions::check_pointers`.
+//!
+//! Every access through `%ptr` (reading or writing the word at the pointer entity,
+//! or calling one of the byte and halfword intrinsics) gets a check right before it,
+//! which sets `%%ptr_check` to something other than zero if the pointer is null,
+//! out of bounds or misaligned. The interpreter stops at the `!INTERPRETER: CHECK PTR` marker
+//! in that case, while Minecraft prints the address and carries on.
+
+use crate::cir::FuncCall as McFuncCall;
+use crate::cir::Function as McFunction;
+use crate::cir::{self, Command, Execute, ExecuteCondKind, ExecuteCondition, ScoreHolder, Tellraw};
+use crate::compile_ir::{assign, assign_lit, make_op, ptr, MemoryLayout, OBJECTIVE};
+
+/// Zero if the pointer being checked is valid
+pub fn ptr_check() -> ScoreHolder {
+    ScoreHolder::new("%%ptr_check".to_string()).unwrap()
+}
+
+/// The size and required alignment of the access that `cmd` makes, if it goes through `%ptr`
+fn access_kind(cmd: &Command) -> Option<(i32, i32)> {
+    match cmd {
+        Command::FuncCall(McFuncCall { id }) => match id.name.as_str() {
+            "intrinsic:load_byte" | "intrinsic:store_byte" => Some((1, 1)),
+            "intrinsic:load_halfword" | "intrinsic:store_halfword" => Some((2, 2)),
+            "intrinsic:load_halfword_unaligned" | "intrinsic:store_halfword_unaligned" => Some((2, 1)),
+            "intrinsic:load_word_unaligned" | "intrinsic:store_word_unaligned" => Some((4, 1)),
+            _ => None,
+        },
+        Command::Execute(_) => {
+            let as_string = cmd.to_string();
+            if as_string.starts_with("execute at @e[tag=ptr]") && as_string.contains("RecordItem.tag.Memory") {
+                Some((4, 4))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Checks that an access of `size` bytes at `%ptr` is aligned to `align` and lies
+/// within memory, skipping the first word so that null (and anything near it) is caught
+pub fn check_ptr(function: &str, size: i32, align: i32, layout: &MemoryLayout) -> Vec<Command> {
+    let mut cmds = Vec::new();
+
+    if align > 1 {
+        cmds.push(assign(ptr_check(), ptr()));
+        cmds.push(make_op(ptr_check(), "%=", ScoreHolder::new(format!("%%{}", align)).unwrap()));
+    } else {
+        cmds.push(assign_lit(ptr_check(), 0));
+    }
+
+    let mut out_of_bounds = Execute::new();
+    out_of_bounds.with_unless(ExecuteCondition::Score {
+        target: ptr().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((4..=layout.bytes() as i32 - size).into()),
+    });
+    out_of_bounds.with_run(assign_lit(ptr_check(), 1));
+    cmds.push(out_of_bounds.into());
+
+    cmds.push(Command::Comment(format!("!INTERPRETER: CHECK PTR {}", function)));
+
+    let mut report = Execute::new();
+    report.with_unless(ExecuteCondition::Score {
+        target: ptr_check().into(),
+        target_obj: OBJECTIVE.into(),
+        kind: ExecuteCondKind::Matches((0..=0).into()),
+    });
+    report.with_run(Tellraw {
+        target: cir::Selector {
+            var: cir::SelectorVariable::AllPlayers,
+            args: Vec::new(),
+        }
+        .into(),
+        message: cir::TextBuilder::new()
+            .append_text("invalid pointer ".into())
+            .append_score(ptr(), OBJECTIVE.into(), None)
+            .append_text(format!(" in {}", function))
+            .build(),
+    });
+    cmds.push(report.into());
+
+    cmds
+}
+
+/// Inserts a check before every memory access through `%ptr` in the compiled functions
+pub(crate) fn check_memory_accesses(funcs: &mut [McFunction], layout: &MemoryLayout) {
+    for func in funcs.iter_mut() {
+        // The intrinsics are checked where they're called
+        if func.id.name.starts_with("intrinsic:") {
+            continue;
+        }
+
+        let function = func.id.name.clone();
+        func.cmds = func
+            .cmds
+            .drain(..)
+            .flat_map(|cmd| {
+                let mut cmds = access_kind(&cmd)
+                    .map(|(size, align)| check_ptr(&function, size, align, layout))
+                    .unwrap_or_default();
+                cmds.push(cmd);
+                cmds
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,278 @@
+//! The storage memory backend, which keeps memory in `data storage` instead of jukeboxes,
+//! so it keeps working without any loaded chunks.
+//!
+//! Each slice of the memory area along X becomes one int array (a "page") in `pages`.
+//! NBT paths can't be indexed by a score, so `intrinsic:setptr` splits the address
+//! into `%%page` and `%%offset`, and then a binary tree of functions turns each of those
+//! into a constant index. Accessing a word copies its page into the scratch array `page`
+//! (and back again for a write), so each access only needs one tree per index.
+//!
+//! The rest of the compiler still generates accesses for the jukebox backend,
+//! and `lower_memory_accesses` rewrites them afterwards.
+
+use crate::cir::FuncCall as McFuncCall;
+use crate::cir::Function as McFunction;
+use crate::cir::FunctionId as McFuncId;
+use crate::cir::{
+    self, Command, Data, DataKind, DataModifyKind, DataModifySource, DataTarget, Execute,
+    ExecuteStoreKind, ExecuteSubCmd, ScoreGet, ScoreHolder,
+};
+use crate::compile_ir::{assign, dispatch, CompileError, make_op, ptr, read_ptr, write_ptr, write_ptr_const, MemoryLayout, OBJECTIVE};
+use std::ops::Range;
+
+pub const STORAGE: &str = "langcraft:memory";
+
+/// Every generated function starts with this
+static FUNC_PREFIX: &str = "rust:__langcraft_memory/";
+
+/// The index of the page that `intrinsic:setptr` pointed to
+pub fn page() -> ScoreHolder {
+    ScoreHolder::new("%%page".to_string()).unwrap()
+}
+
+/// The index of the word within the page that `intrinsic:setptr` pointed to
+pub fn offset() -> ScoreHolder {
+    ScoreHolder::new("%%offset".to_string()).unwrap()
+}
+
+fn func_id(name: &str) -> McFuncId {
+    McFuncId::new(format!("{}{}", FUNC_PREFIX, name))
+}
+
+/// Returns true if `id` is one of the trees that run exactly one of their branches
+pub(crate) fn is_dispatch(id: &McFuncId) -> bool {
+    id.name.starts_with(FUNC_PREFIX)
+        && !matches!(&id.name[FUNC_PREFIX.len()..], "read" | "write")
+}
+
+fn storage_modify(path: String, kind: DataModifyKind, source: DataModifySource) -> Command {
+    Data {
+        target: DataTarget::Storage(STORAGE.to_string()),
+        kind: DataKind::Modify { path, kind, source },
+    }
+    .into()
+}
+
+fn copy(dest: String, source: String) -> Command {
+    storage_modify(
+        dest,
+        DataModifyKind::Set,
+        DataModifySource::From(DataTarget::Storage(STORAGE.to_string()), source),
+    )
+}
+
+/// Commands for the setup function that create every page, filled with zeroes
+pub fn build_cmds(layout: &MemoryLayout) -> Vec<Command> {
+    let zeroes = vec!["0"; layout.page_words() as usize].join(",");
+
+    let mut cmds = vec![
+        storage_modify("page".to_string(), DataModifyKind::Set, DataModifySource::ValueNbt(format!("[I;{}]", zeroes))),
+        storage_modify("pages".to_string(), DataModifyKind::Set, DataModifySource::ValueNbt("[]".to_string())),
+    ];
+
+    for _ in 0..layout.x {
+        cmds.push(storage_modify(
+            "pages".to_string(),
+            DataModifyKind::Append,
+            DataModifySource::From(DataTarget::Storage(STORAGE.to_string()), "page".to_string()),
+        ));
+    }
+
+    cmds
+}
+
+/// Sets a word whose page and offset are known at compile time
+pub fn set_word(value: i32, page: i32, offset: i32) -> Command {
+    storage_modify(
+        format!("pages[{}][{}]", page, offset),
+        DataModifyKind::Set,
+        DataModifySource::Value(value),
+    )
+}
+
+/// Reads a word whose page and offset are known at compile time into `target`
+pub fn load_word(target: ScoreHolder, page: i32, offset: i32) -> Command {
+    let mut cmd = Execute::new();
+    cmd.with_subcmd(ExecuteSubCmd::Store {
+        is_success: false,
+        kind: ExecuteStoreKind::Score {
+            target: target.into(),
+            objective: OBJECTIVE.to_string(),
+        },
+    });
+    cmd.with_run(Data {
+        target: DataTarget::Storage(STORAGE.to_string()),
+        kind: DataKind::Get {
+            path: format!("pages[{}][{}]", page, offset),
+            scale: 1.0,
+        },
+    });
+    cmd.into()
+}
+
+/// Writes `source` to a word whose page and offset are known at compile time
+pub fn store_word(source: ScoreHolder, page: i32, offset: i32) -> Command {
+    let mut cmd = Execute::new();
+    cmd.with_subcmd(ExecuteSubCmd::Store {
+        is_success: false,
+        kind: ExecuteStoreKind::Data {
+            target: DataTarget::Storage(STORAGE.to_string()),
+            path: format!("pages[{}][{}]", page, offset),
+            ty: "int".to_string(),
+            scale: 1.0,
+        },
+    });
+    cmd.with_run(ScoreGet {
+        target: source.into(),
+        target_obj: OBJECTIVE.to_string(),
+    });
+    cmd.into()
+}
+
+/// The body of `intrinsic:setptr` for this backend
+fn setptr_cmds() -> Vec<Command> {
+    let word = ScoreHolder::new("%%ptr".to_string()).unwrap();
+    let page_words = ScoreHolder::new("%%MEM_PAGE".to_string()).unwrap();
+
+    vec![
+        assign(word.clone(), ptr()),
+        make_op(word.clone(), "/=", ScoreHolder::new("%%4".to_string()).unwrap()),
+        assign(offset(), word.clone()),
+        make_op(offset(), "%=", page_words.clone()),
+        assign(page(), word),
+        make_op(page(), "/=", page_words),
+    ]
+}
+
+/// The functions used by the rewritten memory accesses.
+/// `rust:__langcraft_memory/read` loads the word at the pointer into `word`,
+/// and `rust:__langcraft_memory/write` stores `word` at the pointer.
+pub fn access_funcs(layout: &MemoryLayout) -> Vec<McFunction> {
+    let mut funcs = vec![
+        McFunction::new(
+            func_id("read"),
+            vec![
+                McFuncCall { id: func_id("load_page") }.into(),
+                McFuncCall { id: func_id("read_word") }.into(),
+            ],
+        ),
+        McFunction::new(
+            func_id("write"),
+            vec![
+                McFuncCall { id: func_id("load_page") }.into(),
+                McFuncCall { id: func_id("write_word") }.into(),
+                McFuncCall { id: func_id("store_page") }.into(),
+            ],
+        ),
+    ];
+
+    let pages = 0..layout.x;
+    let words = 0..layout.page_words();
+
+    let mut tree = |name: &str, holder: &ScoreHolder, range: Range<i32>, leaf: &dyn Fn(i32) -> Command| {
+        let id = func_id(name);
+        let cmds = dispatch(&id.name, holder, &range.collect::<Vec<_>>(), leaf, &mut funcs);
+        funcs.push(McFunction::new(id, cmds));
+    };
+
+    tree("load_page", &page(), pages.clone(), &|p| copy("page".to_string(), format!("pages[{}]", p)));
+    tree("store_page", &page(), pages, &|p| copy(format!("pages[{}]", p), "page".to_string()));
+    tree("read_word", &offset(), words.clone(), &|i| copy("word".to_string(), format!("page[{}]", i)));
+    tree("write_word", &offset(), words, &|i| copy(format!("page[{}]", i), "word".to_string()));
+
+    funcs
+}
+
+/// Rewrites the memory accesses in every function to use storage instead of jukeboxes
+pub(crate) fn lower_memory_accesses(funcs: &mut [McFunction]) -> Result<(), Vec<CompileError>> {
+    let mut errors = Vec::new();
+
+    for func in funcs.iter_mut() {
+        if func.id == McFuncId::new("intrinsic:setptr") {
+            func.cmds = setptr_cmds();
+            continue;
+        }
+
+        func.cmds = func
+            .cmds
+            .drain(..)
+            .flat_map(|cmd| lower_cmd(&cmd).unwrap_or_else(|| vec![cmd]))
+            .collect();
+
+        // Anything left over (like a jukebox access written in inline assembly) wouldn't touch memory at all
+        for cmd in func.cmds.iter() {
+            if cmd.to_string().contains("RecordItem.tag.Memory") {
+                errors.push(CompileError::unsupported_instr(&cmd.to_string()).with_context(&func.id.to_string(), None, None));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn lower_cmd(cmd: &Command) -> Option<Vec<Command>> {
+    let (subcommands, run) = if let Command::Execute(Execute { subcommands, run: Some(run) }) = cmd {
+        (subcommands, &**run)
+    } else {
+        return None;
+    };
+
+    let as_string = cmd.to_string();
+
+    match (&subcommands[..], run) {
+        ([_, ExecuteSubCmd::Store { kind: ExecuteStoreKind::Score { target: cir::Target::Uuid(target), .. }, .. }], _)
+            if as_string == read_ptr(target.clone()).to_string() =>
+        {
+            let mut get = Execute::new();
+            get.with_subcmd(ExecuteSubCmd::Store {
+                is_success: false,
+                kind: ExecuteStoreKind::Score {
+                    target: target.clone().into(),
+                    objective: OBJECTIVE.to_string(),
+                },
+            });
+            get.with_run(Data {
+                target: DataTarget::Storage(STORAGE.to_string()),
+                kind: DataKind::Get {
+                    path: "word".to_string(),
+                    scale: 1.0,
+                },
+            });
+
+            Some(vec![McFuncCall { id: func_id("read") }.into(), get.into()])
+        }
+        ([_, _], Command::ScoreGet(ScoreGet { target: cir::Target::Uuid(target), .. }))
+            if as_string == write_ptr(target.clone()).to_string() =>
+        {
+            let mut set = Execute::new();
+            set.with_subcmd(ExecuteSubCmd::Store {
+                is_success: false,
+                kind: ExecuteStoreKind::Data {
+                    target: DataTarget::Storage(STORAGE.to_string()),
+                    path: "word".to_string(),
+                    ty: "int".to_string(),
+                    scale: 1.0,
+                },
+            });
+            set.with_run(ScoreGet {
+                target: target.clone().into(),
+                target_obj: OBJECTIVE.to_string(),
+            });
+
+            Some(vec![set.into(), McFuncCall { id: func_id("write") }.into()])
+        }
+        ([_], Command::Data(Data { kind: DataKind::Modify { source: DataModifySource::Value(value), .. }, .. }))
+            if as_string == write_ptr_const(*value).to_string() =>
+        {
+            Some(vec![
+                storage_modify("word".to_string(), DataModifyKind::Set, DataModifySource::Value(*value)),
+                McFuncCall { id: func_id("write") }.into(),
+            ])
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,258 @@
+//! Integer constants wider than 64 bits.
+//!
+//! `llvm_ir` only keeps the low 64 bits of an integer constant, so while the input is being
+//! linked, `name_wide_ints` replaces each wider constant that can't be recovered from its low
+//! 64 bits with `ptrtoint` of a declaration named after its words (`__langcraft_wide_int.W0.W1...`),
+//! and `words` turns either form back into the words of the value.
+
+use crate::llvm_sys::core::{
+    LLVMAddFunction, LLVMConstArray, LLVMConstNamedStruct, LLVMConstPtrToInt,
+    LLVMConstStructInContext, LLVMConstVector, LLVMDisposeMessage, LLVMFunctionType,
+    LLVMGetElementType, LLVMGetFirstBasicBlock, LLVMGetFirstFunction, LLVMGetFirstGlobal,
+    LLVMGetFirstInstruction, LLVMGetInitializer, LLVMGetInstructionOpcode, LLVMGetIntTypeWidth,
+    LLVMGetNamedFunction, LLVMGetNextBasicBlock, LLVMGetNextFunction, LLVMGetNextGlobal,
+    LLVMGetNextInstruction, LLVMGetNumOperands, LLVMGetOperand, LLVMGetStructName,
+    LLVMGetTypeContext, LLVMGetValueName2, LLVMIsAConstant, LLVMIsAConstantArray,
+    LLVMIsAConstantExpr, LLVMIsAConstantInt, LLVMIsAConstantStruct, LLVMIsAConstantVector,
+    LLVMIsPackedStruct, LLVMPrintValueToString, LLVMSetInitializer, LLVMSetOperand, LLVMTypeOf,
+    LLVMVoidTypeInContext,
+};
+use crate::llvm_sys::prelude::{LLVMModuleRef, LLVMValueRef};
+use crate::llvm_sys::LLVMOpcode;
+use llvm_ir::{Constant, Name};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+
+/// The start of the name of every declaration that stands in for a wide constant
+static WIDE_INT_PREFIX: &str = "__langcraft_wide_int.";
+
+/// Replaces every integer constant wider than 64 bits in `module` whose upper words
+/// aren't just the sign extension of its low 64 bits with one that `llvm_ir` can read all of
+///
+/// # Safety
+/// `module` must be a valid module
+pub(crate) unsafe fn name_wide_ints(module: LLVMModuleRef) -> Result<(), String> {
+    let mut rewriter = Rewriter { module, done: HashMap::new() };
+
+    let mut global = LLVMGetFirstGlobal(module);
+    while !global.is_null() {
+        let init = LLVMGetInitializer(global);
+        if !init.is_null() {
+            if let Some(new) = rewriter.rewrite(init).map_err(|e| format!("@{}: {}", value_name(global), e))? {
+                LLVMSetInitializer(global, new);
+            }
+        }
+
+        global = LLVMGetNextGlobal(global);
+    }
+
+    let mut func = LLVMGetFirstFunction(module);
+    while !func.is_null() {
+        let mut block = LLVMGetFirstBasicBlock(func);
+        while !block.is_null() {
+            let mut instr = LLVMGetFirstInstruction(block);
+            while !instr.is_null() {
+                for idx in 0..LLVMGetNumOperands(instr) as u32 {
+                    let operand = LLVMGetOperand(instr, idx);
+                    if operand.is_null() || LLVMIsAConstant(operand).is_null() {
+                        continue;
+                    }
+
+                    let new = rewriter.rewrite(operand).map_err(|e| format!("@{}: {}", value_name(func), e))?;
+                    if let Some(new) = new {
+                        // The cases of a switch have to stay plain integers
+                        if LLVMGetInstructionOpcode(instr) == LLVMOpcode::LLVMSwitch {
+                            return Err(format!("@{}: switch case {} isn't supported", value_name(func), print(operand)));
+                        }
+
+                        LLVMSetOperand(instr, idx, new);
+                    }
+                }
+
+                instr = LLVMGetNextInstruction(instr);
+            }
+
+            block = LLVMGetNextBasicBlock(block);
+        }
+
+        func = LLVMGetNextFunction(func);
+    }
+
+    Ok(())
+}
+
+struct Rewriter {
+    module: LLVMModuleRef,
+    /// The replacement for each constant that's already been looked at
+    done: HashMap<LLVMValueRef, Option<LLVMValueRef>>,
+}
+
+impl Rewriter {
+    /// Returns the constant that should be used instead of `value`, if it needs to change
+    unsafe fn rewrite(&mut self, value: LLVMValueRef) -> Result<Option<LLVMValueRef>, String> {
+        if let Some(new) = self.done.get(&value) {
+            return Ok(*new);
+        }
+
+        let ty = LLVMTypeOf(value);
+
+        let new = if !LLVMIsAConstantInt(value).is_null() && LLVMGetIntTypeWidth(ty) > 64 {
+            let bits = LLVMGetIntTypeWidth(ty);
+            let printed = print(value);
+            let words = parse_words(bits, &printed).ok_or_else(|| format!("failed to read constant {}", printed))?;
+
+            if sign_extend(bits, words[0] as u32 as u64 | (words[1] as u32 as u64) << 32) == words {
+                None
+            } else {
+                let name = format!(
+                    "{}{}",
+                    WIDE_INT_PREFIX,
+                    words.iter().map(|w| (*w as u32).to_string()).collect::<Vec<_>>().join(".")
+                );
+                let name = CString::new(name).unwrap();
+
+                let mut decl = LLVMGetNamedFunction(self.module, name.as_ptr());
+                if decl.is_null() {
+                    let void = LLVMVoidTypeInContext(LLVMGetTypeContext(ty));
+                    decl = LLVMAddFunction(self.module, name.as_ptr(), LLVMFunctionType(void, std::ptr::null_mut(), 0, 0));
+                }
+
+                Some(LLVMConstPtrToInt(decl, ty))
+            }
+        } else if !LLVMIsAConstantStruct(value).is_null()
+            || !LLVMIsAConstantArray(value).is_null()
+            || !LLVMIsAConstantVector(value).is_null()
+            || !LLVMIsAConstantExpr(value).is_null()
+        {
+            let mut changed = false;
+            let mut elements = Vec::new();
+            for idx in 0..LLVMGetNumOperands(value) as u32 {
+                let element = LLVMGetOperand(value, idx);
+                match self.rewrite(element)? {
+                    Some(new) => {
+                        changed = true;
+                        elements.push(new);
+                    }
+                    None => elements.push(element),
+                }
+            }
+
+            if !changed {
+                None
+            } else if !LLVMIsAConstantStruct(value).is_null() {
+                if LLVMGetStructName(ty).is_null() {
+                    Some(LLVMConstStructInContext(
+                        LLVMGetTypeContext(ty),
+                        elements.as_mut_ptr(),
+                        elements.len() as u32,
+                        LLVMIsPackedStruct(ty),
+                    ))
+                } else {
+                    Some(LLVMConstNamedStruct(ty, elements.as_mut_ptr(), elements.len() as u32))
+                }
+            } else if !LLVMIsAConstantArray(value).is_null() {
+                Some(LLVMConstArray(LLVMGetElementType(ty), elements.as_mut_ptr(), elements.len() as u32))
+            } else if !LLVMIsAConstantVector(value).is_null() {
+                Some(LLVMConstVector(elements.as_mut_ptr(), elements.len() as u32))
+            } else {
+                return Err(format!("integer constant in {} isn't supported", print(value)));
+            }
+        } else {
+            None
+        };
+
+        self.done.insert(value, new);
+        Ok(new)
+    }
+}
+
+unsafe fn print(value: LLVMValueRef) -> String {
+    let text = LLVMPrintValueToString(value);
+    let printed = CStr::from_ptr(text).to_string_lossy().into_owned();
+    LLVMDisposeMessage(text);
+    printed
+}
+
+unsafe fn value_name(value: LLVMValueRef) -> String {
+    let mut len = 0;
+    let name = LLVMGetValueName2(value, &mut len);
+    String::from_utf8_lossy(std::slice::from_raw_parts(name as *const u8, len)).into_owned()
+}
+
+/// Reads the words of an integer constant printed like `i128 -5`
+fn parse_words(bits: u32, printed: &str) -> Option<Vec<i32>> {
+    let digits = printed.rsplit(' ').next()?;
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits),
+    };
+
+    // There's always at least two words, so that the low 64 bits can be compared
+    let mut words = vec![0u32; (bits as usize).div_ceil(32).max(2)];
+    for digit in digits.chars() {
+        let mut carry = digit.to_digit(10)? as u64;
+        for word in words.iter_mut() {
+            let next = *word as u64 * 10 + carry;
+            *word = next as u32;
+            carry = next >> 32;
+        }
+    }
+
+    if negative {
+        let mut carry = 1;
+        for word in words.iter_mut() {
+            let next = !*word as u64 + carry;
+            *word = next as u32;
+            carry = next >> 32;
+        }
+    }
+
+    Some(words.into_iter().map(|w| w as i32).collect())
+}
+
+/// The words of a `bits`-wide integer, guessing the upper words from the sign of the low 64 bits
+fn sign_extend(bits: u32, value: u64) -> Vec<i32> {
+    let upper = if (value as i64) < 0 { -1 } else { 0 };
+
+    (0..(bits as usize).div_ceil(32).max(2))
+        .map(|idx| match idx {
+            0 => value as i32,
+            1 => (value >> 32) as i32,
+            _ => upper,
+        })
+        .collect()
+}
+
+/// Returns the words of `con` if it's an integer constant wider than 64 bits
+pub(crate) fn words(con: &Constant) -> Option<Vec<i32>> {
+    match con {
+        // Anything that `name_wide_ints` didn't replace is just its low 64 bits sign extended
+        Constant::Int { bits, value } if *bits > 64 => Some(sign_extend(*bits, *value)),
+        Constant::PtrToInt(ptr_to_int) => match &*ptr_to_int.operand {
+            Constant::GlobalReference { name: Name::Name(name), .. } => name
+                .strip_prefix(WIDE_INT_PREFIX)?
+                .split('.')
+                .map(|word| word.parse::<u32>().ok().map(|w| w as i32))
+                .collect(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_words(128, "i128 5"), Some(vec![5, 0, 0, 0]));
+        assert_eq!(parse_words(128, "i128 -5"), Some(vec![-5, -1, -1, -1]));
+        assert_eq!(parse_words(128, "i128 18446744073709551621"), Some(vec![5, 0, 1, 0]));
+        assert_eq!(
+            parse_words(128, "i128 -1267650600228229401496703205376"),
+            Some(vec![0, 0, 0, -16])
+        );
+        assert_eq!(parse_words(96, "i96 x"), None);
+    }
+}
@@ -0,0 +1,23 @@
+; Exports whose names only differ in ways that api function names can't
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define i32 @Foo() {
+  ret i32 1
+}
+
+define i32 @foo() {
+  ret i32 2
+}
+
+define i32 @a.b() {
+  ret i32 3
+}
+
+define i32 @a_b() {
+  ret i32 4
+}
+
+define i32 @other() {
+  ret i32 5
+}
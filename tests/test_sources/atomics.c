@@ -0,0 +1,52 @@
+#include <stdbool.h>
+#include <stdint.h>
+
+void print(int value);
+
+int counter = 5;
+uint8_t flag = 0;
+__int128 wide = 6;
+
+int main() {
+    int local = 100;
+    int *p = &local;
+
+    print(__atomic_fetch_add(&counter, 3, __ATOMIC_SEQ_CST));
+    print(__atomic_fetch_sub(&counter, 1, __ATOMIC_SEQ_CST));
+    print(__atomic_exchange_n(&counter, 12, __ATOMIC_SEQ_CST));
+    print(__atomic_fetch_and(&counter, 10, __ATOMIC_SEQ_CST));
+    print(__atomic_fetch_nand(&counter, 3, __ATOMIC_SEQ_CST));
+    print(__atomic_fetch_or(&counter, 0x100, __ATOMIC_SEQ_CST));
+    print(__atomic_fetch_xor(&counter, -1, __ATOMIC_SEQ_CST));
+    print(counter);
+
+    // max/min through a pointer only known at runtime
+    __atomic_fetch_max(p, 50, __ATOMIC_SEQ_CST);
+    print(local);
+    __atomic_fetch_min(p, -7, __ATOMIC_SEQ_CST);
+    print(local);
+    __atomic_fetch_umax(p, 3, __ATOMIC_SEQ_CST);
+    print(local);
+    __atomic_fetch_umin(p, 3, __ATOMIC_SEQ_CST);
+    print(local);
+
+    int expected = 3;
+    print(__atomic_compare_exchange_n(&counter, &expected, 1, false, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST));
+    print(expected);
+    expected = 3;
+    print(__atomic_compare_exchange_n(p, &expected, 9, true, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST));
+    print(local);
+
+    uint8_t unset = 0;
+    print(__atomic_compare_exchange_n(&flag, &unset, 1, false, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST));
+    print(__atomic_compare_exchange_n(&flag, &unset, 1, false, __ATOMIC_SEQ_CST, __ATOMIC_SEQ_CST));
+    print(unset);
+
+    __atomic_thread_fence(__ATOMIC_SEQ_CST);
+
+    __atomic_fetch_nand(&wide, 3, __ATOMIC_SEQ_CST);
+    print(wide >> 96);
+    print(wide);
+
+    return 0;
+}
@@ -0,0 +1,99 @@
+; ModuleID = 'atomics.c'
+source_filename = "atomics.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@counter = dso_local global i32 5, align 4
+@flag = dso_local global i8 0, align 1
+@wide = dso_local global i128 6, align 4
+
+define dso_local i32 @main() #0 {
+entry:
+  %local = alloca i32, align 4
+  %expected = alloca i32, align 4
+  %unset = alloca i8, align 1
+  store i32 100, i32* %local, align 4
+  %0 = atomicrmw add i32* @counter, i32 3 seq_cst
+  call void @print(i32 %0)
+  %1 = atomicrmw sub i32* @counter, i32 1 seq_cst
+  call void @print(i32 %1)
+  %2 = atomicrmw xchg i32* @counter, i32 12 seq_cst
+  call void @print(i32 %2)
+  %3 = atomicrmw and i32* @counter, i32 10 seq_cst
+  call void @print(i32 %3)
+  %4 = atomicrmw nand i32* @counter, i32 3 seq_cst
+  call void @print(i32 %4)
+  %5 = atomicrmw or i32* @counter, i32 256 seq_cst
+  call void @print(i32 %5)
+  %6 = atomicrmw xor i32* @counter, i32 -1 seq_cst
+  call void @print(i32 %6)
+  %7 = load i32, i32* @counter, align 4
+  call void @print(i32 %7)
+  %8 = atomicrmw max i32* %local, i32 50 seq_cst
+  %9 = load i32, i32* %local, align 4
+  call void @print(i32 %9)
+  %10 = atomicrmw min i32* %local, i32 -7 seq_cst
+  %11 = load i32, i32* %local, align 4
+  call void @print(i32 %11)
+  %12 = atomicrmw umax i32* %local, i32 3 seq_cst
+  %13 = load i32, i32* %local, align 4
+  call void @print(i32 %13)
+  %14 = atomicrmw umin i32* %local, i32 3 seq_cst
+  %15 = load i32, i32* %local, align 4
+  call void @print(i32 %15)
+  store i32 3, i32* %expected, align 4
+  %16 = cmpxchg i32* @counter, i32 3, i32 1 seq_cst seq_cst
+  %17 = extractvalue { i32, i1 } %16, 0
+  %18 = extractvalue { i32, i1 } %16, 1
+  br i1 %18, label %cmpxchg.continue, label %cmpxchg.store_expected
+
+cmpxchg.store_expected:
+  store i32 %17, i32* %expected, align 4
+  br label %cmpxchg.continue
+
+cmpxchg.continue:
+  %19 = zext i1 %18 to i32
+  call void @print(i32 %19)
+  %20 = load i32, i32* %expected, align 4
+  call void @print(i32 %20)
+  %21 = cmpxchg weak i32* %local, i32 3, i32 9 seq_cst seq_cst
+  %22 = extractvalue { i32, i1 } %21, 1
+  %23 = zext i1 %22 to i32
+  call void @print(i32 %23)
+  %24 = load i32, i32* %local, align 4
+  call void @print(i32 %24)
+  store i8 0, i8* %unset, align 1
+  %25 = cmpxchg i8* @flag, i8 0, i8 1 seq_cst seq_cst
+  %26 = extractvalue { i8, i1 } %25, 1
+  %27 = zext i1 %26 to i32
+  call void @print(i32 %27)
+  %28 = cmpxchg i8* @flag, i8 0, i8 1 seq_cst seq_cst
+  %29 = extractvalue { i8, i1 } %28, 0
+  %30 = extractvalue { i8, i1 } %28, 1
+  br i1 %30, label %cmpxchg.continue2, label %cmpxchg.store_expected2
+
+cmpxchg.store_expected2:
+  store i8 %29, i8* %unset, align 1
+  br label %cmpxchg.continue2
+
+cmpxchg.continue2:
+  %31 = zext i1 %30 to i32
+  call void @print(i32 %31)
+  %32 = load i8, i8* %unset, align 1
+  %33 = zext i8 %32 to i32
+  call void @print(i32 %33)
+  fence seq_cst
+  %wide_old = atomicrmw nand i128* @wide, i128 3 seq_cst
+  %wide_new = load i128, i128* @wide, align 4
+  %wide_hi = lshr i128 %wide_new, 96
+  %wide_hi32 = trunc i128 %wide_hi to i32
+  call void @print(i32 %wide_hi32)
+  %wide_lo32 = trunc i128 %wide_new to i32
+  call void @print(i32 %wide_lo32)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
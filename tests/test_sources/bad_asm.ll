@@ -0,0 +1,19 @@
+; Inline assembly with commands that can't be parsed
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define i32 @add(i32 %x) {
+entry:
+  %0 = call i32 asm sideeffect "scoreboard players add $0 rust", "=r,0"(i32 %x)
+  ret i32 %0
+}
+
+define i32 @main() {
+entry:
+  %0 = call i32 asm sideeffect "time set day", "=r"()
+  %1 = call i32 @add(i32 %0)
+  call void @print(i32 %1)
+  ret i32 0
+}
+
+declare void @print(i32)
@@ -0,0 +1,13 @@
+void print(int value);
+
+int load(int *p) {
+    return *p;
+}
+
+int main() {
+    int x = 0x1234;
+    print(load(&x));
+    print(((unsigned char *)&x)[1]);
+    print(load((int *)0));
+    return 0;
+}
@@ -0,0 +1,31 @@
+; ModuleID = 'bad_pointer.c'
+source_filename = "bad_pointer.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define dso_local i32 @load(i32* %p) #0 {
+entry:
+  %value = load i32, i32* %p, align 4
+  ret i32 %value
+}
+
+define dso_local i32 @main() #0 {
+entry:
+  %x = alloca i32, align 4
+  store i32 4660, i32* %x, align 4
+  %call = call i32 @load(i32* %x)
+  call void @print(i32 %call)
+  %bytes = bitcast i32* %x to i8*
+  %byte_ptr = getelementptr inbounds i8, i8* %bytes, i32 1
+  %byte = load i8, i8* %byte_ptr, align 1
+  %byte_int = zext i8 %byte to i32
+  call void @print(i32 %byte_int)
+  %call1 = call i32 @load(i32* null)
+  call void @print(i32 %call1)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
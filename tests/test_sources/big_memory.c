@@ -0,0 +1,11 @@
+#include <mcinterface.h>
+
+// Past the end of the default 128 KiB of memory
+int far_addr = 200000;
+
+int main() {
+    volatile int *far = (volatile int *)far_addr;
+    *far = 42;
+    print(*far);
+    return 0;
+}
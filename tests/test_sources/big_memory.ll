@@ -0,0 +1,21 @@
+; ModuleID = 'big_memory.c'
+source_filename = "big_memory.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@far_addr = dso_local global i32 200000, align 4
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = load i32, i32* @far_addr, align 4
+  %2 = inttoptr i32 %1 to i32*
+  store volatile i32 42, i32* %2, align 4
+  %3 = load volatile i32, i32* %2, align 4
+  call void @print(i32 %3)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind optnone }
+attributes #1 = { "frame-pointer"="all" }
@@ -0,0 +1,35 @@
+#include <stdint.h>
+
+void print(int value);
+
+uint8_t bytes[8] = { 1, 2, 3, 4, 5, 6, 7, 8 };
+uint16_t halves[2] = { 0x1234, 0xFFFF };
+
+struct __attribute__((packed)) {
+    uint8_t tag;
+    int32_t value;
+} packed = { 7, -2 };
+
+int main() {
+    bytes[1] = 0xAB;
+    bytes[3] = 0xFF;
+    print(bytes[0] + bytes[1] * 256);
+    print(bytes[3]);
+    print(bytes[2]);
+
+    bytes[5] = bytes[0] + 0x7F;
+    print(bytes[5]);
+    print(bytes[6]);
+
+    halves[1] = 0x8001;
+    print(halves[0]);
+    print(halves[1]);
+
+    // Spans two words
+    print(packed.value);
+    packed.value = 0x12345678;
+    print(packed.tag);
+    print(packed.value);
+
+    return 0;
+}
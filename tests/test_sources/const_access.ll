@@ -0,0 +1,58 @@
+; ModuleID = 'const_access.c'
+source_filename = "const_access.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+%struct.anon = type <{ i8, i32 }>
+
+@bytes = dso_local global [8 x i8] c"\01\02\03\04\05\06\07\08", align 1
+@halves = dso_local global [2 x i16] [i16 4660, i16 -1], align 2
+@packed = dso_local global %struct.anon <{ i8 7, i32 -2 }>, align 1
+
+define dso_local i32 @main() #0 {
+entry:
+  store i8 -85, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 1), align 1
+  store i8 -1, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 3), align 1
+  %b0 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 0), align 1
+  %b0_int = zext i8 %b0 to i32
+  %b1 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 1), align 1
+  %b1_int = zext i8 %b1 to i32
+  %b1_shifted = mul nsw i32 %b1_int, 256
+  %low = add nsw i32 %b0_int, %b1_shifted
+  call void @print(i32 %low)
+  %b3 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 3), align 1
+  %b3_int = zext i8 %b3 to i32
+  call void @print(i32 %b3_int)
+  %b2 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 2), align 1
+  %b2_int = zext i8 %b2 to i32
+  call void @print(i32 %b2_int)
+  %sum = add i8 %b0, 127
+  store i8 %sum, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 5), align 1
+  %b5 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 5), align 1
+  %b5_int = zext i8 %b5 to i32
+  call void @print(i32 %b5_int)
+  %b6 = load i8, i8* getelementptr inbounds ([8 x i8], [8 x i8]* @bytes, i32 0, i32 6), align 1
+  %b6_int = zext i8 %b6 to i32
+  call void @print(i32 %b6_int)
+  store i16 -32767, i16* getelementptr inbounds ([2 x i16], [2 x i16]* @halves, i32 0, i32 1), align 2
+  %h0 = load i16, i16* getelementptr inbounds ([2 x i16], [2 x i16]* @halves, i32 0, i32 0), align 2
+  %h0_int = zext i16 %h0 to i32
+  call void @print(i32 %h0_int)
+  %h1 = load i16, i16* getelementptr inbounds ([2 x i16], [2 x i16]* @halves, i32 0, i32 1), align 2
+  %h1_int = zext i16 %h1 to i32
+  call void @print(i32 %h1_int)
+  %v = load i32, i32* getelementptr inbounds (%struct.anon, %struct.anon* @packed, i32 0, i32 1), align 1
+  call void @print(i32 %v)
+  store i32 305419896, i32* getelementptr inbounds (%struct.anon, %struct.anon* @packed, i32 0, i32 1), align 1
+  %tag = load i8, i8* getelementptr inbounds (%struct.anon, %struct.anon* @packed, i32 0, i32 0), align 1
+  %tag_int = zext i8 %tag to i32
+  call void @print(i32 %tag_int)
+  %v2 = load i32, i32* getelementptr inbounds (%struct.anon, %struct.anon* @packed, i32 0, i32 1), align 1
+  call void @print(i32 %v2)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
@@ -0,0 +1,92 @@
+; Exceptions thrown several calls deep, unwinding through frames with and without landing pads
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@_ZTIi = external constant i8*
+
+define void @thrower(i32 %x) {
+entry:
+  %exception = call i8* @__cxa_allocate_exception(i32 4)
+  %0 = bitcast i8* %exception to i32*
+  store i32 %x, i32* %0, align 4
+  call void @__cxa_throw(i8* %exception, i8* bitcast (i8** @_ZTIi to i8*), i8* null)
+  unreachable
+}
+
+define i32 @three(i32 %x) {
+entry:
+  %y = mul i32 %x, 3
+  call void @thrower(i32 %y)
+  ret i32 %y
+}
+
+define i32 @two(i32 %x) personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  %y = add i32 %x, 2
+  %r = invoke i32 @three(i32 %y)
+          to label %cont unwind label %lpad
+
+cont:
+  ret i32 %r
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          cleanup
+  call void @print(i32 %y)
+  resume { i8*, i32 } %0
+}
+
+define i32 @one(i32 %x) {
+entry:
+  %y = add i32 %x, 1
+  %r = call i32 @two(i32 %y)
+  %z = add i32 %r, %y
+  ret i32 %z
+}
+
+define i32 @main() personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  %kept = add i32 40, 2
+  %a = invoke i32 @one(i32 1)
+          to label %unreached unwind label %lpad
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %1 = extractvalue { i8*, i32 } %0, 0
+  %2 = call i8* @__cxa_begin_catch(i8* %1)
+  %3 = bitcast i8* %2 to i32*
+  %4 = load i32, i32* %3, align 4
+  call void @print(i32 %4)
+  call void @__cxa_end_catch()
+  %b = invoke i32 @three(i32 5)
+          to label %unreached unwind label %lpad2
+
+lpad2:
+  %5 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %6 = extractvalue { i8*, i32 } %5, 0
+  %7 = call i8* @__cxa_begin_catch(i8* %6)
+  %8 = bitcast i8* %7 to i32*
+  %9 = load i32, i32* %8, align 4
+  call void @print(i32 %9)
+  call void @__cxa_end_catch()
+  call void @print(i32 %kept)
+  ret i32 0
+
+unreached:
+  call void @print(i32 -1)
+  ret i32 1
+}
+
+declare i8* @__cxa_allocate_exception(i32)
+
+declare void @__cxa_throw(i8*, i8*, i8*)
+
+declare i32 @__gxx_personality_v0(...)
+
+declare i8* @__cxa_begin_catch(i8*)
+
+declare void @__cxa_end_catch()
+
+declare void @print(i32)
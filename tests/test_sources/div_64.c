@@ -0,0 +1,25 @@
+#include <mcinterface.h>
+
+unsigned long long value = 18446744073709551557ULL;
+long long neg = -9876543210987;
+unsigned long long divisor = 0x123456789;
+
+/* Prints the digits of x, least significant first */
+void print_digits(unsigned long long x) {
+    do {
+        print((int) (x % 10));
+        x /= 10;
+    } while (x != 0);
+}
+
+int main() {
+    print_digits(value);
+
+    print((int) (value / divisor));
+    print((int) (value % divisor));
+
+    print((int) (neg / 1000000));
+    print((int) (neg % 1000000));
+    print((int) (neg / -1000000));
+    print((int) (neg % -1000000));
+}
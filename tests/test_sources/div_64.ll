@@ -0,0 +1,68 @@
+; ModuleID = 'div_64.c'
+source_filename = "div_64.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@value = dso_local global i64 -59, align 8
+@neg = dso_local global i64 -9876543210987, align 8
+@divisor = dso_local global i64 4886718345, align 8
+
+; Function Attrs: noinline nounwind optnone
+define dso_local void @print_digits(i64 %0) #0 {
+  %2 = alloca i64, align 8
+  store i64 %0, i64* %2, align 8
+  br label %3
+
+3:                                                ; preds = %8, %1
+  %4 = load i64, i64* %2, align 8
+  %5 = urem i64 %4, 10
+  %6 = trunc i64 %5 to i32
+  call void @print(i32 %6)
+  %7 = udiv i64 %4, 10
+  store i64 %7, i64* %2, align 8
+  br label %8
+
+8:                                                ; preds = %3
+  %9 = load i64, i64* %2, align 8
+  %10 = icmp ne i64 %9, 0
+  br i1 %10, label %3, label %11
+
+11:                                               ; preds = %8
+  ret void
+}
+
+declare void @print(i32) #1
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = load i64, i64* @value, align 8
+  call void @print_digits(i64 %1)
+  %2 = load i64, i64* @divisor, align 8
+  %3 = udiv i64 %1, %2
+  %4 = trunc i64 %3 to i32
+  call void @print(i32 %4)
+  %5 = urem i64 %1, %2
+  %6 = trunc i64 %5 to i32
+  call void @print(i32 %6)
+  %7 = load i64, i64* @neg, align 8
+  %8 = sdiv i64 %7, 1000000
+  %9 = trunc i64 %8 to i32
+  call void @print(i32 %9)
+  %10 = srem i64 %7, 1000000
+  %11 = trunc i64 %10 to i32
+  call void @print(i32 %11)
+  %12 = sdiv i64 %7, -1000000
+  %13 = trunc i64 %12 to i32
+  call void @print(i32 %13)
+  %14 = srem i64 %7, -1000000
+  %15 = trunc i64 %14 to i32
+  call void @print(i32 %15)
+  ret i32 0
+}
+
+attributes #0 = { noinline nounwind optnone "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+attributes #1 = { "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+
+!llvm.ident = !{!0}
+
+!0 = !{!"clang version 10.0.1 "}
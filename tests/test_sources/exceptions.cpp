@@ -0,0 +1,37 @@
+extern "C" {
+
+void print(int value);
+
+struct Guard {
+    ~Guard() { print(-1); }
+};
+
+void thrower(int x) {
+    if (x > 0) {
+        throw x;
+    }
+    print(x);
+}
+
+int middle(int x) {
+    Guard guard;
+    thrower(x);
+    return x + 1;
+}
+
+int main() {
+    try {
+        print(middle(0));
+        middle(7);
+        print(100);
+    } catch (long l) {
+        print(1000);
+    } catch (int e) {
+        print(e);
+    }
+
+    print(42);
+    return 0;
+}
+
+}
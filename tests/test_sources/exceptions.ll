@@ -0,0 +1,110 @@
+; ModuleID = 'exceptions.cpp'
+source_filename = "exceptions.cpp"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@_ZTIi = external constant i8*
+@_ZTIl = external constant i8*
+
+define dso_local void @thrower(i32 %x) #0 {
+entry:
+  %positive = icmp sgt i32 %x, 0
+  br i1 %positive, label %if.then, label %if.end
+
+if.then:
+  %exception = call i8* @__cxa_allocate_exception(i32 4)
+  %0 = bitcast i8* %exception to i32*
+  store i32 %x, i32* %0, align 16
+  call void @__cxa_throw(i8* %exception, i8* bitcast (i8** @_ZTIi to i8*), i8* null)
+  unreachable
+
+if.end:
+  call void @print(i32 %x)
+  ret void
+}
+
+define dso_local i32 @middle(i32 %x) #0 personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  invoke void @thrower(i32 %x)
+          to label %invoke.cont unwind label %lpad
+
+invoke.cont:
+  call void @print(i32 -1)
+  %add = add nsw i32 %x, 1
+  ret i32 %add
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          cleanup
+  call void @print(i32 -1)
+  resume { i8*, i32 } %0
+}
+
+define dso_local i32 @main() #0 personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  %call = invoke i32 @middle(i32 0)
+          to label %invoke.cont unwind label %lpad
+
+invoke.cont:
+  call void @print(i32 %call)
+  %call1 = invoke i32 @middle(i32 7)
+          to label %invoke.cont2 unwind label %lpad
+
+invoke.cont2:
+  call void @print(i32 100)
+  br label %try.cont
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIl to i8*)
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %1 = extractvalue { i8*, i32 } %0, 0
+  %2 = extractvalue { i8*, i32 } %0, 1
+  %3 = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIl to i8*))
+  %matches = icmp eq i32 %2, %3
+  br i1 %matches, label %catch4, label %catch.fallthrough
+
+catch.fallthrough:
+  %4 = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIi to i8*))
+  %matches3 = icmp eq i32 %2, %4
+  br i1 %matches3, label %catch, label %eh.resume
+
+catch:
+  %5 = call i8* @__cxa_begin_catch(i8* %1)
+  %6 = bitcast i8* %5 to i32*
+  %7 = load i32, i32* %6, align 4
+  call void @print(i32 %7)
+  call void @__cxa_end_catch()
+  br label %try.cont
+
+catch4:
+  %8 = call i8* @__cxa_begin_catch(i8* %1)
+  call void @print(i32 1000)
+  call void @__cxa_end_catch()
+  br label %try.cont
+
+try.cont:
+  call void @print(i32 42)
+  ret i32 0
+
+eh.resume:
+  resume { i8*, i32 } %0
+}
+
+declare dso_local i8* @__cxa_allocate_exception(i32)
+
+declare dso_local void @__cxa_throw(i8*, i8*, i8*)
+
+declare dso_local i32 @__gxx_personality_v0(...)
+
+declare i32 @llvm.eh.typeid.for(i8*) #2
+
+declare dso_local i8* @__cxa_begin_catch(i8*)
+
+declare dso_local void @__cxa_end_catch()
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
+attributes #2 = { nounwind readnone }
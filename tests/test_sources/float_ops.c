@@ -0,0 +1,22 @@
+#include <mcinterface.h>
+
+float a = 1.5f;
+double b = -2.25;
+unsigned char n = 200;
+double big = 1.5e19;
+float f = 3e9f;
+
+int main() {
+    float c = a * 3.0f + 0.25f;
+    double d = b / 0.5 - (double) c;
+    print((int) (d * 4.0));
+    print((int) -c);
+    print(c > (float) b);
+    print(d == -9.25);
+    print((int) (float) (d * 100.0));
+    print((int) ((double) n / 8.0));
+    unsigned long long u = (unsigned long long) big;
+    print((int) (u >> 32));
+    print((int) u);
+    print((int) (unsigned) f);
+}
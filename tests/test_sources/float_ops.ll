@@ -0,0 +1,74 @@
+; ModuleID = 'float_ops.c'
+source_filename = "float_ops.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@a = dso_local global float 1.500000e+00, align 4
+@b = dso_local global double -2.250000e+00, align 8
+@n = dso_local global i8 -56, align 1
+@big = dso_local global double 1.500000e+19, align 8
+@f = dso_local global float 3.000000e+09, align 4
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = alloca float, align 4
+  %2 = alloca double, align 8
+  %3 = load float, float* @a, align 4
+  %4 = fmul float %3, 3.000000e+00
+  %5 = fadd float %4, 2.500000e-01
+  store float %5, float* %1, align 4
+  %6 = load double, double* @b, align 8
+  %7 = fdiv double %6, 5.000000e-01
+  %8 = load float, float* %1, align 4
+  %9 = fpext float %8 to double
+  %10 = fsub double %7, %9
+  store double %10, double* %2, align 8
+  %11 = load double, double* %2, align 8
+  %12 = fmul double %11, 4.000000e+00
+  %13 = fptosi double %12 to i32
+  call void @print(i32 %13)
+  %14 = load float, float* %1, align 4
+  %15 = fneg float %14
+  %16 = fptosi float %15 to i32
+  call void @print(i32 %16)
+  %17 = load float, float* %1, align 4
+  %18 = load double, double* @b, align 8
+  %19 = fptrunc double %18 to float
+  %20 = fcmp ogt float %17, %19
+  %21 = zext i1 %20 to i32
+  call void @print(i32 %21)
+  %22 = load double, double* %2, align 8
+  %23 = fcmp oeq double %22, -9.250000e+00
+  %24 = zext i1 %23 to i32
+  call void @print(i32 %24)
+  %25 = load double, double* %2, align 8
+  %26 = fmul double %25, 1.000000e+02
+  %27 = fptrunc double %26 to float
+  %28 = fptosi float %27 to i32
+  call void @print(i32 %28)
+  %29 = load i8, i8* @n, align 1
+  %30 = uitofp i8 %29 to double
+  %31 = fdiv double %30, 8.000000e+00
+  %32 = fptosi double %31 to i32
+  call void @print(i32 %32)
+  %33 = load double, double* @big, align 8
+  %34 = fptoui double %33 to i64
+  %35 = lshr i64 %34, 32
+  %36 = trunc i64 %35 to i32
+  call void @print(i32 %36)
+  %37 = trunc i64 %34 to i32
+  call void @print(i32 %37)
+  %38 = load float, float* @f, align 4
+  %39 = fptoui float %38 to i32
+  call void @print(i32 %39)
+  ret i32 0
+}
+
+declare void @print(i32) #1
+
+attributes #0 = { noinline nounwind optnone "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+attributes #1 = { "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+
+!llvm.ident = !{!0}
+
+!0 = !{!"clang version 10.0.1 "}
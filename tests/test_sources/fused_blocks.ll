@@ -0,0 +1,50 @@
+; Straight-line blocks, a diamond and a switch that can all run in the same tick, and a loop that can't
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define i32 @main() {
+entry:
+  br label %first
+
+first:
+  %x = add i32 1, 2
+  call void @print(i32 %x)
+  br label %second
+
+second:
+  %big = icmp sgt i32 %x, 2
+  br i1 %big, label %is_big, label %is_small
+
+is_big:
+  call void @print(i32 1)
+  br label %join
+
+is_small:
+  call void @print(i32 0)
+  br label %join
+
+join:
+  %picked = phi i32 [ 10, %is_big ], [ 20, %is_small ]
+  call void @print(i32 %picked)
+  switch i32 %x, label %other [ i32 3, label %three ]
+
+three:
+  call void @print(i32 3)
+  br label %loop
+
+other:
+  call void @print(i32 -1)
+  br label %loop
+
+loop:
+  %i = phi i32 [ 0, %three ], [ 0, %other ], [ %next, %loop ]
+  %next = add i32 %i, 1
+  %again = icmp slt i32 %next, 5
+  br i1 %again, label %loop, label %exit
+
+exit:
+  call void @print(i32 %next)
+  ret i32 0
+}
+
+declare void @print(i32)
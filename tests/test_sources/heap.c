@@ -0,0 +1,37 @@
+#include <stdlib.h>
+
+void print(int value);
+
+void *__rust_alloc(unsigned size, unsigned align);
+void __rust_dealloc(void *ptr, unsigned size, unsigned align);
+
+int main() {
+    int *a = malloc(10 * sizeof(int));
+    for (int i = 0; i < 10; i++) {
+        a[i] = i * i;
+    }
+
+    int *b = calloc(4, sizeof(int));
+    print(b[0] + b[3]);
+
+    int *old = a;
+    a = realloc(a, 20 * sizeof(int));
+    print(a[9]);
+
+    // The first free block that fits gets reused
+    free(b);
+    int *c = malloc(8);
+    print(c == old);
+
+    print(malloc(1 << 30) == NULL);
+    print(malloc(0xFFFFFFF4) == NULL);
+    print(__rust_alloc(4, 16) == NULL);
+
+    void *r = __rust_alloc(12, 8);
+    print((unsigned)r % 8);
+    __rust_dealloc(r, 12, 8);
+
+    free(a);
+    // `c` is leaked on purpose
+    return 0;
+}
@@ -0,0 +1,78 @@
+; ModuleID = 'heap.c'
+source_filename = "heap.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define dso_local i32 @main() #0 {
+entry:
+  %call = call i8* @malloc(i32 40)
+  %a = bitcast i8* %call to i32*
+  br label %loop
+
+loop:
+  %i = phi i32 [ 0, %entry ], [ %inc, %body ]
+  %done = icmp eq i32 %i, 10
+  br i1 %done, label %after, label %body
+
+body:
+  %sq = mul i32 %i, %i
+  %slot = getelementptr inbounds i32, i32* %a, i32 %i
+  store i32 %sq, i32* %slot, align 4
+  %inc = add nsw i32 %i, 1
+  br label %loop
+
+after:
+  %call1 = call i8* @calloc(i32 4, i32 4)
+  %b = bitcast i8* %call1 to i32*
+  %b0 = load i32, i32* %b, align 4
+  %b3_ptr = getelementptr inbounds i32, i32* %b, i32 3
+  %b3 = load i32, i32* %b3_ptr, align 4
+  %sum = add nsw i32 %b0, %b3
+  call void @print(i32 %sum)
+  %call2 = call i8* @realloc(i8* %call, i32 80)
+  %a2 = bitcast i8* %call2 to i32*
+  %a9_ptr = getelementptr inbounds i32, i32* %a2, i32 9
+  %a9 = load i32, i32* %a9_ptr, align 4
+  call void @print(i32 %a9)
+  call void @free(i8* %call1)
+  %call3 = call i8* @malloc(i32 8)
+  %same = icmp eq i8* %call3, %call
+  %same_int = zext i1 %same to i32
+  call void @print(i32 %same_int)
+  %call4 = call i8* @malloc(i32 1073741824)
+  %huge_null = icmp eq i8* %call4, null
+  %huge_int = zext i1 %huge_null to i32
+  call void @print(i32 %huge_int)
+  %call6 = call i8* @malloc(i32 -12)
+  %wrapped_null = icmp eq i8* %call6, null
+  %wrapped_int = zext i1 %wrapped_null to i32
+  call void @print(i32 %wrapped_int)
+  %call5 = call i8* @__rust_alloc(i32 4, i32 16)
+  %aligned_null = icmp eq i8* %call5, null
+  %aligned_int = zext i1 %aligned_null to i32
+  call void @print(i32 %aligned_int)
+  %r = call i8* @__rust_alloc(i32 12, i32 8)
+  %r_addr = ptrtoint i8* %r to i32
+  %r_rem = urem i32 %r_addr, 8
+  call void @print(i32 %r_rem)
+  call void @__rust_dealloc(i8* %r, i32 12, i32 8)
+  call void @free(i8* %call2)
+  ret i32 0
+}
+
+declare dso_local i8* @malloc(i32) #1
+
+declare dso_local i8* @calloc(i32, i32) #1
+
+declare dso_local i8* @realloc(i8*, i32) #1
+
+declare dso_local void @free(i8*) #1
+
+declare dso_local i8* @__rust_alloc(i32, i32) #1
+
+declare dso_local void @__rust_dealloc(i8*, i32, i32) #1
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
@@ -0,0 +1,30 @@
+void print(int value);
+
+int add(int a, int b) {
+    int sum;
+    asm("scoreboard players operation %0 rust = %1 rust\n"
+        "scoreboard players operation %0 rust += %2 rust"
+        : "=r"(sum) : "r"(a), "r"(b));
+    return sum;
+}
+
+int main() {
+    print(add(3, 4));
+
+    int x = 10;
+    asm("scoreboard players add %0 rust %1" : "+r"(x) : "i"(5));
+    print(x);
+
+    int q, r;
+    asm("scoreboard players operation %0 rust = %2 rust\n"
+        "scoreboard players operation %0 rust /= %3 rust\n"
+        "scoreboard players operation %1 rust = %2 rust\n"
+        "scoreboard players operation %1 rust %%= %3 rust"
+        : "=&r"(q), "=&r"(r) : "r"(x), "r"(4));
+    print(q);
+    print(r);
+
+    asm volatile("tellraw @a [{\"text\":\"hello\"}]");
+
+    return 0;
+}
@@ -0,0 +1,30 @@
+; ModuleID = 'inline_asm.c'
+source_filename = "inline_asm.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define dso_local i32 @add(i32 %a, i32 %b) #0 {
+entry:
+  %0 = call i32 asm "scoreboard players operation $0 rust = $1 rust\0Ascoreboard players operation $0 rust += $2 rust", "=r,r,r,~{dirflag},~{fpsr},~{flags}"(i32 %a, i32 %b)
+  ret i32 %0
+}
+
+define dso_local i32 @main() #0 {
+entry:
+  %call = call i32 @add(i32 3, i32 4)
+  call void @print(i32 %call)
+  %0 = call i32 asm "scoreboard players add $0 rust $1", "=r,i,0,~{dirflag},~{fpsr},~{flags}"(i32 5, i32 10)
+  call void @print(i32 %0)
+  %1 = call { i32, i32 } asm "scoreboard players operation $0 rust = $2 rust\0Ascoreboard players operation $0 rust /= $3 rust\0Ascoreboard players operation $1 rust = $2 rust\0Ascoreboard players operation $1 rust %= $3 rust", "=&r,=&r,r,r,~{dirflag},~{fpsr},~{flags}"(i32 %0, i32 4)
+  %q = extractvalue { i32, i32 } %1, 0
+  %r = extractvalue { i32, i32 } %1, 1
+  call void @print(i32 %q)
+  call void @print(i32 %r)
+  call void asm sideeffect "tellraw @a [{\22text\22:\22hello\22}]", "~{dirflag},~{fpsr},~{flags}"()
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
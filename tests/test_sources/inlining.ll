@@ -0,0 +1,89 @@
+; Small functions that get inlined at -O1, next to ones that don't
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@counter = global i32 0
+
+define i32 @add(i32 %a, i32 %b) {
+entry:
+  %sum = add i32 %a, %b
+  ret i32 %sum
+}
+
+; Uses the same names as `main`, which mustn't get mixed up
+define i32 @twice(i32 %i) {
+entry:
+  %s = add i32 %i, %i
+  ret i32 %s
+}
+
+define i64 @widen(i32 %x) {
+entry:
+  %w = sext i32 %x to i64
+  %r = add i64 %w, 4294967296
+  ret i64 %r
+}
+
+define void @bump(i32 %by) {
+entry:
+  %old = load i32, i32* @counter
+  %new = add i32 %old, %by
+  store i32 %new, i32* @counter
+  ret void
+}
+
+define i32 @fact(i32 %n) {
+entry:
+  %done = icmp sle i32 %n, 1
+  br i1 %done, label %base, label %recurse
+
+base:
+  ret i32 1
+
+recurse:
+  %m = sub i32 %n, 1
+  %rest = call i32 @fact(i32 %m)
+  %result = mul i32 %n, %rest
+  ret i32 %result
+}
+
+define i32 @difference(i32 %a, i32 %b) #0 {
+entry:
+  %d = sub i32 %a, %b
+  ret i32 %d
+}
+
+define i32 @main() {
+entry:
+  br label %loop
+
+loop:
+  %i = phi i32 [ 0, %entry ], [ %next, %loop ]
+  call void @bump(i32 %i)
+  %s = call i32 @add(i32 %i, i32 10)
+  %t = call i32 @twice(i32 %s)
+  %u = add i32 %t, %s
+  call void @print(i32 %u)
+  %next = add i32 %i, 1
+  %again = icmp slt i32 %next, 3
+  br i1 %again, label %loop, label %exit
+
+exit:
+  %total = load i32, i32* @counter
+  call void @print(i32 %total)
+  %wide = call i64 @widen(i32 -2)
+  %lo = trunc i64 %wide to i32
+  %hi64 = lshr i64 %wide, 32
+  %hi = trunc i64 %hi64 to i32
+  call void @print(i32 %lo)
+  call void @print(i32 %hi)
+  %f = call i32 @fact(i32 5)
+  call void @print(i32 %f)
+  %d = call i32 @difference(i32 9, i32 4)
+  call void @print(i32 %d)
+  ret i32 0
+}
+
+declare void @print(i32)
+
+attributes #0 = { noinline }
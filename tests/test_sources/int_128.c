@@ -0,0 +1,63 @@
+#include <mcinterface.h>
+
+long long a_lo = 0xFEDCBA9876543210;
+long long a_hi = 0x0123456789ABCDEF;
+long long b_lo = 0x0000000100000003;
+long long b_hi = -1;
+int amount_37 = 37;
+int amount_100 = 100;
+int minus_five = -5;
+long long c = 0x100000000;
+long long d = 1;
+long long e = -3;
+long long f = 2;
+__int128 wide = ((__int128) 1 << 64) + 5;
+struct { int n; __int128 x; } pair = { 7, -((__int128) 1 << 100) };
+
+void print128(unsigned __int128 x) {
+    print((int) x);
+    print((int) (x >> 32));
+    print((int) (x >> 64));
+    print((int) (x >> 96));
+}
+
+int main() {
+    __int128 a = ((__int128) a_hi << 64) | (unsigned long long) a_lo;
+    __int128 b = ((__int128) b_hi << 64) | (unsigned long long) b_lo;
+
+    print128(a + b);
+    print128(a - b);
+    print128(a * b);
+
+    print128(a << 4);
+    print128(a << 68);
+    print128(a << amount_37);
+
+    print128((unsigned __int128) b >> 40);
+    print128(b >> 40);
+    print128(b >> amount_100);
+
+    print(a < b);
+    print((unsigned __int128) a < (unsigned __int128) b);
+    print(a == a);
+    print(b <= b);
+    print((unsigned __int128) a > (unsigned __int128) b);
+
+    long long t = (long long) a;
+    print((int) t);
+    print((int) (t >> 32));
+
+    print128((__int128) minus_five);
+    print128((unsigned __int128) (unsigned) minus_five);
+
+    long long diff = c - d;
+    print((int) diff);
+    print((int) (diff >> 32));
+    print(e < f);
+
+    print128(wide);
+    print128(wide - ((__int128) 1 << 100));
+    volatile __int128 probe = (__int128) 1 << 64;
+    print((int) (probe >> 64));
+    print128(pair.x);
+}
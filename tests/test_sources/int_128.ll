@@ -0,0 +1,134 @@
+; ModuleID = 'int_128.c'
+source_filename = "int_128.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@a_lo = dso_local global i64 -81985529216486896, align 8
+@a_hi = dso_local global i64 81985529216486895, align 8
+@b_lo = dso_local global i64 4294967299, align 8
+@b_hi = dso_local global i64 -1, align 8
+@amount_37 = dso_local global i32 37, align 4
+@amount_100 = dso_local global i32 100, align 4
+@minus_five = dso_local global i32 -5, align 4
+@c = dso_local global i64 4294967296, align 8
+@d = dso_local global i64 1, align 8
+@e = dso_local global i64 -3, align 8
+@f = dso_local global i64 2, align 8
+@wide = dso_local global i128 18446744073709551621, align 4
+@pair = dso_local global { i32, i128 } { i32 7, i128 -1267650600228229401496703205376 }, align 4
+
+; Function Attrs: noinline nounwind optnone
+define dso_local void @print128(i128 %0) #0 {
+  %2 = trunc i128 %0 to i32
+  call void @print(i32 %2)
+  %3 = lshr i128 %0, 32
+  %4 = trunc i128 %3 to i32
+  call void @print(i32 %4)
+  %5 = lshr i128 %0, 64
+  %6 = trunc i128 %5 to i32
+  call void @print(i32 %6)
+  %7 = lshr i128 %0, 96
+  %8 = trunc i128 %7 to i32
+  call void @print(i32 %8)
+  ret void
+}
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = load i64, i64* @a_hi, align 8
+  %2 = sext i64 %1 to i128
+  %3 = shl i128 %2, 64
+  %4 = load i64, i64* @a_lo, align 8
+  %5 = zext i64 %4 to i128
+  %a = or i128 %3, %5
+  %6 = load i64, i64* @b_hi, align 8
+  %7 = sext i64 %6 to i128
+  %8 = shl i128 %7, 64
+  %9 = load i64, i64* @b_lo, align 8
+  %10 = zext i64 %9 to i128
+  %b = or i128 %8, %10
+  %11 = add i128 %a, %b
+  call void @print128(i128 %11)
+  %12 = sub i128 %a, %b
+  call void @print128(i128 %12)
+  %13 = mul i128 %a, %b
+  call void @print128(i128 %13)
+  %14 = shl i128 %a, 4
+  call void @print128(i128 %14)
+  %15 = shl i128 %a, 68
+  call void @print128(i128 %15)
+  %16 = load i32, i32* @amount_37, align 4
+  %17 = zext i32 %16 to i128
+  %18 = shl i128 %a, %17
+  call void @print128(i128 %18)
+  %19 = lshr i128 %b, 40
+  call void @print128(i128 %19)
+  %20 = ashr i128 %b, 40
+  call void @print128(i128 %20)
+  %21 = load i32, i32* @amount_100, align 4
+  %22 = zext i32 %21 to i128
+  %23 = ashr i128 %b, %22
+  call void @print128(i128 %23)
+  %24 = icmp slt i128 %a, %b
+  %25 = zext i1 %24 to i32
+  call void @print(i32 %25)
+  %26 = icmp ult i128 %a, %b
+  %27 = zext i1 %26 to i32
+  call void @print(i32 %27)
+  %28 = icmp eq i128 %a, %a
+  %29 = zext i1 %28 to i32
+  call void @print(i32 %29)
+  %30 = icmp sle i128 %b, %b
+  %31 = zext i1 %30 to i32
+  call void @print(i32 %31)
+  %32 = icmp ugt i128 %a, %b
+  %33 = zext i1 %32 to i32
+  call void @print(i32 %33)
+  %34 = trunc i128 %a to i64
+  %35 = trunc i64 %34 to i32
+  call void @print(i32 %35)
+  %36 = lshr i64 %34, 32
+  %37 = trunc i64 %36 to i32
+  call void @print(i32 %37)
+  %38 = load i32, i32* @minus_five, align 4
+  %39 = sext i32 %38 to i128
+  call void @print128(i128 %39)
+  %40 = zext i32 %38 to i128
+  call void @print128(i128 %40)
+  %41 = load i64, i64* @c, align 8
+  %42 = load i64, i64* @d, align 8
+  %43 = sub i64 %41, %42
+  %44 = trunc i64 %43 to i32
+  call void @print(i32 %44)
+  %45 = lshr i64 %43, 32
+  %46 = trunc i64 %45 to i32
+  call void @print(i32 %46)
+  %47 = load i64, i64* @e, align 8
+  %48 = load i64, i64* @f, align 8
+  %49 = icmp slt i64 %47, %48
+  %50 = zext i1 %49 to i32
+  call void @print(i32 %50)
+  %51 = load i128, i128* @wide, align 4
+  call void @print128(i128 %51)
+  %52 = add i128 %51, -1267650600228229401496703205376
+  call void @print128(i128 %52)
+  %53 = alloca i128, align 4
+  store volatile i128 18446744073709551616, i128* %53, align 4
+  %54 = load volatile i128, i128* %53, align 4
+  %55 = lshr i128 %54, 64
+  %56 = trunc i128 %55 to i32
+  call void @print(i32 %56)
+  %57 = getelementptr { i32, i128 }, { i32, i128 }* @pair, i32 0, i32 1
+  %58 = load i128, i128* %57, align 4
+  call void @print128(i128 %58)
+  ret i32 0
+}
+
+declare void @print(i32) #1
+
+attributes #0 = { noinline nounwind optnone "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+attributes #1 = { "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+
+!llvm.ident = !{!0}
+
+!0 = !{!"clang version 10.0.1 "}
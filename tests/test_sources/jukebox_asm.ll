@@ -0,0 +1,12 @@
+; Inline assembly that reads memory from a jukebox, which doesn't work with the storage backend
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define i32 @main() {
+entry:
+  %0 = call i32 asm sideeffect "execute store result score $0 rust run data get block ~ ~ ~ RecordItem.tag.Memory 1", "=r"()
+  call void @print(i32 %0)
+  ret i32 0
+}
+
+declare void @print(i32)
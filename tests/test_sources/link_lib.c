@@ -0,0 +1,10 @@
+int counter = 5;
+
+static int helper(int x) {
+    return x * 2;
+}
+
+int lib_double(int x) {
+    counter += 1;
+    return helper(x);
+}
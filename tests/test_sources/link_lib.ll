@@ -0,0 +1,37 @@
+; ModuleID = 'link_lib.c'
+source_filename = "link_lib.c"
+target datalayout = "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+target triple = "x86_64-pc-linux-gnu"
+
+@counter = dso_local global i32 5, align 4
+
+; Function Attrs: noinline nounwind optnone sspstrong uwtable
+define dso_local i32 @lib_double(i32 %0) #0 {
+  %2 = alloca i32, align 4
+  store i32 %0, i32* %2, align 4
+  %3 = load i32, i32* @counter, align 4
+  %4 = add nsw i32 %3, 1
+  store i32 %4, i32* @counter, align 4
+  %5 = load i32, i32* %2, align 4
+  %6 = call i32 @helper(i32 %5)
+  ret i32 %6
+}
+
+; Function Attrs: noinline nounwind optnone sspstrong uwtable
+define internal i32 @helper(i32 %0) #0 {
+  %2 = alloca i32, align 4
+  store i32 %0, i32* %2, align 4
+  %3 = load i32, i32* %2, align 4
+  %4 = mul nsw i32 %3, 2
+  ret i32 %4
+}
+
+attributes #0 = { noinline nounwind optnone sspstrong uwtable "correctly-rounded-divide-sqrt-fp-math"="false" "disable-tail-calls"="false" "frame-pointer"="all" "less-precise-fpmad"="false" "min-legal-vector-width"="0" "no-infs-fp-math"="false" "no-jump-tables"="false" "no-nans-fp-math"="false" "no-signed-zeros-fp-math"="false" "no-trapping-math"="false" "stack-protector-buffer-size"="8" "target-cpu"="x86-64" "target-features"="+cx8,+fxsr,+mmx,+sse,+sse2,+x87" "unsafe-fp-math"="false" "use-soft-float"="false" }
+
+!llvm.module.flags = !{!0, !1, !2}
+!llvm.ident = !{!3}
+
+!0 = !{i32 1, !"wchar_size", i32 4}
+!1 = !{i32 7, !"PIC Level", i32 2}
+!2 = !{i32 7, !"PIE Level", i32 2}
+!3 = !{!"clang version 10.0.1 "}
@@ -0,0 +1,16 @@
+#include <mcinterface.h>
+
+extern int counter;
+
+int lib_double(int x);
+
+static int helper(int x) {
+    return x + 1;
+}
+
+int main() {
+    print(lib_double(21));
+    print(helper(1));
+    print(counter);
+    return 0;
+}
@@ -0,0 +1,18 @@
+void print(int value);
+
+#define STDOUT (*(volatile char *)0xFFFF0000)
+#define TICK (*(volatile int *)0xFFFF0004)
+
+void puts_mmio(const char *s) {
+    while (*s) {
+        STDOUT = *s++;
+    }
+}
+
+int main() {
+    int start = TICK;
+    puts_mmio("Hi!\n");
+    int end = TICK;
+    print(end >= start);
+    return 0;
+}
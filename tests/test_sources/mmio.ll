@@ -0,0 +1,41 @@
+; ModuleID = 'mmio.c'
+source_filename = "mmio.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@.str = private unnamed_addr constant [5 x i8] c"Hi!\0A\00", align 1
+
+define dso_local void @puts_mmio(i8* %s) #0 {
+entry:
+  br label %loop
+
+loop:
+  %p = phi i8* [ %s, %entry ], [ %next, %body ]
+  %c = load i8, i8* %p, align 1
+  %done = icmp eq i8 %c, 0
+  br i1 %done, label %exit, label %body
+
+body:
+  store volatile i8 %c, i8* inttoptr (i32 -65536 to i8*), align 1
+  %next = getelementptr inbounds i8, i8* %p, i32 1
+  br label %loop
+
+exit:
+  ret void
+}
+
+define dso_local i32 @main() #0 {
+entry:
+  %start = load volatile i32, i32* inttoptr (i32 -65532 to i32*), align 4
+  call void @puts_mmio(i8* getelementptr inbounds ([5 x i8], [5 x i8]* @.str, i32 0, i32 0))
+  %end = load volatile i32, i32* inttoptr (i32 -65532 to i32*), align 4
+  %later = icmp sge i32 %end, %start
+  %later_int = zext i1 %later to i32
+  call void @print(i32 %later_int)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
@@ -0,0 +1,26 @@
+; Lots of short-lived values, only a few of which are still needed after each call
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define i32 @twice(i32 %x) noinline {
+entry:
+  %y = add i32 %x, %x
+  ret i32 %y
+}
+
+define i32 @main() {
+entry:
+  %a = add i32 1, 2
+  %b = mul i32 %a, 3
+  %c = add i32 %b, 4
+  %d = call i32 @twice(i32 %b)
+  %e = add i32 %d, %a
+  %f = call i32 @twice(i32 %e)
+  %g = add i32 %f, %c
+  %h = mul i32 %g, %a
+  call void @print(i32 %c)
+  call void @print(i32 %h)
+  ret i32 0
+}
+
+declare void @print(i32)
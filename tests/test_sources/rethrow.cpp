@@ -0,0 +1,48 @@
+extern "C" {
+
+void print(int value);
+
+void thrower(int x) {
+    throw x;
+}
+
+void rethrower(int x) {
+    try {
+        thrower(x);
+    } catch (int e) {
+        print(e);
+        throw;
+    }
+}
+
+void nested() {
+    try {
+        thrower(10);
+    } catch (int outer) {
+        try {
+            thrower(20);
+        } catch (int inner) {
+            print(inner);
+        }
+        throw;
+    }
+}
+
+int main() {
+    try {
+        rethrower(5);
+    } catch (int e) {
+        print(e + 1);
+    }
+
+    try {
+        nested();
+    } catch (int e) {
+        print(e);
+    }
+
+    print(42);
+    return 0;
+}
+
+}
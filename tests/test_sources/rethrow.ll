@@ -0,0 +1,188 @@
+; ModuleID = 'rethrow.cpp'
+source_filename = "rethrow.cpp"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@_ZTIi = external constant i8*
+
+define dso_local void @thrower(i32 %x) #0 {
+entry:
+  %exception = call i8* @__cxa_allocate_exception(i32 4)
+  %0 = bitcast i8* %exception to i32*
+  store i32 %x, i32* %0, align 16
+  call void @__cxa_throw(i8* %exception, i8* bitcast (i8** @_ZTIi to i8*), i8* null)
+  unreachable
+}
+
+define dso_local void @rethrower(i32 %x) #0 personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  invoke void @thrower(i32 %x)
+          to label %try.cont unwind label %lpad
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %1 = extractvalue { i8*, i32 } %0, 0
+  %2 = extractvalue { i8*, i32 } %0, 1
+  %3 = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIi to i8*))
+  %matches = icmp eq i32 %2, %3
+  br i1 %matches, label %catch, label %eh.resume
+
+catch:
+  %4 = call i8* @__cxa_begin_catch(i8* %1)
+  %5 = bitcast i8* %4 to i32*
+  %6 = load i32, i32* %5, align 4
+  call void @print(i32 %6)
+  invoke void @__cxa_rethrow()
+          to label %unreachable unwind label %lpad1
+
+lpad1:
+  %7 = landingpad { i8*, i32 }
+          cleanup
+  call void @__cxa_end_catch()
+  resume { i8*, i32 } %7
+
+try.cont:
+  ret void
+
+eh.resume:
+  resume { i8*, i32 } %0
+
+unreachable:
+  unreachable
+}
+
+define dso_local void @nested() #0 personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  invoke void @thrower(i32 10)
+          to label %try.cont6 unwind label %lpad
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %1 = extractvalue { i8*, i32 } %0, 0
+  %2 = extractvalue { i8*, i32 } %0, 1
+  %3 = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIi to i8*))
+  %matches = icmp eq i32 %2, %3
+  br i1 %matches, label %catch, label %eh.resume
+
+catch:
+  %4 = call i8* @__cxa_begin_catch(i8* %1)
+  invoke void @thrower(i32 20)
+          to label %try.cont unwind label %lpad1
+
+lpad1:
+  %5 = landingpad { i8*, i32 }
+          cleanup
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %6 = extractvalue { i8*, i32 } %5, 0
+  %7 = extractvalue { i8*, i32 } %5, 1
+  %matches2 = icmp eq i32 %7, %3
+  br i1 %matches2, label %catch3, label %ehcleanup
+
+catch3:
+  %8 = call i8* @__cxa_begin_catch(i8* %6)
+  %9 = bitcast i8* %8 to i32*
+  %10 = load i32, i32* %9, align 4
+  call void @print(i32 %10)
+  call void @__cxa_end_catch()
+  br label %try.cont
+
+try.cont:
+  invoke void @__cxa_rethrow()
+          to label %unreachable unwind label %lpad4
+
+lpad4:
+  %11 = landingpad { i8*, i32 }
+          cleanup
+  br label %ehcleanup
+
+ehcleanup:
+  %12 = phi { i8*, i32 } [ %5, %lpad1 ], [ %11, %lpad4 ]
+  call void @__cxa_end_catch()
+  resume { i8*, i32 } %12
+
+try.cont6:
+  ret void
+
+eh.resume:
+  resume { i8*, i32 } %0
+
+unreachable:
+  unreachable
+}
+
+define dso_local i32 @main() #0 personality i8* bitcast (i32 (...)* @__gxx_personality_v0 to i8*) {
+entry:
+  invoke void @rethrower(i32 5)
+          to label %try.cont unwind label %lpad
+
+lpad:
+  %0 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %1 = extractvalue { i8*, i32 } %0, 0
+  %2 = extractvalue { i8*, i32 } %0, 1
+  %3 = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIi to i8*))
+  %matches = icmp eq i32 %2, %3
+  br i1 %matches, label %catch, label %eh.resume
+
+catch:
+  %4 = call i8* @__cxa_begin_catch(i8* %1)
+  %5 = bitcast i8* %4 to i32*
+  %6 = load i32, i32* %5, align 4
+  %add = add nsw i32 %6, 1
+  call void @print(i32 %add)
+  call void @__cxa_end_catch()
+  br label %try.cont
+
+try.cont:
+  invoke void @nested()
+          to label %try.cont3 unwind label %lpad1
+
+lpad1:
+  %7 = landingpad { i8*, i32 }
+          catch i8* bitcast (i8** @_ZTIi to i8*)
+  %8 = extractvalue { i8*, i32 } %7, 0
+  %9 = extractvalue { i8*, i32 } %7, 1
+  %typeid = call i32 @llvm.eh.typeid.for(i8* bitcast (i8** @_ZTIi to i8*))
+  %matches2 = icmp eq i32 %9, %typeid
+  br i1 %matches2, label %catch2, label %eh.resume4
+
+catch2:
+  %10 = call i8* @__cxa_begin_catch(i8* %8)
+  %11 = bitcast i8* %10 to i32*
+  %12 = load i32, i32* %11, align 4
+  call void @print(i32 %12)
+  call void @__cxa_end_catch()
+  br label %try.cont3
+
+try.cont3:
+  call void @print(i32 42)
+  ret i32 0
+
+eh.resume:
+  resume { i8*, i32 } %0
+
+eh.resume4:
+  resume { i8*, i32 } %7
+}
+
+declare dso_local i8* @__cxa_allocate_exception(i32)
+
+declare dso_local void @__cxa_throw(i8*, i8*, i8*)
+
+declare dso_local void @__cxa_rethrow()
+
+declare dso_local i32 @__gxx_personality_v0(...)
+
+declare i32 @llvm.eh.typeid.for(i8*) #2
+
+declare dso_local i8* @__cxa_begin_catch(i8*)
+
+declare dso_local void @__cxa_end_catch()
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
+attributes #2 = { nounwind readnone }
@@ -0,0 +1,37 @@
+#include <mcinterface.h>
+
+long long a = 0xFEDCBA9876543210;
+int small = 4;
+int big = 36;
+
+void print64(unsigned long long x) {
+    print((int) x);
+    print((int) (x >> 32));
+}
+
+void shift_all(int s) {
+    print64(a << s);
+    print64((unsigned long long) a >> s);
+    print64(a >> s);
+}
+
+int main() {
+    shift_all(small);
+    shift_all(big);
+
+    print64(a << 1);
+    print64((unsigned long long) a >> 1);
+    print64(a >> 1);
+
+    print64(a << 31);
+    print64((unsigned long long) a >> 31);
+    print64(a >> 31);
+
+    print64(a << 32);
+    print64((unsigned long long) a >> 32);
+    print64(a >> 32);
+
+    print64(a << 40);
+    print64((unsigned long long) a >> 40);
+    print64(a >> 40);
+}
@@ -0,0 +1,77 @@
+; ModuleID = 'shift_64.c'
+source_filename = "shift_64.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+@a = dso_local global i64 -81985529216486896, align 8
+@small = dso_local global i32 4, align 4
+@big = dso_local global i32 36, align 4
+
+; Function Attrs: noinline nounwind optnone
+define dso_local void @print64(i64 %0) #0 {
+  %2 = trunc i64 %0 to i32
+  call void @print(i32 %2)
+  %3 = lshr i64 %0, 32
+  %4 = trunc i64 %3 to i32
+  call void @print(i32 %4)
+  ret void
+}
+
+; Function Attrs: noinline nounwind optnone
+define dso_local void @shift_all(i32 %0) #0 {
+  %2 = load i64, i64* @a, align 8
+  %3 = zext i32 %0 to i64
+  %4 = shl i64 %2, %3
+  call void @print64(i64 %4)
+  %5 = lshr i64 %2, %3
+  call void @print64(i64 %5)
+  %6 = ashr i64 %2, %3
+  call void @print64(i64 %6)
+  ret void
+}
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = load i32, i32* @small, align 4
+  call void @shift_all(i32 %1)
+  %2 = load i32, i32* @big, align 4
+  call void @shift_all(i32 %2)
+  %3 = load i64, i64* @a, align 8
+  %4 = shl i64 %3, 1
+  call void @print64(i64 %4)
+  %5 = lshr i64 %3, 1
+  call void @print64(i64 %5)
+  %6 = ashr i64 %3, 1
+  call void @print64(i64 %6)
+  %7 = load i64, i64* @a, align 8
+  %8 = shl i64 %7, 31
+  call void @print64(i64 %8)
+  %9 = lshr i64 %7, 31
+  call void @print64(i64 %9)
+  %10 = ashr i64 %7, 31
+  call void @print64(i64 %10)
+  %11 = load i64, i64* @a, align 8
+  %12 = shl i64 %11, 32
+  call void @print64(i64 %12)
+  %13 = lshr i64 %11, 32
+  call void @print64(i64 %13)
+  %14 = ashr i64 %11, 32
+  call void @print64(i64 %14)
+  %15 = load i64, i64* @a, align 8
+  %16 = shl i64 %15, 40
+  call void @print64(i64 %16)
+  %17 = lshr i64 %15, 40
+  call void @print64(i64 %17)
+  %18 = ashr i64 %15, 40
+  call void @print64(i64 %18)
+  ret i32 0
+}
+
+declare void @print(i32) #1
+
+attributes #0 = { noinline nounwind optnone "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+attributes #1 = { "frame-pointer"="all" "no-trapping-math"="false" "target-cpu"="pentium4" }
+
+!llvm.ident = !{!0}
+
+!0 = !{!"clang version 10.0.1 "}
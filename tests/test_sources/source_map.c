@@ -0,0 +1,6 @@
+#include <mcinterface.h>
+
+int main() {
+    print(7);
+    return 0;
+}
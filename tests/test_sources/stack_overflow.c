@@ -0,0 +1,13 @@
+void print(int value);
+
+int recurse(int n) {
+    volatile int local = n;
+    return recurse(n + 1) + local;
+}
+
+int main() {
+    print(1);
+    recurse(0);
+    print(2);
+    return 0;
+}
@@ -0,0 +1,28 @@
+; ModuleID = 'stack_overflow.c'
+source_filename = "stack_overflow.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define dso_local i32 @recurse(i32 %n) #0 {
+entry:
+  %local = alloca i32, align 4
+  store volatile i32 %n, i32* %local, align 4
+  %add = add nsw i32 %n, 1
+  %call = call i32 @recurse(i32 %add)
+  %value = load volatile i32, i32* %local, align 4
+  %result = add nsw i32 %call, %value
+  ret i32 %result
+}
+
+define dso_local i32 @main() #0 {
+entry:
+  call void @print(i32 1)
+  %call = call i32 @recurse(i32 0)
+  call void @print(i32 2)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
@@ -0,0 +1,15 @@
+// What a Rust panic with `panic=unwind` boils down to, with nothing to catch it
+void print(int value);
+int _Unwind_RaiseException(void *exception);
+
+void panic(void) {
+    print(1);
+    _Unwind_RaiseException(0);
+    print(2);
+}
+
+int main() {
+    panic();
+    print(3);
+    return 0;
+}
@@ -0,0 +1,26 @@
+; ModuleID = 'uncaught.c'
+source_filename = "uncaught.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define dso_local void @panic() #0 {
+entry:
+  call void @print(i32 1)
+  %call = call i32 @_Unwind_RaiseException(i8* null)
+  call void @print(i32 2)
+  ret void
+}
+
+define dso_local i32 @main() #0 {
+entry:
+  call void @panic()
+  call void @print(i32 3)
+  ret i32 0
+}
+
+declare dso_local void @print(i32) #1
+
+declare dso_local i32 @_Unwind_RaiseException(i8*) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { "frame-pointer"="all" }
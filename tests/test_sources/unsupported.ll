@@ -0,0 +1,42 @@
+; Two functions that each use an instruction that can't be compiled, which should both be reported
+source_filename = "unsupported.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+define float @rem_f32(float %a, float %b) !dbg !7 {
+entry:
+  %r = frem float %a, %b, !dbg !11
+  ret float %r, !dbg !12
+}
+
+define i32 @main() !dbg !13 {
+entry:
+  br label %body, !dbg !14
+
+body:
+  %r = frem double 5.0, 3.0, !dbg !15
+  %i = fptosi double %r to i32, !dbg !15
+  call void @print(i32 %i), !dbg !16
+  ret i32 0, !dbg !16
+}
+
+declare void @print(i32)
+
+!llvm.dbg.cu = !{!0}
+!llvm.module.flags = !{!3, !4}
+
+!0 = distinct !DICompileUnit(language: DW_LANG_C99, file: !1, producer: "clang version 10.0.1 ", isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug, enums: !2, splitDebugInlining: false, nameTableKind: None)
+!1 = !DIFile(filename: "unsupported.c", directory: "/tmp")
+!2 = !{}
+!3 = !{i32 7, !"Dwarf Version", i32 4}
+!4 = !{i32 2, !"Debug Info Version", i32 3}
+!7 = distinct !DISubprogram(name: "rem_f32", scope: !1, file: !1, line: 3, type: !8, scopeLine: 3, spFlags: DISPFlagDefinition, unit: !0, retainedNodes: !2)
+!8 = !DISubroutineType(types: !9)
+!9 = !{!10}
+!10 = !DIBasicType(name: "int", size: 32, encoding: DW_ATE_signed)
+!11 = !DILocation(line: 4, column: 14, scope: !7)
+!12 = !DILocation(line: 4, column: 5, scope: !7)
+!13 = distinct !DISubprogram(name: "main", scope: !1, file: !1, line: 7, type: !8, scopeLine: 7, spFlags: DISPFlagDefinition, unit: !0, retainedNodes: !2)
+!14 = !DILocation(line: 8, column: 5, scope: !13)
+!15 = !DILocation(line: 9, column: 11, scope: !13)
+!16 = !DILocation(line: 10, column: 5, scope: !13)
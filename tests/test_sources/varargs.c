@@ -0,0 +1,49 @@
+#include <mcinterface.h>
+#include <stdarg.h>
+
+int sum(int count, ...) {
+    va_list args, copy;
+    va_start(args, count);
+    va_copy(copy, args);
+
+    int total = 0;
+    for (int i = 0; i < count; ++i) {
+        total += va_arg(args, int);
+    }
+
+    // Read the first argument again through the copy
+    total += 100 * va_arg(copy, int);
+
+    va_end(copy);
+    va_end(args);
+    return total;
+}
+
+long long last_wide(int count, ...) {
+    // Calls another variadic function before looking at our own arguments
+    print(sum(1, count));
+
+    va_list args;
+    va_start(args, count);
+
+    long long last = 0;
+    for (int i = 0; i < count; ++i) {
+        last = va_arg(args, long long);
+    }
+
+    va_end(args);
+    return last;
+}
+
+int main() {
+    print(sum(3, 1, 2, 3));
+
+    long long wide = last_wide(2, 1LL, 0x100000007LL);
+    print((int)wide);
+    print((int)(wide >> 32));
+
+    int (*func)(int, ...) = sum;
+    print(func(2, 10, 20));
+
+    return 0;
+}
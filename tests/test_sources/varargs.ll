@@ -0,0 +1,93 @@
+; ModuleID = 'varargs.c'
+source_filename = "varargs.c"
+target datalayout = "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+target triple = "i386-pc-linux-gnu"
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @sum(i32 %0, ...) #0 {
+  %2 = alloca i8*, align 4
+  %3 = alloca i8*, align 4
+  %4 = bitcast i8** %2 to i8*
+  call void @llvm.va_start(i8* %4)
+  %5 = bitcast i8** %3 to i8*
+  call void @llvm.va_copy(i8* %5, i8* %4)
+  br label %6
+
+6:                                                ; preds = %10, %1
+  %7 = phi i32 [ 0, %1 ], [ %13, %10 ]
+  %8 = phi i32 [ 0, %1 ], [ %12, %10 ]
+  %9 = icmp slt i32 %7, %0
+  br i1 %9, label %10, label %14
+
+10:                                               ; preds = %6
+  %11 = va_arg i8** %2, i32
+  %12 = add nsw i32 %8, %11
+  %13 = add nsw i32 %7, 1
+  br label %6
+
+14:                                               ; preds = %6
+  %15 = va_arg i8** %3, i32
+  %16 = mul nsw i32 100, %15
+  %17 = add nsw i32 %8, %16
+  call void @llvm.va_end(i8* %5)
+  call void @llvm.va_end(i8* %4)
+  ret i32 %17
+}
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i64 @last_wide(i32 %0, ...) #0 {
+  %2 = alloca i8*, align 4
+  %3 = call i32 (i32, ...) @sum(i32 1, i32 %0)
+  call void @print(i32 %3)
+  %4 = bitcast i8** %2 to i8*
+  call void @llvm.va_start(i8* %4)
+  br label %5
+
+5:                                                ; preds = %9, %1
+  %6 = phi i32 [ 0, %1 ], [ %11, %9 ]
+  %7 = phi i64 [ 0, %1 ], [ %10, %9 ]
+  %8 = icmp slt i32 %6, %0
+  br i1 %8, label %9, label %12
+
+9:                                                ; preds = %5
+  %10 = va_arg i8** %2, i64
+  %11 = add nsw i32 %6, 1
+  br label %5
+
+12:                                               ; preds = %5
+  call void @llvm.va_end(i8* %4)
+  ret i64 %7
+}
+
+; Function Attrs: noinline nounwind optnone
+define dso_local i32 @main() #0 {
+  %1 = call i32 (i32, ...) @sum(i32 3, i32 1, i32 2, i32 3)
+  call void @print(i32 %1)
+  %2 = call i64 (i32, ...) @last_wide(i32 2, i64 1, i64 4294967303)
+  %3 = trunc i64 %2 to i32
+  call void @print(i32 %3)
+  %4 = lshr i64 %2, 32
+  %5 = trunc i64 %4 to i32
+  call void @print(i32 %5)
+  %6 = alloca i32 (i32, ...)*, align 4
+  store i32 (i32, ...)* @sum, i32 (i32, ...)** %6, align 4
+  %7 = load i32 (i32, ...)*, i32 (i32, ...)** %6, align 4
+  %8 = call i32 (i32, ...) %7(i32 2, i32 10, i32 20)
+  call void @print(i32 %8)
+  ret i32 0
+}
+
+; Function Attrs: nounwind
+declare void @llvm.va_start(i8*) #1
+
+; Function Attrs: nounwind
+declare void @llvm.va_copy(i8*, i8*) #1
+
+; Function Attrs: nounwind
+declare void @llvm.va_end(i8*) #1
+
+declare dso_local void @print(i32) #2
+
+attributes #0 = { noinline nounwind optnone }
+attributes #1 = { nounwind }
+attributes #2 = { "frame-pointer"="all" }
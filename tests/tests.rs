@@ -1,13 +1,23 @@
-use langcraft::{Datapack, Interpreter, BuildOptions};
+use langcraft::{Datapack, Interpreter, BuildOptions, MemoryBackend, MemoryLayout};
 use langcraft::cir::ScoreHolder;
+use langcraft::interpreter::InterpError;
 use std::path::Path;
 
-pub fn compile_and_run(path: &Path) -> Interpreter {
-    let datapack = Datapack::from_bc(path, &BuildOptions::default()).unwrap();
+pub fn compile_and_run(path: &Path, options: &BuildOptions) -> Interpreter {
+    compile_and_run_files(&[path], options)
+}
+
+/// Compiles the program, but leaves running it to the caller
+pub fn compile(paths: &[&Path], options: &BuildOptions) -> Interpreter {
+    let datapack = Datapack::from_files(paths, options).unwrap();
 
     let idx = datapack.functions.iter().enumerate().find(|(_, f)| f.id.name == "run").unwrap().0;
 
-    let mut interp = Interpreter::new(datapack, idx, "");
+    Interpreter::new(datapack, idx, "")
+}
+
+pub fn compile_and_run_files(paths: &[&Path], options: &BuildOptions) -> Interpreter {
+    let mut interp = compile(paths, options);
 
     if let Err(err) = interp.run_to_end() {
         for f in interp.program() {
@@ -17,11 +27,11 @@ pub fn compile_and_run(path: &Path) -> Interpreter {
             }
         }
         eprintln!("==========================");
-        eprintln!("Failure in test {:?}", path);
+        eprintln!("Failure in test {:?}", paths);
         eprintln!("Encountered error: {}", err);
         eprintln!("Stack:");
-        for (f, c) in interp.call_stack() {
-            eprintln!("{} line {}", f.id, c);
+        for frame in interp.backtrace() {
+            eprintln!("{}", frame);
         }
         panic!();
     }
@@ -32,7 +42,7 @@ pub fn compile_and_run(path: &Path) -> Interpreter {
 #[test]
 pub fn fibonacci() {
     assert_eq!(
-        compile_and_run(Path::new("./tests/fibonacci.bc")).output,
+        compile_and_run(Path::new("./tests/fibonacci.bc"), &BuildOptions::default()).output,
         vec!["0", "1", "1", "2", "3", "5", "8", "13", "21", "34"]
     );
 }
@@ -40,13 +50,13 @@ pub fn fibonacci() {
 #[test]
 pub fn dyn_call() {
     assert_eq!(
-        compile_and_run(Path::new("./tests/dyn_call.bc")).output,
+        compile_and_run(Path::new("./tests/dyn_call.bc"), &BuildOptions::default()).output,
         vec!["42"],
     )
 }
 
 pub fn do_c_test(path: &Path, output: Vec<&str>) {
-    let interp = compile_and_run(path);
+    let interp = compile_and_run(path, &BuildOptions::default());
     assert_eq!(interp.get_rust_score(&ScoreHolder::new("%return%0".to_string()).unwrap()).unwrap(), 0);
     assert_eq!(interp.output, output);
 }
@@ -67,30 +77,482 @@ pub fn run_c_tests() {
 
 #[test]
 pub fn add_overflow() {
-    let interp = compile_and_run(Path::new("./tests/add_overflow.bc"));
+    let interp = compile_and_run(Path::new("./tests/add_overflow.bc"), &BuildOptions::default());
     assert_eq!(interp.output, vec!["42"]);
 }
 
 #[test]
 pub fn func_ptr_cast() {
-    let interp = compile_and_run(Path::new("./tests/func_ptr_cast.bc"));
+    let interp = compile_and_run(Path::new("./tests/func_ptr_cast.bc"), &BuildOptions::default());
     assert_eq!(interp.output, vec!["42", "42"]);
 }
 
 #[test]
 pub fn func_ptr_direct_cast() {
-    let interp = compile_and_run(Path::new("./tests/func_ptr_direct_cast.bc"));
+    let interp = compile_and_run(Path::new("./tests/func_ptr_direct_cast.bc"), &BuildOptions::default());
     assert_eq!(interp.output, vec!["1"]);
 }
 
 #[test]
 pub fn sext_8to64() {
-    let interp = compile_and_run(Path::new("./tests/sext_8to64.bc"));
+    let interp = compile_and_run(Path::new("./tests/sext_8to64.bc"), &BuildOptions::default());
     assert_eq!(interp.output, vec!["127", "0", "-128", "-1"]);
 }
 
 #[test]
 pub fn arith_shr() {
-    let interp = compile_and_run(Path::new("./tests/arith_shr.bc"));
+    let interp = compile_and_run(Path::new("./tests/arith_shr.bc"), &BuildOptions::default());
     assert_eq!(interp.output, vec!["-23131"]);
 }
+
+#[test]
+pub fn float_ops() {
+    let interp = compile_and_run(Path::new("./tests/float_ops.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["-37", "-4", "1", "1", "-925", "25", "-802507642", "-824442880", "-1294967296"]);
+}
+
+#[test]
+pub fn shift_64() {
+    let interp = compile_and_run(Path::new("./tests/shift_64.bc"), &BuildOptions::default());
+    assert_eq!(
+        interp.output,
+        vec![
+            "1698898176", "-305419897", "-2023406815", "267242409", "-2023406815", "-1193047",
+            "0", "1698898176", "267242409", "0", "-1193047", "-1",
+            "-324508640", "-38177488", "992614664", "2137939276", "992614664", "-9544372",
+            "0", "992614664", "-38177488", "1", "-38177488", "-1",
+            "0", "1985229328", "-19088744", "0", "-19088744", "-1",
+            "0", "1412567040", "16702650", "0", "-74566", "-1",
+        ]
+    );
+}
+
+#[test]
+pub fn int_128() {
+    let interp = compile_and_run(Path::new("./tests/int_128.bc"), &BuildOptions::default());
+    assert_eq!(
+        interp.output,
+        vec![
+            "1985229331", "-19088743", "-1985229330", "19088743",
+            "1985229325", "-19088745", "-1985229328", "19088743",
+            "1660720688", "1927963097", "629928536", "-1908874354",
+            "1698898176", "-305419897", "-1698898177", "305419896",
+            "0", "0", "1698898176", "-305419897",
+            "0", "-897170944", "-610839794", "897170943",
+            "-16777216", "-1", "16777215", "0",
+            "-16777216", "-1", "-1", "-1",
+            "-1", "-1", "-1", "-1",
+            "0", "1", "1", "1", "0",
+            "1985229328", "-19088744",
+            "-5", "-1", "-1", "-1",
+            "-5", "0", "0", "0",
+            "-1", "0",
+            "1",
+            "5", "0", "1", "0",
+            "5", "0", "1", "-16",
+            "1",
+            "0", "0", "0", "-16",
+        ]
+    );
+}
+
+#[test]
+pub fn div_64() {
+    let interp = compile_and_run(Path::new("./tests/div_64.bc"), &BuildOptions::default());
+    assert_eq!(
+        interp.output,
+        vec![
+            "7", "5", "5", "1", "5", "5", "9", "0", "7", "3", "7", "0", "4", "4", "7", "6", "4",
+            "4", "8", "1", "-520093696", "-1761607739", "-9876543", "-210987", "9876543",
+            "-210987",
+        ]
+    );
+}
+
+#[test]
+pub fn source_map() {
+    let datapack = Datapack::from_bc(Path::new("./tests/source_map.bc"), &BuildOptions::default()).unwrap();
+
+    let lines = datapack.functions
+        .iter()
+        .flat_map(|f| f.locs.values())
+        .map(|loc| {
+            assert_eq!(loc.file, "/tmp/source_map.c");
+            (loc.line, loc.col)
+        })
+        .collect::<std::collections::BTreeSet<_>>();
+    assert_eq!(lines.into_iter().collect::<Vec<_>>(), vec![(4, Some(5)), (5, Some(5))]);
+
+    for f in datapack.functions.iter() {
+        assert!(f.cmds.iter().all(|c| !c.to_string().contains("!LOC")));
+    }
+
+    let interp = compile_and_run(Path::new("./tests/source_map.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["7"]);
+}
+
+#[test]
+pub fn textual_ir() {
+    let interp = compile_and_run(Path::new("./tests/test_sources/sext_8to64.ll"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["127", "0", "-128", "-1"]);
+}
+
+#[test]
+pub fn link_modules() {
+    let interp = compile_and_run_files(&[Path::new("./tests/link_main.bc"), Path::new("./tests/test_sources/link_lib.ll")], &BuildOptions::default());
+    assert_eq!(interp.output, vec!["42", "2", "6"]);
+}
+
+#[test]
+pub fn link_missing_symbol() {
+    let err = Datapack::from_bc(Path::new("./tests/link_main.bc"), &BuildOptions::default()).err().unwrap();
+    assert!(err.contains("undefined symbol @lib_double in function `main`"), "{}", err);
+    assert!(err.contains("undefined symbol @counter in function `main`"), "{}", err);
+}
+
+#[test]
+pub fn unsupported_instrs() {
+    let err = Datapack::from_bc(Path::new("./tests/unsupported.bc"), &BuildOptions::default()).err().unwrap();
+    let errors = err.lines().collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2, "{}", err);
+    assert!(errors.iter().all(|e| e.starts_with("unsupported instruction FRem")), "{}", err);
+    assert!(err.contains("in function `rem_f32`, block %entry at /tmp/unsupported.c:4:14"), "{}", err);
+    assert!(err.contains("in function `main`, block %body at /tmp/unsupported.c:9:11"), "{}", err);
+}
+
+#[test]
+pub fn link_duplicate_symbol() {
+    let paths = [Path::new("./tests/link_lib.bc"), Path::new("./tests/link_lib.bc")];
+    let err = Datapack::from_files(&paths, &BuildOptions::default()).err().unwrap();
+    assert!(err.contains("symbol multiply defined"), "{}", err);
+}
+
+#[test]
+pub fn library_mode() {
+    let options = BuildOptions { library: Some("mylib".to_string()), ..BuildOptions::default() };
+    let datapack = Datapack::from_bc(Path::new("./tests/link_lib.bc"), &options).unwrap();
+    assert_eq!(datapack.run_index(), None);
+
+    let idx = datapack.functions.iter().position(|f| f.id.to_string() == "mylib:api/lib_double").unwrap();
+
+    let mut interp = Interpreter::new(datapack, idx, "");
+    interp.rust_scores.insert(ScoreHolder::new("$arg0%0".to_string()).unwrap(), 21);
+    interp.run_to_end().unwrap();
+
+    assert_eq!(interp.get_rust_score(&ScoreHolder::new("$ret%0".to_string()).unwrap()).unwrap(), 42);
+    assert_eq!(interp.get_rust_score(&ScoreHolder::new("$done".to_string()).unwrap()).unwrap(), 1);
+}
+
+#[test]
+pub fn library_name_collision() {
+    let options = BuildOptions { library: Some("mylib".to_string()), ..BuildOptions::default() };
+    let err = Datapack::from_bc(Path::new("./tests/api_collision.bc"), &options).err().unwrap();
+    assert_eq!(err.lines().count(), 2, "{}", err);
+    assert!(err.contains("@Foo and @foo would both be mylib:api/foo"), "{}", err);
+    assert!(err.contains("@a.b and @a_b would both be mylib:api/a_b"), "{}", err);
+}
+
+#[test]
+pub fn varargs() {
+    assert_eq!(
+        compile_and_run(Path::new("./tests/varargs.bc"), &BuildOptions::default()).output,
+        vec!["106", "202", "7", "1", "1030"],
+    );
+}
+
+#[test]
+pub fn big_memory() {
+    let path = Path::new("./tests/big_memory.bc");
+
+    let mut interp = compile(&[path], &BuildOptions::default());
+    assert!(interp.run_to_end().is_err());
+
+    let options = BuildOptions { memory: "256x16x16".parse().unwrap(), ..BuildOptions::default() };
+    assert_eq!(options.memory, MemoryLayout::new(256, 16, 16).unwrap());
+
+    let interp = compile_and_run(path, &options);
+    assert_eq!(interp.output, vec!["42"]);
+    assert_eq!(interp.get_word(200000).unwrap(), 42);
+}
+
+#[test]
+pub fn storage_backend() {
+    let options = BuildOptions {
+        memory: MemoryLayout::default().with_backend(MemoryBackend::Storage),
+        ..BuildOptions::default()
+    };
+
+    for (file, output) in [
+        ("dyn_call.bc", vec!["42"]),
+        ("varargs.bc", vec!["106", "202", "7", "1", "1030"]),
+    ].iter() {
+        let interp = compile_and_run(&Path::new("./tests").join(file), &options);
+        assert_eq!(&interp.output, output);
+
+        let jukebox_cmds = interp.program().iter().flat_map(|f| f.cmds.iter()).filter(|c| c.to_string().contains("RecordItem"));
+        assert_eq!(jukebox_cmds.count(), 0);
+    }
+}
+
+#[test]
+pub fn storage_invalid_access() {
+    let options = BuildOptions {
+        memory: MemoryLayout::default().with_backend(MemoryBackend::Storage),
+        ..BuildOptions::default()
+    };
+
+    let mut interp = compile(&[Path::new("./tests/storage_asm.bc")], &options);
+    assert_eq!(
+        interp.run_to_end(),
+        Err(InterpError::InvalidStorageAccess("pages[100000][0]".to_string()))
+    );
+    assert_eq!(interp.output, vec!["1"]);
+
+    let err = Datapack::from_bc(Path::new("./tests/jukebox_asm.bc"), &options).err().unwrap();
+    assert!(err.starts_with("unsupported instruction \"execute store result score"), "{}", err);
+    assert!(err.contains("RecordItem.tag.Memory"), "{}", err);
+}
+
+#[test]
+pub fn heap() {
+    let interp = compile_and_run(Path::new("./tests/heap.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["0", "81", "1", "1", "1", "1", "0"]);
+
+    // Only the block from the second `malloc(8)` is still allocated
+    let usage = interp.heap_usage().unwrap().unwrap();
+    assert_eq!(usage.used_blocks, 1);
+    assert_eq!(usage.used_bytes, 16);
+}
+
+#[test]
+pub fn stack_overflow() {
+    let options = BuildOptions {
+        memory: MemoryLayout::new(8, 16, 16).unwrap(),
+        stack_check: true,
+        ..BuildOptions::default()
+    };
+
+    let mut interp = compile(&[Path::new("./tests/stack_overflow.bc")], &options);
+    assert_eq!(interp.run_to_end(), Err(InterpError::StackOverflow("recurse".to_string())));
+    assert_eq!(&interp.output, &["1"]);
+}
+
+#[test]
+pub fn check_pointers() {
+    let options = BuildOptions {
+        check_pointers: true,
+        ..BuildOptions::default()
+    };
+
+    let mut interp = compile(&[Path::new("./tests/bad_pointer.bc")], &options);
+    assert_eq!(interp.run_to_end(), Err(InterpError::InvalidPointer(0, "load".to_string())));
+    assert_eq!(&interp.output, &["4660", "18"]);
+
+    // Correct programs shouldn't be affected
+    for (file, output) in [
+        ("dyn_call.bc", vec!["42"]),
+        ("heap.bc", vec!["0", "81", "1", "1", "1", "1", "0"]),
+    ].iter() {
+        let interp = compile_and_run(&Path::new("./tests").join(file), &options);
+        assert_eq!(&interp.output, output);
+    }
+}
+
+#[test]
+pub fn mmio() {
+    let options = BuildOptions {
+        mmio: Some(langcraft::mmio::DEFAULT_BASE),
+        ..BuildOptions::default()
+    };
+
+    let interp = compile_and_run(Path::new("./tests/mmio.bc"), &options);
+    assert_eq!(&interp.output, &["Hi!", "1"]);
+
+    // The ports can't overlap memory
+    let options = BuildOptions {
+        mmio: Some(0x1000),
+        ..BuildOptions::default()
+    };
+    assert!(Datapack::from_bc(Path::new("./tests/mmio.bc"), &options).is_err());
+}
+
+#[test]
+pub fn const_access() {
+    let output = vec!["43777", "255", "3", "128", "7", "4660", "32769", "-2", "7", "305419896"];
+
+    for backend in [MemoryBackend::Jukebox, MemoryBackend::Storage].iter() {
+        let options = BuildOptions {
+            memory: MemoryLayout::default().with_backend(*backend),
+            ..BuildOptions::default()
+        };
+
+        let interp = compile_and_run(Path::new("./tests/const_access.bc"), &options);
+        assert_eq!(interp.output, output);
+
+        // Every access is at a constant address, so none of them should need an intrinsic
+        let main_cmds = interp.program().iter().filter(|f| f.id.name.starts_with("rust:main")).flat_map(|f| f.cmds.iter());
+        assert!(main_cmds.map(|c| c.to_string()).all(|c| !c.contains("intrinsic:load") && !c.contains("intrinsic:store")));
+    }
+}
+
+#[test]
+pub fn atomics() {
+    let interp = compile_and_run(Path::new("./tests/atomics.bc"), &BuildOptions::default());
+    assert_eq!(
+        interp.output,
+        vec!["5", "8", "7", "12", "8", "-1", "-1", "0", "100", "-7", "-7", "3", "0", "0", "1", "9", "1", "0", "1", "-1", "-3"]
+    );
+}
+
+#[test]
+pub fn exceptions() {
+    let interp = compile_and_run(Path::new("./tests/exceptions.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["0", "-1", "1", "-1", "7", "42"]);
+    assert_eq!(
+        interp.unwind_trace,
+        vec!["unwind __cxa_throw", "unwind thrower", "catch middle", "unwind middle", "catch main"]
+    );
+}
+
+#[test]
+pub fn rethrow() {
+    let interp = compile_and_run(Path::new("./tests/rethrow.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["5", "6", "20", "10", "42"]);
+
+    // The outer exception in `nested` is still freed after the inner one is
+    let usage = interp.heap_usage().unwrap().unwrap();
+    assert_eq!(usage.used_blocks, 0);
+}
+
+#[test]
+pub fn deep_unwind() {
+    let interp = compile_and_run(Path::new("./tests/deep_unwind.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["4", "12", "15", "42"]);
+    assert_eq!(
+        interp.unwind_trace,
+        vec![
+            "unwind __cxa_throw", "unwind thrower", "unwind three", "catch two", "unwind two", "unwind one", "catch main",
+            "unwind __cxa_throw", "unwind thrower", "unwind three", "catch main",
+        ]
+    );
+
+    // There are enough call sites that finding where each one unwinds to goes through more than one function
+    let tree = interp.program().iter().filter(|f| f.id.to_string().contains("__langcraft_unwind/")).count();
+    assert!(tree >= 2, "{} functions in the unwind tree", tree);
+}
+
+#[test]
+pub fn uncaught() {
+    let mut interp = compile(&[Path::new("./tests/uncaught.bc")], &BuildOptions::default());
+    assert_eq!(interp.run_to_end(), Err(InterpError::UncaughtException));
+    assert_eq!(interp.output, vec!["1", "uncaught exception"]);
+    assert_eq!(interp.unwind_trace, vec!["unwind _Unwind_RaiseException", "unwind panic", "unwind main"]);
+}
+
+#[test]
+pub fn inline_asm() {
+    let interp = compile_and_run(Path::new("./tests/inline_asm.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["7", "15", "3", "3", "hello"]);
+}
+
+#[test]
+pub fn invalid_asm() {
+    let err = Datapack::from_bc(Path::new("./tests/bad_asm.bc"), &BuildOptions::default()).err().unwrap();
+    let errors = err.lines().collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2, "{}", err);
+    assert!(errors.iter().all(|e| e.starts_with("invalid inline assembly: invalid command")), "{}", err);
+    assert!(err.contains("unexpected end of command in function `add`"), "{}", err);
+    assert!(err.contains("`time set day`: unexpected `set` in function `main`"), "{}", err);
+}
+
+#[test]
+pub fn optimize() {
+    let files = ["fibonacci.bc", "div_64.bc", "float_ops.bc", "heap.bc", "atomics.bc", "exceptions.bc", "const_access.bc"];
+
+    for file in files.iter() {
+        let mut outputs = Vec::new();
+        let mut counts = Vec::new();
+
+        for opt_level in 0..=2 {
+            let options = BuildOptions {
+                opt_level,
+                ..BuildOptions::default()
+            };
+
+            let interp = compile_and_run(&Path::new("./tests").join(file), &options);
+            counts.push(interp.program().iter().map(|f| f.cmds.len()).sum::<usize>());
+            outputs.push(interp.output);
+        }
+
+        // Optimizing shouldn't change what the program does, only how many commands it takes
+        assert_eq!(outputs[1], outputs[0], "{} at -O1", file);
+        assert_eq!(outputs[2], outputs[0], "{} at -O2", file);
+        assert!(counts[2] < counts[0], "{} went from {} to {} commands", file, counts[0], counts[2]);
+    }
+}
+
+#[test]
+pub fn opt_report() {
+    let path = Path::new("./tests/fibonacci.bc");
+    assert_eq!(Datapack::from_bc(path, &BuildOptions::default()).unwrap().opt_report, None);
+
+    let options = BuildOptions { opt_level: 2, ..BuildOptions::default() };
+    let report = Datapack::from_bc(path, &options).unwrap().opt_report.unwrap();
+    assert!(report.after < report.before, "{}", report);
+    assert_eq!(report.before - report.after, report.removed.iter().map(|(_, n)| n).sum::<usize>(), "{}", report);
+}
+
+#[test]
+pub fn inlining() {
+    let expected = vec!["30", "33", "36", "3", "-2", "0", "120", "5"];
+
+    for opt_level in 0..=1 {
+        let options = BuildOptions {
+            opt_level,
+            ..BuildOptions::default()
+        };
+
+        let interp = compile_and_run(Path::new("./tests/inlining.bc"), &options);
+        assert_eq!(interp.output, expected, "at -O{}", opt_level);
+
+        // Only the calls to `fact` and `difference` are still calls once inlining is on
+        let calls = interp.program().iter().filter(|f| f.id.name.ends_with("main") && f.id.sub != 0).count();
+        assert_eq!(calls, if opt_level == 0 { 6 } else { 2 });
+    }
+}
+
+#[test]
+pub fn fused_blocks() {
+    let interp = compile_and_run(Path::new("./tests/fused_blocks.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["3", "1", "10", "3", "5"]);
+
+    // Twelve blocks run, but only going back around the loop needs another command block
+    assert!(interp.tick <= 7, "took {} ticks", interp.tick);
+
+    // Both sides of the diamond are called directly
+    let second = interp.program().iter().find(|f| f.id.to_string().ends_with("main-blocksecond")).unwrap();
+    let calls = second.cmds.iter().map(|c| c.to_string()).filter(|c| c.contains("run function rust:main-blockis_")).count();
+    assert_eq!(calls, 2);
+}
+
+#[test]
+pub fn register_allocation() {
+    let interp = compile_and_run(Path::new("./tests/regalloc.bc"), &BuildOptions::default());
+    assert_eq!(interp.output, vec!["13", "165"]);
+
+    // Between the two calls, only `%a` and `%c` are popped and pushed again,
+    // where saving every holder would move all eight of the locals in `main` each way
+    let between = interp.program().iter().find(|f| f.id.to_string().ends_with("main-blockentry-sub1")).unwrap();
+    let moves = between.cmds.iter().map(|c| c.to_string()).filter(|c| c.contains("RecordItem.tag.Memory") && c.contains("%%reg")).count();
+    assert_eq!(moves, 4);
+
+    // Eight locals fit in four registers
+    let registers = interp
+        .program()
+        .iter()
+        .filter(|f| f.id.name.starts_with("main"))
+        .flat_map(|f| f.cmds.iter())
+        .flat_map(|c| c.to_string().split_whitespace().filter(|w| w.starts_with("%%reg")).map(str::to_string).collect::<Vec<_>>())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(registers.len(), 4, "{:?}", registers);
+}